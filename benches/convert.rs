@@ -0,0 +1,137 @@
+//! Timings of the parser hot paths and the JSON/CSV writers, on a typical
+//! 2048-pixel spectrum and a 65536-point one.
+//!
+//! `cargo bench` runs all of them; `cargo bench -- rle` only those whose
+//! name contains `rle`. Criterion reports the time per call and the
+//! throughput over the input (or output) bytes, and the change since the
+//! previous run.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use spc_converter::output::{write_csv_spc, write_json_spc};
+use spc_converter::parser::{
+    checksum, decrypt, rle0_decode, rle0_encode, rle8_decode, rle8_encode, unpack_container, BLOCK_SIZE,
+    ENCRYPTION_KEY,
+};
+use spc_converter::spectre::SpcFileBuilder;
+use spc_converter::{Calibration, SpcFile, StorageObject};
+use std::hint::black_box;
+use std::sync::LazyLock;
+
+/// A spectrum and what is derived from it once, outside the timings.
+struct Fixture {
+    points: usize,
+    spc: SpcFile,
+    /// The encoded .spc file
+    file: Vec<u8>,
+    /// The largest decoded StorageObject buffer (the data object)
+    object: Vec<u8>,
+    /// `object` RLE8- and RLE0-encoded
+    rle8: Vec<u8>,
+    rle0: Vec<u8>,
+    json_len: usize,
+    csv_len: usize,
+}
+
+static FIXTURES: LazyLock<Vec<Fixture>> = LazyLock::new(|| {
+    [2048, 65536]
+        .into_iter()
+        .map(|points| {
+            let spc = spectrum(points);
+            let file = spc.to_bytes();
+            let buffers = unpack_container(&file).expect("the generated file unpacks");
+            let object = buffers.into_iter().max_by_key(Vec::len).expect("the file has buffers");
+            let mut json = Vec::new();
+            write_json_spc(&spc, &mut json, false).expect("JSON is written");
+            let mut csv = Vec::new();
+            write_csv_spc(&spc, &mut csv).expect("CSV is written");
+            Fixture {
+                points,
+                spc,
+                file,
+                rle8: rle8_encode(&object),
+                rle0: rle0_encode(&object),
+                object,
+                json_len: json.len(),
+                csv_len: csv.len(),
+            }
+        })
+        .collect()
+});
+
+/// A spectrum of `points` points: a few Gaussian bands on a sloping
+/// baseline with deterministic noise, calibrated, with a blank.
+fn spectrum(points: usize) -> SpcFile {
+    let mut state = 0x2545_f491_u32;
+    let mut noise = move || {
+        state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        f64::from(state >> 16) / 65536.0 - 0.5
+    };
+    let data: Vec<f64> = (0..points)
+        .map(|i| {
+            let x = i as f64 / points as f64;
+            let bands: f64 = [(0.2, 4000.0), (0.45, 12000.0), (0.7, 6500.0)]
+                .iter()
+                .map(|(center, height)| height * (-((x - center) / 0.01).powi(2)).exp())
+                .sum();
+            (800.0 + 300.0 * x + bands + 40.0 * noise()).round()
+        })
+        .collect();
+    let blank = (0..points).map(|_| (600.0 + 20.0 * noise()).round()).collect();
+    SpcFileBuilder::new("CAM-BENCH", data)
+        .blank(blank)
+        .calibration(Calibration::legendre(vec![850.0, 60.0, -0.5, 0.1]))
+        .laser_wavelength(785.0)
+        .build()
+}
+
+/// Time `f` on each fixture as `name/<points>`, with the throughput over
+/// `bytes` of it.
+fn bench<T>(c: &mut Criterion, name: &str, bytes: impl Fn(&Fixture) -> usize, f: impl Fn(&Fixture) -> T) {
+    let mut group = c.benchmark_group(name);
+    for fixture in FIXTURES.iter() {
+        group.throughput(Throughput::Bytes(bytes(fixture) as u64));
+        group.bench_function(BenchmarkId::from_parameter(fixture.points), |b| b.iter(|| f(black_box(fixture))));
+    }
+    group.finish();
+}
+
+fn container(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decrypt");
+    for fixture in FIXTURES.iter() {
+        group.throughput(Throughput::Bytes(fixture.file.len() as u64));
+        group.bench_function(BenchmarkId::from_parameter(fixture.points), |b| {
+            b.iter_batched_ref(
+                || fixture.file.clone(),
+                |file| decrypt(file, ENCRYPTION_KEY, 0x1234_5678, BLOCK_SIZE),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+
+    bench(c, "checksum", |fixture| fixture.file.len(), |fixture| checksum(&fixture.file));
+}
+
+fn decoding(c: &mut Criterion) {
+    bench(c, "rle8_decode", |fixture| fixture.object.len(), |fixture| rle8_decode(&fixture.rle8, fixture.object.len()));
+    bench(c, "rle0_decode", |fixture| fixture.object.len(), |fixture| rle0_decode(&fixture.rle0, fixture.object.len()));
+}
+
+fn parsing(c: &mut Criterion) {
+    bench(c, "storage_object", |fixture| fixture.object.len(), |fixture| StorageObject::from_bytes(&fixture.object));
+    bench(c, "spc_file", |fixture| fixture.file.len(), |fixture| SpcFile::from_bytes(&fixture.file));
+}
+
+fn writing(c: &mut Criterion) {
+    bench(c, "write_json", |fixture| fixture.json_len, |fixture| {
+        let mut out = Vec::with_capacity(fixture.json_len);
+        write_json_spc(&fixture.spc, &mut out, false).map(|_| out)
+    });
+    bench(c, "write_csv", |fixture| fixture.csv_len, |fixture| {
+        let mut out = Vec::with_capacity(fixture.csv_len);
+        write_csv_spc(&fixture.spc, &mut out).map(|_| out)
+    });
+}
+
+criterion_group!(benches, container, decoding, parsing, writing);
+criterion_main!(benches);
@@ -0,0 +1,22 @@
+//! Packed StorageObjects, owned, viewed and salvaged.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use spc_converter::parser::{salvage_object, ObjectView, StorageObject};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(obj) = StorageObject::from_bytes(data) {
+        // Whatever parses must survive a round trip
+        let bytes = obj.to_bytes();
+        let again = StorageObject::from_bytes(&bytes).expect("re-serialized object must parse");
+        assert_eq!(again.to_bytes(), bytes);
+    }
+    if let Ok(view) = ObjectView::parse(data) {
+        let _ = view.f64_values();
+        for child in view.children().flatten() {
+            let _ = child.f64_values();
+        }
+    }
+    let _ = salvage_object(data, "", &mut Vec::new());
+});
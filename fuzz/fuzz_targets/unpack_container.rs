@@ -0,0 +1,13 @@
+//! Containers: decryption, the buffer table, decompression and recovery.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use spc_converter::parser::unpack_container;
+use spc_converter::SpcFile;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = unpack_container(data);
+    let _ = SpcFile::from_bytes(data);
+    let _ = SpcFile::recover(data);
+});
@@ -0,0 +1,48 @@
+/*
+ * C interface of spc_converter (see src/ffi.rs).
+ *
+ * Build the shared library with `cargo build --release`
+ * (target/release/libspc_converter.so, .dylib or spc_converter.dll).
+ *
+ * Arrays and strings returned by the accessors belong to the handle and
+ * stay valid until spc_free(). Failing functions return NULL (or NaN) and
+ * leave a message for spc_last_error().
+ */
+#ifndef SPC_CONVERTER_H
+#define SPC_CONVERTER_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct SpcHandle SpcHandle;
+
+/* Parse the first spectrum of a file in any readable format. */
+SpcHandle *spc_parse_file(const char *path);
+/* Message of the last failure on this thread, or NULL. */
+const char *spc_last_error(void);
+/* Release a handle; NULL is ignored. */
+void spc_free(SpcHandle *handle);
+
+const char *spc_uid(const SpcHandle *handle);
+/* Arrays: the count goes to *len (if len is not NULL); NULL if absent. */
+const double *spc_data(const SpcHandle *handle, size_t *len);
+const double *spc_blank(const SpcHandle *handle, size_t *len);
+const double *spc_wavelength_axis(const SpcHandle *handle, size_t *len);
+const double *spc_raman_shift_axis(const SpcHandle *handle, size_t *len);
+/* Settings: NaN if unknown. */
+double spc_laser_wavelength(const SpcHandle *handle);
+double spc_exposure(const SpcHandle *handle);
+double spc_gain(const SpcHandle *handle);
+
+/* The whole spectrum as JSON; free it with spc_free_string(). */
+char *spc_to_json(const SpcHandle *handle);
+void spc_free_string(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SPC_CONVERTER_H */
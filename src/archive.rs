@@ -0,0 +1,93 @@
+//! `--output <FILE>.zip`: outputs are written to a staging directory as if
+//! it were the output directory, and moved into the archive after each
+//! input.
+
+use spc_converter::output::ZipWriter;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+pub struct Archive {
+    /// A new directory of its own, removed when the archive is dropped
+    staging: TempDir,
+    zip: ZipWriter<BufWriter<File>>,
+}
+
+/// Whether an output path names a ZIP archive.
+pub fn is_archive(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+impl Archive {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let staging = tempfile::Builder::new().prefix("spc-convert-").tempdir()?;
+        Ok(Archive {
+            staging,
+            zip: ZipWriter::new(BufWriter::new(File::create(path)?)),
+        })
+    }
+
+    /// The directory outputs are written to.
+    pub fn staging(&self) -> &Path {
+        self.staging.path()
+    }
+
+    /// Move everything staged so far into the archive.
+    pub fn collect(&mut self) -> io::Result<()> {
+        let mut files = Vec::new();
+        staged_files(self.staging(), &mut files)?;
+        files.sort();
+        for file in files {
+            let name = file.strip_prefix(self.staging()).unwrap_or(&file);
+            let name: Vec<String> = name.components().map(|part| part.as_os_str().to_string_lossy().into_owned()).collect();
+            self.zip.add(&name.join("/"), &std::fs::read(&file)?)?;
+            std::fs::remove_file(&file)?;
+        }
+        Ok(())
+    }
+
+    /// Write the archive's directory and remove the staging directory.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.collect()?;
+        self.zip.finish()?;
+        self.staging.close()
+    }
+}
+
+fn staged_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            staged_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_staging() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = Archive::create(&dir.path().join("a.zip")).unwrap();
+        let second = Archive::create(&dir.path().join("b.zip")).unwrap();
+        assert_ne!(first.staging(), second.staging());
+        assert_eq!(std::fs::read_dir(first.staging()).unwrap().count(), 0);
+
+        // Dropped without finishing, e.g. on an error, the staging goes too
+        let staging = first.staging().to_path_buf();
+        std::fs::write(staging.join("a.json"), "{}").unwrap();
+        drop(first);
+        assert!(!staging.exists());
+
+        let staging = second.staging().to_path_buf();
+        second.finish().unwrap();
+        assert!(!staging.exists());
+        assert!(dir.path().join("b.zip").is_file());
+    }
+}
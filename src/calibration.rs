@@ -0,0 +1,229 @@
+//! Fitting and checking wavelength calibrations.
+//!
+//! Given the pixel positions of lines with known wavelengths (e.g. a neon
+//! lamp) or Raman shifts (e.g. silicon at 520.7 cm⁻¹), the Legendre
+//! coefficients of [`Calibration`] are fitted by least squares.
+//! [`check_calibration`] flags calibrations that would produce nonsense axes.
+
+use crate::processing::linalg::least_squares;
+use crate::spectre::{legendre, Calibration, CalibrationModel};
+use serde::Serialize;
+
+/// Number of Legendre coefficients fitted when neither a term count nor a
+/// starting calibration is given.
+pub const DEFAULT_CALIBRATION_TERMS: usize = 4;
+
+/// Plausible wavelength range of a silicon detector, in nm.
+pub const PLAUSIBLE_WAVELENGTH_NM: (f64, f64) = (200.0, 1100.0);
+
+/// A reference line observed at a (fractional) pixel position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReferenceLine {
+    pub pixel: f64,
+    /// Known wavelength, in nm
+    pub wavelength: f64,
+}
+
+impl ReferenceLine {
+    /// A line given by its Raman shift (cm⁻¹) for a laser wavelength (nm).
+    pub fn from_raman_shift(pixel: f64, raman_shift: f64, laser_wavelength: f64) -> Self {
+        ReferenceLine {
+            pixel,
+            wavelength: 1.0 / (1.0 / laser_wavelength - raman_shift / 1e7),
+        }
+    }
+}
+
+/// Fit residual of one reference line.
+#[derive(Debug, Clone, Serialize)]
+pub struct LineResidual {
+    pub pixel: f64,
+    pub reference_nm: f64,
+    pub fitted_nm: f64,
+    /// Fitted minus reference wavelength, in nm
+    pub residual_nm: f64,
+}
+
+/// A fitted calibration with its residuals.
+#[derive(Debug, Clone, Serialize)]
+pub struct CalibrationFit {
+    pub calibration: Calibration,
+    /// Number of coefficients fitted; the others were held fixed
+    pub fitted_terms: usize,
+    /// Total number of coefficients
+    pub terms: usize,
+    pub residuals: Vec<LineResidual>,
+    /// Root-mean-square residual, in nm
+    pub rms_nm: f64,
+}
+
+/// Fit `terms` calibration coefficients to reference lines on a detector of
+/// `num_pixels` pixels.
+///
+/// `terms` defaults to the length of `initial` (used only if it is a
+/// Legendre calibration), else
+/// [`DEFAULT_CALIBRATION_TERMS`]. With fewer lines than coefficients, only
+/// the lowest-order coefficients are fitted (one per line) and the rest are
+/// taken from `initial`, or zero without it. A single silicon line therefore
+/// corrects the offset of an existing calibration.
+pub fn fit_calibration(
+    lines: &[ReferenceLine],
+    num_pixels: usize,
+    initial: Option<&Calibration>,
+    terms: Option<usize>,
+) -> Result<CalibrationFit, String> {
+    let initial = initial.filter(|cal| cal.model == CalibrationModel::Legendre);
+    if lines.is_empty() {
+        return Err("no reference lines given".to_string());
+    }
+    if num_pixels < 2 {
+        return Err(format!("cannot calibrate a detector of {} pixels", num_pixels));
+    }
+    if let Some(line) = lines.iter().find(|l| !l.pixel.is_finite() || !l.wavelength.is_finite() || l.wavelength <= 0.0) {
+        return Err(format!("invalid reference line at pixel {} ({} nm)", line.pixel, line.wavelength));
+    }
+
+    let total = terms
+        .or(initial.map(|cal| cal.coefficients.len()))
+        .filter(|&n| n > 0)
+        .unwrap_or(DEFAULT_CALIBRATION_TERMS);
+    let mut coefficients = initial.map(|cal| cal.coefficients.clone()).unwrap_or_default();
+    coefficients.resize(total, 0.0);
+    let fitted_terms = lines.len().min(total);
+
+    // Fit the lowest coefficients to what the fixed ones leave over
+    let basis: Vec<Vec<f64>> = lines
+        .iter()
+        .map(|l| legendre(normalize(l.pixel, num_pixels), total))
+        .collect();
+    let rows: Vec<Vec<f64>> = basis.iter().map(|b| b[..fitted_terms].to_vec()).collect();
+    let remainder: Vec<f64> = lines
+        .iter()
+        .zip(&basis)
+        .map(|(l, b)| l.wavelength - (fitted_terms..total).map(|k| coefficients[k] * b[k]).sum::<f64>())
+        .collect();
+    let fitted = least_squares(&rows, &remainder)
+        .filter(|c| c.iter().all(|v| v.is_finite()))
+        .ok_or("reference lines do not determine a calibration (duplicate pixel positions?)")?;
+    coefficients[..fitted_terms].copy_from_slice(&fitted);
+
+    let residuals: Vec<LineResidual> = lines
+        .iter()
+        .zip(&basis)
+        .map(|(l, b)| {
+            let fitted_nm: f64 = coefficients.iter().zip(b).map(|(c, p)| c * p).sum();
+            LineResidual {
+                pixel: l.pixel,
+                reference_nm: l.wavelength,
+                fitted_nm,
+                residual_nm: fitted_nm - l.wavelength,
+            }
+        })
+        .collect();
+    let rms_nm = (residuals.iter().map(|r| r.residual_nm.powi(2)).sum::<f64>() / residuals.len() as f64).sqrt();
+
+    Ok(CalibrationFit {
+        calibration: Calibration::legendre(coefficients),
+        fitted_terms,
+        terms: total,
+        residuals,
+        rms_nm,
+    })
+}
+
+/// Check a calibration for a detector of `num_pixels` pixels and describe
+/// every problem found: non-finite coefficients, an axis that cannot be
+/// generated, is not strictly monotonic or leaves
+/// [`PLAUSIBLE_WAVELENGTH_NM`]. An empty result means the calibration looks
+/// sane.
+pub fn check_calibration(calibration: &Calibration, num_pixels: usize) -> Vec<String> {
+    let mut problems = Vec::new();
+    let values = calibration.coefficients.iter().chain(calibration.knots.iter().flat_map(|(p, w)| [p, w]));
+    if values.clone().any(|v| !v.is_finite()) {
+        problems.push("non-finite coefficients".to_string());
+        return problems;
+    }
+
+    let Some(axis) = calibration.generate_wavelength_axis(num_pixels) else {
+        problems.push(format!("no wavelength axis can be generated for {} points", num_pixels));
+        return problems;
+    };
+    if axis.iter().any(|v| !v.is_finite()) {
+        problems.push("wavelength axis has non-finite values".to_string());
+        return problems;
+    }
+
+    let rising = axis.windows(2).filter(|w| w[1] > w[0]).count();
+    let falling = axis.windows(2).filter(|w| w[1] < w[0]).count();
+    let steps = axis.len().saturating_sub(1);
+    if rising != steps && falling != steps {
+        let turn = axis
+            .windows(3)
+            .position(|w| (w[1] - w[0]) * (w[2] - w[1]) <= 0.0)
+            .map_or(0, |i| i + 1);
+        problems.push(format!("wavelength axis is not monotonic (turns at pixel {})", turn));
+    }
+
+    let min = axis.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = axis.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let (low, high) = PLAUSIBLE_WAVELENGTH_NM;
+    if min < low || max > high {
+        problems.push(format!(
+            "wavelength axis spans {:.1}-{:.1} nm, outside the plausible {}-{} nm",
+            min, max, low, high
+        ));
+    }
+    problems
+}
+
+/// Pixel index to the calibration's -1..1 coordinate.
+fn normalize(pixel: f64, num_pixels: usize) -> f64 {
+    2.0 * pixel / ((num_pixels - 1) as f64) - 1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_calibration() {
+        let truth = Calibration::legendre(vec![850.0, 60.0, -0.5, 0.1]);
+        let wavelength = |pixel: f64| -> f64 {
+            let basis = legendre(normalize(pixel, 512), 4);
+            truth.coefficients.iter().zip(&basis).map(|(c, p)| c * p).sum()
+        };
+        let lines: Vec<ReferenceLine> = [12.5, 100.0, 230.25, 400.0, 505.0]
+            .iter()
+            .map(|&pixel| ReferenceLine {
+                pixel,
+                wavelength: wavelength(pixel),
+            })
+            .collect();
+        let fit = fit_calibration(&lines, 512, None, None).unwrap();
+        for (a, b) in fit.calibration.coefficients.iter().zip(&truth.coefficients) {
+            assert!((a - b).abs() < 1e-9);
+        }
+        assert!(fit.rms_nm < 1e-9);
+
+        // One silicon line only shifts the offset of an existing calibration
+        let shifted = Calibration::legendre(vec![849.0, 60.0, -0.5, 0.1]);
+        let pixel = truth.raman_shift_to_pixel(520.7, 512, 785.0).unwrap();
+        let silicon = ReferenceLine::from_raman_shift(pixel, 520.7, 785.0);
+        let fit = fit_calibration(&[silicon], 512, Some(&shifted), None).unwrap();
+        assert_eq!(fit.fitted_terms, 1);
+        assert!((fit.calibration.coefficients[0] - 850.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_check_calibration() {
+        assert!(check_calibration(&Calibration::legendre(vec![850.0, 60.0, -0.5, 0.1]), 512).is_empty());
+
+        let folded = check_calibration(&Calibration::legendre(vec![850.0, 0.0, 60.0]), 512);
+        assert_eq!(folded.len(), 1);
+        assert!(folded[0].contains("not monotonic"));
+
+        let corrupt = check_calibration(&Calibration::legendre(vec![1e30, 1e29, 0.0, 0.0]), 512);
+        assert!(corrupt[0].contains("outside the plausible"));
+        assert!(!check_calibration(&Calibration::legendre(vec![f64::NAN]), 512).is_empty());
+    }
+}
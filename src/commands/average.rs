@@ -0,0 +1,66 @@
+//! `average` subcommand: combine replicate acquisitions into one spectrum.
+
+use clap::{Args, ValueEnum};
+use spc_converter::{output, processing};
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct AverageArgs {
+    /// Input .spc files, directories or glob patterns
+    #[arg(required = true)]
+    input: Vec<PathBuf>,
+
+    /// Output file (defaults to stdout)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value = "json")]
+    format: AverageFormat,
+}
+
+#[derive(Clone, ValueEnum)]
+enum AverageFormat {
+    Json,
+    Csv,
+    /// LLM-friendly x,y pairs with context header
+    Pairs,
+    /// Thermo Galactic SPC (GRAMS)
+    Galactic,
+    /// Spectrum Analyzer Suite .spc
+    Spc,
+}
+
+/// Every input must parse; files with a different point count,
+/// calibration or laser wavelength are rejected rather than skipped.
+pub fn run(args: &AverageArgs) -> Result<bool, Box<dyn Error>> {
+    let mut spectra = Vec::new();
+    for path in crate::inputs::expand_paths(&args.input)? {
+        let spc = super::load_with_sidecar(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        spectra.push(spc);
+    }
+    let averaged = processing::average(&spectra)?;
+
+    let writer: Box<dyn Write> = match args.output {
+        Some(ref path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout()),
+    };
+    let mut writer = BufWriter::new(writer);
+
+    match args.format {
+        AverageFormat::Json => output::write_json_spc(&averaged, &mut writer, true)?,
+        AverageFormat::Csv => output::write_csv_spc(&averaged, &mut writer)?,
+        AverageFormat::Pairs => output::write_pairs(&averaged, &mut writer)?,
+        AverageFormat::Galactic => output::write_galactic(&averaged, &mut writer)?,
+        AverageFormat::Spc => {
+            averaged.check_storable()?;
+            writer.write_all(&averaged.to_bytes())?
+        }
+    }
+    writer.flush()?;
+
+    Ok(true)
+}
@@ -0,0 +1,124 @@
+//! `calibrate` subcommand: fit calibration coefficients to reference lines.
+
+use clap::Args;
+use spc_converter::calibration::{self, ReferenceLine};
+use spc_converter::{Calibration, SpcFile};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+#[derive(Args)]
+pub struct CalibrateArgs {
+    /// Reference measurement (supplies the pixel count, laser wavelength and
+    /// the starting calibration)
+    reference: Option<PathBuf>,
+
+    /// Reference line as PIXEL=VALUE, e.g. --line 215.4=585.249 (repeatable)
+    #[arg(long = "line", value_name = "PIXEL=VALUE", value_parser = parse_line, required = true)]
+    lines: Vec<(f64, f64)>,
+
+    /// Line values are Raman shifts in cm⁻¹ instead of wavelengths in nm
+    #[arg(long)]
+    raman: bool,
+
+    /// Laser wavelength in nm for --raman (defaults to the reference's)
+    #[arg(long)]
+    laser_nm: Option<f64>,
+
+    /// Detector pixel count (defaults to the reference's point count)
+    #[arg(long)]
+    pixels: Option<usize>,
+
+    /// Number of Legendre coefficients (defaults to the reference's, else 4)
+    #[arg(long)]
+    terms: Option<usize>,
+
+    /// Write the calibration to this .json or .toml file (for --calibration)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Write the reference measurement with the new calibration to this .spc file
+    #[arg(long, value_name = "FILE", requires = "reference")]
+    write_spc: Option<PathBuf>,
+
+    /// Print the fit as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+pub fn run(args: &CalibrateArgs) -> Result<bool, Box<dyn Error>> {
+    let reference = match args.reference {
+        Some(ref path) => Some(SpcFile::from_file(path).map_err(|e| format!("{}: {}", path.display(), e))?),
+        None => None,
+    };
+    let num_pixels = args
+        .pixels
+        .or(reference.as_ref().map(|spc| spc.data.len()))
+        .ok_or("--pixels is required without a reference file")?;
+
+    let lines = if args.raman {
+        let laser = args
+            .laser_nm
+            .or(reference.as_ref().and_then(|spc| spc.config.as_ref()?.raman_wavelength))
+            .ok_or("--laser-nm is required for --raman without a reference laser wavelength")?;
+        args.lines
+            .iter()
+            .map(|&(pixel, shift)| ReferenceLine::from_raman_shift(pixel, shift, laser))
+            .collect::<Vec<_>>()
+    } else {
+        args.lines
+            .iter()
+            .map(|&(pixel, wavelength)| ReferenceLine { pixel, wavelength })
+            .collect()
+    };
+
+    let initial = reference.as_ref().and_then(|spc| spc.calibration.as_ref());
+    let fit = calibration::fit_calibration(&lines, num_pixels, initial, args.terms)?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&fit)?);
+    } else {
+        println!("Coefficients: {:?}", fit.calibration.coefficients);
+        println!("Fitted terms: {} of {}", fit.fitted_terms, fit.terms);
+        println!("RMS residual: {:.4} nm", fit.rms_nm);
+        println!("{:>10}  {:>12}  {:>12}  {:>10}", "pixel", "reference_nm", "fitted_nm", "residual");
+        for r in &fit.residuals {
+            println!(
+                "{:>10.3}  {:>12.4}  {:>12.4}  {:>10.4}",
+                r.pixel, r.reference_nm, r.fitted_nm, r.residual_nm
+            );
+        }
+    }
+
+    if let Some(ref path) = args.output {
+        write_calibration(&fit.calibration, path)?;
+    }
+    if let (Some(path), Some(mut spc)) = (&args.write_spc, reference) {
+        spc.set_calibration(fit.calibration.clone());
+        spc.write_file(path)?;
+    }
+
+    Ok(true)
+}
+
+/// Write a calibration file readable by `Calibration::from_file`.
+fn write_calibration(calibration: &Calibration, path: &Path) -> Result<(), Box<dyn Error>> {
+    let text = match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => toml::to_string(calibration)?,
+        Some("json") => serde_json::to_string_pretty(calibration)?,
+        _ => return Err(format!("{}: expected a .toml or .json extension", path.display()).into()),
+    };
+    std::fs::write(path, text)?;
+    Ok(())
+}
+
+fn parse_line(s: &str) -> Result<(f64, f64), String> {
+    let (pixel, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("invalid PIXEL=VALUE: no `=` found in `{}`", s))?;
+    let parse = |v: &str| {
+        v.trim()
+            .parse::<f64>()
+            .map_err(|_| format!("invalid PIXEL=VALUE: `{}` is not a number", v))
+    };
+    Ok((parse(pixel)?, parse(value)?))
+}
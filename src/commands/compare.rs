@@ -0,0 +1,119 @@
+//! `compare` subcommand: spectral similarity of a sample to a reference.
+
+use clap::Args;
+use serde::Serialize;
+use spc_converter::processing::{self, MatrixOptions, SpectrumSimilarity};
+use spc_converter::spectre::AxisType;
+use std::error::Error;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct CompareArgs {
+    /// Reference .spc file (e.g. the standard's reference measurement)
+    reference: PathBuf,
+
+    /// Sample .spc file (e.g. today's measurement of the standard)
+    sample: PathBuf,
+
+    /// Minimum hit quality index; below it the exit code is 1
+    #[arg(long)]
+    min_hqi: Option<f64>,
+
+    /// Axis to compare on [default: the reference's]
+    #[arg(long, value_enum)]
+    axis: Option<crate::AxisArg>,
+
+    /// Lowest x value compared
+    #[arg(long, allow_hyphen_values = true)]
+    xmin: Option<f64>,
+
+    /// Highest x value compared
+    #[arg(long, allow_hyphen_values = true)]
+    xmax: Option<f64>,
+
+    /// Subtract each file's blank first
+    #[arg(long)]
+    subtract_blank: bool,
+
+    /// Subtract a baseline with default parameters first
+    #[arg(long, value_enum)]
+    baseline: Option<crate::BaselineArg>,
+
+    /// Normalization before the Euclidean distance
+    #[arg(long, value_enum, default_value = "vector")]
+    normalize: crate::NormalizeArg,
+
+    /// Print the comparison as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Serialize)]
+struct CompareReport {
+    reference: String,
+    sample: String,
+    #[serde(flatten)]
+    similarity: SpectrumSimilarity,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min_hqi: Option<f64>,
+    pass: bool,
+}
+
+/// Exit code 1 when the hit quality index is below `--min-hqi` (or cannot
+/// be computed).
+pub fn run(args: &CompareArgs) -> Result<bool, Box<dyn Error>> {
+    let reference = super::load_with_sidecar(&args.reference)?;
+    let sample = super::load_with_sidecar(&args.sample)?;
+    let options = MatrixOptions {
+        axis: args.axis.map(Into::into),
+        x_min: args.xmin,
+        x_max: args.xmax,
+        subtract_blank: args.subtract_blank,
+        baseline: args.baseline.map(|method| match method {
+            crate::BaselineArg::Als => processing::BaselineMethod::als(),
+            crate::BaselineArg::Polynomial => processing::BaselineMethod::polynomial(),
+        }),
+        normalize: Some(args.normalize.into()),
+        ..Default::default()
+    };
+    let similarity = processing::compare_spectra(&reference, &sample, &options)?;
+    let pass = args.min_hqi.is_none_or(|min| similarity.hqi.is_some_and(|hqi| hqi >= min));
+    let report = CompareReport {
+        reference: args.reference.display().to_string(),
+        sample: args.sample.display().to_string(),
+        similarity,
+        min_hqi: args.min_hqi,
+        pass,
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report);
+    }
+
+    Ok(report.pass)
+}
+
+fn print_report(report: &CompareReport) {
+    let similarity = &report.similarity;
+    let value = |value: Option<f64>, digits: usize| value.map_or("n/a".to_string(), |v| format!("{:.*}", digits, v));
+    println!("Reference:           {}", report.reference);
+    println!("Sample:              {}", report.sample);
+    let axis = match similarity.axis {
+        AxisType::RamanShifts => "Raman shift",
+        AxisType::Wavelengths => "wavelength",
+        AxisType::Pixels => "pixel",
+    };
+    println!(
+        "Compared:            {} points, {} {} to {}",
+        similarity.points, axis, similarity.range.0, similarity.range.1
+    );
+    println!("Pearson correlation: {}", value(similarity.pearson, 6));
+    println!("Hit quality index:   {}", value(similarity.hqi, 6));
+    println!("Spectral angle:      {}°", value(similarity.spectral_angle, 3));
+    println!("Euclidean distance:  {} ({} normalized)", value(similarity.euclidean, 6), similarity.normalization);
+    if let Some(min) = report.min_hqi {
+        println!("{} (minimum HQI {})", if report.pass { "PASS" } else { "FAIL" }, min);
+    }
+}
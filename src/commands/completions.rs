@@ -0,0 +1,21 @@
+//! `completions` subcommand: print a shell completion script.
+//!
+//! The scripts are generated by clap_complete from the CLI definition
+//! itself, so new flags and subcommands complete without further changes.
+
+use clap::{Args, CommandFactory};
+use clap_complete::Shell;
+use std::error::Error;
+use std::io;
+
+#[derive(Args)]
+pub struct CompletionsArgs {
+    /// Shell to complete for
+    #[arg(value_enum)]
+    shell: Shell,
+}
+
+pub fn run(args: &CompletionsArgs) -> Result<bool, Box<dyn Error>> {
+    clap_complete::generate(args.shell, &mut crate::Cli::command(), "spc-convert", &mut io::stdout());
+    Ok(true)
+}
@@ -0,0 +1,178 @@
+//! `diff` subcommand: compare two files.
+
+use clap::Args;
+use serde::Serialize;
+use serde_json::Value;
+use spc_converter::SpcFile;
+use std::error::Error;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct DiffArgs {
+    /// First .spc file
+    a: PathBuf,
+
+    /// Second .spc file
+    b: PathBuf,
+
+    /// Maximum absolute difference for intensities, blank and calibration coefficients
+    #[arg(short, long, default_value_t = 0.0)]
+    tolerance: f64,
+
+    /// Do not count a differing UID as a mismatch
+    #[arg(long)]
+    ignore_uid: bool,
+
+    /// Print the comparison as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Serialize)]
+struct DiffReport {
+    tolerance: f64,
+    matches: bool,
+    uid: ValueDiff,
+    /// Config fields that differ (missing on one side is `null`)
+    config: Vec<FieldDiff>,
+    calibration: SeriesDiff,
+    data: SeriesDiff,
+    blank: SeriesDiff,
+}
+
+#[derive(Serialize)]
+struct ValueDiff {
+    a: Value,
+    b: Value,
+    equal: bool,
+}
+
+#[derive(Serialize)]
+struct FieldDiff {
+    field: String,
+    a: Value,
+    b: Value,
+}
+
+/// Point-wise comparison of two series.
+#[derive(Serialize)]
+struct SeriesDiff {
+    len_a: usize,
+    len_b: usize,
+    /// Largest absolute difference over the common points (null if none, or
+    /// if a point is NaN on either side)
+    max_abs_diff: Option<f64>,
+    mean_abs_diff: Option<f64>,
+    within_tolerance: bool,
+}
+
+impl SeriesDiff {
+    fn new(a: &[f64], b: &[f64], tolerance: f64) -> Self {
+        let diffs: Vec<f64> = a.iter().zip(b).map(|(x, y)| (x - y).abs()).collect();
+        // f64::max ignores NaN, so a NaN on either side counts as an unbounded difference
+        let max_abs_diff = diffs
+            .iter()
+            .map(|&d| if d.is_nan() { f64::INFINITY } else { d })
+            .reduce(f64::max);
+        let mean_abs_diff = (!diffs.is_empty()).then(|| diffs.iter().sum::<f64>() / diffs.len() as f64);
+        SeriesDiff {
+            len_a: a.len(),
+            len_b: b.len(),
+            max_abs_diff,
+            mean_abs_diff,
+            within_tolerance: a.len() == b.len() && max_abs_diff.is_none_or(|d| d <= tolerance),
+        }
+    }
+}
+
+/// Exit code 0 when the files match within the tolerance, 1 when they differ.
+pub fn run(args: &DiffArgs) -> Result<bool, Box<dyn Error>> {
+    let a = SpcFile::from_file(&args.a)?;
+    let b = SpcFile::from_file(&args.b)?;
+    let report = compare(&a, &b, args);
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(args, &report);
+    }
+
+    Ok(report.matches)
+}
+
+fn compare(a: &SpcFile, b: &SpcFile, args: &DiffArgs) -> DiffReport {
+    let uid = ValueDiff {
+        a: Value::from(a.uid.as_str()),
+        b: Value::from(b.uid.as_str()),
+        equal: a.uid == b.uid,
+    };
+
+    // Compare configs field by field through their serialized form
+    let to_map = |spc: &SpcFile| match serde_json::to_value(&spc.config) {
+        Ok(Value::Object(map)) => map,
+        _ => serde_json::Map::new(),
+    };
+    let (config_a, config_b) = (to_map(a), to_map(b));
+    let mut fields: Vec<&String> = config_a.keys().chain(config_b.keys()).collect();
+    fields.sort();
+    fields.dedup();
+    let config: Vec<FieldDiff> = fields
+        .into_iter()
+        .filter_map(|field| {
+            let va = config_a.get(field).cloned().unwrap_or(Value::Null);
+            let vb = config_b.get(field).cloned().unwrap_or(Value::Null);
+            (va != vb).then(|| FieldDiff { field: field.clone(), a: va, b: vb })
+        })
+        .collect();
+
+    let coefficients = |spc: &SpcFile| spc.calibration.as_ref().map(|c| c.coefficients.clone()).unwrap_or_default();
+    let calibration = SeriesDiff::new(&coefficients(a), &coefficients(b), args.tolerance);
+    let data = SeriesDiff::new(&a.data, &b.data, args.tolerance);
+    let blank = SeriesDiff::new(&a.blank, &b.blank, args.tolerance);
+
+    DiffReport {
+        tolerance: args.tolerance,
+        matches: (uid.equal || args.ignore_uid)
+            && config.is_empty()
+            && calibration.within_tolerance
+            && data.within_tolerance
+            && blank.within_tolerance,
+        uid,
+        config,
+        calibration,
+        data,
+        blank,
+    }
+}
+
+fn print_report(args: &DiffArgs, report: &DiffReport) {
+    println!("--- {}", args.a.display());
+    println!("+++ {}", args.b.display());
+
+    if !report.uid.equal {
+        println!("uid: {} -> {}", report.uid.a, report.uid.b);
+    }
+    for field in &report.config {
+        println!("config.{}: {} -> {}", field.field, field.a, field.b);
+    }
+    for (name, series) in [
+        ("calibration", &report.calibration),
+        ("data", &report.data),
+        ("blank", &report.blank),
+    ] {
+        if series.len_a != series.len_b {
+            println!("{}: {} -> {} values", name, series.len_a, series.len_b);
+        }
+        if let (Some(max), Some(mean)) = (series.max_abs_diff, series.mean_abs_diff) {
+            if max > 0.0 {
+                println!("{}: max |diff| {}, mean |diff| {}", name, max, mean);
+            }
+        }
+    }
+
+    if report.matches {
+        println!("Files match (tolerance {})", report.tolerance);
+    } else {
+        println!("Files differ (tolerance {})", report.tolerance);
+    }
+}
@@ -0,0 +1,132 @@
+//! `dump` subcommand: print the raw StorageObject tree for diagnostics.
+
+use clap::Args;
+use spc_converter::parser::{unpack_container, ContainerHeader, StorageObject, Variable};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+#[derive(Args)]
+pub struct DumpArgs {
+    /// Input .spc files, directories or glob patterns
+    #[arg(required = true)]
+    input: Vec<PathBuf>,
+
+    /// Bytes of each variable to show as hex
+    #[arg(long, default_value_t = 16)]
+    preview: usize,
+
+    /// Variables to list per object before eliding the rest (0 = all)
+    #[arg(long, default_value_t = 8)]
+    max_vars: usize,
+}
+
+pub fn run(args: &DumpArgs) -> Result<bool, Box<dyn Error>> {
+    let mut error_count = 0;
+
+    let input = crate::inputs::expand_paths(&args.input)?;
+    for (i, path) in input.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("{}", path.display());
+        if let Err(e) = dump_file(args, path) {
+            error_count += 1;
+            println!("  error: {}", e);
+        }
+    }
+
+    Ok(error_count == 0)
+}
+
+fn dump_file(args: &DumpArgs, path: &Path) -> Result<(), Box<dyn Error>> {
+    let bytes = std::fs::read(path)?;
+    let header = ContainerHeader::from_bytes(&bytes)?;
+    println!(
+        "  container: {} buffers, seed {:#010x}, checksum {:#010x}",
+        header.num_buffers, header.seed, header.checksum
+    );
+
+    for (i, buffer) in unpack_container(&bytes)?.iter().enumerate() {
+        println!("  buffer {} ({} bytes)", i, buffer.len());
+        match StorageObject::from_bytes(buffer) {
+            Ok(obj) => print_object(args, &obj, 2),
+            Err(e) => {
+                println!("    error: {}", e);
+                println!("    {}", hex_preview(buffer, args.preview));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn print_object(args: &DumpArgs, obj: &StorageObject, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let owner = if obj.owner_name.is_empty() {
+        String::new()
+    } else {
+        format!(" (owner {})", obj.owner_name)
+    };
+    println!(
+        "{}{}: {}{} [{} vars, {} children]",
+        indent,
+        obj.var_name,
+        obj.type_name,
+        owner,
+        obj.variables.len(),
+        obj.children.len()
+    );
+
+    let shown = match args.max_vars {
+        0 => obj.variables.len(),
+        n => n.min(obj.variables.len()),
+    };
+    for var in &obj.variables[..shown] {
+        print_variable(args, var, depth + 1);
+    }
+    if shown < obj.variables.len() {
+        println!("{}  ... {} more", indent, obj.variables.len() - shown);
+    }
+
+    for child in &obj.children {
+        print_object(args, child, depth + 1);
+    }
+}
+
+fn print_variable(args: &DumpArgs, var: &Variable, depth: usize) {
+    let name = if var.name.is_empty() { "<unnamed>" } else { &var.name };
+    let value = decode_value(var).map(|v| format!(" = {}", v)).unwrap_or_default();
+    println!(
+        "{}{}: {} ({} bytes){}  {}",
+        "  ".repeat(depth),
+        name,
+        var.type_name,
+        var.data.len(),
+        value,
+        hex_preview(&var.data, args.preview)
+    );
+}
+
+/// Decoded value for common scalar types.
+fn decode_value(var: &Variable) -> Option<String> {
+    let data = &var.data;
+    match (var.type_name.as_str(), data.len()) {
+        ("double", 8) => Some(f64::from_le_bytes(data[..8].try_into().ok()?).to_string()),
+        ("int", 4) => Some(i32::from_le_bytes(data[..4].try_into().ok()?).to_string()),
+        ("size_t", 8) => Some(u64::from_le_bytes(data[..8].try_into().ok()?).to_string()),
+        ("bool", 1) => Some((data[0] != 0).to_string()),
+        ("char", _) => {
+            let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+            Some(format!("{:?}", String::from_utf8_lossy(&data[..end])))
+        }
+        _ => None,
+    }
+}
+
+fn hex_preview(data: &[u8], max: usize) -> String {
+    let mut hex: Vec<String> = data.iter().take(max).map(|b| format!("{:02x}", b)).collect();
+    if data.len() > max {
+        hex.push("..".to_string());
+    }
+    hex.join(" ")
+}
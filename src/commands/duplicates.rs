@@ -0,0 +1,97 @@
+//! `duplicates` subcommand: near-duplicate report by spectral similarity.
+
+use clap::Args;
+use serde::Serialize;
+use spc_converter::{processing, SpcFile};
+use std::error::Error;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct DuplicatesArgs {
+    /// Input .spc files, directories or glob patterns
+    #[arg(required = true)]
+    input: Vec<PathBuf>,
+
+    /// Minimum Pearson correlation for two spectra to count as duplicates
+    #[arg(short, long, default_value_t = 0.995)]
+    threshold: f64,
+
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Serialize)]
+struct DuplicateGroup<'a> {
+    files: Vec<String>,
+    uids: Vec<&'a str>,
+    min_similarity: f64,
+    exact: bool,
+}
+
+#[derive(Serialize)]
+struct DuplicatesReport<'a> {
+    threshold: f64,
+    files_compared: usize,
+    groups: Vec<DuplicateGroup<'a>>,
+}
+
+pub fn run(args: &DuplicatesArgs) -> Result<bool, Box<dyn Error>> {
+    let mut paths = Vec::new();
+    let mut spectra = Vec::new();
+    let mut error_count = 0;
+    let input = crate::inputs::expand_paths(&args.input)?;
+    for path in &input {
+        match SpcFile::from_file(path) {
+            Ok(spc) => {
+                paths.push(path);
+                spectra.push(spc);
+            }
+            Err(e) => {
+                error_count += 1;
+                eprintln!("Error reading {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    let data: Vec<&[f64]> = spectra.iter().map(|s| s.data.as_slice()).collect();
+    let groups = processing::group_similar(&data, args.threshold);
+
+    let report = DuplicatesReport {
+        threshold: args.threshold,
+        files_compared: spectra.len(),
+        groups: groups
+            .iter()
+            .map(|g| DuplicateGroup {
+                files: g.members.iter().map(|&i| paths[i].display().to_string()).collect(),
+                uids: g.members.iter().map(|&i| spectra[i].uid.as_str()).collect(),
+                min_similarity: g.min_similarity,
+                exact: g.exact,
+            })
+            .collect(),
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else if report.groups.is_empty() {
+        println!(
+            "No near-duplicates among {} file(s) (threshold {})",
+            report.files_compared, report.threshold
+        );
+    } else {
+        for (n, group) in report.groups.iter().enumerate() {
+            let kind = if group.exact { "exact" } else { "near" };
+            println!(
+                "Group {} ({} duplicate, min r = {:.6}):",
+                n + 1,
+                kind,
+                group.min_similarity
+            );
+            for file in &group.files {
+                println!("  {}", file);
+            }
+        }
+    }
+
+    Ok(error_count == 0)
+}
@@ -0,0 +1,160 @@
+//! `generate` subcommand: write small synthetic .spc files as test inputs.
+
+use clap::{Args, ValueEnum};
+use spc_converter::parser::{ENCODING_NONE, ENCODING_RLE0, ENCODING_RLE8, ENCODING_ZLIB};
+use spc_converter::spectre::SpcFileBuilder;
+use spc_converter::{import, Calibration};
+use std::error::Error;
+use std::path::PathBuf;
+
+/// Calibration of a typical 785 nm Raman spectrometer (about 800 to 1000 nm).
+const DEFAULT_CALIBRATION: [f64; 4] = [900.0, 100.0, -1.0, 0.1];
+
+#[derive(Args)]
+pub struct GenerateArgs {
+    /// Output .spc file
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// UID (camera serial) to store
+    #[arg(long, default_value = "SYNTH-0001")]
+    uid: String,
+
+    /// Take the intensities (and blank, if present) from a text file (CSV,
+    /// pairs or one value per line) instead of generating them
+    #[arg(long, value_name = "FILE")]
+    data: Option<PathBuf>,
+
+    /// Number of points of a generated spectrum
+    #[arg(long, default_value_t = 1024, value_parser = clap::value_parser!(u64).range(2..))]
+    points: u64,
+
+    /// Seed of the generated peaks and noise
+    #[arg(long, default_value_t = 1)]
+    seed: u64,
+
+    /// Add a generated blank (dark) spectrum
+    #[arg(long)]
+    blank: bool,
+
+    /// Legendre or polynomial calibration file (.json or .toml) instead of
+    /// the default one
+    #[arg(long, value_name = "FILE", conflicts_with = "no_calibration")]
+    calibration: Option<PathBuf>,
+
+    /// Leave out the calibration
+    #[arg(long)]
+    no_calibration: bool,
+
+    /// Leave out the config (laser wavelength, exposure and gain)
+    #[arg(long)]
+    no_config: bool,
+
+    /// Laser wavelength in nm
+    #[arg(long, default_value_t = 785.0)]
+    laser_nm: f64,
+
+    /// Buffer encoding of the container
+    #[arg(long, value_enum, default_value = "none")]
+    encoding: EncodingArg,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum EncodingArg {
+    None,
+    Rle8,
+    Rle0,
+    Zlib,
+}
+
+impl EncodingArg {
+    fn id(self) -> u8 {
+        match self {
+            EncodingArg::None => ENCODING_NONE,
+            EncodingArg::Rle8 => ENCODING_RLE8,
+            EncodingArg::Rle0 => ENCODING_RLE0,
+            EncodingArg::Zlib => ENCODING_ZLIB,
+        }
+    }
+}
+
+pub fn run(args: &GenerateArgs) -> Result<bool, Box<dyn Error>> {
+    let (data, blank) = match args.data {
+        Some(ref path) => {
+            let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            let spc = import::read_text(&text, None).map_err(|e| format!("{}: {}", path.display(), e))?;
+            (spc.data, spc.blank)
+        }
+        None => (synthetic(args.points as usize, args.seed), Vec::new()),
+    };
+    let blank = match args.blank {
+        true if blank.is_empty() => dark(data.len(), args.seed),
+        _ => blank,
+    };
+
+    let mut builder = SpcFileBuilder::new(&args.uid, data).blank(blank);
+    if !args.no_calibration {
+        builder = builder.calibration(match args.calibration {
+            Some(ref path) => Calibration::from_file(path)?,
+            None => Calibration::legendre(DEFAULT_CALIBRATION.to_vec()),
+        });
+    }
+    if !args.no_config {
+        builder = builder.laser_wavelength(args.laser_nm).exposure(1000.0).gain(1.0);
+    }
+    let spc = builder.build();
+    spc.check_storable()?;
+
+    let bytes = spc.to_bytes_encoded(args.encoding.id()).expect("all listed encodings can be written");
+    std::fs::write(&args.output, bytes).map_err(|e| format!("{}: {}", args.output.display(), e))?;
+    Ok(true)
+}
+
+/// Xorshift64* generator, so that a seed always gives the same file.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // Xorshift needs a non-zero state
+        Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    /// Uniform in [0, 1).
+    fn next(&mut self) -> f64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (self.0.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// A Raman-like spectrum: a sloping fluorescence background, a few
+/// Gaussian peaks and noise, in whole counts.
+fn synthetic(points: usize, seed: u64) -> Vec<f64> {
+    let mut rng = Rng::new(seed);
+    let peaks: Vec<(f64, f64, f64)> = (0..5)
+        .map(|_| {
+            let center = rng.next() * points as f64;
+            let width = 2.0 + rng.next() * points as f64 / 100.0;
+            let height = 500.0 + rng.next() * 4500.0;
+            (center, width, height)
+        })
+        .collect();
+    (0..points)
+        .map(|i| {
+            let x = i as f64;
+            let background = 1000.0 + 800.0 * (1.0 - x / points as f64);
+            let signal: f64 = peaks
+                .iter()
+                .map(|&(center, width, height)| height * (-0.5 * ((x - center) / width).powi(2)).exp())
+                .sum();
+            (background + signal + 40.0 * (rng.next() - 0.5)).round()
+        })
+        .collect()
+}
+
+/// A flat dark spectrum with a little noise.
+fn dark(points: usize, seed: u64) -> Vec<f64> {
+    let mut rng = Rng::new(seed.wrapping_add(1));
+    (0..points).map(|_| (100.0 + 10.0 * (rng.next() - 0.5)).round()).collect()
+}
@@ -0,0 +1,167 @@
+//! `info` subcommand: print a file's metadata without converting it.
+
+use clap::Args;
+use serde::Serialize;
+use spc_converter::spectre::Instrument;
+use spc_converter::{calibration, SpcFile};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct InfoArgs {
+    /// Input .spc files, directories or glob patterns
+    #[arg(required = true)]
+    input: Vec<PathBuf>,
+
+    /// Print JSON instead of text
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Serialize)]
+struct FileInfo {
+    file: String,
+    uid: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    acquired: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    operator: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sample: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instrument: Option<Instrument>,
+    points: usize,
+    blank_points: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    calibration: Option<Vec<f64>>,
+    /// Problems found by `calibration::check_calibration`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    calibration_warnings: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    laser_nm: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exposure: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gain: Option<f64>,
+    /// Derivable axes and their (first, last) values
+    axes: BTreeMap<&'static str, (f64, f64)>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<String, String>,
+}
+
+impl FileInfo {
+    fn new(file: String, spc: SpcFile) -> Self {
+        let config = spc.config.as_ref();
+        let mut axes = BTreeMap::new();
+        for (name, axis) in [
+            ("wavelength", &spc.wavelength_axis),
+            ("raman_shift", &spc.raman_shift_axis),
+        ] {
+            if let Some(values) = axis {
+                if let (Some(&first), Some(&last)) = (values.first(), values.last()) {
+                    axes.insert(name, (first, last));
+                }
+            }
+        }
+
+        let calibration_warnings = spc
+            .calibration
+            .as_ref()
+            .map(|c| calibration::check_calibration(c, spc.data.len()))
+            .unwrap_or_default();
+
+        FileInfo {
+            file,
+            uid: spc.uid,
+            acquired: spc.acquired,
+            operator: spc.operator,
+            sample: spc.sample,
+            instrument: spc.instrument,
+            points: spc.data.len(),
+            blank_points: spc.blank.len(),
+            calibration: spc.calibration.map(|c| c.coefficients),
+            calibration_warnings,
+            laser_nm: config.and_then(|c| c.raman_wavelength),
+            exposure: config.and_then(|c| c.exposure),
+            gain: config.and_then(|c| c.gain),
+            axes,
+            metadata: spc.metadata,
+        }
+    }
+}
+
+pub fn run(args: &InfoArgs) -> Result<bool, Box<dyn Error>> {
+    let mut infos = Vec::new();
+    let mut error_count = 0;
+
+    let input = crate::inputs::expand_paths(&args.input)?;
+
+    for path in &input {
+        match super::load_with_sidecar(path) {
+            Ok(spc) => infos.push(FileInfo::new(path.display().to_string(), spc)),
+            Err(e) => {
+                error_count += 1;
+                eprintln!("Error reading {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&infos)?);
+    } else {
+        for (i, info) in infos.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            print_info(info);
+        }
+    }
+
+    Ok(error_count == 0)
+}
+
+fn print_info(info: &FileInfo) {
+    let or_none = |v: Option<f64>| v.map_or("-".to_string(), |v| v.to_string());
+
+    println!("{}", info.file);
+    println!("  UID:          {}", info.uid);
+    for (label, value) in [("Acquired:", &info.acquired), ("Operator:", &info.operator), ("Sample:", &info.sample)] {
+        if let Some(value) = value {
+            println!("  {:<13} {}", label, value);
+        }
+    }
+    if let Some(ref instrument) = info.instrument {
+        let mut parts: Vec<String> = [&instrument.model, &instrument.serial].into_iter().flatten().cloned().collect();
+        if let Some(ref firmware) = instrument.firmware {
+            parts.push(format!("firmware {}", firmware));
+        }
+        if let Some(temperature) = instrument.detector_temperature {
+            parts.push(format!("detector temperature {}", temperature));
+        }
+        println!("  Instrument:   {}", parts.join(", "));
+    }
+    println!("  Points:       {} (blank: {})", info.points, info.blank_points);
+    match info.calibration {
+        Some(ref coefficients) => println!("  Calibration:  {:?}", coefficients),
+        None => println!("  Calibration:  -"),
+    }
+    for warning in &info.calibration_warnings {
+        println!("  Warning:      calibration {}", warning);
+    }
+    println!("  Laser:        {} nm", or_none(info.laser_nm));
+    println!("  Exposure:     {}", or_none(info.exposure));
+    println!("  Gain:         {}", or_none(info.gain));
+    if info.axes.is_empty() {
+        println!("  Axes:         pixel index only");
+    }
+    if let Some((first, last)) = info.axes.get("wavelength") {
+        println!("  Wavelength:   {:.2} .. {:.2} nm", first, last);
+    }
+    if let Some((first, last)) = info.axes.get("raman_shift") {
+        println!("  Raman shift:  {:.2} .. {:.2} cm⁻¹", first, last);
+    }
+    for (key, value) in &info.metadata {
+        println!("  {}: {}", key, value);
+    }
+}
@@ -0,0 +1,30 @@
+//! `mangen` subcommand (hidden): write man pages for packagers.
+//!
+//! Like the completions, the pages are rendered from the CLI definition, by
+//! clap_mangen: `spc-convert(1)` and one `spc-convert-<command>(1)` per
+//! subcommand.
+
+use clap::{Args, CommandFactory};
+use std::error::Error;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct MangenArgs {
+    /// Write spc-convert.1 and a page per subcommand into this directory
+    /// instead of printing spc-convert.1 to stdout
+    #[arg(short, long, value_name = "DIR")]
+    output: Option<PathBuf>,
+}
+
+pub fn run(args: &MangenArgs) -> Result<bool, Box<dyn Error>> {
+    let command = crate::Cli::command();
+    match args.output {
+        Some(ref dir) => {
+            std::fs::create_dir_all(dir)?;
+            clap_mangen::generate_to(command, dir)?;
+        }
+        None => clap_mangen::Man::new(command).render(&mut io::stdout())?,
+    }
+    Ok(true)
+}
@@ -0,0 +1,131 @@
+//! `matrix` subcommand: a sample × point matrix for PCA/PLS in external tools.
+
+use clap::{Args, ValueEnum};
+use spc_converter::processing::{self, MatrixOptions, SpectraMatrix};
+use spc_converter::{output, SpcFile};
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct MatrixArgs {
+    /// Input .spc files, directories, glob patterns or archives
+    #[arg(required = true)]
+    input: Vec<PathBuf>,
+
+    /// Matrix file (defaults to stdout for CSV; required otherwise)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Matrix format
+    #[arg(short, long, value_enum, default_value = "csv")]
+    format: MatrixFormat,
+
+    /// Axis to align the spectra on [default: the first file's]
+    #[arg(long, value_enum)]
+    axis: Option<crate::AxisArg>,
+
+    /// Lowest x value kept
+    #[arg(long, allow_hyphen_values = true)]
+    xmin: Option<f64>,
+
+    /// Highest x value kept
+    #[arg(long, allow_hyphen_values = true)]
+    xmax: Option<f64>,
+
+    /// Resample onto an even grid from --xmin to --xmax with this spacing
+    /// [default: the first file's x values]
+    #[arg(long, requires_all = ["xmin", "xmax"])]
+    step: Option<f64>,
+
+    /// Subtract each file's blank
+    #[arg(long)]
+    subtract_blank: bool,
+
+    /// Subtract a baseline with default parameters
+    #[arg(long, value_enum)]
+    baseline: Option<crate::BaselineArg>,
+
+    /// Normalize each spectrum on the shared axis
+    #[arg(long, value_enum)]
+    normalize: Option<crate::NormalizeArg>,
+
+    /// Subtract the mean spectrum from every row
+    #[arg(long)]
+    mean_center: bool,
+}
+
+#[derive(Clone, ValueEnum)]
+enum MatrixFormat {
+    /// A heading row of x values, then one row per file
+    Csv,
+    /// NumPy array of the intensities, shaped (files, points)
+    Npy,
+    /// NumPy archive of the intensities (X), axis (x) and file names (samples)
+    Npz,
+}
+
+/// Files are rows in input order, named by their path below the input
+/// directory without the extension.
+pub fn run(args: &MatrixArgs) -> Result<bool, Box<dyn Error>> {
+    let mut spectra: Vec<(String, SpcFile)> = Vec::new();
+    let mut error_count = 0;
+    for input in crate::inputs::expand(&args.input)? {
+        let spc = match input.archived {
+            Some(ref archived) => archived.read().map_err(Into::into).and_then(|mut data| SpcFile::from_bytes_in_place(&mut data)),
+            None => super::load_with_sidecar(&input.path),
+        };
+        match spc {
+            Ok(spc) => spectra.push((input.relative.with_extension("").display().to_string(), spc)),
+            Err(e) => {
+                error_count += 1;
+                eprintln!("Error reading {}: {}", input.path.display(), e);
+            }
+        }
+    }
+
+    let grid = match (args.step, args.xmin, args.xmax) {
+        (Some(step), Some(min), Some(max)) => {
+            if step.is_nan() || step <= 0.0 || max < min {
+                return Err("--step must be positive and --xmax at least --xmin".into());
+            }
+            let points = ((max - min) / step + 1e-9).floor() as usize + 1;
+            Some((0..points).map(|i| min + i as f64 * step).collect())
+        }
+        _ => None,
+    };
+    let options = MatrixOptions {
+        axis: args.axis.map(Into::into),
+        grid,
+        x_min: args.xmin,
+        x_max: args.xmax,
+        subtract_blank: args.subtract_blank,
+        smooth: None,
+        baseline: args.baseline.map(|method| match method {
+            crate::BaselineArg::Als => processing::BaselineMethod::als(),
+            crate::BaselineArg::Polynomial => processing::BaselineMethod::polynomial(),
+        }),
+        normalize: args.normalize.map(Into::into),
+        mean_center: args.mean_center,
+    };
+    let named: Vec<(&str, &SpcFile)> = spectra.iter().map(|(name, spc)| (name.as_str(), spc)).collect();
+    let matrix = SpectraMatrix::from_named(&named, &options)?;
+
+    let writer: Box<dyn Write> = match (&args.output, &args.format) {
+        (Some(path), _) => Box::new(File::create(path)?),
+        (None, MatrixFormat::Csv) => Box::new(io::stdout()),
+        (None, _) => return Err("NumPy output needs -o <FILE>".into()),
+    };
+    let mut writer = BufWriter::new(writer);
+    match args.format {
+        MatrixFormat::Csv => output::write_matrix_csv(&matrix, &output::CsvOptions::default(), &mut writer)?,
+        MatrixFormat::Npy => output::write_matrix_npy(&matrix, &mut writer)?,
+        MatrixFormat::Npz => {
+            output::write_matrix_npz(&matrix, &mut writer)?;
+        }
+    }
+    writer.flush()?;
+
+    Ok(error_count == 0)
+}
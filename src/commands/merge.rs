@@ -0,0 +1,148 @@
+//! `merge` subcommand: many files into one dataset with a shared x-axis.
+
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+use spc_converter::{output, processing};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct MergeArgs {
+    /// Input .spc files, directories or glob patterns
+    #[arg(required = true)]
+    input: Vec<PathBuf>,
+
+    /// Dataset file (defaults to stdout)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Dataset format
+    #[arg(short, long, value_enum, default_value = "csv")]
+    format: MergeFormat,
+}
+
+#[derive(Clone, ValueEnum)]
+enum MergeFormat {
+    /// One x column, then one intensity column per file
+    Csv,
+    /// Shared x array plus one series per file
+    Json,
+}
+
+#[derive(Serialize)]
+struct Dataset {
+    axis: &'static str,
+    unit: &'static str,
+    x: Vec<f64>,
+    spectra: Vec<Series>,
+}
+
+#[derive(Serialize)]
+struct Series {
+    name: String,
+    file: String,
+    uid: String,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<String, String>,
+    /// Intensities on the shared axis (null outside this file's range)
+    intensity: Vec<f64>,
+}
+
+/// The first file's best axis is shared; other files are linearly
+/// interpolated onto it when their axis differs.
+pub fn run(args: &MergeArgs) -> Result<bool, Box<dyn Error>> {
+    let mut dataset: Option<Dataset> = None;
+    let mut error_count = 0;
+
+    for input in crate::inputs::expand(&args.input)? {
+        let path = &input.path;
+        let spc = match super::load_with_sidecar(path) {
+            Ok(spc) => spc,
+            Err(e) => {
+                error_count += 1;
+                eprintln!("Error reading {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        let axis = output::select_best_axis(&spc);
+
+        let dataset = dataset.get_or_insert_with(|| Dataset {
+            axis: axis.name,
+            unit: axis.unit,
+            x: axis.values.to_vec(),
+            spectra: Vec::new(),
+        });
+        if axis.name != dataset.axis {
+            error_count += 1;
+            eprintln!(
+                "Error merging {}: axis is {}, dataset uses {}",
+                path.display(),
+                axis.name,
+                dataset.axis
+            );
+            continue;
+        }
+
+        let intensity = if axis.values == dataset.x {
+            spc.data
+        } else {
+            processing::resample(&axis.values, &spc.data, &dataset.x)
+        };
+        dataset.spectra.push(Series {
+            name: input.relative.with_extension("").display().to_string(),
+            file: path.display().to_string(),
+            uid: spc.uid,
+            metadata: spc.metadata,
+            intensity,
+        });
+    }
+
+    let Some(dataset) = dataset else {
+        return Err("no files could be read".into());
+    };
+
+    let writer: Box<dyn Write> = match args.output {
+        Some(ref path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout()),
+    };
+    let mut writer = BufWriter::new(writer);
+
+    match args.format {
+        MergeFormat::Json => {
+            serde_json::to_writer_pretty(&mut writer, &dataset)?;
+            writeln!(writer)?;
+        }
+        MergeFormat::Csv => write_csv(&dataset, &mut writer)?,
+    }
+    writer.flush()?;
+
+    Ok(error_count == 0)
+}
+
+/// Header `x,<name>,...`; values outside a file's range are left empty.
+fn write_csv<W: Write>(dataset: &Dataset, mut writer: W) -> io::Result<()> {
+    let x_name = if dataset.unit.is_empty() {
+        dataset.axis.to_string()
+    } else {
+        format!("{} ({})", dataset.axis, dataset.unit)
+    };
+    let mut header = vec![super::csv_field(&x_name)];
+    header.extend(dataset.spectra.iter().map(|s| super::csv_field(&s.name)));
+    writeln!(writer, "{}", header.join(","))?;
+
+    for (i, x) in dataset.x.iter().enumerate() {
+        let mut fields = vec![x.to_string()];
+        for series in &dataset.spectra {
+            match series.intensity.get(i) {
+                Some(v) if !v.is_nan() => fields.push(v.to_string()),
+                _ => fields.push(String::new()),
+            }
+        }
+        writeln!(writer, "{}", fields.join(","))?;
+    }
+
+    Ok(())
+}
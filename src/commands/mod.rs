@@ -0,0 +1,120 @@
+//! CLI subcommands.
+//!
+//! Each subcommand returns `Ok(true)` on success, `Ok(false)` when it ran but
+//! the result should fail the process (exit code 1), and `Err` on errors
+//! (exit code 2).
+
+use clap::Subcommand;
+use spc_converter::parser::ParseError;
+use spc_converter::spectre::sidecar;
+use spc_converter::SpcFile;
+use std::error::Error;
+use std::path::Path;
+
+mod average;
+mod calibrate;
+mod compare;
+mod completions;
+mod diff;
+mod dump;
+mod duplicates;
+mod generate;
+mod info;
+mod mangen;
+mod matrix;
+mod merge;
+mod peaks;
+mod qc;
+mod roi;
+mod schema;
+mod series;
+mod stats;
+mod validate;
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Average replicate acquisitions into one spectrum
+    Average(average::AverageArgs),
+    /// Fit calibration coefficients to reference lines at known pixels
+    Calibrate(calibrate::CalibrateArgs),
+    /// Score how closely a sample matches a reference (hit quality index)
+    Compare(compare::CompareArgs),
+    /// Print a shell completion script (bash, zsh, fish, powershell or elvish)
+    Completions(completions::CompletionsArgs),
+    /// Compare two files (exit code 1 if they differ)
+    Diff(diff::DiffArgs),
+    /// Print the raw StorageObject tree of a file (diagnostics)
+    Dump(dump::DumpArgs),
+    /// Report groups of near-identical spectra in a batch
+    Duplicates(duplicates::DuplicatesArgs),
+    /// Write a small synthetic .spc file (test inputs without proprietary samples)
+    Generate(generate::GenerateArgs),
+    /// Print a file's metadata without converting it
+    Info(info::InfoArgs),
+    /// Write man pages generated from the CLI definition (for packagers)
+    #[command(hide = true)]
+    Mangen(mangen::MangenArgs),
+    /// Build a sample × point matrix (CSV or NumPy) for PCA/PLS
+    Matrix(matrix::MatrixArgs),
+    /// Combine files into one dataset with a shared x-axis
+    Merge(merge::MergeArgs),
+    /// Detect peaks and print a peak table (position, intensity, FWHM, prominence)
+    Peaks(peaks::PeaksArgs),
+    /// Check files against quality-control rules (pass/fail per file)
+    Qc(qc::QcArgs),
+    /// Report region-of-interest areas, maxima and ratios across a batch
+    Roi(roi::RoiArgs),
+    /// Print the JSON Schema of the JSON output
+    Schema(schema::SchemaArgs),
+    /// Export a kinetic session as a time × x matrix or waterfall plot
+    Series(series::SeriesArgs),
+    /// Print intensity statistics and axis coverage per file
+    Stats(stats::StatsArgs),
+    /// Check file integrity and list every structural problem found
+    Validate(validate::ValidateArgs),
+}
+
+impl Command {
+    /// Run the subcommand.
+    pub fn run(&self) -> Result<bool, Box<dyn Error>> {
+        match self {
+            Command::Average(args) => average::run(args),
+            Command::Calibrate(args) => calibrate::run(args),
+            Command::Compare(args) => compare::run(args),
+            Command::Completions(args) => completions::run(args),
+            Command::Duplicates(args) => duplicates::run(args),
+            Command::Diff(args) => diff::run(args),
+            Command::Dump(args) => dump::run(args),
+            Command::Generate(args) => generate::run(args),
+            Command::Info(args) => info::run(args),
+            Command::Mangen(args) => mangen::run(args),
+            Command::Matrix(args) => matrix::run(args),
+            Command::Merge(args) => merge::run(args),
+            Command::Peaks(args) => peaks::run(args),
+            Command::Qc(args) => qc::run(args),
+            Command::Roi(args) => roi::run(args),
+            Command::Schema(args) => schema::run(args),
+            Command::Series(args) => series::run(args),
+            Command::Stats(args) => stats::run(args),
+            Command::Validate(args) => validate::run(args),
+        }
+    }
+}
+
+/// Parse a file and merge its sidecar metadata, if any.
+fn load_with_sidecar(path: &Path) -> Result<SpcFile, ParseError> {
+    let mut spc = SpcFile::from_file(path)?;
+    if let Some(sidecar_path) = sidecar::find_sidecar(path) {
+        spc.extend_metadata(sidecar::read_sidecar(&sidecar_path)?);
+    }
+    Ok(spc)
+}
+
+/// Quote a CSV field if it contains a delimiter, quote or newline.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
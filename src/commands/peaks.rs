@@ -0,0 +1,198 @@
+//! `peaks` subcommand: peak table per file.
+
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+use spc_converter::output;
+use spc_converter::processing::{self, LineShape, Peak, PeakFit, PeakOptions};
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct PeaksArgs {
+    /// Input .spc files, directories or glob patterns
+    #[arg(required = true)]
+    input: Vec<PathBuf>,
+
+    /// Minimum peak intensity
+    #[arg(long)]
+    min_height: Option<f64>,
+
+    /// Minimum peak prominence in intensity units [default: 5x noise level]
+    #[arg(long)]
+    min_prominence: Option<f64>,
+
+    /// Minimum FWHM in axis units
+    #[arg(long)]
+    min_width: Option<f64>,
+
+    /// Maximum FWHM in axis units
+    #[arg(long)]
+    max_width: Option<f64>,
+
+    /// Minimum distance between peaks in axis units (the more intense one is kept)
+    #[arg(long)]
+    min_distance: Option<f64>,
+
+    /// Minimum drop from a peak to its neighbouring points in intensity units
+    /// (scipy's threshold)
+    #[arg(long)]
+    threshold: Option<f64>,
+
+    /// Fit each peak with this line shape and add the fitted parameters
+    #[arg(long, value_enum)]
+    fit: Option<FitShape>,
+
+    /// Peak table file (defaults to stdout)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Peak table format
+    #[arg(short, long, value_enum, default_value = "csv")]
+    format: PeaksFormat,
+}
+
+#[derive(Clone, ValueEnum)]
+enum PeaksFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum FitShape {
+    Gaussian,
+    Lorentzian,
+    /// Pseudo-Voigt
+    Voigt,
+}
+
+impl From<FitShape> for LineShape {
+    fn from(shape: FitShape) -> Self {
+        match shape {
+            FitShape::Gaussian => LineShape::Gaussian,
+            FitShape::Lorentzian => LineShape::Lorentzian,
+            FitShape::Voigt => LineShape::Voigt,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct FilePeaks {
+    file: String,
+    uid: String,
+    axis: &'static str,
+    unit: &'static str,
+    peaks: Vec<PeakRow>,
+}
+
+#[derive(Serialize)]
+struct PeakRow {
+    #[serde(flatten)]
+    peak: Peak,
+    /// Fitted parameters (null if the fit failed)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fit: Option<Option<PeakFit>>,
+}
+
+pub fn run(args: &PeaksArgs) -> Result<bool, Box<dyn Error>> {
+    let options = PeakOptions {
+        min_height: args.min_height,
+        min_prominence: args.min_prominence,
+        min_width: args.min_width,
+        max_width: args.max_width,
+        min_distance: args.min_distance,
+        threshold: args.threshold,
+    };
+    let mut rows = Vec::new();
+    let mut error_count = 0;
+
+    let input = crate::inputs::expand_paths(&args.input)?;
+    for path in &input {
+        match super::load_with_sidecar(path) {
+            Ok(spc) => {
+                let axis = output::select_best_axis(&spc);
+                let peaks = processing::find_peaks(&spc, options.clone())
+                    .into_iter()
+                    .map(|peak| PeakRow {
+                        fit: args.fit.map(|shape| {
+                            processing::fit_peak(&axis.values, &spc.data, &peak, shape.into(), processing::DEFAULT_FIT_WINDOW)
+                        }),
+                        peak,
+                    })
+                    .collect();
+                rows.push(FilePeaks {
+                    file: path.display().to_string(),
+                    peaks,
+                    uid: spc.uid.clone(),
+                    axis: axis.name,
+                    unit: axis.unit,
+                });
+            }
+            Err(e) => {
+                error_count += 1;
+                eprintln!("Error reading {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    let writer: Box<dyn Write> = match args.output {
+        Some(ref path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout()),
+    };
+    let mut writer = BufWriter::new(writer);
+
+    match args.format {
+        PeaksFormat::Json => {
+            serde_json::to_writer_pretty(&mut writer, &rows)?;
+            writeln!(writer)?;
+        }
+        PeaksFormat::Csv => write_csv(&rows, args.fit.is_some(), &mut writer)?,
+    }
+    writer.flush()?;
+
+    Ok(error_count == 0)
+}
+
+/// One row per peak; fitted parameters are empty where a fit failed.
+fn write_csv<W: Write>(rows: &[FilePeaks], fitted: bool, mut writer: W) -> io::Result<()> {
+    write!(writer, "file,uid,axis,unit,position,intensity,fwhm,prominence")?;
+    if fitted {
+        write!(writer, ",fit_center,fit_amplitude,fit_fwhm,fit_offset,fit_eta,fit_rms_residual,fit_r_squared")?;
+    }
+    writeln!(writer)?;
+
+    for row in rows {
+        for PeakRow { peak, fit } in &row.peaks {
+            write!(
+                writer,
+                "{},{},{},{},{},{},{},{}",
+                super::csv_field(&row.file),
+                super::csv_field(&row.uid),
+                row.axis,
+                row.unit,
+                peak.position,
+                peak.intensity,
+                peak.fwhm,
+                peak.prominence
+            )?;
+            match fit {
+                Some(Some(f)) => write!(
+                    writer,
+                    ",{},{},{},{},{},{},{}",
+                    f.center,
+                    f.amplitude,
+                    f.fwhm,
+                    f.offset,
+                    f.eta.map(|e| e.to_string()).unwrap_or_default(),
+                    f.rms_residual,
+                    f.r_squared
+                )?,
+                Some(None) => write!(writer, ",,,,,,,")?,
+                None => {}
+            }
+            writeln!(writer)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,150 @@
+//! `qc` subcommand: pass/fail quality gate per file.
+
+use clap::Args;
+use serde::Serialize;
+use spc_converter::qc::{QcReport, QcRules};
+use spc_converter::SpcFile;
+use std::error::Error;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct QcArgs {
+    /// Input .spc files, directories or glob patterns
+    #[arg(required = true)]
+    input: Vec<PathBuf>,
+
+    /// TOML file with QC rules (flags below override it)
+    #[arg(long)]
+    rules: Option<PathBuf>,
+
+    /// Minimum signal-to-noise ratio
+    #[arg(long)]
+    min_snr: Option<f64>,
+
+    /// Maximum number of saturated pixels (0 = no saturation allowed)
+    #[arg(long)]
+    max_saturated: Option<usize>,
+
+    /// Intensity at which a pixel counts as saturated [default: 65535]
+    #[arg(long)]
+    saturation_level: Option<f64>,
+
+    /// Expected laser wavelength in nm
+    #[arg(long)]
+    expected_laser: Option<f64>,
+
+    /// Allowed laser wavelength deviation in nm [default: 0.5]
+    #[arg(long)]
+    laser_tolerance: Option<f64>,
+
+    /// Minimum number of data points
+    #[arg(long)]
+    min_points: Option<usize>,
+
+    /// Maximum number of data points
+    #[arg(long)]
+    max_points: Option<usize>,
+
+    /// Require a calibration block
+    #[arg(long)]
+    require_calibration: bool,
+
+    /// Write the JSON findings report to this file ("-" for stdout)
+    #[arg(long)]
+    report: Option<PathBuf>,
+}
+
+#[derive(Serialize)]
+struct FileResult {
+    file: String,
+    passed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    report: Option<QcReport>,
+}
+
+#[derive(Serialize)]
+struct QcSummary {
+    rules: QcRules,
+    passed: usize,
+    failed: usize,
+    files: Vec<FileResult>,
+}
+
+pub fn run(args: &QcArgs) -> Result<bool, Box<dyn Error>> {
+    let mut rules: QcRules = match args.rules {
+        Some(ref path) => toml::from_str(&std::fs::read_to_string(path)?)?,
+        None => QcRules::default(),
+    };
+    rules.min_snr = args.min_snr.or(rules.min_snr);
+    rules.max_saturated = args.max_saturated.or(rules.max_saturated);
+    rules.saturation_level = args.saturation_level.or(rules.saturation_level);
+    rules.expected_laser_nm = args.expected_laser.or(rules.expected_laser_nm);
+    rules.laser_tolerance_nm = args.laser_tolerance.or(rules.laser_tolerance_nm);
+    rules.min_points = args.min_points.or(rules.min_points);
+    rules.max_points = args.max_points.or(rules.max_points);
+    rules.require_calibration |= args.require_calibration;
+
+    // Keep stdout clean when the JSON report goes there
+    let report_to_stdout = args.report.as_ref().is_some_and(|p| p.as_os_str() == "-");
+
+    let mut files = Vec::new();
+    let input = crate::inputs::expand_paths(&args.input)?;
+    for path in &input {
+        let result = match SpcFile::from_file(path) {
+            Ok(spc) => {
+                let report = rules.evaluate(&spc);
+                FileResult {
+                    file: path.display().to_string(),
+                    passed: report.passed,
+                    error: None,
+                    report: Some(report),
+                }
+            }
+            Err(e) => FileResult {
+                file: path.display().to_string(),
+                passed: false,
+                error: Some(e.to_string()),
+                report: None,
+            },
+        };
+
+        if !report_to_stdout {
+            print_result(&result);
+        }
+        files.push(result);
+    }
+
+    let passed = files.iter().filter(|f| f.passed).count();
+    let summary = QcSummary {
+        rules,
+        passed,
+        failed: files.len() - passed,
+        files,
+    };
+
+    if let Some(ref path) = args.report {
+        let json = serde_json::to_string_pretty(&summary)?;
+        if report_to_stdout {
+            println!("{}", json);
+        } else {
+            std::fs::write(path, json)?;
+        }
+    }
+
+    Ok(summary.failed == 0)
+}
+
+fn print_result(result: &FileResult) {
+    let status = if result.passed { "PASS" } else { "FAIL" };
+    println!("{}  {}", status, result.file);
+    if let Some(ref error) = result.error {
+        println!("      error: {}", error);
+    }
+    for finding in result.report.iter().flat_map(|r| &r.findings) {
+        if !finding.passed {
+            println!("      {}: {}", finding.rule, finding.message);
+        }
+    }
+}
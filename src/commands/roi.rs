@@ -0,0 +1,121 @@
+//! `roi` subcommand: consolidated region-of-interest report across a batch.
+
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+use spc_converter::processing::{RoiReport, RoiSet};
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct RoiArgs {
+    /// Input .spc files, directories or glob patterns
+    #[arg(required = true)]
+    input: Vec<PathBuf>,
+
+    /// TOML file defining the regions (`[[roi]]`) and ratios (`[[ratio]]`)
+    #[arg(short, long)]
+    regions: PathBuf,
+
+    /// Report file (defaults to stdout)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Report format
+    #[arg(short, long, value_enum, default_value = "csv")]
+    format: RoiFormat,
+}
+
+#[derive(Clone, ValueEnum)]
+enum RoiFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Serialize)]
+struct FileRoi {
+    file: String,
+    uid: String,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<String, String>,
+    #[serde(flatten)]
+    report: RoiReport,
+}
+
+pub fn run(args: &RoiArgs) -> Result<bool, Box<dyn Error>> {
+    let set: RoiSet = toml::from_str(&std::fs::read_to_string(&args.regions)?)?;
+    set.validate()?;
+
+    let mut rows = Vec::new();
+    let mut error_count = 0;
+    let input = crate::inputs::expand_paths(&args.input)?;
+    for path in &input {
+        match super::load_with_sidecar(path) {
+            Ok(spc) => rows.push(FileRoi {
+                file: path.display().to_string(),
+                report: set.evaluate(&spc),
+                uid: spc.uid,
+                metadata: spc.metadata,
+            }),
+            Err(e) => {
+                error_count += 1;
+                eprintln!("Error reading {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    let writer: Box<dyn Write> = match args.output {
+        Some(ref path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout()),
+    };
+    let mut writer = BufWriter::new(writer);
+
+    match args.format {
+        RoiFormat::Json => {
+            serde_json::to_writer_pretty(&mut writer, &rows)?;
+            writeln!(writer)?;
+        }
+        RoiFormat::Csv => write_csv(&set, &rows, &mut writer)?,
+    }
+    writer.flush()?;
+
+    Ok(error_count == 0)
+}
+
+/// One row per file: identity, metadata, then area/max/position per ROI and the ratios.
+fn write_csv<W: Write>(set: &RoiSet, rows: &[FileRoi], mut writer: W) -> io::Result<()> {
+    let meta_keys: BTreeSet<&str> = rows
+        .iter()
+        .flat_map(|r| r.metadata.keys().map(String::as_str))
+        .collect();
+
+    let mut header = vec!["file".to_string(), "uid".to_string(), "axis".to_string()];
+    header.extend(meta_keys.iter().map(|k| k.to_string()));
+    for roi in &set.roi {
+        header.push(format!("{}_area", roi.name));
+        header.push(format!("{}_max", roi.name));
+        header.push(format!("{}_max_position", roi.name));
+    }
+    header.extend(set.ratio.iter().map(|r| r.name()));
+    writeln!(writer, "{}", header.iter().map(|h| super::csv_field(h)).collect::<Vec<_>>().join(","))?;
+
+    for row in rows {
+        let mut fields = vec![super::csv_field(&row.file), super::csv_field(&row.uid), row.report.axis.to_string()];
+        for key in &meta_keys {
+            fields.push(super::csv_field(row.metadata.get(*key).map(String::as_str).unwrap_or("")));
+        }
+        for region in &row.report.regions {
+            fields.push(region.area.to_string());
+            fields.push(region.max_intensity.to_string());
+            fields.push(region.max_position.to_string());
+        }
+        for ratio in &row.report.ratios {
+            fields.push(ratio.value.to_string());
+        }
+        writeln!(writer, "{}", fields.join(","))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,21 @@
+//! `schema` subcommand: print the JSON Schema of the JSON output.
+
+use clap::Args;
+use spc_converter::output;
+use std::error::Error;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct SchemaArgs {
+    /// Write the schema to this file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+pub fn run(args: &SchemaArgs) -> Result<bool, Box<dyn Error>> {
+    match args.output {
+        Some(ref path) => std::fs::write(path, output::JSON_SCHEMA)?,
+        None => print!("{}", output::JSON_SCHEMA),
+    }
+    Ok(true)
+}
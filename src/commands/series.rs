@@ -0,0 +1,98 @@
+//! `series` subcommand: a kinetic session as a time × x matrix or waterfall plot.
+
+use clap::{Args, ValueEnum};
+use spc_converter::formats::{ReadOptions, Registry};
+use spc_converter::spectre::{sidecar, TimeSeries};
+use spc_converter::{output, SpcFile};
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Args)]
+pub struct SeriesArgs {
+    /// A file holding several spectra, or one file per time point
+    /// (files, directories or glob patterns)
+    #[arg(required = true)]
+    input: Vec<PathBuf>,
+
+    /// Output file (defaults to stdout for CSV; required otherwise)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value = "csv")]
+    format: SeriesFormat,
+
+    /// Plot width in pixels
+    #[arg(long, default_value = "1200")]
+    plot_width: u32,
+
+    /// Plot height in pixels
+    #[arg(long, default_value = "800")]
+    plot_height: u32,
+}
+
+#[derive(Clone, ValueEnum)]
+enum SeriesFormat {
+    /// Time column, then one column per x value
+    Csv,
+    /// Waterfall plot, one offset line per spectrum
+    Png,
+    /// time, x and intensity [time, x] datasets
+    #[cfg(feature = "hdf5")]
+    Hdf5,
+}
+
+/// Spectra are ordered by their `time_s` metadata (from the file or a
+/// sidecar), else their `sequence`, else input order.
+pub fn run(args: &SeriesArgs) -> Result<bool, Box<dyn Error>> {
+    let registry = Registry::default();
+    let mut spectra: Vec<SpcFile> = Vec::new();
+    for path in crate::inputs::expand_paths(&args.input)? {
+        spectra.extend(read_spectra(&registry, &path).map_err(|e| format!("{}: {}", path.display(), e))?);
+    }
+    let series = TimeSeries::from_spectra(&spectra)?;
+
+    match args.format {
+        SeriesFormat::Csv => {
+            let writer: Box<dyn Write> = match args.output {
+                Some(ref path) => Box::new(File::create(path)?),
+                None => Box::new(io::stdout()),
+            };
+            let mut writer = BufWriter::new(writer);
+            output::write_series_csv(&series, &mut writer)?;
+            writer.flush()?;
+        }
+        SeriesFormat::Png => {
+            let path = args.output.as_ref().ok_or("a plot needs -o <FILE>")?;
+            let options = output::PlotOptions {
+                width: args.plot_width,
+                height: args.plot_height,
+                ..Default::default()
+            };
+            output::write_waterfall(&series, path, &options)?;
+        }
+        #[cfg(feature = "hdf5")]
+        SeriesFormat::Hdf5 => {
+            let path = args.output.as_ref().ok_or("HDF5 output needs -o <FILE>")?;
+            output::write_series_hdf5(&series, path)?;
+        }
+    }
+
+    Ok(true)
+}
+
+/// Every spectrum in a file, with its sidecar metadata.
+fn read_spectra(registry: &Registry, path: &Path) -> Result<Vec<SpcFile>, Box<dyn Error>> {
+    let mut bytes = std::fs::read(path)?;
+    let reader = registry.reader_for(path, &bytes).ok_or("unrecognized input format")?;
+    let (mut spectra, _) = reader.read(&mut bytes, &ReadOptions::default())?;
+    if let Some(sidecar_path) = sidecar::find_sidecar(path) {
+        let metadata = sidecar::read_sidecar(&sidecar_path)?;
+        for spc in &mut spectra {
+            spc.extend_metadata(metadata.clone());
+        }
+    }
+    Ok(spectra)
+}
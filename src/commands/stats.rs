@@ -0,0 +1,85 @@
+//! `stats` subcommand: per-file intensity statistics.
+
+use clap::Args;
+use serde::Serialize;
+use spc_converter::processing::{self, SpectrumStats};
+use spc_converter::SpcFile;
+use std::error::Error;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct StatsArgs {
+    /// Input .spc files, directories or glob patterns
+    #[arg(required = true)]
+    input: Vec<PathBuf>,
+
+    /// Intensity at which a pixel counts as saturated
+    #[arg(long, default_value_t = processing::DEFAULT_SATURATION_LEVEL)]
+    saturation_level: f64,
+
+    /// Print JSON instead of a table
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Serialize)]
+struct FileStats {
+    file: String,
+    uid: String,
+    #[serde(flatten)]
+    stats: SpectrumStats,
+}
+
+pub fn run(args: &StatsArgs) -> Result<bool, Box<dyn Error>> {
+    let mut rows = Vec::new();
+    let mut error_count = 0;
+
+    let input = crate::inputs::expand_paths(&args.input)?;
+    for path in &input {
+        match SpcFile::from_file(path) {
+            Ok(spc) => rows.push(FileStats {
+                file: path.display().to_string(),
+                stats: processing::summarize(&spc, args.saturation_level),
+                uid: spc.uid,
+            }),
+            Err(e) => {
+                error_count += 1;
+                eprintln!("Error reading {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&rows)?);
+    } else {
+        print_table(&rows);
+    }
+
+    Ok(error_count == 0)
+}
+
+fn print_table(rows: &[FileStats]) {
+    let width = rows.iter().map(|r| r.file.len()).max().unwrap_or(0).max(4);
+    println!(
+        "{:<width$}  {:>6}  {:>10}  {:>10}  {:>10}  {:>8}  {:>8}  {:>9}  {:>5}  axis",
+        "file", "points", "min", "max", "mean", "noise", "snr", "dyn.range", "sat."
+    );
+    for row in rows {
+        let s = &row.stats;
+        println!(
+            "{:<width$}  {:>6}  {:>10.2}  {:>10.2}  {:>10.2}  {:>8.3}  {:>8.1}  {:>9.1}  {:>5}  {} {:.1}..{:.1}",
+            row.file,
+            s.points,
+            s.min,
+            s.max,
+            s.mean,
+            s.noise,
+            s.snr,
+            s.dynamic_range,
+            s.saturated,
+            s.axis,
+            s.axis_min,
+            s.axis_max
+        );
+    }
+}
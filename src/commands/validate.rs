@@ -0,0 +1,79 @@
+//! `validate` subcommand: structural integrity check per file.
+
+use clap::Args;
+use serde::Serialize;
+use spc_converter::validate::{self, Severity, ValidationReport};
+use std::error::Error;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct ValidateArgs {
+    /// Input .spc files, directories or glob patterns
+    #[arg(required = true)]
+    input: Vec<PathBuf>,
+
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+
+    /// Treat warnings as failures
+    #[arg(long)]
+    strict: bool,
+
+    /// Maximum decompressed size of a file in MiB; larger files fail
+    /// [default: 1024]
+    #[arg(long, value_name = "MIB", value_parser = clap::value_parser!(u64).range(1..=1 << 20))]
+    max_decoded_mib: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct FileValidation {
+    file: String,
+    #[serde(flatten)]
+    report: ValidationReport,
+}
+
+pub fn run(args: &ValidateArgs) -> Result<bool, Box<dyn Error>> {
+    let mut files = Vec::new();
+
+    let limits = crate::decode_limits(args.max_decoded_mib);
+    let input = crate::inputs::expand_paths(&args.input)?;
+    for path in &input {
+        let mut report = match std::fs::read(path) {
+            Ok(bytes) => validate::validate_with(&bytes, &limits),
+            Err(e) => ValidationReport {
+                valid: false,
+                problems: vec![validate::Problem {
+                    severity: Severity::Error,
+                    location: "file".to_string(),
+                    message: e.to_string(),
+                }],
+            },
+        };
+        if args.strict && !report.problems.is_empty() {
+            report.valid = false;
+        }
+        files.push(FileValidation {
+            file: path.display().to_string(),
+            report,
+        });
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&files)?);
+    } else {
+        for file in &files {
+            let status = if file.report.valid { "OK  " } else { "FAIL" };
+            println!("{}  {}", status, file.file);
+            for problem in &file.report.problems {
+                let severity = match problem.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                };
+                println!("      {} [{}]: {}", severity, problem.location, problem.message);
+            }
+        }
+    }
+
+    Ok(files.iter().all(|f| f.report.valid))
+}
@@ -0,0 +1,256 @@
+//! C ABI for acquisition frontends (LabVIEW, C#, C) that call the parser
+//! directly. See `include/spc_converter.h` for the declarations.
+//!
+//! A file is parsed into an opaque handle with [`spc_parse_file`], read
+//! with the accessors and released with [`spc_free`]. Arrays returned by
+//! the accessors belong to the handle and stay valid until it is freed.
+//! Functions that fail return NULL (or NaN) and leave a message for
+//! [`spc_last_error`].
+
+use crate::formats::{ReadOptions, Registry};
+use crate::output;
+use crate::SpcFile;
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::ptr;
+
+/// A parsed spectrum, owned by the caller until [`spc_free`].
+pub struct SpcHandle {
+    spc: SpcFile,
+    uid: CString,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(message: impl Into<String>) {
+    let message = CString::new(message.into().replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Parse the first spectrum of a file in any readable format. Returns NULL
+/// on failure.
+///
+/// # Safety
+/// `path` must be a NUL-terminated UTF-8 string.
+#[no_mangle]
+pub unsafe extern "C" fn spc_parse_file(path: *const c_char) -> *mut SpcHandle {
+    if path.is_null() {
+        set_error("path is NULL");
+        return ptr::null_mut();
+    }
+    let Ok(path) = CStr::from_ptr(path).to_str() else {
+        set_error("path is not UTF-8");
+        return ptr::null_mut();
+    };
+    match panic::catch_unwind(|| parse(Path::new(path))) {
+        Ok(Ok(spc)) => {
+            let uid = CString::new(spc.uid.replace('\0', "")).unwrap_or_default();
+            Box::into_raw(Box::new(SpcHandle { spc, uid }))
+        }
+        Ok(Err(message)) => {
+            set_error(format!("{}: {}", path, message));
+            ptr::null_mut()
+        }
+        Err(_) => {
+            set_error(format!("{}: internal error while parsing", path));
+            ptr::null_mut()
+        }
+    }
+}
+
+fn parse(path: &Path) -> Result<SpcFile, String> {
+    let mut bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    let registry = Registry::default();
+    let reader = registry.reader_for(path, &bytes).ok_or("unrecognized input format")?;
+    let (spectra, _) = reader.read(&mut bytes, &ReadOptions::default()).map_err(|e| e.to_string())?;
+    spectra.into_iter().next().ok_or_else(|| "no spectrum in the file".to_string())
+}
+
+/// The message of the last failure on this thread, or NULL. Valid until
+/// the next failing call on the thread.
+#[no_mangle]
+pub extern "C" fn spc_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
+
+/// Release a handle. NULL is ignored.
+///
+/// # Safety
+/// `handle` must come from [`spc_parse_file`] and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn spc_free(handle: *mut SpcHandle) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// The uid (camera serial), NUL-terminated.
+///
+/// # Safety
+/// `handle` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn spc_uid(handle: *const SpcHandle) -> *const c_char {
+    (*handle).uid.as_ptr()
+}
+
+unsafe fn array(values: Option<&Vec<f64>>, len: *mut usize) -> *const f64 {
+    let values = values.map_or(&[][..], Vec::as_slice);
+    if !len.is_null() {
+        *len = values.len();
+    }
+    if values.is_empty() {
+        ptr::null()
+    } else {
+        values.as_ptr()
+    }
+}
+
+/// The intensities; their count goes to `len` (if not NULL).
+///
+/// # Safety
+/// `handle` must be a live handle and `len` NULL or writable.
+#[no_mangle]
+pub unsafe extern "C" fn spc_data(handle: *const SpcHandle, len: *mut usize) -> *const f64 {
+    array(Some(&(*handle).spc.data), len)
+}
+
+/// The blank/reference spectrum, or NULL (with `len` 0) if there is none.
+///
+/// # Safety
+/// `handle` must be a live handle and `len` NULL or writable.
+#[no_mangle]
+pub unsafe extern "C" fn spc_blank(handle: *const SpcHandle, len: *mut usize) -> *const f64 {
+    array(Some(&(*handle).spc.blank), len)
+}
+
+/// Wavelengths in nm, or NULL (with `len` 0) without a calibration.
+///
+/// # Safety
+/// `handle` must be a live handle and `len` NULL or writable.
+#[no_mangle]
+pub unsafe extern "C" fn spc_wavelength_axis(handle: *const SpcHandle, len: *mut usize) -> *const f64 {
+    array((*handle).spc.wavelength_axis.as_ref(), len)
+}
+
+/// Raman shifts in cm⁻¹, or NULL (with `len` 0) without a calibration and
+/// laser wavelength.
+///
+/// # Safety
+/// `handle` must be a live handle and `len` NULL or writable.
+#[no_mangle]
+pub unsafe extern "C" fn spc_raman_shift_axis(handle: *const SpcHandle, len: *mut usize) -> *const f64 {
+    array((*handle).spc.raman_shift_axis.as_ref(), len)
+}
+
+/// Laser wavelength in nm, or NaN if unknown.
+///
+/// # Safety
+/// `handle` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn spc_laser_wavelength(handle: *const SpcHandle) -> f64 {
+    (*handle).spc.config.as_ref().and_then(|config| config.raman_wavelength).unwrap_or(f64::NAN)
+}
+
+/// Exposure time as stored, or NaN if unknown.
+///
+/// # Safety
+/// `handle` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn spc_exposure(handle: *const SpcHandle) -> f64 {
+    (*handle).spc.config.as_ref().and_then(|config| config.exposure).unwrap_or(f64::NAN)
+}
+
+/// Camera gain, or NaN if unknown.
+///
+/// # Safety
+/// `handle` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn spc_gain(handle: *const SpcHandle) -> f64 {
+    (*handle).spc.config.as_ref().and_then(|config| config.gain).unwrap_or(f64::NAN)
+}
+
+/// The whole spectrum as JSON (as written by `-f json`), for fields
+/// without an accessor. Free it with [`spc_free_string`]; NULL on failure.
+///
+/// # Safety
+/// `handle` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn spc_to_json(handle: *const SpcHandle) -> *mut c_char {
+    let spc = AssertUnwindSafe(&(*handle).spc);
+    match panic::catch_unwind(|| output::to_json_string_spc(&spc, false)) {
+        Ok(Ok(json)) => CString::new(json).map_or(ptr::null_mut(), CString::into_raw),
+        Ok(Err(e)) => {
+            set_error(e.to_string());
+            ptr::null_mut()
+        }
+        Err(_) => {
+            set_error("internal error while writing JSON");
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Release a string from [`spc_to_json`]. NULL is ignored.
+///
+/// # Safety
+/// `string` must come from [`spc_to_json`] and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn spc_free_string(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ffi() {
+        let spc = SpcFile {
+            uid: "CAM-1".to_string(),
+            data: vec![1.0, 2.0, 3.0],
+            blank: Vec::new(),
+            calibration: None,
+            config: None,
+            wavelength_axis: None,
+            raman_shift_axis: None,
+            acquired: None,
+            operator: None,
+            sample: None,
+            instrument: None,
+            provenance: None,
+            stats: None,
+            metadata: Default::default(),
+            history: Vec::new(),
+            extras: Vec::new(),
+        };
+        let path = std::env::temp_dir().join(format!("spc_ffi_{}.spc", std::process::id()));
+        std::fs::write(&path, spc.to_bytes()).unwrap();
+        let c_path = CString::new(path.to_str().unwrap()).unwrap();
+
+        unsafe {
+            let handle = spc_parse_file(c_path.as_ptr());
+            assert!(!handle.is_null());
+            assert_eq!(CStr::from_ptr(spc_uid(handle)).to_str(), Ok("CAM-1"));
+            let mut len = 0;
+            let data = spc_data(handle, &mut len);
+            assert_eq!(std::slice::from_raw_parts(data, len), &[1.0, 2.0, 3.0]);
+            assert!(spc_wavelength_axis(handle, &mut len).is_null() && len == 0);
+            assert!(spc_laser_wavelength(handle).is_nan());
+            let json = spc_to_json(handle);
+            assert!(CStr::from_ptr(json).to_str().unwrap().contains("\"uid\":\"CAM-1\""));
+            spc_free_string(json);
+            spc_free(handle);
+
+            let missing = CString::new("/nonexistent/file.spc").unwrap();
+            assert!(spc_parse_file(missing.as_ptr()).is_null());
+            assert!(CStr::from_ptr(spc_last_error()).to_str().unwrap().starts_with("/nonexistent/file.spc: "));
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+}
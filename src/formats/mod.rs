@@ -0,0 +1,301 @@
+//! Pluggable input and output formats.
+//!
+//! Every format the converter reads implements [`SpectrumReader`] and every
+//! format it writes [`SpectrumWriter`]. A [`Registry`] holds them, picks a
+//! reader for a file by its extension and content and a writer by name, so
+//! new formats (including ones defined in other crates) plug in without
+//! changes to the CLI:
+//!
+//! ```
+//! # use spc_converter::formats::{FormatError, Registry, SpectrumWriter, WriteOptions};
+//! # use spc_converter::SpcFile;
+//! struct Tsv;
+//!
+//! impl SpectrumWriter for Tsv {
+//!     fn name(&self) -> &str { "tsv" }
+//!     fn extension(&self) -> &str { "tsv" }
+//!     fn description(&self) -> &str { "Tab-separated intensities" }
+//!     fn write(&self, spc: &SpcFile, writer: &mut dyn std::io::Write, _: &WriteOptions) -> Result<(), FormatError> {
+//!         for (i, y) in spc.data.iter().enumerate() {
+//!             writeln!(writer, "{}\t{}", i, y)?;
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let mut registry = Registry::default();
+//! registry.register_writer(Box::new(Tsv));
+//! assert!(registry.writer("tsv").is_some());
+//! ```
+
+mod readers;
+mod writers;
+
+pub use readers::*;
+pub use writers::*;
+
+use crate::output::{CsvOptions, PairsOptions, PlotOptions};
+use crate::parser::{ParseError, ParseOptions};
+use crate::spectre::{AxisType, RecoveryReport, SpcFile};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Error from a format reader or writer.
+pub type FormatError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Options for [`SpectrumReader::read`].
+#[derive(Debug, Clone, Default)]
+pub struct ReadOptions {
+    /// Options for Spectrum Analyzer Suite containers
+    pub parse: ParseOptions,
+    /// Axis of unlabelled x values in text files [default: Raman shift]
+    pub text_x_axis: Option<AxisType>,
+}
+
+/// Options for [`SpectrumWriter::write`].
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    /// Indent JSON output
+    pub pretty: bool,
+    /// X-axis for tabular and plot output [default: the best available]
+    pub axis: Option<AxisType>,
+    /// Image options for plot output
+    pub plot: PlotOptions,
+    /// Delimiter and number format of CSV output
+    pub csv: CsvOptions,
+    /// Delimiter and number format of pairs output
+    pub pairs: PairsOptions,
+    /// Add to an existing output instead of replacing it (batch formats
+    /// that support it)
+    pub append: bool,
+    /// Store intensities as 32-bit floats (binary formats that support
+    /// it); text formats follow
+    /// [`single_precision`](crate::processing::single_precision)
+    pub single_precision: bool,
+}
+
+/// A format spectra are read from.
+pub trait SpectrumReader: Send + Sync {
+    /// Short name, e.g. `spc`.
+    fn name(&self) -> &str;
+
+    /// File extensions (lowercase, without the dot) this format uses.
+    fn extensions(&self) -> &[&str];
+
+    /// Whether `bytes` are recognizably in this format. Formats without a
+    /// signature (e.g. text) return `false` and are chosen by extension.
+    fn detect(&self, bytes: &[u8]) -> bool;
+
+    /// Read all spectra in a file, with warnings about what was skipped.
+    /// `bytes` may be modified (e.g. decrypted in place).
+    fn read(&self, bytes: &mut [u8], options: &ReadOptions) -> Result<(Vec<SpcFile>, Vec<String>), ParseError>;
+
+    /// What one of several spectra in a file is called (e.g. `frame`).
+    fn item_name(&self) -> &str {
+        "spectrum"
+    }
+
+    /// Salvage what can be read from a damaged file, if the format
+    /// supports it, within the limits of `options`.
+    fn recover(&self, _bytes: &[u8], _options: &ReadOptions) -> Option<Result<(Vec<SpcFile>, RecoveryReport), ParseError>> {
+        None
+    }
+}
+
+/// A format spectra are written in.
+pub trait SpectrumWriter: Send + Sync {
+    /// Name selected with `--format`, e.g. `json`.
+    fn name(&self) -> &str;
+
+    /// Extension of the output file (without the dot).
+    fn extension(&self) -> &str;
+
+    /// One-line description for help output.
+    fn description(&self) -> &str;
+
+    /// Write one spectrum.
+    fn write(&self, spc: &SpcFile, writer: &mut dyn Write, options: &WriteOptions) -> Result<(), FormatError>;
+
+    /// Write one spectrum to a file. Formats that manage their own file
+    /// (e.g. HDF5) override this.
+    #[tracing::instrument(name = "write", level = "debug", skip_all, fields(format = self.name()))]
+    fn write_file(&self, spc: &SpcFile, path: &Path, options: &WriteOptions) -> Result<(), FormatError> {
+        tracing::debug!("writing {} as {} to {}", spc.uid, self.name(), path.display());
+        let mut writer = BufWriter::new(std::fs::File::create(path)?);
+        self.write(spc, &mut writer, options)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Whether this writer reaches the axes only through
+    /// [`SpcFile::wavelengths`], [`SpcFile::raman_shifts`] or
+    /// [`select_axis`](crate::output::select_axis), not the stored fields,
+    /// so its input may be parsed with
+    /// [`lazy_axes`](ParseOptions::lazy_axes).
+    fn lazy_axes(&self) -> bool {
+        false
+    }
+
+    /// Whether all inputs go into a single output file, written with
+    /// [`write_batch`](Self::write_batch).
+    fn is_batch(&self) -> bool {
+        false
+    }
+
+    /// Write several `(file_id, spectrum)` pairs into one file.
+    fn write_batch(
+        &self,
+        spectra: &[(&str, &SpcFile)],
+        _path: &Path,
+        _options: &WriteOptions,
+    ) -> Result<(), FormatError> {
+        Err(format!("cannot write {} spectra into one {} file", spectra.len(), self.name()).into())
+    }
+}
+
+/// The known readers and writers.
+///
+/// [`Registry::default`] holds the built-in formats (those enabled by
+/// features). Formats registered later take precedence over earlier ones
+/// with the same name or extension.
+pub struct Registry {
+    readers: Vec<Box<dyn SpectrumReader>>,
+    writers: Vec<Box<dyn SpectrumWriter>>,
+}
+
+impl Registry {
+    /// A registry without any format.
+    pub fn empty() -> Self {
+        Self {
+            readers: Vec::new(),
+            writers: Vec::new(),
+        }
+    }
+
+    /// Add a reader.
+    pub fn register_reader(&mut self, reader: Box<dyn SpectrumReader>) {
+        self.readers.push(reader);
+    }
+
+    /// Add a writer.
+    pub fn register_writer(&mut self, writer: Box<dyn SpectrumWriter>) {
+        self.writers.push(writer);
+    }
+
+    /// All readers, in registration order.
+    pub fn readers(&self) -> impl Iterator<Item = &dyn SpectrumReader> {
+        self.readers.iter().map(|reader| reader.as_ref())
+    }
+
+    /// All writers, in registration order, without those replaced by a
+    /// later one of the same name.
+    pub fn writers(&self) -> impl Iterator<Item = &dyn SpectrumWriter> {
+        self.writers
+            .iter()
+            .enumerate()
+            .filter(|(i, writer)| !self.writers[i + 1..].iter().any(|later| later.name() == writer.name()))
+            .map(|(_, writer)| writer.as_ref())
+    }
+
+    /// The writer with the given name.
+    pub fn writer(&self, name: &str) -> Option<&dyn SpectrumWriter> {
+        self.writers.iter().rev().find(|writer| writer.name() == name).map(|writer| writer.as_ref())
+    }
+
+    /// The reader for a file: one that claims its extension and recognizes
+    /// its content, else one that claims the extension (e.g. text, or a
+    /// damaged file), else one that recognizes the content.
+    pub fn reader_for(&self, path: &Path, bytes: &[u8]) -> Option<&dyn SpectrumReader> {
+        let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default().to_lowercase();
+        let claims = |reader: &&dyn SpectrumReader| reader.extensions().contains(&extension.as_str());
+        let readers = || self.readers.iter().rev().map(|reader| reader.as_ref());
+        let reader = readers()
+            .filter(claims)
+            .find(|reader| reader.detect(bytes))
+            .or_else(|| readers().find(claims))
+            .or_else(|| readers().find(|reader| reader.detect(bytes)));
+        tracing::debug!("{}: read as {}", path.display(), reader.map_or("unknown", |reader| reader.name()));
+        reader
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        // Damaged .spc files go to the container reader, registered last
+        registry.register_reader(Box::new(GalacticReader));
+        registry.register_reader(Box::new(SpcReader));
+        registry.register_reader(Box::new(SpeReader));
+        registry.register_reader(Box::new(TextReader));
+        registry.register_reader(Box::new(JsonReader));
+
+        registry.register_writer(Box::new(JsonWriter));
+        registry.register_writer(Box::new(YamlWriter));
+        registry.register_writer(Box::new(CsvWriter));
+        registry.register_writer(Box::new(CsvMatrixWriter));
+        registry.register_writer(Box::new(PairsWriter));
+        registry.register_writer(Box::new(XyWriter));
+        registry.register_writer(Box::new(GalacticWriter));
+        registry.register_writer(Box::new(SpcWriter));
+        #[cfg(feature = "plot")]
+        registry.register_writer(Box::new(PngWriter));
+        #[cfg(feature = "plot")]
+        registry.register_writer(Box::new(PdfWriter));
+        registry.register_writer(Box::new(NdjsonWriter));
+        #[cfg(feature = "hdf5")]
+        registry.register_writer(Box::new(Hdf5Writer));
+        #[cfg(feature = "parquet")]
+        registry.register_writer(Box::new(ParquetWriter));
+        #[cfg(feature = "sqlite")]
+        registry.register_writer(Box::new(SqliteWriter));
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::to_galactic_bytes;
+
+    #[test]
+    fn test_reader_selection() {
+        let registry = Registry::default();
+        let spc = SpcFile {
+            uid: "CAM-1".to_string(),
+            data: vec![1.0, 2.0, 3.0],
+            blank: vec![0.5; 3],
+            calibration: None,
+            config: None,
+            wavelength_axis: None,
+            raman_shift_axis: None,
+            acquired: None,
+            operator: None,
+            sample: None,
+            instrument: None,
+            provenance: None,
+            stats: None,
+            metadata: Default::default(),
+            history: Vec::new(),
+            extras: Vec::new(),
+        };
+        let container = spc.to_bytes();
+        let galactic = to_galactic_bytes(&spc).unwrap();
+
+        let name = |path: &str, bytes: &[u8]| registry.reader_for(Path::new(path), bytes).map(|r| r.name());
+        assert_eq!(name("a.spc", &container), Some("spc"));
+        assert_eq!(name("a.spc", &galactic), Some("galactic"));
+        assert_eq!(name("a.bin", &galactic), Some("galactic"));
+        // Damaged files still go to the reader of their extension
+        assert_eq!(name("a.SPC", b"garbage"), Some("spc"));
+        assert_eq!(name("a.csv", b"NM,intensity\n1,2\n"), Some("text"));
+        assert_eq!(name("a.bin", b"garbage"), None);
+
+        let (spectra, _) = registry
+            .reader_for(Path::new("a.spc"), &galactic)
+            .unwrap()
+            .read(&mut galactic.clone(), &ReadOptions::default())
+            .unwrap();
+        assert_eq!(spectra[0].data, spc.data);
+        assert_eq!(registry.writer("galactic").unwrap().extension(), "galactic.spc");
+    }
+}
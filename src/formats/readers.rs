@@ -0,0 +1,132 @@
+//! Built-in readers.
+
+use super::{ReadOptions, SpectrumReader};
+use crate::import;
+use crate::parser::{ContainerHeader, ParseError};
+use crate::spectre::{RecoveryReport, SpcDataset, SpcFile};
+
+/// Spectrum Analyzer Suite `.spc` containers.
+pub struct SpcReader;
+
+impl SpectrumReader for SpcReader {
+    fn name(&self) -> &str {
+        "spc"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["spc"]
+    }
+
+    fn detect(&self, bytes: &[u8]) -> bool {
+        bytes.len() >= 4 && ContainerHeader::version_of(u32::from_le_bytes(bytes[..4].try_into().unwrap())).is_some()
+    }
+
+    fn read(&self, bytes: &mut [u8], options: &ReadOptions) -> Result<(Vec<SpcFile>, Vec<String>), ParseError> {
+        let (dataset, warnings) = SpcDataset::from_bytes_in_place_with(bytes, &options.parse)?;
+        Ok((dataset.spectra, warnings))
+    }
+
+    fn recover(&self, bytes: &[u8], options: &ReadOptions) -> Option<Result<(Vec<SpcFile>, RecoveryReport), ParseError>> {
+        Some(SpcFile::recover_with(bytes, &options.parse.limits).map(|(spc, report)| (vec![spc], report)))
+    }
+}
+
+/// Thermo Galactic SPC files.
+pub struct GalacticReader;
+
+impl SpectrumReader for GalacticReader {
+    fn name(&self) -> &str {
+        "galactic"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["spc"]
+    }
+
+    fn detect(&self, bytes: &[u8]) -> bool {
+        import::is_galactic(bytes)
+    }
+
+    fn read(&self, bytes: &mut [u8], _: &ReadOptions) -> Result<(Vec<SpcFile>, Vec<String>), ParseError> {
+        Ok((import::read_galactic(bytes)?, Vec::new()))
+    }
+
+    fn item_name(&self) -> &str {
+        "subfile"
+    }
+}
+
+/// Princeton Instruments SPE files.
+pub struct SpeReader;
+
+impl SpectrumReader for SpeReader {
+    fn name(&self) -> &str {
+        "spe"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["spe"]
+    }
+
+    fn detect(&self, bytes: &[u8]) -> bool {
+        import::is_spe(bytes)
+    }
+
+    fn read(&self, bytes: &mut [u8], _: &ReadOptions) -> Result<(Vec<SpcFile>, Vec<String>), ParseError> {
+        Ok((import::read_spe(bytes)?, Vec::new()))
+    }
+
+    fn item_name(&self) -> &str {
+        "frame"
+    }
+}
+
+/// The converter's own JSON and NDJSON output.
+pub struct JsonReader;
+
+impl SpectrumReader for JsonReader {
+    fn name(&self) -> &str {
+        "json"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["json", "ndjson", "jsonl"]
+    }
+
+    fn detect(&self, _: &[u8]) -> bool {
+        false
+    }
+
+    fn read(&self, bytes: &mut [u8], _: &ReadOptions) -> Result<(Vec<SpcFile>, Vec<String>), ParseError> {
+        let text = std::str::from_utf8(bytes).map_err(|e| ParseError::InvalidImport {
+            format: "JSON".to_string(),
+            message: format!("not valid UTF-8: {}", e),
+        })?;
+        Ok((import::read_json(text)?, Vec::new()))
+    }
+}
+
+/// Delimited text (CSV, TSV, whitespace-separated columns).
+pub struct TextReader;
+
+impl SpectrumReader for TextReader {
+    fn name(&self) -> &str {
+        "text"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["csv", "tsv", "txt", "dat"]
+    }
+
+    fn detect(&self, _: &[u8]) -> bool {
+        false
+    }
+
+    fn read(&self, bytes: &mut [u8], options: &ReadOptions) -> Result<(Vec<SpcFile>, Vec<String>), ParseError> {
+        let text = std::str::from_utf8(bytes).map_err(|e| ParseError::InvalidImport {
+            format: "text".to_string(),
+            message: format!("not valid UTF-8: {}", e),
+        })?;
+        Ok((vec![import::read_text(text, options.text_x_axis)?], Vec::new()))
+    }
+}
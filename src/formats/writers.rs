@@ -0,0 +1,461 @@
+//! Built-in writers.
+
+use super::{FormatError, SpectrumWriter, WriteOptions};
+use crate::output;
+use crate::spectre::SpcFile;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// JSON, the default output.
+pub struct JsonWriter;
+
+impl SpectrumWriter for JsonWriter {
+    fn name(&self) -> &str {
+        "json"
+    }
+
+    fn extension(&self) -> &str {
+        "json"
+    }
+
+    fn description(&self) -> &str {
+        "JSON with data, blank, calibration, config and axes"
+    }
+
+    fn write(&self, spc: &SpcFile, writer: &mut dyn Write, options: &WriteOptions) -> Result<(), FormatError> {
+        Ok(output::write_json_spc(spc, writer, options.pretty)?)
+    }
+}
+
+/// YAML, the JSON document in block style.
+pub struct YamlWriter;
+
+impl SpectrumWriter for YamlWriter {
+    fn name(&self) -> &str {
+        "yaml"
+    }
+
+    fn extension(&self) -> &str {
+        "yaml"
+    }
+
+    fn description(&self) -> &str {
+        "YAML with the same fields as the JSON output"
+    }
+
+    fn write(&self, spc: &SpcFile, writer: &mut dyn Write, _: &WriteOptions) -> Result<(), FormatError> {
+        Ok(output::write_yaml(spc, writer)?)
+    }
+}
+
+/// CSV table, one row per point.
+pub struct CsvWriter;
+
+impl SpectrumWriter for CsvWriter {
+    fn name(&self) -> &str {
+        "csv"
+    }
+
+    fn extension(&self) -> &str {
+        "csv"
+    }
+
+    fn description(&self) -> &str {
+        "CSV table with index, axes, intensity and blank columns"
+    }
+
+    fn lazy_axes(&self) -> bool {
+        true
+    }
+
+    fn write(&self, spc: &SpcFile, writer: &mut dyn Write, options: &WriteOptions) -> Result<(), FormatError> {
+        let csv = output::CsvOptions {
+            axis: options.axis,
+            ..options.csv.clone()
+        };
+        Ok(output::write_csv_spc_with(spc, &csv, writer)?)
+    }
+}
+
+/// Wide CSV matrix, one intensity column per input.
+pub struct CsvMatrixWriter;
+
+impl SpectrumWriter for CsvMatrixWriter {
+    fn name(&self) -> &str {
+        "csv-matrix"
+    }
+
+    fn extension(&self) -> &str {
+        "csv"
+    }
+
+    fn description(&self) -> &str {
+        "One CSV matrix for all inputs (x column, one intensity column per input)"
+    }
+
+    fn lazy_axes(&self) -> bool {
+        true
+    }
+
+    fn write(&self, spc: &SpcFile, writer: &mut dyn Write, options: &WriteOptions) -> Result<(), FormatError> {
+        write_matrix(&[(spc.uid.as_str(), spc)], writer, options)
+    }
+
+    fn is_batch(&self) -> bool {
+        true
+    }
+
+    #[tracing::instrument(name = "write", level = "debug", skip_all, fields(format = self.name()))]
+    fn write_batch(&self, spectra: &[(&str, &SpcFile)], path: &Path, options: &WriteOptions) -> Result<(), FormatError> {
+        tracing::debug!("writing {} spectra as {} to {}", spectra.len(), self.name(), path.display());
+        if path.as_os_str() == "-" {
+            return write_matrix(spectra, &mut io::stdout().lock(), options);
+        }
+        let mut writer = io::BufWriter::new(std::fs::File::create(path)?);
+        write_matrix(spectra, &mut writer, options)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// x,y pairs with a short context header.
+pub struct PairsWriter;
+
+impl SpectrumWriter for PairsWriter {
+    fn name(&self) -> &str {
+        "pairs"
+    }
+
+    fn extension(&self) -> &str {
+        "txt"
+    }
+
+    fn description(&self) -> &str {
+        "LLM-friendly x,y pairs with context header"
+    }
+
+    fn lazy_axes(&self) -> bool {
+        true
+    }
+
+    fn write(&self, spc: &SpcFile, writer: &mut dyn Write, options: &WriteOptions) -> Result<(), FormatError> {
+        let pairs = output::PairsOptions {
+            axis: options.axis,
+            ..options.pairs.clone()
+        };
+        Ok(output::write_pairs_with(spc, &pairs, writer)?)
+    }
+}
+
+/// PDF report with the plot and acquisition details.
+#[cfg(feature = "plot")]
+pub struct PdfWriter;
+
+#[cfg(feature = "plot")]
+impl SpectrumWriter for PdfWriter {
+    fn name(&self) -> &str {
+        "pdf"
+    }
+
+    fn extension(&self) -> &str {
+        "pdf"
+    }
+
+    fn description(&self) -> &str {
+        "PDF report with plot, acquisition details, provenance and a sign-off block"
+    }
+
+    fn lazy_axes(&self) -> bool {
+        true
+    }
+
+    fn write(&self, spc: &SpcFile, writer: &mut dyn Write, options: &WriteOptions) -> Result<(), FormatError> {
+        let plot = output::PlotOptions {
+            axis: options.axis,
+            ..options.plot.clone()
+        };
+        Ok(output::write_pdf(spc, &plot, writer)?)
+    }
+}
+
+/// Bare `x<TAB>y` lines for legacy tools.
+pub struct XyWriter;
+
+impl SpectrumWriter for XyWriter {
+    fn name(&self) -> &str {
+        "xy"
+    }
+
+    fn extension(&self) -> &str {
+        "txt"
+    }
+
+    fn description(&self) -> &str {
+        "Two tab-separated x and y columns without a header"
+    }
+
+    fn lazy_axes(&self) -> bool {
+        true
+    }
+
+    fn write(&self, spc: &SpcFile, writer: &mut dyn Write, options: &WriteOptions) -> Result<(), FormatError> {
+        Ok(output::write_xy(spc, options.axis, writer)?)
+    }
+}
+
+/// Thermo Galactic SPC.
+pub struct GalacticWriter;
+
+impl SpectrumWriter for GalacticWriter {
+    fn name(&self) -> &str {
+        "galactic"
+    }
+
+    // Distinct from the input's own .spc extension
+    fn extension(&self) -> &str {
+        "galactic.spc"
+    }
+
+    fn description(&self) -> &str {
+        "Thermo Galactic SPC (GRAMS), written as <name>.galactic.spc"
+    }
+
+    fn lazy_axes(&self) -> bool {
+        true
+    }
+
+    fn write(&self, spc: &SpcFile, writer: &mut dyn Write, _: &WriteOptions) -> Result<(), FormatError> {
+        Ok(output::write_galactic(spc, writer)?)
+    }
+}
+
+/// Spectrum Analyzer Suite .spc, e.g. from edited JSON.
+pub struct SpcWriter;
+
+impl SpectrumWriter for SpcWriter {
+    fn name(&self) -> &str {
+        "spc"
+    }
+
+    fn extension(&self) -> &str {
+        "spc"
+    }
+
+    fn description(&self) -> &str {
+        "Spectrum Analyzer Suite .spc (e.g. JSON converted back)"
+    }
+
+    fn lazy_axes(&self) -> bool {
+        true
+    }
+
+    fn write(&self, spc: &SpcFile, writer: &mut dyn Write, _: &WriteOptions) -> Result<(), FormatError> {
+        spc.check_storable()?;
+        Ok(writer.write_all(&spc.to_bytes())?)
+    }
+}
+
+/// PNG plot.
+#[cfg(feature = "plot")]
+pub struct PngWriter;
+
+#[cfg(feature = "plot")]
+impl SpectrumWriter for PngWriter {
+    fn name(&self) -> &str {
+        "png"
+    }
+
+    fn extension(&self) -> &str {
+        "png"
+    }
+
+    fn description(&self) -> &str {
+        "PNG spectrum plot (see the --plot-* options)"
+    }
+
+    fn lazy_axes(&self) -> bool {
+        true
+    }
+
+    fn write(&self, _: &SpcFile, _: &mut dyn Write, _: &WriteOptions) -> Result<(), FormatError> {
+        Err(needs_file(self.name()))
+    }
+
+    #[tracing::instrument(name = "write", level = "debug", skip_all, fields(format = self.name()))]
+    fn write_file(&self, spc: &SpcFile, path: &Path, options: &WriteOptions) -> Result<(), FormatError> {
+        tracing::debug!("writing {} as {} to {}", spc.uid, self.name(), path.display());
+        let plot = output::PlotOptions {
+            axis: options.axis,
+            ..options.plot.clone()
+        };
+        Ok(output::write_plot_with(spc, path, &plot)?)
+    }
+}
+
+/// HDF5 datasets and attributes.
+#[cfg(feature = "hdf5")]
+pub struct Hdf5Writer;
+
+#[cfg(feature = "hdf5")]
+impl SpectrumWriter for Hdf5Writer {
+    fn name(&self) -> &str {
+        "hdf5"
+    }
+
+    fn extension(&self) -> &str {
+        "h5"
+    }
+
+    fn description(&self) -> &str {
+        "HDF5 datasets and attributes"
+    }
+
+    fn lazy_axes(&self) -> bool {
+        true
+    }
+
+    fn write(&self, _: &SpcFile, _: &mut dyn Write, _: &WriteOptions) -> Result<(), FormatError> {
+        Err(needs_file(self.name()))
+    }
+
+    #[tracing::instrument(name = "write", level = "debug", skip_all, fields(format = self.name()))]
+    fn write_file(&self, spc: &SpcFile, path: &Path, _: &WriteOptions) -> Result<(), FormatError> {
+        tracing::debug!("writing {} as {} to {}", spc.uid, self.name(), path.display());
+        Ok(output::write_hdf5(spc, path)?)
+    }
+}
+
+/// Newline-delimited JSON, one line per input, e.g. for bulk loading into
+/// a search index or warehouse.
+pub struct NdjsonWriter;
+
+impl SpectrumWriter for NdjsonWriter {
+    fn name(&self) -> &str {
+        "ndjson"
+    }
+
+    fn extension(&self) -> &str {
+        "ndjson"
+    }
+
+    fn description(&self) -> &str {
+        "One JSON line per input, in one file (\"-\" for stdout)"
+    }
+
+    fn write(&self, spc: &SpcFile, writer: &mut dyn Write, _: &WriteOptions) -> Result<(), FormatError> {
+        Ok(output::write_ndjson(&[(spc.uid.as_str(), spc)], writer)?)
+    }
+
+    fn is_batch(&self) -> bool {
+        true
+    }
+
+    #[tracing::instrument(name = "write", level = "debug", skip_all, fields(format = self.name()))]
+    fn write_batch(&self, spectra: &[(&str, &SpcFile)], path: &Path, options: &WriteOptions) -> Result<(), FormatError> {
+        tracing::debug!("writing {} spectra as {} to {}", spectra.len(), self.name(), path.display());
+        if path.as_os_str() == "-" {
+            return Ok(output::write_ndjson(spectra, io::stdout().lock())?);
+        }
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(options.append)
+            .truncate(!options.append)
+            .open(path)?;
+        Ok(output::write_ndjson(spectra, io::BufWriter::new(file))?)
+    }
+}
+
+/// One Parquet table for all inputs.
+#[cfg(feature = "parquet")]
+pub struct ParquetWriter;
+
+#[cfg(feature = "parquet")]
+impl SpectrumWriter for ParquetWriter {
+    fn name(&self) -> &str {
+        "parquet"
+    }
+
+    fn extension(&self) -> &str {
+        "parquet"
+    }
+
+    fn description(&self) -> &str {
+        "One Parquet table for all inputs (file_id, index, x, intensity, blank)"
+    }
+
+    fn lazy_axes(&self) -> bool {
+        true
+    }
+
+    fn write(&self, _: &SpcFile, _: &mut dyn Write, _: &WriteOptions) -> Result<(), FormatError> {
+        Err(needs_file(self.name()))
+    }
+
+    fn is_batch(&self) -> bool {
+        true
+    }
+
+    #[tracing::instrument(name = "write", level = "debug", skip_all, fields(format = self.name()))]
+    fn write_batch(&self, spectra: &[(&str, &SpcFile)], path: &Path, options: &WriteOptions) -> Result<(), FormatError> {
+        tracing::debug!("writing {} spectra as {} to {}", spectra.len(), self.name(), path.display());
+        if path.as_os_str() == "-" {
+            return Ok(output::write_parquet_with(spectra, io::stdout(), options.single_precision)?);
+        }
+        let writer = io::BufWriter::new(std::fs::File::create(path)?);
+        Ok(output::write_parquet_with(spectra, writer, options.single_precision)?)
+    }
+}
+
+/// One SQLite database for all inputs.
+#[cfg(feature = "sqlite")]
+pub struct SqliteWriter;
+
+#[cfg(feature = "sqlite")]
+impl SpectrumWriter for SqliteWriter {
+    fn name(&self) -> &str {
+        "sqlite"
+    }
+
+    fn extension(&self) -> &str {
+        "db"
+    }
+
+    fn description(&self) -> &str {
+        "One SQLite database for all inputs (spectra, points and metadata tables)"
+    }
+
+    fn lazy_axes(&self) -> bool {
+        true
+    }
+
+    fn write(&self, _: &SpcFile, _: &mut dyn Write, _: &WriteOptions) -> Result<(), FormatError> {
+        Err(needs_file(self.name()))
+    }
+
+    fn is_batch(&self) -> bool {
+        true
+    }
+
+    #[tracing::instrument(name = "write", level = "debug", skip_all, fields(format = self.name()))]
+    fn write_batch(&self, spectra: &[(&str, &SpcFile)], path: &Path, options: &WriteOptions) -> Result<(), FormatError> {
+        tracing::debug!("writing {} spectra as {} to {}", spectra.len(), self.name(), path.display());
+        if path.as_os_str() == "-" {
+            return Err(needs_file(self.name()));
+        }
+        Ok(output::write_sqlite(spectra, path, options.append)?)
+    }
+}
+
+fn write_matrix(spectra: &[(&str, &SpcFile)], writer: &mut dyn Write, options: &WriteOptions) -> Result<(), FormatError> {
+    let csv = output::CsvOptions {
+        axis: options.axis,
+        ..options.csv.clone()
+    };
+    Ok(output::write_csv_matrix_named(spectra, &csv, writer)?)
+}
+
+#[cfg(any(feature = "plot", feature = "hdf5", feature = "parquet", feature = "sqlite"))]
+fn needs_file(format: &str) -> FormatError {
+    io::Error::new(io::ErrorKind::Unsupported, format!("{} output must be written to a file", format)).into()
+}
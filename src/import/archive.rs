@@ -0,0 +1,384 @@
+//! Files inside ZIP and TAR archives (plain or gzip-compressed), so zipped
+//! session folders can be converted without extracting them first.
+
+use super::text_at;
+use crate::parser::{Limits, ParseError};
+use crate::spectre::SpcFile;
+use flate2::read::{DeflateDecoder, GzDecoder};
+use std::borrow::Cow;
+use std::io::Read;
+
+/// A file read from an archive.
+#[derive(Debug, Clone)]
+pub struct ArchiveEntry {
+    /// Path inside the archive, `/`-separated, without `.` or `..` parts
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+/// A file of an archive, located but not yet decompressed.
+#[derive(Debug, Clone)]
+pub struct ArchiveMember {
+    /// Path inside the archive, `/`-separated, without `.` or `..` parts
+    pub name: String,
+    /// Decompressed size as recorded in the archive
+    pub size: u64,
+    /// Where the stored (possibly compressed) data lies in the archive
+    start: usize,
+    len: usize,
+    deflated: bool,
+}
+
+/// An archive whose files are decompressed one at a time, on request, so
+/// that only the one being read is held in memory.
+pub struct ArchiveReader<'a> {
+    /// The archive, or the TAR inside a `.tar.gz`
+    data: Cow<'a, [u8]>,
+    members: Vec<ArchiveMember>,
+}
+
+impl<'a> ArchiveReader<'a> {
+    /// List the regular files of an archive whose name passes `wanted`, in
+    /// archive order. Fails if together they would decompress to more than
+    /// `max_size` bytes; a `.tar.gz` is decompressed here, to at most
+    /// [`Limits::DEFAULT_MAX_TOTAL_SIZE`] bytes.
+    pub fn open(
+        bytes: impl Into<Cow<'a, [u8]>>,
+        wanted: impl Fn(&str) -> bool,
+        max_size: u64,
+    ) -> Result<Self, ParseError> {
+        let mut data = bytes.into();
+        let members = if data.starts_with(ZIP_LOCAL_HEADER) || data.starts_with(ZIP_END) {
+            list_zip(&data, &wanted)?
+        } else {
+            if data.starts_with(GZIP_MAGIC) {
+                data = Cow::Owned(inflate(GzDecoder::new(&data[..]), "gzip archive", Limits::DEFAULT_MAX_TOTAL_SIZE)?);
+            }
+            list_tar(&data, &wanted)?
+        };
+        let reader = ArchiveReader { data, members };
+        if reader.size() > max_size {
+            return Err(ParseError::LimitExceeded {
+                what: "decompressed archive".to_string(),
+                limit: max_size,
+            });
+        }
+        Ok(reader)
+    }
+
+    /// The listed files.
+    pub fn members(&self) -> &[ArchiveMember] {
+        &self.members
+    }
+
+    /// Decompressed size of all the listed files together.
+    pub fn size(&self) -> u64 {
+        self.members.iter().map(|member| member.size).sum()
+    }
+
+    /// Decompress listed file `index`. A file larger than
+    /// [`Limits::DEFAULT_MAX_TOTAL_SIZE`], or than its recorded size, fails.
+    pub fn read(&self, index: usize) -> Result<Vec<u8>, ParseError> {
+        let member = &self.members[index];
+        let limit = Limits::DEFAULT_MAX_TOTAL_SIZE;
+        if member.size > limit as u64 {
+            return Err(ParseError::LimitExceeded {
+                what: member.name.clone(),
+                limit: limit as u64,
+            });
+        }
+        let stored = &self.data[member.start..member.start + member.len];
+        if !member.deflated {
+            return Ok(stored.to_vec());
+        }
+        // The archive's total was checked against the recorded sizes
+        inflate(DeflateDecoder::new(stored), &member.name, member.size as usize).map_err(|e| match e {
+            ParseError::LimitExceeded { .. } => invalid("ZIP", format!("{} is larger than its recorded size", member.name)),
+            e => e,
+        })
+    }
+}
+
+const ZIP_LOCAL_HEADER: &[u8] = b"PK\x03\x04";
+const ZIP_END: &[u8] = b"PK\x05\x06";
+const ZIP_CENTRAL_HEADER: &[u8] = b"PK\x01\x02";
+const GZIP_MAGIC: &[u8] = b"\x1f\x8b";
+const TAR_BLOCK: usize = 512;
+
+/// Whether `bytes` look like a ZIP archive, a TAR archive or gzip data
+/// (taken to be a `.tar.gz`).
+pub fn is_archive(bytes: &[u8]) -> bool {
+    bytes.starts_with(ZIP_LOCAL_HEADER)
+        || bytes.starts_with(ZIP_END)
+        || bytes.starts_with(GZIP_MAGIC)
+        || bytes.get(257..262) == Some(b"ustar")
+}
+
+/// The regular files of an archive whose name passes `wanted`, in archive
+/// order. All of them together, like a `.tar.gz` as a whole, may decompress
+/// to at most [`Limits::DEFAULT_MAX_TOTAL_SIZE`] bytes; use
+/// [`ArchiveReader`] to read larger archives a file at a time.
+pub fn read_archive(bytes: &[u8], wanted: impl Fn(&str) -> bool) -> Result<Vec<ArchiveEntry>, ParseError> {
+    let reader = ArchiveReader::open(bytes, wanted, Limits::DEFAULT_MAX_TOTAL_SIZE as u64)?;
+    (0..reader.members.len())
+        .map(|index| {
+            Ok(ArchiveEntry {
+                name: reader.members[index].name.clone(),
+                data: reader.read(index)?,
+            })
+        })
+        .collect()
+}
+
+/// A `.spc` file of an archive: its name and the parsed spectrum or error.
+pub type ArchivedSpectrum = (String, Result<SpcFile, ParseError>);
+
+/// Parse every `.spc` file of an archive. Files that fail to parse are
+/// returned with their error; only an unreadable archive fails as a whole.
+pub fn read_spc_archive(bytes: &[u8]) -> Result<Vec<ArchivedSpectrum>, ParseError> {
+    let entries = read_archive(bytes, |name| name.to_lowercase().ends_with(".spc"))?;
+    Ok(entries
+        .into_iter()
+        .map(|mut entry| {
+            let spc = SpcFile::from_bytes_in_place(&mut entry.data);
+            (entry.name, spc)
+        })
+        .collect())
+}
+
+fn invalid(format: &str, message: impl Into<String>) -> ParseError {
+    ParseError::InvalidImport {
+        format: format.to_string(),
+        message: message.into(),
+    }
+}
+
+/// Read a decompressing reader to the end, to at most `limit` bytes.
+fn inflate(reader: impl Read, what: &str, limit: usize) -> Result<Vec<u8>, ParseError> {
+    let mut data = Vec::new();
+    reader.take(limit as u64 + 1).read_to_end(&mut data).map_err(|e| invalid(what, e.to_string()))?;
+    if data.len() > limit {
+        return Err(ParseError::LimitExceeded {
+            what: what.to_string(),
+            limit: limit as u64,
+        });
+    }
+    Ok(data)
+}
+
+/// An archive path without empty, `.` and `..` parts, so that it stays
+/// below whatever directory it is joined to.
+fn clean_name(name: &str) -> String {
+    name.split(['/', '\\']).filter(|part| !matches!(*part, "" | "." | "..")).collect::<Vec<_>>().join("/")
+}
+
+fn list_zip(bytes: &[u8], wanted: &dyn Fn(&str) -> bool) -> Result<Vec<ArchiveMember>, ParseError> {
+    let u16_at = |at: usize| bytes.get(at..at + 2).map(|b| u16::from_le_bytes([b[0], b[1]]) as usize);
+    let u32_at = |at: usize| bytes.get(at..at + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize);
+    let truncated = || invalid("ZIP", "truncated archive");
+
+    // The end of central directory record, followed by at most a 64 KiB comment
+    let search = bytes.len().saturating_sub(22 + 0xFFFF);
+    let end = (search..bytes.len().saturating_sub(21))
+        .rev()
+        .find(|&at| bytes[at..].starts_with(ZIP_END))
+        .ok_or_else(|| invalid("ZIP", "no central directory"))?;
+    let count = u16_at(end + 10).ok_or_else(truncated)?;
+    let mut at = u32_at(end + 16).ok_or_else(truncated)?;
+    if at == 0xFFFF_FFFF {
+        return Err(invalid("ZIP", "ZIP64 archives are not supported"));
+    }
+
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        if bytes.get(at..at + 4) != Some(ZIP_CENTRAL_HEADER) {
+            return Err(invalid("ZIP", format!("bad central directory entry at offset {}", at)));
+        }
+        let flags = u16_at(at + 8).ok_or_else(truncated)?;
+        let method = u16_at(at + 10).ok_or_else(truncated)?;
+        let compressed_size = u32_at(at + 20).ok_or_else(truncated)?;
+        let size = u32_at(at + 24).ok_or_else(truncated)?;
+        let name_len = u16_at(at + 28).ok_or_else(truncated)?;
+        let extra_len = u16_at(at + 30).ok_or_else(truncated)?;
+        let comment_len = u16_at(at + 32).ok_or_else(truncated)?;
+        let offset = u32_at(at + 42).ok_or_else(truncated)?;
+        let raw_name = bytes.get(at + 46..at + 46 + name_len).ok_or_else(truncated)?;
+        let raw_name = String::from_utf8_lossy(raw_name);
+        at += 46 + name_len + extra_len + comment_len;
+
+        let name = clean_name(&raw_name);
+        if raw_name.ends_with('/') || name.is_empty() || !wanted(&name) {
+            continue;
+        }
+        if flags & 1 != 0 {
+            return Err(invalid("ZIP", format!("{} is encrypted", name)));
+        }
+        if compressed_size == 0xFFFF_FFFF || size == 0xFFFF_FFFF || offset == 0xFFFF_FFFF {
+            return Err(invalid("ZIP", "ZIP64 archives are not supported"));
+        }
+        if bytes.get(offset..offset + 4) != Some(ZIP_LOCAL_HEADER) {
+            return Err(invalid("ZIP", format!("bad local header for {}", name)));
+        }
+        let start = offset + 30 + u16_at(offset + 26).ok_or_else(truncated)? + u16_at(offset + 28).ok_or_else(truncated)?;
+        bytes.get(start..start + compressed_size).ok_or_else(truncated)?;
+        let deflated = match method {
+            0 => false,
+            8 => true,
+            method => return Err(invalid("ZIP", format!("{} uses unsupported compression method {}", name, method))),
+        };
+        entries.push(ArchiveMember {
+            name,
+            // Stored data is its own size, whatever the record says
+            size: if deflated { size as u64 } else { compressed_size as u64 },
+            start,
+            len: compressed_size,
+            deflated,
+        });
+    }
+    Ok(entries)
+}
+
+fn list_tar(bytes: &[u8], wanted: &dyn Fn(&str) -> bool) -> Result<Vec<ArchiveMember>, ParseError> {
+    let mut entries = Vec::new();
+    let mut long_name = None;
+    let mut at = 0;
+    while let Some(header) = bytes.get(at..at + TAR_BLOCK) {
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        let size_field = text_at(&header[124..136]);
+        let size = usize::from_str_radix(&size_field, 8)
+            .map_err(|_| invalid("TAR", format!("bad size {:?} at offset {}", size_field, at)))?;
+        let start = at + TAR_BLOCK;
+        let data = bytes.get(start..start + size).ok_or_else(|| invalid("TAR", "truncated archive"))?;
+        at += TAR_BLOCK + size.div_ceil(TAR_BLOCK) * TAR_BLOCK;
+
+        match header[156] {
+            // GNU long name of the next entry
+            b'L' => long_name = Some(text_at(data)),
+            // PAX extended header; only the path is used
+            b'x' => long_name = pax_path(data).or(long_name),
+            b'0' | 0 => {
+                let name = long_name.take().unwrap_or_else(|| {
+                    let name = text_at(&header[..100]);
+                    let prefix = text_at(&header[345..500]);
+                    if &header[257..262] == b"ustar" && !prefix.is_empty() {
+                        format!("{}/{}", prefix, name)
+                    } else {
+                        name
+                    }
+                });
+                let name = clean_name(&name);
+                if !name.is_empty() && wanted(&name) {
+                    entries.push(ArchiveMember {
+                        name,
+                        size: size as u64,
+                        start,
+                        len: size,
+                        deflated: false,
+                    });
+                }
+            }
+            _ => long_name = None,
+        }
+    }
+    Ok(entries)
+}
+
+/// The `path` record of a PAX extended header (`"<len> path=<value>\n"`).
+fn pax_path(data: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(data)
+        .lines()
+        .filter_map(|record| record.split_once(' ')?.1.strip_prefix("path="))
+        .next()
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::ZipWriter;
+
+    fn tar_header(name: &str, size: usize, kind: u8) -> Vec<u8> {
+        let mut header = vec![0; TAR_BLOCK];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[124..135].copy_from_slice(format!("{:011o}", size).as_bytes());
+        header[156] = kind;
+        header[257..262].copy_from_slice(b"ustar");
+        header
+    }
+
+    fn padded(data: &[u8]) -> Vec<u8> {
+        let mut block = data.to_vec();
+        block.resize(data.len().div_ceil(TAR_BLOCK) * TAR_BLOCK, 0);
+        block
+    }
+
+    #[test]
+    fn test_read_archive() {
+        let spc = crate::spectre::SpcFileBuilder::new("CAM-1", vec![1.0, 2.0, 3.0]).build().to_bytes();
+
+        let mut zip = ZipWriter::new(Vec::new());
+        zip.add("session/a.spc", &spc).unwrap();
+        zip.add("session/notes.txt", b"notes").unwrap();
+        zip.add("../escape/b.SPC", b"not a container").unwrap();
+        let zip = zip.finish().unwrap();
+        assert!(is_archive(&zip));
+        let spectra = read_spc_archive(&zip).unwrap();
+        assert_eq!(spectra.len(), 2);
+        assert_eq!(spectra[0].0, "session/a.spc");
+        assert_eq!(spectra[0].1.as_ref().unwrap().data, vec![1.0, 2.0, 3.0]);
+        assert_eq!(spectra[1].0, "escape/b.SPC");
+        assert!(spectra[1].1.is_err());
+
+        // A long name from a GNU header, then a plain entry
+        let long = format!("{}/c.spc", "d".repeat(120));
+        let mut tar = tar_header("././@LongLink", long.len(), b'L');
+        tar.extend(padded(long.as_bytes()));
+        tar.extend(tar_header("short.txt", spc.len(), b'0'));
+        tar.extend(padded(&spc));
+        tar.extend(tar_header("x.spc", spc.len(), b'0'));
+        tar.extend(padded(&spc));
+        tar.extend(vec![0; 2 * TAR_BLOCK]);
+        assert!(is_archive(&tar));
+        let entries = read_archive(&tar, |_| true).unwrap();
+        let names: Vec<&str> = entries.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, [long.as_str(), "x.spc"]);
+        assert_eq!(entries[1].data, spc);
+
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut gzip, &tar).unwrap();
+        assert_eq!(read_spc_archive(&gzip.finish().unwrap()).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_archive_reader() {
+        let mut zip = ZipWriter::new(Vec::new());
+        zip.add("a.spc", &[0; 10_000]).unwrap();
+        zip.add("notes.txt", b"notes").unwrap();
+        zip.add("b.spc", b"b").unwrap();
+        let zip = zip.finish().unwrap();
+        let spc = |name: &str| name.ends_with(".spc");
+
+        // Files are listed with their recorded size and read on request
+        let reader = ArchiveReader::open(&zip[..], spc, 10_001).unwrap();
+        let members: Vec<(&str, u64)> = reader.members().iter().map(|m| (m.name.as_str(), m.size)).collect();
+        assert_eq!(members, [("a.spc", 10_000), ("b.spc", 1)]);
+        assert_eq!(reader.read(1).unwrap(), b"b");
+        assert_eq!(reader.read(0).unwrap(), [0; 10_000]);
+
+        // The listed files together must fit the budget
+        match ArchiveReader::open(&zip[..], spc, 10_000) {
+            Err(ParseError::LimitExceeded { limit, .. }) => assert_eq!(limit, 10_000),
+            other => panic!("unexpected result: {:?}", other.map(|r| r.size())),
+        }
+
+        // A file decompressing to more than its recorded size fails
+        let mut lying = zip.clone();
+        let central = lying.windows(4).position(|w| w == ZIP_CENTRAL_HEADER).unwrap();
+        lying[central + 24..central + 28].copy_from_slice(&100u32.to_le_bytes());
+        let reader = ArchiveReader::open(&lying[..], spc, 10_001).unwrap();
+        assert_eq!(reader.size(), 101);
+        assert!(reader.read(0).unwrap_err().to_string().contains("larger than its recorded size"));
+    }
+}
@@ -0,0 +1,278 @@
+//! Thermo Galactic SPC input (GRAMS "new format", version 0x4B).
+//!
+//! The counterpart of [`crate::output::galactic`]. Each subfile becomes a
+//! spectrum; x values in nanometers or Raman shift become the wavelength or
+//! Raman shift axis, other x units are kept as pixel order only.
+
+use crate::output::galactic::{
+    unpack_date, FLOAT_DATA, HEADER_SIZE, SUBHEADER_SIZE, TXVALS, VERSION_NEW_LSB, X_NANOMETERS, X_RAMAN_SHIFT,
+};
+use super::{spectrum, text_at};
+use crate::parser::ParseError;
+use crate::spectre::SpcFile;
+use std::collections::BTreeMap;
+
+/// `ftflgs`: 16-bit integer y values.
+const TSPREC: u8 = 0x01;
+/// `ftflgs`: several subfiles.
+const TMULTI: u8 = 0x04;
+/// `ftflgs`: each subfile has its own x values.
+const TXYXYS: u8 = 0x40;
+/// Old-format (0x4D) and MSB (0x4C) version bytes, which are not read.
+const VERSION_OLD: u8 = 0x4D;
+const VERSION_NEW_MSB: u8 = 0x4C;
+
+/// Whether `bytes` look like a Galactic SPC file rather than a Spectrum
+/// Analyzer Suite container (both use the `.spc` extension), whose second
+/// byte is always `C`.
+pub fn is_galactic(bytes: &[u8]) -> bool {
+    matches!(bytes.get(1), Some(&(VERSION_NEW_LSB | VERSION_NEW_MSB | VERSION_OLD)))
+}
+
+/// Read a Galactic SPC file, one spectrum per subfile.
+///
+/// A comment written by [`write_galactic`](crate::output::write_galactic)
+/// (`UID: ...; Laser: ... nm`) restores the UID and laser wavelength; any
+/// other comment is kept as `comment` metadata. The `fdate` field gives the
+/// acquisition time, to the minute. Subfiles of a multi-subfile
+/// file carry their `subfile` index and `z` value as metadata.
+pub fn read_galactic(bytes: &[u8]) -> Result<Vec<SpcFile>, ParseError> {
+    if bytes.len() < HEADER_SIZE {
+        return Err(ParseError::FileTooSmall {
+            expected: HEADER_SIZE,
+            actual: bytes.len(),
+        });
+    }
+    let invalid = |message: String| ParseError::InvalidImport {
+        format: "Galactic SPC".to_string(),
+        message,
+    };
+    match bytes[1] {
+        VERSION_NEW_LSB => {}
+        VERSION_OLD => return Err(invalid("old-format (0x4D) files are not supported".to_string())),
+        VERSION_NEW_MSB => return Err(invalid("big-endian (0x4C) files are not supported".to_string())),
+        version => return Err(invalid(format!("unknown version byte 0x{:02X}", version))),
+    }
+
+    let flags = bytes[0];
+    let exponent = bytes[3];
+    let num_points = u32_at(bytes, 4) as usize;
+    let (first, last) = (f64_at(bytes, 8), f64_at(bytes, 16));
+    let num_subfiles = match u32_at(bytes, 24) {
+        n if flags & TMULTI != 0 => n as usize,
+        _ => 1,
+    };
+    if num_points > bytes.len() {
+        return Err(invalid(format!("{} points do not fit in {} bytes", num_points, bytes.len())));
+    }
+    let x_type = bytes[28];
+    let acquired = unpack_date(u32_at(bytes, 32));
+    let comment = text_at(&bytes[88..218]);
+
+    let mut reader = Reader { bytes, position: HEADER_SIZE };
+    let shared_x = if flags & TXYXYS != 0 {
+        None
+    } else if flags & TXVALS != 0 {
+        Some(reader.f32_values(num_points)?)
+    } else {
+        let step = if num_points > 1 { (last - first) / (num_points - 1) as f64 } else { 0.0 };
+        Some((0..num_points).map(|i| first + step * i as f64).collect())
+    };
+
+    let (uid, laser, comment) = parse_comment(&comment);
+    let mut spectra = Vec::with_capacity(num_subfiles.min(bytes.len() / SUBHEADER_SIZE));
+    for index in 0..num_subfiles {
+        let subheader = reader.take(SUBHEADER_SIZE)?;
+        let z = f32::from_le_bytes(subheader[4..8].try_into().unwrap());
+        let (x, n) = match &shared_x {
+            Some(x) => (x.clone(), num_points),
+            None => {
+                let n = u32::from_le_bytes(subheader[16..20].try_into().unwrap()) as usize;
+                (reader.f32_values(n)?, n)
+            }
+        };
+        // Single files may leave the subfile exponent zero
+        let exponent = if flags & TMULTI != 0 { subheader[1] } else { exponent };
+        let data = reader.y_values(n, exponent, flags & TSPREC != 0)?;
+
+        let mut metadata = BTreeMap::new();
+        if let Some(ref comment) = comment {
+            metadata.insert("comment".to_string(), comment.clone());
+        }
+        if num_subfiles > 1 {
+            metadata.insert("subfile".to_string(), index.to_string());
+            metadata.insert("z".to_string(), z.to_string());
+        }
+
+        let (wavelength_axis, raman_shift_axis) = match (x_type, laser) {
+            (X_NANOMETERS, _) => (Some(x), None),
+            (X_RAMAN_SHIFT, Some(laser)) => {
+                let nm = x.iter().map(|&shift| 1.0 / (1.0 / laser - shift / 1e7)).collect();
+                (Some(nm), None)
+            }
+            (X_RAMAN_SHIFT, None) => (None, Some(x)),
+            _ => (None, None),
+        };
+
+        let mut spc = SpcFile {
+            uid: uid.clone(),
+            wavelength_axis,
+            raman_shift_axis,
+            acquired: acquired.clone(),
+            metadata,
+            ..spectrum(data)
+        };
+        if let Some(laser) = laser {
+            spc.set_laser_wavelength(laser);
+        }
+        spectra.push(spc);
+    }
+
+    Ok(spectra)
+}
+
+/// Split a comment into the UID and laser written by `write_galactic`, or
+/// return it as is.
+fn parse_comment(comment: &str) -> (String, Option<f64>, Option<String>) {
+    let Some(rest) = comment.strip_prefix("UID: ") else {
+        return (String::new(), None, (!comment.is_empty()).then(|| comment.to_string()));
+    };
+    match rest.split_once("; Laser: ") {
+        Some((uid, laser)) => {
+            let laser = laser.trim_end_matches(" nm").parse().ok();
+            (uid.to_string(), laser, None)
+        }
+        None => (rest.to_string(), None, None),
+    }
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn f64_at(bytes: &[u8], offset: usize) -> f64 {
+    f64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+/// Sequential reads past the main header.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, size: usize) -> Result<&'a [u8], ParseError> {
+        let end = self.position.checked_add(size).filter(|&end| end <= self.bytes.len());
+        match end {
+            Some(end) => {
+                let slice = &self.bytes[self.position..end];
+                self.position = end;
+                Ok(slice)
+            }
+            None => Err(ParseError::InvalidOffset {
+                offset: (self.position as u64).saturating_add(size as u64),
+                size: self.bytes.len(),
+            }),
+        }
+    }
+
+    fn f32_values(&mut self, n: usize) -> Result<Vec<f64>, ParseError> {
+        let bytes = self.take(n.saturating_mul(4))?;
+        Ok(bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()) as f64)
+            .collect())
+    }
+
+    /// Y values: IEEE floats, or integers scaled by `2^(exponent - bits)`.
+    fn y_values(&mut self, n: usize, exponent: u8, short: bool) -> Result<Vec<f64>, ParseError> {
+        if exponent == FLOAT_DATA {
+            return self.f32_values(n);
+        }
+        let exponent = exponent as i8 as i32;
+        if short {
+            let scale = 2f64.powi(exponent - 16);
+            let bytes = self.take(n.saturating_mul(2))?;
+            Ok(bytes
+                .chunks_exact(2)
+                .map(|b| i16::from_le_bytes(b.try_into().unwrap()) as f64 * scale)
+                .collect())
+        } else {
+            let scale = 2f64.powi(exponent - 32);
+            let bytes = self.take(n.saturating_mul(4))?;
+            Ok(bytes
+                .chunks_exact(4)
+                .map(|b| i32::from_le_bytes(b.try_into().unwrap()) as f64 * scale)
+                .collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::to_galactic_bytes;
+    use crate::Calibration;
+
+    #[test]
+    fn test_galactic_round_trip() {
+        let mut spc = SpcFile {
+            uid: "CAM-1".to_string(),
+            data: vec![10.0, 20.5, 30.25, 15.0],
+            blank: Vec::new(),
+            calibration: None,
+            config: None,
+            wavelength_axis: None,
+            raman_shift_axis: None,
+            acquired: None,
+            operator: None,
+            sample: None,
+            instrument: None,
+            provenance: None,
+            stats: None,
+            metadata: BTreeMap::new(),
+            history: Vec::new(),
+            extras: Vec::new(),
+        };
+        spc.set_calibration(Calibration::legendre(vec![800.0, 20.0]));
+        spc.set_laser_wavelength(785.0);
+
+        let bytes = to_galactic_bytes(&spc).unwrap();
+        assert!(is_galactic(&bytes));
+        let spectra = read_galactic(&bytes).unwrap();
+        assert_eq!(spectra.len(), 1);
+        let read = &spectra[0];
+        assert_eq!(read.uid, "CAM-1");
+        assert_eq!(read.data, spc.data);
+        assert_eq!(read.config.as_ref().unwrap().raman_wavelength, Some(785.0));
+        let shift = read.raman_shift_axis.as_ref().unwrap();
+        for (read, written) in shift.iter().zip(spc.raman_shift_axis.as_ref().unwrap()) {
+            assert!((read - written).abs() < 0.01);
+        }
+
+        // Two subfiles of 16-bit integers with an evenly spaced axis
+        let mut multi = vec![0u8; HEADER_SIZE];
+        multi[0] = TMULTI | TSPREC;
+        multi[1] = VERSION_NEW_LSB;
+        multi[4..8].copy_from_slice(&3u32.to_le_bytes());
+        multi[8..16].copy_from_slice(&100.0f64.to_le_bytes());
+        multi[16..24].copy_from_slice(&300.0f64.to_le_bytes());
+        multi[24..28].copy_from_slice(&2u32.to_le_bytes());
+        multi[28] = X_NANOMETERS;
+        for (z, scale) in [(0.0f32, 1i16), (1.5, 2)] {
+            let mut subheader = [0u8; SUBHEADER_SIZE];
+            subheader[1] = 16; // 2^(16 - 16) = 1
+            subheader[4..8].copy_from_slice(&z.to_le_bytes());
+            multi.extend_from_slice(&subheader);
+            for y in [1i16, -2, 3] {
+                multi.extend_from_slice(&(y * scale).to_le_bytes());
+            }
+        }
+        let spectra = read_galactic(&multi).unwrap();
+        assert_eq!(spectra.len(), 2);
+        assert_eq!(spectra[1].data, vec![2.0, -4.0, 6.0]);
+        assert_eq!(spectra[1].wavelength_axis, Some(vec![100.0, 200.0, 300.0]));
+        assert_eq!(spectra[1].metadata["z"], "1.5");
+        assert!(read_galactic(&multi[..multi.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,100 @@
+//! The converter's own JSON output, read back as spectra.
+
+use crate::output::SCHEMA_VERSION;
+use crate::parser::ParseError;
+use crate::spectre::SpcFile;
+use serde_json::Value;
+
+/// Read spectra written by the JSON or NDJSON output: one document, an
+/// array of documents, an object of documents keyed by file id, or one
+/// document per line. Documents of a newer
+/// `schema_version` than this build writes are rejected. Missing axes are
+/// generated from the calibration and laser wavelength; `stats`, `history`
+/// and `extras` are not read back.
+pub fn read_json(text: &str) -> Result<Vec<SpcFile>, ParseError> {
+    let invalid = |message: String| ParseError::InvalidImport {
+        format: "JSON".to_string(),
+        message,
+    };
+
+    let documents = match serde_json::from_str::<Value>(text) {
+        Ok(Value::Array(documents)) => documents,
+        // Documents keyed by file id (--single-output --keyed)
+        Ok(Value::Object(keyed)) if !keyed.contains_key("data") && keyed.values().all(Value::is_object) => {
+            keyed.into_iter().map(|(_, document)| document).collect()
+        }
+        Ok(document) => vec![document],
+        // NDJSON
+        Err(_) => text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(number, line)| serde_json::from_str(line).map_err(|e| invalid(format!("line {}: {}", number + 1, e))))
+            .collect::<Result<_, _>>()?,
+    };
+    if documents.is_empty() {
+        return Err(invalid("no spectrum in the file".to_string()));
+    }
+
+    documents
+        .into_iter()
+        .enumerate()
+        .map(|(index, document)| {
+            let version = document.get("schema_version").and_then(Value::as_u64).unwrap_or(1);
+            if version > SCHEMA_VERSION as u64 {
+                return Err(invalid(format!(
+                    "spectrum {}: schema_version {} is newer than the supported {}",
+                    index, version, SCHEMA_VERSION
+                )));
+            }
+            let mut spc: SpcFile =
+                serde_json::from_value(document).map_err(|e| invalid(format!("spectrum {}: {}", index, e)))?;
+            if spc.wavelength_axis.is_none() {
+                if let Some(calibration) = spc.calibration.clone() {
+                    spc.set_calibration(calibration);
+                }
+            }
+            Ok(spc)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::{to_json_string_spc, write_json_object, write_ndjson};
+    use crate::spectre::SpcFileBuilder;
+    use crate::Calibration;
+
+    #[test]
+    fn test_read_json() {
+        let spc = SpcFileBuilder::new("CAM-1", vec![1.0, f64::NAN, 3.0])
+            .blank(vec![0.5; 3])
+            .calibration(Calibration::legendre(vec![850.0, 60.0, -0.5, 0.1]))
+            .laser_wavelength(785.0)
+            .build();
+
+        let parsed = read_json(&to_json_string_spc(&spc, true).unwrap()).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].uid, "CAM-1");
+        assert!(parsed[0].data[1].is_nan());
+        assert_eq!(parsed[0].raman_shift_axis, spc.raman_shift_axis);
+        // A .spc written from the JSON parses like the original
+        let bytes = parsed[0].to_bytes();
+        assert_eq!(SpcFile::from_bytes(&bytes).unwrap().calibration.unwrap().coefficients.len(), 4);
+
+        let mut ndjson = Vec::new();
+        write_ndjson(&[("a", &spc), ("b", &spc)], &mut ndjson).unwrap();
+        assert_eq!(read_json(std::str::from_utf8(&ndjson).unwrap()).unwrap().len(), 2);
+        let mut keyed = Vec::new();
+        write_json_object(&[("a", &spc), ("b", &spc)], &mut keyed, false).unwrap();
+        assert_eq!(read_json(std::str::from_utf8(&keyed).unwrap()).unwrap().len(), 2);
+
+        // Axes left out are regenerated
+        let bare = r#"{"uid": "X", "data": [1, 2, 3], "calibration": {"coefficients": [850, 60]}}"#;
+        assert_eq!(read_json(bare).unwrap()[0].wavelength_axis.as_ref().map(Vec::len), Some(3));
+
+        let future = format!(r#"{{"schema_version": {}, "uid": "X", "data": []}}"#, SCHEMA_VERSION + 1);
+        assert!(matches!(read_json(&future), Err(ParseError::InvalidImport { .. })));
+    }
+}
@@ -0,0 +1,43 @@
+//! Readers for other vendors' spectrum formats.
+
+pub mod archive;
+pub mod galactic;
+mod json;
+mod spe;
+mod text;
+
+pub use self::galactic::*;
+pub use self::json::*;
+pub use self::spe::*;
+pub use self::text::*;
+
+use crate::spectre::SpcFile;
+use std::collections::BTreeMap;
+
+/// A spectrum with only intensities, for the readers to fill in.
+fn spectrum(data: Vec<f64>) -> SpcFile {
+    SpcFile {
+        uid: String::new(),
+        data,
+        blank: Vec::new(),
+        calibration: None,
+        config: None,
+        wavelength_axis: None,
+        raman_shift_axis: None,
+        acquired: None,
+        operator: None,
+        sample: None,
+        instrument: None,
+        provenance: None,
+        stats: None,
+        metadata: BTreeMap::new(),
+        history: Vec::new(),
+        extras: Vec::new(),
+    }
+}
+
+/// A NUL-terminated text field of a binary header.
+fn text_at(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).trim().to_string()
+}
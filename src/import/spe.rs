@@ -0,0 +1,279 @@
+//! Princeton Instruments SPE input (WinSpec 2.x and LightField 3.0).
+//!
+//! Both versions share the 4100-byte binary header holding the frame size,
+//! pixel type and frame count. Version 2 stores the wavelength calibration
+//! as a polynomial in the header; version 3 lists the wavelength of every
+//! pixel in the XML footer that follows the data.
+
+use super::{spectrum, text_at};
+use crate::parser::ParseError;
+use crate::spectre::SpcFile;
+
+/// Binary header size; the data follows.
+const HEADER_SIZE: usize = 4100;
+/// `lastvalue`, the last header field, always 0x5555.
+const LAST_VALUE: u16 = 0x5555;
+
+/// Pixel types (`datatype`).
+const FLOAT32: i16 = 0;
+const INT32: i16 = 1;
+const INT16: i16 = 2;
+const UINT16: i16 = 3;
+const FLOAT64: i16 = 5;
+const UINT8: i16 = 6;
+const UINT32: i16 = 8;
+
+/// Whether `bytes` look like an SPE file.
+pub fn is_spe(bytes: &[u8]) -> bool {
+    bytes.len() >= HEADER_SIZE && u16_at(bytes, 4098) == LAST_VALUE
+}
+
+/// Read an SPE file, one spectrum per frame.
+///
+/// Frames of several rows are binned into one spectrum by summing the
+/// rows, as a spectroscopy camera does in full vertical binning; the row
+/// count is kept as `rows` metadata. The wavelength calibration, when valid,
+/// becomes the wavelength axis, and a Raman laser wavelength stored with a
+/// version 2 calibration also gives the Raman shift axis. The date (as
+/// stored, e.g. `01Mar2024`) becomes the acquisition time, and the exposure
+/// time and comments are kept as metadata.
+pub fn read_spe(bytes: &[u8]) -> Result<Vec<SpcFile>, ParseError> {
+    if bytes.len() < HEADER_SIZE {
+        return Err(ParseError::FileTooSmall {
+            expected: HEADER_SIZE,
+            actual: bytes.len(),
+        });
+    }
+    let invalid = |message: String| ParseError::InvalidImport {
+        format: "SPE".to_string(),
+        message,
+    };
+    if !is_spe(bytes) {
+        return Err(invalid("missing the 0x5555 end-of-header marker".to_string()));
+    }
+
+    let width = u16_at(bytes, 42) as usize;
+    let height = u16_at(bytes, 656) as usize;
+    let num_frames = i32_at(bytes, 1446).max(0) as usize;
+    let data_type = u16_at(bytes, 108) as i16;
+    let version = f32_at(bytes, 1992);
+    let pixel_size = match data_type {
+        FLOAT32 | INT32 | UINT32 => 4,
+        INT16 | UINT16 => 2,
+        FLOAT64 => 8,
+        UINT8 => 1,
+        other => return Err(invalid(format!("unknown pixel type {}", other))),
+    };
+
+    let footer = (version >= 3.0)
+        .then(|| u64_at(bytes, 678) as usize)
+        .filter(|&offset| offset > HEADER_SIZE && offset < bytes.len())
+        .map(|offset| String::from_utf8_lossy(&bytes[offset..]).into_owned());
+
+    // LightField may append per-frame metadata: frames are `stride` apart
+    let frame_size = width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(pixel_size))
+        .filter(|&size| size > 0)
+        .ok_or_else(|| invalid(format!("empty or oversized {}x{} frames", width, height)))?;
+    let stride = footer
+        .as_deref()
+        .and_then(|xml| attribute(element(xml, "DataBlock")?, "stride"))
+        .and_then(|stride| stride.parse().ok())
+        .filter(|&stride: &usize| stride >= frame_size)
+        .unwrap_or(frame_size);
+    let data_end = num_frames
+        .checked_mul(stride)
+        .and_then(|size| size.checked_add(HEADER_SIZE))
+        .filter(|&end| end <= bytes.len())
+        .ok_or_else(|| invalid(format!("{} frames of {} bytes do not fit in the file", num_frames, stride)))?;
+
+    let wavelengths = match footer {
+        Some(ref xml) => footer_wavelengths(xml),
+        None => header_wavelengths(bytes, width),
+    }
+    .filter(|axis| axis.len() == width);
+    let laser = match footer {
+        Some(_) => None,
+        None => Some(f64_at(bytes, 3311)).filter(|&nm| nm > 0.0 && nm.is_finite()),
+    };
+
+    let mut metadata = Vec::new();
+    let exposure = f32_at(bytes, 10);
+    if exposure > 0.0 {
+        metadata.push(("exposure_s".to_string(), exposure.to_string()));
+    }
+    let date = text_at(&bytes[20..30]);
+    if !date.is_empty() {
+        metadata.push(("acquired".to_string(), date));
+    }
+    let comments: Vec<String> = (0..5)
+        .map(|i| text_at(&bytes[200 + i * 80..280 + i * 80]))
+        .filter(|comment| !comment.is_empty())
+        .collect();
+    if !comments.is_empty() {
+        metadata.push(("comment".to_string(), comments.join(" ")));
+    }
+    if height > 1 {
+        metadata.push(("rows".to_string(), height.to_string()));
+    }
+
+    let mut spectra = Vec::with_capacity(num_frames);
+    for (index, frame) in bytes[HEADER_SIZE..data_end].chunks_exact(stride).enumerate() {
+        let mut data = vec![0.0; width];
+        for (i, pixel) in frame[..frame_size].chunks_exact(pixel_size).enumerate() {
+            data[i % width] += pixel_value(pixel, data_type);
+        }
+
+        let mut spc = spectrum(data);
+        spc.wavelength_axis = wavelengths.clone();
+        spc.extend_metadata(metadata.iter().cloned());
+        if num_frames > 1 {
+            spc.metadata.insert("frame".to_string(), index.to_string());
+        }
+        if let Some(laser) = laser {
+            spc.set_laser_wavelength(laser);
+        }
+        spectra.push(spc);
+    }
+
+    Ok(spectra)
+}
+
+fn pixel_value(bytes: &[u8], data_type: i16) -> f64 {
+    match data_type {
+        FLOAT32 => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        INT32 => i32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        UINT32 => u32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        INT16 => i16::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        UINT16 => u16::from_le_bytes(bytes.try_into().unwrap()) as f64,
+        FLOAT64 => f64::from_le_bytes(bytes.try_into().unwrap()),
+        _ => bytes[0] as f64,
+    }
+}
+
+/// The version 2 calibration polynomial, evaluated at the 1-based pixel
+/// numbers WinSpec uses.
+fn header_wavelengths(bytes: &[u8], width: usize) -> Option<Vec<f64>> {
+    let valid = bytes[3098] != 0;
+    let order = bytes[3101] as usize;
+    if !valid || order > 5 {
+        return None;
+    }
+    let coefficients: Vec<f64> = (0..=order).map(|i| f64_at(bytes, 3263 + i * 8)).collect();
+    if coefficients.iter().all(|&c| c == 0.0) {
+        return None;
+    }
+    Some(
+        (1..=width)
+            .map(|pixel| coefficients.iter().rev().fold(0.0, |sum, &c| sum * pixel as f64 + c))
+            .collect(),
+    )
+}
+
+/// The comma-separated `<Wavelength>` list of a version 3 footer.
+fn footer_wavelengths(xml: &str) -> Option<Vec<f64>> {
+    let start = xml.find("<Wavelength ").or_else(|| xml.find("<Wavelength>"))?;
+    let content = &xml[start..];
+    let content = &content[content.find('>')? + 1..];
+    let content = &content[..content.find('<')?];
+    content.split(',').map(|value| value.trim().parse().ok()).collect()
+}
+
+/// The opening tag of the first `name` element.
+fn element<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{} ", name))?;
+    let end = start + xml[start..].find('>')?;
+    Some(&xml[start..end])
+}
+
+/// The value of an attribute in an opening tag.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let start = tag.find(&format!(" {}=\"", name))? + name.len() + 3;
+    let end = start + tag[start..].find('"')?;
+    Some(&tag[start..end])
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap())
+}
+
+fn i32_at(bytes: &[u8], offset: usize) -> i32 {
+    i32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn f32_at(bytes: &[u8], offset: usize) -> f32 {
+    f32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+fn f64_at(bytes: &[u8], offset: usize) -> f64 {
+    f64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A header for `frames` frames of 3 x `rows` 16-bit pixels.
+    fn header(rows: u16, frames: i32) -> Vec<u8> {
+        let mut bytes = vec![0u8; HEADER_SIZE];
+        bytes[10..14].copy_from_slice(&0.5f32.to_le_bytes());
+        bytes[42..44].copy_from_slice(&3u16.to_le_bytes());
+        bytes[108..110].copy_from_slice(&UINT16.to_le_bytes());
+        bytes[656..658].copy_from_slice(&rows.to_le_bytes());
+        bytes[1446..1450].copy_from_slice(&frames.to_le_bytes());
+        bytes[4098..4100].copy_from_slice(&LAST_VALUE.to_le_bytes());
+        bytes
+    }
+
+    fn bytes_f32(bytes: &mut [u8], offset: usize, value: f32) {
+        bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    #[test]
+    fn test_read_spe() {
+        // Version 2: two frames, wavelength = 500 + 2 * pixel, Raman laser
+        let mut v2 = header(1, 2);
+        bytes_f32(&mut v2, 1992, 2.5);
+        v2[3098] = 1;
+        v2[3101] = 1;
+        v2[3263..3271].copy_from_slice(&500.0f64.to_le_bytes());
+        v2[3271..3279].copy_from_slice(&2.0f64.to_le_bytes());
+        v2[3311..3319].copy_from_slice(&488.0f64.to_le_bytes());
+        for value in [1u16, 2, 3, 4, 5, 6] {
+            v2.extend_from_slice(&value.to_le_bytes());
+        }
+        let spectra = read_spe(&v2).unwrap();
+        assert_eq!(spectra.len(), 2);
+        assert_eq!(spectra[1].data, vec![4.0, 5.0, 6.0]);
+        assert_eq!(spectra[0].wavelength_axis, Some(vec![502.0, 504.0, 506.0]));
+        assert!(spectra[0].raman_shift_axis.is_some());
+        assert_eq!(spectra[0].metadata["exposure_s"], "0.5");
+        assert_eq!(spectra[1].metadata["frame"], "1");
+
+        // Version 3: one frame of two rows, binned, wavelengths in the footer
+        let mut v3 = header(2, 1);
+        bytes_f32(&mut v3, 1992, 3.0);
+        for value in [1u16, 2, 3, 10, 20, 30] {
+            v3.extend_from_slice(&value.to_le_bytes());
+        }
+        let footer_offset = v3.len() as u64;
+        v3[678..686].copy_from_slice(&footer_offset.to_le_bytes());
+        v3.extend_from_slice(
+            b"<SpeFormat><Calibrations><WavelengthMapping><Wavelength xml:space=\"preserve\">\
+              600.5,601.5,602.5</Wavelength></WavelengthMapping></Calibrations></SpeFormat>",
+        );
+        let spectra = read_spe(&v3).unwrap();
+        assert_eq!(spectra.len(), 1);
+        assert_eq!(spectra[0].data, vec![11.0, 22.0, 33.0]);
+        assert_eq!(spectra[0].wavelength_axis, Some(vec![600.5, 601.5, 602.5]));
+        assert_eq!(spectra[0].metadata["rows"], "2");
+
+        // Frames missing from the end of the file
+        assert!(read_spe(&v2[..v2.len() - 2]).is_err());
+    }
+}
@@ -0,0 +1,242 @@
+//! Delimited text input: CSV, TSV and whitespace-separated columns.
+//!
+//! Accepts two-column `x,y` files, index/intensity files, a single column
+//! of intensities, and the CSV and pairs files this crate writes.
+
+use super::spectrum;
+use crate::parser::ParseError;
+use crate::spectre::{AxisType, Config, SpcFile};
+
+/// What a column holds, from its heading.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Column {
+    Index,
+    Wavelength,
+    RamanShift,
+    Intensity,
+    Blank,
+    Other,
+}
+
+impl Column {
+    fn from_heading(heading: &str) -> Self {
+        let heading = heading.trim().trim_matches('"').to_lowercase();
+        let has = |words: &[&str]| words.iter().any(|word| heading.contains(word));
+        if heading == "y" {
+            Column::Intensity
+        } else if has(&["blank", "background", "dark"]) {
+            Column::Blank
+        } else if has(&["raman", "shift", "cm-1", "cm⁻¹", "wavenumber"]) {
+            Column::RamanShift
+        } else if has(&["wavelength", "nm", "lambda"]) {
+            Column::Wavelength
+        } else if has(&["index", "pixel"]) {
+            Column::Index
+        } else if has(&["intensity", "counts", "signal"]) {
+            Column::Intensity
+        } else {
+            Column::Other
+        }
+    }
+
+    fn from_axis(axis: AxisType) -> Self {
+        match axis {
+            AxisType::Pixels => Column::Index,
+            AxisType::Wavelengths => Column::Wavelength,
+            AxisType::RamanShifts => Column::RamanShift,
+        }
+    }
+}
+
+/// Read a spectrum from delimited text.
+///
+/// The delimiter (tab, `;`, `,` or whitespace) is detected from the first
+/// row; with `;`, decimal commas are accepted. An optional heading row
+/// names the columns (`wavelength_nm`, `raman_shift`, `intensity`, `blank`,
+/// ...). Without one, a single column is the intensity and with two or more
+/// the first is x and the last the intensity. Unlabelled x values that are
+/// not a `0, 1, 2, ...` index are taken as `x_axis`, by default Raman shift.
+///
+/// `# key: value` comment lines become metadata, except the `X-axis` and
+/// `Laser` lines of the pairs format, which set the x-axis (and whether a
+/// third column is the blank) and laser wavelength, and the CSV acquisition header (`uid`, `laser_nm`,
+/// `exposure`, `gain`; see also [`SpcFile::extend_metadata`]).
+pub fn read_text(text: &str, x_axis: Option<AxisType>) -> Result<SpcFile, ParseError> {
+    let invalid = |message: String| ParseError::InvalidImport {
+        format: "text".to_string(),
+        message,
+    };
+
+    let mut metadata = Vec::new();
+    let mut uid = String::new();
+    let mut laser = None;
+    let mut settings = Config::default();
+    let mut x_axis = x_axis.map(Column::from_axis);
+    let mut pairs_blank = false;
+    let mut headings: Option<Vec<Column>> = None;
+    let mut delimiter = None;
+    let mut rows: Vec<Vec<f64>> = Vec::new();
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim().trim_start_matches('\u{feff}');
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(comment) = line.strip_prefix('#') {
+            match comment.trim().split_once(':') {
+                Some(("X-axis", value)) => {
+                    let name = value.split(',').next().unwrap_or_default();
+                    x_axis = x_axis.or(Some(Column::from_heading(name)));
+                    pairs_blank = value.trim_end().ends_with("Blank");
+                }
+                Some(("Laser", value)) => {
+                    laser = value.split(',').next().and_then(|nm| nm.trim().trim_end_matches("nm").parse().ok());
+                }
+                // The CSV acquisition header; the calibration is already
+                // applied to the axis columns
+                Some(("uid", value)) => uid = value.trim().to_string(),
+                Some(("laser_nm", value)) => laser = value.trim().parse().ok(),
+                Some(("exposure", value)) => settings.exposure = value.trim().parse().ok(),
+                Some(("gain", value)) => settings.gain = value.trim().parse().ok(),
+                Some(("processing" | "calibration" | "calibration_coefficients", _)) | None => {}
+                Some((key, value)) => metadata.push((key.trim().to_string(), value.trim().to_string())),
+            }
+            continue;
+        }
+
+        let delimiter = *delimiter.get_or_insert_with(|| detect_delimiter(line));
+        let fields = split(line, delimiter);
+        let values: Option<Vec<f64>> = fields.iter().map(|field| parse_number(field, delimiter)).collect();
+        match values {
+            Some(values) => {
+                if rows.first().is_some_and(|first| first.len() != values.len()) {
+                    return Err(invalid(format!(
+                        "line {}: {} columns, expected {}",
+                        number + 1,
+                        values.len(),
+                        rows[0].len()
+                    )));
+                }
+                rows.push(values);
+            }
+            None if rows.is_empty() && headings.is_none() => {
+                headings = Some(fields.iter().map(|field| Column::from_heading(field)).collect());
+            }
+            None => return Err(invalid(format!("line {}: not a number in `{}`", number + 1, line))),
+        }
+    }
+
+    let num_columns = rows.first().map(Vec::len).ok_or_else(|| invalid("no data rows".to_string()))?;
+    let columns = match headings {
+        Some(headings) if headings.len() == num_columns => label_columns(headings),
+        Some(headings) => {
+            return Err(invalid(format!("{} headings for {} columns", headings.len(), num_columns)));
+        }
+        None => {
+            let mut columns = vec![Column::Other; num_columns];
+            columns[num_columns - 1] = Column::Intensity;
+            // Pairs with a blank: x, intensity, blank
+            if pairs_blank && num_columns == 3 {
+                columns[1..].copy_from_slice(&[Column::Intensity, Column::Blank]);
+            }
+            columns
+        }
+    };
+
+    let column = |kind: Column| -> Option<Vec<f64>> {
+        let index = columns.iter().position(|&c| c == kind)?;
+        Some(rows.iter().map(|row| row[index]).collect())
+    };
+    let data = column(Column::Intensity).ok_or_else(|| invalid("no intensity column".to_string()))?;
+    let mut spc = spectrum(data);
+    spc.uid = uid;
+    if settings.exposure.is_some() || settings.gain.is_some() {
+        spc.config = Some(settings);
+    }
+    spc.blank = column(Column::Blank).unwrap_or_default();
+    spc.wavelength_axis = column(Column::Wavelength);
+    spc.raman_shift_axis = column(Column::RamanShift);
+
+    // An unlabelled x column: an index, or the given (or pairs header) axis
+    if let Some(x) = column(Column::Other).filter(|_| columns[0] == Column::Other) {
+        let is_index = x.iter().enumerate().all(|(i, &v)| v == i as f64 || v == (i + 1) as f64);
+        match x_axis.unwrap_or(Column::RamanShift) {
+            _ if is_index => {}
+            Column::Wavelength => spc.wavelength_axis = Some(x),
+            Column::RamanShift => spc.raman_shift_axis = Some(x),
+            _ => {}
+        }
+    }
+
+    spc.extend_metadata(metadata);
+    if let Some(laser) = laser {
+        spc.set_laser_wavelength(laser);
+    }
+    Ok(spc)
+}
+
+/// Without an intensity heading, the last unrecognized column is the
+/// intensity.
+fn label_columns(mut columns: Vec<Column>) -> Vec<Column> {
+    if !columns.contains(&Column::Intensity) {
+        if let Some(last) = columns.iter().rposition(|&c| c == Column::Other) {
+            columns[last] = Column::Intensity;
+        }
+    }
+    columns
+}
+
+fn detect_delimiter(line: &str) -> Option<char> {
+    ['\t', ';', ','].into_iter().find(|&d| line.contains(d))
+}
+
+fn split(line: &str, delimiter: Option<char>) -> Vec<&str> {
+    match delimiter {
+        Some(delimiter) => line.split(delimiter).map(str::trim).collect(),
+        None => line.split_whitespace().collect(),
+    }
+}
+
+fn parse_number(field: &str, delimiter: Option<char>) -> Option<f64> {
+    let field = field.trim_matches('"');
+    match delimiter {
+        Some(';' | '\t') => field.replace(',', ".").parse().ok(),
+        _ => field.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_text() {
+        // Our own CSV output
+        let csv = "# sample: A1\nindex,wavelength_nm,intensity,blank\n0,800,10,1\n1,801,11,1\n2,802,12.5,1\n";
+        let spc = read_text(csv, None).unwrap();
+        assert_eq!(spc.data, vec![10.0, 11.0, 12.5]);
+        assert_eq!(spc.blank, vec![1.0; 3]);
+        assert_eq!(spc.wavelength_axis, Some(vec![800.0, 801.0, 802.0]));
+        assert_eq!(spc.sample.as_deref(), Some("A1"));
+
+        // Headerless, semicolons with decimal commas, x given by the caller
+        let spc = read_text("500,5;3\n501,5;4\n", Some(AxisType::Wavelengths)).unwrap();
+        assert_eq!(spc.data, vec![3.0, 4.0]);
+        assert_eq!(spc.wavelength_axis, Some(vec![500.5, 501.5]));
+
+        // Pairs output: the x-axis and laser come from the header
+        let pairs = "# Raman Spectrum\n# X-axis: Raman Shift (cm⁻¹), Y-axis: Intensity\n\
+                     # Laser: 785nm, Points: 2\n\n200.5, 7\n210.5, 8\n";
+        let spc = read_text(pairs, None).unwrap();
+        assert_eq!(spc.raman_shift_axis, Some(vec![200.5, 210.5]));
+        assert_eq!(spc.config.unwrap().raman_wavelength, Some(785.0));
+
+        // Tab-separated index/intensity
+        let spc = read_text("0\t4\n1\t5\n2\t6\n", None).unwrap();
+        assert!(spc.raman_shift_axis.is_none() && spc.wavelength_axis.is_none());
+        assert_eq!(spc.data, vec![4.0, 5.0, 6.0]);
+
+        assert!(read_text("1,2\n3\n", None).is_err());
+        assert!(read_text("# nothing\n", None).is_err());
+    }
+}
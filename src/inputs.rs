@@ -0,0 +1,263 @@
+//! Input discovery: expand directories, glob patterns and archives into
+//! .spc files.
+
+use spc_converter::import::archive::ArchiveReader;
+use std::collections::HashSet;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+/// Decompressed size of the .spc files of all archives of a run together.
+const MAX_ARCHIVED_SIZE: u64 = 16 << 30;
+
+/// One discovered input file.
+pub struct InputFile {
+    pub path: PathBuf,
+    /// Path below the directory or glob base it was found in (just the file
+    /// name for explicitly listed files); used to mirror the tree on output.
+    pub relative: PathBuf,
+    /// For a file inside an archive, whose `path` is
+    /// `<archive>/<name inside it>`, where to read it from
+    pub archived: Option<ArchivedFile>,
+}
+
+/// A file inside an archive, decompressed only when read.
+#[derive(Clone)]
+pub struct ArchivedFile {
+    archive: Arc<ArchiveReader<'static>>,
+    index: usize,
+}
+
+impl ArchivedFile {
+    /// The decompressed contents.
+    pub fn read(&self) -> io::Result<Vec<u8>> {
+        self.archive.read(self.index).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// Expand the command-line inputs.
+///
+/// Existing files are kept as given, directories are searched recursively
+/// for `*.spc`, and anything else containing `*`, `?` or `[` is treated as a
+/// glob pattern (`data/**/*.spc`). Directory and glob results are sorted.
+/// ZIP and TAR archives, listed or matched, stand for the .spc files they
+/// contain, in archive order; together, those may decompress to at most
+/// 16 GiB. A file reached through several inputs is kept once, where it was
+/// first found.
+pub fn expand(inputs: &[PathBuf]) -> io::Result<Vec<InputFile>> {
+    let mut files = Vec::new();
+    let mut archive_budget = MAX_ARCHIVED_SIZE;
+
+    for input in inputs {
+        if input.is_dir() {
+            let mut found = Vec::new();
+            find_spc_files(input, &mut found)?;
+            if found.is_empty() {
+                return Err(not_found(input));
+            }
+            found.sort();
+            files.extend(found.into_iter().map(|path| relative_to(path, input)));
+        } else if !input.exists() && is_pattern(input) {
+            let pattern = input.to_string_lossy();
+            let paths = glob::glob(&pattern)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("{}: {}", pattern, e)))?;
+            let mut found = Vec::new();
+            for path in paths {
+                let path = path.map_err(io::Error::from)?;
+                if path.is_file() {
+                    found.push(path);
+                }
+            }
+            if found.is_empty() {
+                return Err(not_found(input));
+            }
+            found.sort();
+            let base = glob_base(input);
+            for path in found {
+                if is_archive(&path) {
+                    files.extend(archived_files(&path, &mut archive_budget)?);
+                } else {
+                    files.push(relative_to(path, &base));
+                }
+            }
+        } else if is_archive(input) {
+            files.extend(archived_files(input, &mut archive_budget)?);
+        } else {
+            files.push(InputFile {
+                relative: input.file_name().map(PathBuf::from).unwrap_or_default(),
+                path: input.clone(),
+                archived: None,
+            });
+        }
+    }
+
+    let mut seen = HashSet::new();
+    files.retain(|file| seen.insert(std::fs::canonicalize(&file.path).unwrap_or_else(|_| file.path.clone())));
+    Ok(files)
+}
+
+/// Expand the inputs, keeping only the paths. Archives are only read by
+/// the conversion itself.
+pub fn expand_paths(inputs: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    if let Some(input) = inputs.iter().find(|input| input.is_file() && is_archive(input)) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{}: archives can only be converted; extract it first", input.display()),
+        ));
+    }
+    Ok(expand(inputs)?.into_iter().map(|f| f.path).collect())
+}
+
+/// Whether a path names a ZIP or (compressed) TAR archive.
+pub fn is_archive(path: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy().to_lowercase();
+    [".zip", ".tar", ".tgz", ".tar.gz"].iter().any(|ext| name.ends_with(ext))
+}
+
+/// Name of an archive without its extension, e.g. `session` for
+/// `session.tar.gz`.
+pub fn archive_stem(path: &Path) -> String {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let lower = name.to_lowercase();
+    let ext = [".tar.gz", ".tgz", ".tar", ".zip"].iter().find(|ext| lower.ends_with(*ext)).map_or(0, |ext| ext.len());
+    name[..name.len() - ext].to_string()
+}
+
+/// The .spc files inside an archive, mirrored below it on output. Their
+/// decompressed size is taken from `budget`.
+fn archived_files(path: &Path, budget: &mut u64) -> io::Result<Vec<InputFile>> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), message));
+    let bytes = std::fs::read(path)?;
+    let reader = ArchiveReader::open(bytes, |name| name.to_lowercase().ends_with(".spc"), *budget)
+        .map_err(|e| invalid(e.to_string()))?;
+    if reader.members().is_empty() {
+        return Err(invalid("no .spc files in the archive".to_string()));
+    }
+    *budget -= reader.size();
+    let archive = Arc::new(reader);
+    Ok(archive
+        .members()
+        .iter()
+        .enumerate()
+        .map(|(index, member)| InputFile {
+            path: path.join(&member.name),
+            relative: PathBuf::from(&member.name),
+            archived: Some(ArchivedFile {
+                archive: Arc::clone(&archive),
+                index,
+            }),
+        })
+        .collect())
+}
+
+fn find_spc_files(dir: &Path, found: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_spc_files(&path, found)?;
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("spc"))
+        {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn is_pattern(path: &Path) -> bool {
+    path.to_string_lossy().contains(['*', '?', '['])
+}
+
+/// The leading components of a pattern that contain no wildcards.
+fn glob_base(pattern: &Path) -> PathBuf {
+    pattern
+        .components()
+        .take_while(|c| !matches!(c, Component::Normal(s) if is_pattern(Path::new(s))))
+        .collect()
+}
+
+fn relative_to(path: PathBuf, base: &Path) -> InputFile {
+    let relative = match path.strip_prefix(base) {
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => path.file_name().map(PathBuf::from).unwrap_or_default(),
+    };
+    InputFile {
+        path,
+        relative,
+        archived: None,
+    }
+}
+
+fn not_found(input: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("no .spc files found for {}", input.display()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A scratch tree of empty files below the temp directory.
+    fn tree(name: &str, files: &[&str]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("spc_inputs_{}_{}", name, std::process::id()));
+        for file in files {
+            let path = dir.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"").unwrap();
+        }
+        dir
+    }
+
+    fn relative(files: &[InputFile]) -> Vec<&Path> {
+        files.iter().map(|file| file.relative.as_path()).collect()
+    }
+
+    #[test]
+    fn test_expand_directory() {
+        let dir = tree("dir", &["b.spc", "2024-01/a.SPC", "2024-01/deep/c.spc", "notes.txt"]);
+        let files = expand(std::slice::from_ref(&dir)).unwrap();
+        let explicit = expand(&[dir.join("notes.txt")]).unwrap();
+        std::fs::create_dir(dir.join("empty")).unwrap();
+        let empty = expand(&[dir.join("empty")]);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // Searched recursively, sorted, case-insensitive on the extension
+        assert_eq!(relative(&files), [Path::new("2024-01/a.SPC"), Path::new("2024-01/deep/c.spc"), Path::new("b.spc")]);
+        assert_eq!(files[1].path, dir.join("2024-01/deep/c.spc"));
+        // Listed files are kept whatever their extension
+        assert_eq!(relative(&explicit), [Path::new("notes.txt")]);
+        assert_eq!(empty.err().unwrap().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_expand_glob() {
+        let dir = tree("glob", &["2024-01/a.spc", "2024-01/x/b.spc", "2024-02/c.spc", "2023-12/d.spc"]);
+        let files = expand(&[dir.join("2024-*/**/*.spc")]).unwrap();
+        let none = expand(&[dir.join("2025-*/*.spc")]).err().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // Relative to the pattern's fixed prefix
+        assert_eq!(glob_base(&dir.join("2024-*/**/*.spc")), dir);
+        assert_eq!(
+            relative(&files),
+            [Path::new("2024-01/a.spc"), Path::new("2024-01/x/b.spc"), Path::new("2024-02/c.spc")]
+        );
+        assert_eq!(none.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_expand_duplicates() {
+        let dir = tree("duplicates", &["a.spc", "sub/b.spc"]);
+        let inputs = [dir.join("sub/b.spc"), dir.clone(), dir.join("*.spc"), dir.join("sub/../a.spc")];
+        let files = expand(&inputs).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // Each file once, where it was first found
+        let paths: Vec<&Path> = files.iter().map(|file| file.path.as_path()).collect();
+        assert_eq!(paths, [dir.join("sub/b.spc"), dir.join("a.spc")]);
+        assert_eq!(relative(&files), [Path::new("b.spc"), Path::new("a.spc")]);
+    }
+}
@@ -1,10 +1,20 @@
 //! SPC Converter Library
 //!
-//! Parses Spectrum Analyzer Suite .spc files and converts them to open formats.
+//! Parses Spectrum Analyzer Suite .spc files (and imports a few other
+//! vendors' formats) and converts them to open formats.
 
 pub mod parser;
 pub mod spectre;
 pub mod output;
+pub mod import;
+pub mod formats;
+pub mod processing;
+pub mod qc;
+pub mod validate;
+pub mod calibration;
+pub mod ffi;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 pub use parser::StorageObject;
-pub use spectre::{SpectreFile, SpcFile, Calibration, Config};
+pub use spectre::{SpectreFile, SpcFile, SpcDataset, Calibration, Config};
@@ -0,0 +1,120 @@
+//! Messages on stderr: the conversion's warnings, errors and summary, and
+//! with `--verbose` the file details and the library's debug events.
+//!
+//! The library reports through `tracing` (spans for container unpacking,
+//! object parsing and output writing); a `tracing-subscriber` filtered to
+//! the same level prints them. The CLI's own messages go through `log`.
+//!
+//! Messages about one input carry its path as the `file` key. They are
+//! dropped with `--quiet`, which leaves only run-level errors.
+
+use clap::ValueEnum;
+use log::kv::{self, Key, Value, VisitSource};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde_json::{Map, Value as Json};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing_subscriber::filter::{self, Targets};
+use tracing_subscriber::fmt;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Plain messages
+    Text,
+    /// One JSON object per line (time, level, target, message, file)
+    Json,
+}
+
+struct StderrLogger {
+    format: LogFormat,
+    quiet: bool,
+}
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.target().starts_with("spc_converter")
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let file = record.key_values().get(Key::from_str("file"));
+        if self.quiet && file.is_some() {
+            return;
+        }
+        match self.format {
+            LogFormat::Text => eprintln!("{}", text_line(record, file)),
+            LogFormat::Json => eprintln!("{}", json_line(record)),
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+fn text_line(record: &Record, file: Option<Value>) -> String {
+    match (record.level(), file) {
+        (Level::Error, Some(file)) => format!("Error processing {}: {}", file, record.args()),
+        (Level::Error, None) => format!("Error: {}", record.args()),
+        (Level::Warn, Some(file)) => format!("Warning: {}: {}", file, record.args()),
+        (Level::Warn, None) => format!("Warning: {}", record.args()),
+        (Level::Info, _) => record.args().to_string(),
+        (level, _) => format!("  [{}] {}", level.as_str().to_lowercase(), record.args()),
+    }
+}
+
+fn json_line(record: &Record) -> String {
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0.0, |elapsed| elapsed.as_millis() as f64 / 1000.0);
+    let mut line = Map::new();
+    line.insert("time".to_string(), time.into());
+    line.insert("level".to_string(), record.level().as_str().to_lowercase().into());
+    line.insert("target".to_string(), record.target().into());
+    line.insert("message".to_string(), record.args().to_string().into());
+    let _ = record.key_values().visit(&mut Fields(&mut line));
+    Json::Object(line).to_string()
+}
+
+/// Copies key-values into a JSON object, integers as numbers.
+struct Fields<'a>(&'a mut Map<String, Json>);
+
+impl<'kvs> VisitSource<'kvs> for Fields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        let value = match value.to_u64() {
+            Some(number) => number.into(),
+            None => value.to_string().into(),
+        };
+        self.0.insert(key.to_string(), value);
+        Ok(())
+    }
+}
+
+/// Send messages up to `level` to stderr in `format`; `quiet` drops those
+/// about single inputs.
+pub fn init(level: LevelFilter, format: LogFormat, quiet: bool) {
+    let logger = Box::leak(Box::new(StderrLogger { format, quiet }));
+    if log::set_logger(logger).is_ok() {
+        log::set_max_level(level);
+    }
+    init_tracing(level, format);
+}
+
+/// Print the library's `tracing` events up to `level`, with the spans they
+/// occur in, to stderr in `format`.
+fn init_tracing(level: LevelFilter, format: LogFormat) {
+    let level = match level {
+        LevelFilter::Off => filter::LevelFilter::OFF,
+        LevelFilter::Error => filter::LevelFilter::ERROR,
+        LevelFilter::Warn => filter::LevelFilter::WARN,
+        LevelFilter::Info => filter::LevelFilter::INFO,
+        LevelFilter::Debug => filter::LevelFilter::DEBUG,
+        LevelFilter::Trace => filter::LevelFilter::TRACE,
+    };
+    let text = (format == LogFormat::Text).then(|| fmt::layer().with_writer(std::io::stderr).without_time());
+    let json = (format == LogFormat::Json).then(|| fmt::layer().json().with_writer(std::io::stderr));
+    let _ = tracing_subscriber::registry()
+        .with(Targets::new().with_target("spc_converter", level))
+        .with(text)
+        .with(json)
+        .try_init();
+}
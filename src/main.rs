@@ -2,28 +2,78 @@
 //!
 //! Convert Spectrum Analyzer Suite .spc files to JSON or CSV format.
 
-use clap::{Parser, ValueEnum};
-use spc_converter::{output, SpcFile};
-use std::fs::File;
-use std::io::{BufWriter, Write};
-use std::path::PathBuf;
+mod archive;
+mod commands;
+mod inputs;
+mod logging;
+mod report;
+
+use clap::builder::{PossibleValue, PossibleValuesParser};
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser, ValueEnum};
+use indicatif::{ProgressBar, ProgressStyle};
+use spc_converter::spectre::{sidecar, AxisType, Provenance};
+use spc_converter::parser::{Limits, ParseError, ParseOptions};
+use spc_converter::formats::{FormatError, ReadOptions, Registry, SpectrumReader, SpectrumWriter, WriteOptions};
+use spc_converter::{calibration, output, processing, Calibration, SpcFile};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
 
 #[derive(Parser)]
 #[command(name = "spc-convert")]
 #[command(about = "Convert Spectrum Analyzer Suite .spc files to open formats")]
 #[command(version)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Cli {
-    /// Input .spc file(s)
+    #[command(subcommand)]
+    command: Option<commands::Command>,
+
+    /// Input .spc file(s), directories (searched recursively) or glob patterns
     #[arg(required = true)]
     input: Vec<PathBuf>,
 
-    /// Output file path (for single input) or directory (for multiple inputs)
+    /// Path of each expanded input relative to its directory/glob base
+    #[arg(skip)]
+    relative_input: HashMap<PathBuf, PathBuf>,
+
+    /// The options given on the command line, for --provenance
+    #[arg(skip)]
+    given_options: Vec<String>,
+
+    /// Inputs inside archives, decompressed as they are processed
+    #[arg(skip)]
+    archived: HashMap<PathBuf, inputs::ArchivedFile>,
+
+    /// Staging directory of a ZIP --output, used as the output directory
+    #[arg(skip)]
+    staging: Option<PathBuf>,
+
+    /// Calibration loaded from --calibration
+    #[arg(skip)]
+    calibration_override: Option<Calibration>,
+
+    /// Curve loaded from --response
+    #[arg(skip)]
+    response_curve: Option<processing::ResponseCurve>,
+
+    /// Output file path (for single input, "-" for stdout), directory (for multiple inputs) or .zip archive
     #[arg(short, long)]
     output: Option<PathBuf>,
 
     /// Output format
-    #[arg(short, long, value_enum, default_value = "json")]
-    format: OutputFormat,
+    #[arg(short, long, default_value = "json", value_parser = format_parser())]
+    format: String,
+
+    /// Write all spectra into one JSON array in FILE ("-" for stdout) instead of a file per input
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["output", "append"])]
+    single_output: Option<PathBuf>,
+
+    /// With --single-output, write one JSON object keyed by file id instead of an array
+    #[arg(long, requires = "single_output")]
+    keyed: bool,
 
     /// Pretty-print JSON output
     #[arg(short, long)]
@@ -33,47 +83,714 @@ struct Cli {
     #[arg(short, long)]
     verbose: bool,
 
+    /// Do not print warnings, per-file errors or the summary; rely on the exit code and --report
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Format of the messages on stderr
+    #[arg(long, value_enum, default_value = "text")]
+    log_format: logging::LogFormat,
+
+    /// Do not show a progress bar for multi-file runs
+    #[arg(long)]
+    no_progress: bool,
+
+    /// Write a JSON summary of the run (per-file status, outputs, errors, timing) to this file ("-" for stdout)
+    #[arg(long, value_name = "FILE")]
+    report: Option<PathBuf>,
+
     /// Generate PNG plot(s) of the spectrum
     #[arg(long)]
     plot: bool,
+
+    /// Plot image width in pixels
+    #[arg(long, default_value_t = 1200)]
+    plot_width: u32,
+
+    /// Plot image height in pixels
+    #[arg(long, default_value_t = 600)]
+    plot_height: u32,
+
+    /// Plot intensities on a logarithmic axis
+    #[arg(long)]
+    log_y: bool,
+
+    /// X-axis for CSV, pairs and plot output [default: the axis stored in the
+    /// file, else the best available]
+    #[arg(long, value_enum)]
+    axis: Option<AxisArg>,
+
+    /// Field separator of CSV output
+    #[arg(long, value_enum, default_value = "comma")]
+    csv_delimiter: DelimiterArg,
+
+    /// Decimal separator of CSV output
+    #[arg(long, value_enum, default_value = "point")]
+    csv_decimal: DecimalArg,
+
+    /// Digits after the decimal point in CSV output [default: as many as needed]
+    #[arg(long, value_name = "DIGITS")]
+    csv_precision: Option<usize>,
+
+    /// Leave out the index column of CSV output
+    #[arg(long)]
+    csv_no_index: bool,
+
+    /// Start CSV output with comment lines for the uid, laser wavelength, exposure, gain and calibration
+    #[arg(long)]
+    csv_header: bool,
+
+    /// Separator between the values of a pairs line
+    #[arg(long, value_enum, default_value = "comma")]
+    pairs_delimiter: DelimiterArg,
+
+    /// Digits after the decimal point in pairs output [default: as many as needed]
+    #[arg(long, value_name = "DIGITS")]
+    pairs_precision: Option<usize>,
+
+    /// Add the blank as a third value to pairs output
+    #[arg(long)]
+    pairs_blank: bool,
+
+    /// Reduce pairs output to this many points, e.g. to fit a context window
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    pairs_points: Option<u64>,
+
+    /// How runs of points are reduced with --pairs-points
+    #[arg(long, value_enum, default_value = "mean", requires = "pairs_points")]
+    pairs_downsample: DownsampleArg,
+
+    /// Restrict the plot to an x-range in axis units, e.g. --plot-range 200:1800
+    #[arg(long, value_name = "MIN:MAX", value_parser = parse_range, allow_hyphen_values = true)]
+    plot_range: Option<(f64, f64)>,
+
+    /// Attach sample metadata to the output (repeatable), e.g. --meta sample_id=XYZ
+    #[arg(long = "meta", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    meta: Vec<(String, String)>,
+
+    /// Do not merge `<input>.toml` / `<input>.json` sidecar metadata files
+    #[arg(long)]
+    no_sidecar: bool,
+
+    /// Salvage what can be read from damaged or truncated files instead of
+    /// failing
+    #[arg(long)]
+    recover: bool,
+
+    /// Fail on unknown objects, unexpected value sizes and invalid UTF-8
+    /// instead of skipping them with a warning
+    #[arg(long)]
+    strict: bool,
+
+    /// Include objects the converter does not interpret in JSON output,
+    /// with their raw values as hex
+    #[arg(long)]
+    extras: bool,
+
+    /// Add a provenance block (converter version, source file name and
+    /// SHA-256, conversion time and options) to JSON and HDF5 output
+    #[arg(long)]
+    provenance: bool,
+
+    /// Add intensity statistics (min, max, mean, std, noise, saturated
+    /// points, axis range) of the written data to JSON output
+    #[arg(long)]
+    stats: bool,
+
+    /// Intensity at which a pixel counts as saturated, for --stats
+    #[arg(long, value_name = "COUNTS", default_value_t = processing::DEFAULT_SATURATION_LEVEL, requires = "stats")]
+    saturation_level: f64,
+
+    /// Maximum decompressed size of a file in MiB; larger files are
+    /// rejected (guards against decompression bombs) [default: 1024]
+    #[arg(long, value_name = "MIB", value_parser = clap::value_parser!(u64).range(1..=1 << 20))]
+    max_decoded_mib: Option<u64>,
+
+    /// Subfile (Galactic SPC), frame (SPE) or spectrum (multi-accumulation
+    /// .spc) to convert from inputs holding several [default: 0, the first]
+    #[arg(long, visible_aliases = ["frame", "spectrum"], value_name = "INDEX")]
+    subfile: Option<usize>,
+
+    /// Convert every spectrum of inputs holding several, numbered
+    /// <name>_0, <name>_1, ...
+    #[arg(long, conflicts_with = "subfile")]
+    all_spectra: bool,
+
+    /// Axis of unlabelled x values in CSV/TSV/text inputs [default: raman]
+    #[arg(long, value_enum, value_name = "AXIS")]
+    text_x_axis: Option<AxisArg>,
+
+    /// Subtract the stored blank spectrum from the intensities before export
+    #[arg(long)]
+    subtract_blank: bool,
+
+    /// Clip negative intensities to zero after blank subtraction
+    #[arg(long, requires = "subtract_blank")]
+    clip_negative: bool,
+
+    /// Do not read the blank spectrum (less memory for large files; no blank in the output)
+    #[arg(long, conflicts_with_all = ["subtract_blank", "pairs_blank"])]
+    no_blank: bool,
+
+    /// Precision of the exported intensities and blank: f32 rounds them to
+    /// single precision, and Parquet stores them as 32-bit floats
+    #[arg(long, value_enum, default_value = "f64")]
+    export_precision: PrecisionArg,
+
+    /// Use the calibration from this JSON or TOML file, e.g.
+    /// {"coefficients": [a0, a1, a2, a3]} (Legendre), or with
+    /// "model": "polynomial" or "spline"
+    #[arg(long, value_name = "FILE")]
+    calibration: Option<PathBuf>,
+
+    /// Only apply --calibration to files without a stored calibration
+    #[arg(long, requires = "calibration")]
+    keep_calibration: bool,
+
+    /// Raman excitation wavelength in nm, replacing the one stored in the
+    /// file (or supplying it when missing)
+    #[arg(long, value_name = "NM", value_parser = parse_laser_nm)]
+    laser_nm: Option<f64>,
+
+    /// Multiply intensities by the relative response correction in this CSV
+    /// (wavelength in nm or pixel, factor)
+    #[arg(long, value_name = "CSV")]
+    response: Option<PathBuf>,
+
+    /// Remove cosmic-ray spikes before export
+    #[arg(long)]
+    despike: bool,
+
+    /// Spike threshold in median absolute deviations (with --despike)
+    #[arg(long, default_value_t = processing::DEFAULT_DESPIKE_THRESHOLD)]
+    despike_threshold: f64,
+
+    /// Apply the Savitzky-Golay filter stored in each file's config, as the
+    /// acquisition software displayed it
+    #[arg(long)]
+    apply_sgolay: bool,
+
+    /// Replay the processing enabled in each file's config (median filter,
+    /// smoothing, Savitzky-Golay, baseline), as the acquisition software
+    /// displayed it
+    #[arg(long, conflicts_with = "apply_sgolay")]
+    apply_config: bool,
+
+    /// Smooth intensities before export
+    #[arg(long, value_enum)]
+    smooth: Option<SmoothArg>,
+
+    /// Smoothing width in points: window length (boxcar) or FWHM (gaussian)
+    #[arg(long, default_value_t = processing::DEFAULT_SMOOTH_WIDTH, value_parser = parse_smooth_width)]
+    smooth_width: f64,
+
+    /// Subtract an estimated baseline (e.g. fluorescence background) before export
+    #[arg(long, value_enum)]
+    baseline: Option<BaselineArg>,
+
+    /// ALS smoothness; larger gives a stiffer baseline (with --baseline als)
+    #[arg(long, default_value_t = processing::DEFAULT_ALS_LAMBDA)]
+    baseline_lambda: f64,
+
+    /// Polynomial degree (with --baseline polynomial)
+    #[arg(long, default_value_t = processing::DEFAULT_BASELINE_DEGREE)]
+    baseline_degree: usize,
+
+    /// Crop to x >= XMIN, in the units of the output x-axis (--axis, else
+    /// the one stored in the file, else the best available)
+    #[arg(long, allow_hyphen_values = true)]
+    xmin: Option<f64>,
+
+    /// Crop to x <= XMAX, in the units of the output x-axis
+    #[arg(long, allow_hyphen_values = true)]
+    xmax: Option<f64>,
+
+    /// Normalize intensities before export
+    #[arg(long, value_enum)]
+    normalize: Option<NormalizeArg>,
+
+    /// Add to an existing SQLite database or NDJSON file instead of replacing it
+    #[arg(long)]
+    append: bool,
+
+    /// Replace existing output files without a warning
+    #[arg(long, group = "existing")]
+    overwrite: bool,
+
+    /// Fail a file whose output already exists instead of replacing it
+    #[arg(long, group = "existing")]
+    no_clobber: bool,
+
+    /// Leave existing outputs alone and skip their spectra (to resume an interrupted batch)
+    #[arg(long, group = "existing")]
+    skip_existing: bool,
+}
+
+/// The input and output formats.
+static FORMATS: LazyLock<Registry> = LazyLock::new(Registry::default);
+
+/// `--format` values: the registered writers.
+fn format_parser() -> PossibleValuesParser {
+    PossibleValuesParser::new(FORMATS.writers().map(|writer| PossibleValue::new(writer.name()).help(writer.description())))
+}
+
+/// The writer selected with --format.
+fn output_writer(cli: &Cli) -> &'static dyn SpectrumWriter {
+    FORMATS.writer(&cli.format).expect("--format is validated against the registry")
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum AxisArg {
+    /// Pixel index
+    Pixels,
+    /// Wavelength (nm)
+    Wavelength,
+    /// Raman shift (cm⁻¹)
+    Raman,
+}
+
+impl From<AxisArg> for AxisType {
+    fn from(arg: AxisArg) -> Self {
+        match arg {
+            AxisArg::Pixels => AxisType::Pixels,
+            AxisArg::Wavelength => AxisType::Wavelengths,
+            AxisArg::Raman => AxisType::RamanShifts,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum DelimiterArg {
+    Comma,
+    Semicolon,
+    Tab,
+    Space,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum DecimalArg {
+    /// 1.5
+    Point,
+    /// 1,5
+    Comma,
 }
 
-#[derive(Clone, ValueEnum)]
-enum OutputFormat {
-    Json,
-    Csv,
-    /// LLM-friendly x,y pairs with context header
-    Pairs,
+#[derive(Clone, Copy, ValueEnum)]
+enum DownsampleArg {
+    /// Average each run of points
+    Mean,
+    /// Keep the middle point of each run
+    Decimate,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SmoothArg {
+    /// Moving average
+    Boxcar,
+    /// Gaussian-weighted average
+    Gaussian,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum PrecisionArg {
+    /// Double precision, as read
+    F64,
+    /// Single precision
+    F32,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum BaselineArg {
+    /// Asymmetric least squares
+    Als,
+    /// Iterative polynomial fit
+    Polynomial,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum NormalizeArg {
+    /// Divide by the maximum intensity
+    Max,
+    /// Divide by the summed absolute intensity
+    Area,
+    /// Divide by the Euclidean norm
+    Vector,
+    /// Rescale to 0..1
+    Minmax,
+}
+
+impl From<NormalizeArg> for processing::NormalizeMode {
+    fn from(arg: NormalizeArg) -> Self {
+        match arg {
+            NormalizeArg::Max => processing::NormalizeMode::Max,
+            NormalizeArg::Area => processing::NormalizeMode::Area,
+            NormalizeArg::Vector => processing::NormalizeMode::Vector,
+            NormalizeArg::Minmax => processing::NormalizeMode::MinMax,
+        }
+    }
 }
 
 fn main() {
-    let cli = Cli::parse();
+    let command = Cli::command();
+    let matches = command.clone().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    cli.given_options = given_options(&command, &matches);
+    let level = if cli.quiet {
+        log::LevelFilter::Error
+    } else if cli.verbose {
+        log::LevelFilter::Debug
+    } else {
+        log::LevelFilter::Info
+    };
+    logging::init(level, cli.log_format, cli.quiet);
+
+    if let Some(ref command) = cli.command {
+        match command.run() {
+            Ok(true) => return,
+            Ok(false) => std::process::exit(1),
+            Err(e) => {
+                log::error!("{}", e);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    match inputs::expand(&cli.input) {
+        Ok(files) => {
+            cli.input = files.iter().map(|f| f.path.clone()).collect();
+            for file in files {
+                if let Some(data) = file.archived {
+                    cli.archived.insert(file.path.clone(), data);
+                }
+                cli.relative_input.insert(file.path, file.relative);
+            }
+        }
+        Err(e) => {
+            log::error!("{}", e);
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(ref path) = cli.calibration {
+        match Calibration::from_file(path) {
+            Ok(calibration) => cli.calibration_override = Some(calibration),
+            Err(e) => {
+                log::error!("{}", e);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    if let Some(ref path) = cli.response {
+        let curve = std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| processing::ResponseCurve::from_csv(&text));
+        match curve {
+            Ok(curve) => cli.response_curve = Some(curve),
+            Err(e) => {
+                log::error!("invalid response curve {}: {}", path.display(), e);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    if cli.csv_decimal == DecimalArg::Comma && cli.csv_delimiter == DelimiterArg::Comma {
+        log::error!("--csv-decimal comma needs --csv-delimiter semicolon, tab or space");
+        std::process::exit(2);
+    }
+
+    if cli.append && !["sqlite", "ndjson"].contains(&cli.format.as_str()) {
+        log::error!("--append requires --format sqlite or ndjson");
+        std::process::exit(2);
+    }
+
+    if cli.single_output.is_some() && cli.format != "json" {
+        log::error!("--single-output writes JSON and cannot be combined with -f {}", cli.format);
+        std::process::exit(2);
+    }
+
+    if cli.output.as_deref().is_some_and(is_stdout) && cli.input.len() > 1 && !output_writer(&cli).is_batch() {
+        log::error!("-o - writes a single input to stdout; use --single-output - or -f ndjson for several");
+        std::process::exit(2);
+    }
+
+    let mut archive = None;
+    if let Some(output) = cli.output.clone().filter(|output| archive::is_archive(output)) {
+        if output_writer(&cli).is_batch() {
+            log::error!("-f {} writes a single file and cannot write into a ZIP archive", cli.format);
+            std::process::exit(2);
+        }
+        match may_write(&cli, &output) {
+            Ok(true) => {}
+            Ok(false) => return,
+            Err(e) => {
+                log::error!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        match archive::Archive::create(&output) {
+            Ok(created) => {
+                cli.staging = Some(created.staging().to_path_buf());
+                archive = Some(created);
+            }
+            Err(e) => {
+                log::error!("{}: {}", output.display(), e);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let mut run_report = report::RunReport::new(&cli.format);
+
+    if output_writer(&cli).is_batch() || cli.single_output.is_some() {
+        let result = convert_batch(&cli, &mut run_report);
+        write_report(&cli, &mut run_report);
+        if let Err(e) = result {
+            log::error!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
 
     let mut success_count = 0;
+    let mut skip_count = 0;
     let mut error_count = 0;
+    let progress = progress_bar(&cli);
 
     for input_path in &cli.input {
         if cli.verbose {
-            eprintln!("Processing: {}", input_path.display());
+            log::info!("Processing: {}", input_path.display());
         }
 
+        let started = Instant::now();
         match process_file(&cli, input_path) {
-            Ok(output_path) => {
+            Ok((converted, skipped)) => {
+                if converted.is_empty() && !skipped.is_empty() {
+                    skip_count += 1;
+                } else {
+                    success_count += 1;
+                }
+                for (output_path, spc) in converted {
+                    let output_path = shown_path(&cli, &output_path);
+                    run_report.converted(input_path, &output_path, &spc, started.elapsed());
+                    if cli.verbose {
+                        log::info!("  -> {}", output_path.display());
+                    }
+                }
+                for output_path in skipped {
+                    run_report.skipped(input_path, &output_path, started.elapsed());
+                }
+            }
+            Err(e) => {
+                error_count += 1;
+                run_report.failed(input_path, e.as_ref(), started.elapsed());
+                report_failure(&progress, input_path, e.as_ref(), error_count);
+            }
+        }
+        if let Err(e) = archive.as_mut().map_or(Ok(()), archive::Archive::collect) {
+            progress.abandon();
+            log::error!("{}", e);
+            // Exiting skips destructors; remove the staging directory first
+            drop(archive);
+            std::process::exit(1);
+        }
+        progress.inc(1);
+    }
+    progress.finish_and_clear();
+    if let Some(archive) = archive {
+        if let Err(e) = archive.finish() {
+            log::error!("{}", e);
+            std::process::exit(1);
+        }
+    }
+    write_report(&cli, &mut run_report);
+
+    if cli.input.len() > 1 {
+        let skipped = if skip_count > 0 { format!(", {} skipped", skip_count) } else { String::new() };
+        log::info!(
+            files = cli.input.len(), succeeded = success_count, skipped = skip_count, failed = error_count;
+            "Processed {} file(s): {} success, {} errors{}",
+            cli.input.len(),
+            success_count,
+            error_count,
+            skipped
+        );
+    }
+
+    if error_count > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Write the --report file, if requested.
+fn write_report(cli: &Cli, run_report: &mut report::RunReport) {
+    if let Some(ref path) = cli.report {
+        if let Err(e) = run_report.write(path) {
+            log::error!("cannot write report {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Convert a file, returning the written outputs with their spectra and
+/// the outputs skipped by --skip-existing.
+fn process_file(cli: &Cli, input_path: &Path) -> Result<Converted, Box<dyn std::error::Error>> {
+    let spectra = load_spectra(cli, input_path)?;
+    let numbered = spectra.len() > 1;
+
+    let mut converted = Vec::with_capacity(spectra.len());
+    let mut skipped = Vec::new();
+    for (index, spc) in spectra.into_iter().enumerate() {
+        // Determine output path
+        let index = numbered.then_some(index);
+        let output_path = get_output_path(cli, input_path, index);
+        if output_path == input_path {
+            return Err(format!("output would overwrite the input {}; choose another with -o", input_path.display()).into());
+        }
+        if !may_write(cli, &output_path)? {
+            skipped.push(output_path);
+            continue;
+        }
+        if (cli.output.is_some() && cli.input.len() > 1) || cli.archived.contains_key(input_path) {
+            if let Some(parent) = output_path.parent().filter(|_| !is_stdout(&output_path)) {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+
+        // Write output
+        if let Err(e) = write_output(cli, &spc, &output_path) {
+            discard_unwritten(&output_path);
+            return Err(e);
+        }
+
+        // Generate plot if requested (a png output already is one); into
+        // the archive, if writing one
+        if cli.plot && cli.format != "png" {
+            let base = if cli.staging.is_some() { output_path.clone() } else { numbered_path(&beside(cli, input_path), index) };
+            write_plot(cli, &spc, &base)?;
+        }
+
+        converted.push((output_path, spc));
+    }
+    Ok((converted, skipped))
+}
+
+type Converted = (Vec<(PathBuf, SpcFile)>, Vec<PathBuf>);
+
+/// Apply the policy for existing outputs to `path`: `Ok(false)` to skip
+/// it, an error with --no-clobber, and a warning when replacing it without
+/// --overwrite.
+///
+/// A new output is created (empty) here rather than only checked for, so
+/// that one appearing before it is written is not replaced regardless of
+/// the policy; [`discard_unwritten`] removes it if writing then fails.
+fn may_write(cli: &Cli, path: &Path) -> Result<bool, Box<dyn std::error::Error>> {
+    if cli.overwrite || is_stdout(path) {
+        return Ok(true);
+    }
+    match std::fs::OpenOptions::new().write(true).create_new(true).open(path) {
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+        // Created, or e.g. in a directory not created yet; writing reports
+        // any other problem
+        _ => return Ok(true),
+    }
+    if cli.skip_existing {
+        if cli.verbose {
+            log::info!("  Skipped: {} exists", path.display());
+        }
+        return Ok(false);
+    }
+    if cli.no_clobber {
+        return Err(format!("{} already exists (--no-clobber)", path.display()).into());
+    }
+    log::warn!(file:% = path.display(); "replacing the existing file (--overwrite to allow, --no-clobber to refuse)");
+    Ok(true)
+}
+
+/// Remove an output left empty by [`may_write`] after writing it failed, so
+/// that --skip-existing does not take it for a finished one.
+fn discard_unwritten(path: &Path) {
+    if !is_stdout(path) && std::fs::metadata(path).is_ok_and(|metadata| metadata.is_file() && metadata.len() == 0) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// Convert all inputs into a single output file (for table formats and
+/// --single-output).
+fn convert_batch(cli: &Cli, run_report: &mut report::RunReport) -> Result<(), Box<dyn std::error::Error>> {
+    let output_path = match cli.single_output.as_ref().or(cli.output.as_ref()) {
+        Some(output) => output.clone(),
+        None if cli.input.len() == 1 => get_output_path(cli, &cli.input[0], None),
+        None => return Err("writing several inputs into one file requires -o <FILE>".into()),
+    };
+    if !cli.append && !may_write(cli, &output_path)? {
+        for input_path in &cli.input {
+            run_report.skipped(input_path, &output_path, Duration::ZERO);
+        }
+        return Ok(());
+    }
+
+    let mut success_count = 0;
+    let mut error_count = 0;
+    let mut spectra = Vec::new();
+    let progress = progress_bar(cli);
+    let file_ids = file_ids(cli);
+
+    for input_path in &cli.input {
+        if cli.verbose {
+            log::info!("Processing: {}", input_path.display());
+        }
+
+        let started = Instant::now();
+        let result = load_spectra(cli, input_path).and_then(|loaded| {
+            let numbered = loaded.len() > 1;
+            let mut identified = Vec::with_capacity(loaded.len());
+            let file_id = file_ids[input_path.as_path()].to_string_lossy();
+            for (index, spc) in loaded.into_iter().enumerate() {
+                if cli.plot {
+                    write_plot(cli, &spc, &numbered_path(&beside(cli, input_path), numbered.then_some(index)))?;
+                }
+                let id = if numbered { format!("{}_{}", file_id, index) } else { file_id.to_string() };
+                identified.push((id, spc));
+            }
+            Ok(identified)
+        });
+        match result {
+            Ok(identified) => {
                 success_count += 1;
-                if cli.verbose {
-                    eprintln!("  -> {}", output_path.display());
+                for (file_id, spc) in identified {
+                    run_report.loaded(input_path, &spc, started.elapsed());
+                    spectra.push((file_id, spc));
                 }
             }
             Err(e) => {
                 error_count += 1;
-                eprintln!("Error processing {}: {}", input_path.display(), e);
+                run_report.failed(input_path, e.as_ref(), started.elapsed());
+                report_failure(&progress, input_path, e.as_ref(), error_count);
             }
         }
+        progress.inc(1);
+    }
+    progress.finish_and_clear();
+
+    let refs: Vec<(&str, &SpcFile)> = spectra.iter().map(|(id, spc)| (id.as_str(), spc)).collect();
+    let written = match cli.single_output {
+        Some(_) => write_single_output(cli, &refs, &output_path),
+        None => output_writer(cli).write_batch(&refs, &output_path, &write_options(cli)),
+    };
+    if let Err(e) = written {
+        discard_unwritten(&output_path);
+        run_report.run_failed(e.as_ref());
+        return Err(e);
     }
+    run_report.written(&output_path);
 
+    if cli.verbose {
+        log::info!("  -> {}", output_path.display());
+    }
     if cli.input.len() > 1 {
-        eprintln!(
-            "\nProcessed {} file(s): {} success, {} errors",
+        log::info!(
+            files = cli.input.len(), succeeded = success_count, failed = error_count;
+            "Processed {} file(s): {} success, {} errors",
             cli.input.len(),
             success_count,
             error_count
@@ -81,102 +798,676 @@ fn main() {
     }
 
     if error_count > 0 {
-        std::process::exit(1);
+        return Err(format!("{} file(s) failed", error_count).into());
+    }
+    Ok(())
+}
+
+/// Write the spectra of --single-output as one JSON array, or with --keyed
+/// one object.
+fn write_single_output(cli: &Cli, spectra: &[(&str, &SpcFile)], path: &Path) -> Result<(), FormatError> {
+    log::debug!("writing {} spectra as one JSON document to {}", spectra.len(), path.display());
+    let writer: Box<dyn Write> = if is_stdout(path) {
+        Box::new(std::io::stdout().lock())
+    } else {
+        Box::new(std::io::BufWriter::new(std::fs::File::create(path)?))
+    };
+    if cli.keyed {
+        output::write_json_object(spectra, writer, cli.pretty)?;
+    } else {
+        output::write_json_array(spectra, writer, cli.pretty)?;
+    }
+    Ok(())
+}
+
+/// Progress bar for multi-file runs; hidden for single files, with
+/// --verbose, --quiet, --no-progress or JSON messages, and when stderr is
+/// not a terminal.
+fn progress_bar(cli: &Cli) -> ProgressBar {
+    if cli.input.len() < 2 || cli.verbose || cli.quiet || cli.no_progress || cli.log_format == logging::LogFormat::Json {
+        return ProgressBar::hidden();
     }
+    let bar = ProgressBar::new(cli.input.len() as u64);
+    bar.set_style(
+        ProgressStyle::with_template("{elapsed_precise} [{bar:40}] {pos}/{len} (ETA {eta}) {msg}")
+            .expect("valid progress template")
+            .progress_chars("=> "),
+    );
+    bar
+}
+
+/// Print a failure above the progress bar and count it in the bar's message.
+fn report_failure(progress: &ProgressBar, input_path: &Path, error: &dyn std::error::Error, error_count: usize) {
+    progress.suspend(|| log::error!(file:% = input_path.display(); "{}", error));
+    progress.set_message(format!("{} failed", error_count));
 }
 
-fn process_file(cli: &Cli, input_path: &PathBuf) -> Result<PathBuf, Box<dyn std::error::Error>> {
+/// Parse a file and, for each selected spectrum, attach metadata and apply
+/// processing.
+fn load_spectra(cli: &Cli, input_path: &Path) -> Result<Vec<SpcFile>, Box<dyn std::error::Error>> {
     // Parse the SPC file (now with calibration and config)
-    let spc = SpcFile::from_file(input_path)?;
+    // Axes are generated on demand unless the output stores them as parsed
+    let options = ParseOptions {
+        strict: cli.strict,
+        limits: decode_limits(cli.max_decoded_mib),
+        lazy_axes: output_writer(cli).lazy_axes() && cli.single_output.is_none(),
+        skip_blank: cli.no_blank,
+    };
+    let read_options = ReadOptions {
+        parse: options,
+        text_x_axis: cli.text_x_axis.map(Into::into),
+    };
+    let mut bytes = read_input(cli, input_path)?;
+    // Hash the file as read; decryption works in place
+    let provenance = cli.provenance.then(|| Provenance::new(input_path, &bytes).with_options(cli.given_options.clone()));
+    let reader = FORMATS.reader_for(input_path, &bytes).ok_or("unrecognized input format")?;
+    let mut spectra = match reader.read(&mut bytes, &read_options) {
+        Ok((spectra, warnings)) => {
+            for warning in warnings {
+                log::warn!(file:% = input_path.display(); "{}", warning);
+            }
+            spectra
+        }
+        Err(error) if cli.recover => recover_spectra(cli, reader, input_path, &read_options, error)?,
+        Err(error) => return Err(error.into()),
+    };
+    for spc in &mut spectra {
+        spc.provenance = provenance.clone();
+    }
+    let spectra = if cli.all_spectra {
+        spectra
+    } else {
+        vec![select_spectrum(cli, input_path, spectra, reader.item_name())?]
+    };
+    spectra.into_iter().map(|spc| prepare_spectrum(cli, input_path, spc)).collect()
+}
+
+/// Decompression limits for `--max-decoded-mib`.
+fn decode_limits(max_decoded_mib: Option<u64>) -> Limits {
+    let mut limits = Limits::default();
+    if let Some(mib) = max_decoded_mib {
+        let max_size = usize::try_from(mib << 20).unwrap_or(usize::MAX);
+        limits.max_total_size = max_size;
+        limits.max_buffer_size = limits.max_buffer_size.min(max_size);
+    }
+    limits
+}
+
+/// The options given on the command line, by long name and with the values
+/// as parsed, e.g. `--normalize max`; not the inputs.
+fn given_options(command: &clap::Command, matches: &clap::ArgMatches) -> Vec<String> {
+    let mut options = Vec::new();
+    for arg in command.get_arguments().filter(|arg| !arg.is_positional()) {
+        let id = arg.get_id().as_str();
+        if matches.value_source(id) != Some(ValueSource::CommandLine) {
+            continue;
+        }
+        let name = match (arg.get_long(), arg.get_short()) {
+            (Some(long), _) => format!("--{}", long),
+            (None, short) => format!("-{}", short.unwrap_or_default()),
+        };
+        if !arg.get_action().takes_values() {
+            options.push(name);
+            continue;
+        }
+        for occurrence in matches.get_raw_occurrences(id).into_iter().flatten() {
+            options.push(name.clone());
+            options.extend(occurrence.map(|value| value.to_string_lossy().into_owned()));
+        }
+    }
+    options
+}
+
+/// Attach metadata, apply overrides and processing.
+fn prepare_spectrum(cli: &Cli, input_path: &Path, mut spc: SpcFile) -> Result<SpcFile, Box<dyn std::error::Error>> {
+    // Only JSON can carry uninterpreted objects, and only on request
+    if !cli.extras {
+        spc.extras.clear();
+    }
+
+    // Sidecar metadata first, so explicit --meta values take precedence
+    if !cli.no_sidecar {
+        if let Some(sidecar_path) = sidecar::find_sidecar(input_path) {
+            spc.extend_metadata(sidecar::read_sidecar(&sidecar_path)?);
+            if cli.verbose {
+                log::info!("  Sidecar: {}", sidecar_path.display());
+            }
+        }
+    }
+    spc.extend_metadata(cli.meta.iter().cloned());
+
+    if let (Some(calibration), Some(path)) = (&cli.calibration_override, &cli.calibration) {
+        if !(cli.keep_calibration && spc.calibration.is_some()) {
+            spc.set_calibration(calibration.clone());
+            spc.history.push(
+                processing::ProcessingStep::new("set_calibration")
+                    .param("source", path.display().to_string())
+                    .param("calibration", serde_json::to_value(calibration).unwrap_or_default()),
+            );
+        }
+    }
+
+    if let Some(laser_nm) = cli.laser_nm {
+        let stored = spc.config.as_ref().and_then(|c| c.raman_wavelength);
+        spc.set_laser_wavelength(laser_nm);
+        let mut step = processing::ProcessingStep::new("set_laser_wavelength").param("laser_nm", laser_nm);
+        if let Some(stored) = stored {
+            step = step.param("replaced_nm", stored);
+        }
+        spc.history.push(step);
+    }
+
+    if let Some(ref calibration) = spc.calibration {
+        for problem in calibration::check_calibration(calibration, spc.data.len()) {
+            log::warn!(file:% = input_path.display(); "calibration: {}", problem);
+        }
+    }
+
+    apply_processing(cli, &mut spc)?;
+    if cli.stats {
+        spc.stats = Some(processing::summarize(&spc, cli.saturation_level));
+    }
 
     if cli.verbose {
-        eprintln!("  UID: {}", spc.uid);
-        eprintln!("  Data points: {}", spc.data.len());
-        eprintln!("  Blank points: {}", spc.blank.len());
+        log::info!("  UID: {}", spc.uid);
+        log::info!("  Data points: {}", spc.data.len());
+        log::info!("  Blank points: {}", spc.blank.len());
         if let Some(ref cal) = spc.calibration {
-            eprintln!("  Calibration: {:?}", cal.coefficients);
+            log::info!("  Calibration: {:?}", cal.coefficients);
         }
         if let Some(ref cfg) = spc.config {
             if let Some(raman) = cfg.raman_wavelength {
-                eprintln!("  Raman wavelength: {} nm", raman);
+                log::info!("  Raman wavelength: {} nm", raman);
             }
         }
         if spc.has_raman_shift() {
-            eprintln!("  Raman shift axis: available");
+            log::info!("  Raman shift axis: available");
         } else if spc.has_calibration() {
-            eprintln!("  Wavelength axis: available");
+            log::info!("  Wavelength axis: available");
         }
     }
 
-    // Determine output path
-    let output_path = get_output_path(cli, input_path);
+    Ok(spc)
+}
 
-    // Write output
-    let file = File::create(&output_path)?;
-    let mut writer = BufWriter::new(file);
+fn plot_options(cli: &Cli) -> output::PlotOptions {
+    output::PlotOptions {
+        width: cli.plot_width,
+        height: cli.plot_height,
+        log_y: cli.log_y,
+        x_range: cli.plot_range,
+        axis: cli.axis.map(Into::into),
+    }
+}
 
-    match cli.format {
-        OutputFormat::Json => {
-            output::write_json_spc(&spc, &mut writer, cli.pretty)?;
-        }
-        OutputFormat::Csv => {
-            output::write_csv_spc(&spc, &mut writer)?;
+fn csv_options(cli: &Cli) -> output::CsvOptions {
+    output::CsvOptions {
+        axis: cli.axis.map(Into::into),
+        delimiter: match cli.csv_delimiter {
+            DelimiterArg::Comma => ',',
+            DelimiterArg::Semicolon => ';',
+            DelimiterArg::Tab => '\t',
+            DelimiterArg::Space => ' ',
+        },
+        decimal: match cli.csv_decimal {
+            DecimalArg::Point => '.',
+            DecimalArg::Comma => ',',
+        },
+        precision: cli.csv_precision,
+        index: !cli.csv_no_index,
+        header: cli.csv_header,
+    }
+}
+
+fn pairs_options(cli: &Cli) -> output::PairsOptions {
+    output::PairsOptions {
+        axis: cli.axis.map(Into::into),
+        delimiter: match cli.pairs_delimiter {
+            DelimiterArg::Comma => ", ",
+            DelimiterArg::Semicolon => "; ",
+            DelimiterArg::Tab => "\t",
+            DelimiterArg::Space => " ",
         }
-        OutputFormat::Pairs => {
-            output::write_pairs(&spc, &mut writer)?;
+        .to_string(),
+        precision: cli.pairs_precision,
+        blank: cli.pairs_blank,
+        points: cli.pairs_points.map(|points| points as usize),
+        downsample: match cli.pairs_downsample {
+            DownsampleArg::Mean => processing::DownsampleMode::Mean,
+            DownsampleArg::Decimate => processing::DownsampleMode::Decimate,
+        },
+    }
+}
+
+/// Generate a PNG plot alongside the input.
+fn write_plot(cli: &Cli, spc: &SpcFile, input_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let plot_path = input_path.with_extension("png");
+    if !may_write(cli, &plot_path)? {
+        return Ok(());
+    }
+    if let Some(parent) = plot_path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    if cli.verbose {
+        let axis_info = output::select_axis(spc, cli.axis.map(Into::into))?;
+        if axis_info.unit.is_empty() {
+            log::info!("  Plot axis: {}", axis_info.name);
+        } else {
+            log::info!("  Plot axis: {} ({})", axis_info.name, axis_info.unit);
         }
     }
 
-    writer.flush()?;
+    if let Err(e) = output::write_plot_with(spc, &plot_path, &plot_options(cli)) {
+        discard_unwritten(&plot_path);
+        return Err(e.into());
+    }
 
-    // Generate plot if requested
-    if cli.plot {
-        let plot_path = input_path.with_extension("png");
-        
-        if cli.verbose {
-            let axis_info = output::select_best_axis(&spc);
-            if axis_info.unit.is_empty() {
-                eprintln!("  Plot axis: {}", axis_info.name);
-            } else {
-                eprintln!("  Plot axis: {} ({})", axis_info.name, axis_info.unit);
-            }
+    if cli.verbose {
+        log::info!("  -> \"{}\"", shown_path(cli, &plot_path).display());
+    }
+
+    Ok(())
+}
+
+/// Pick the --subfile spectrum of a file holding several (Galactic
+/// subfiles, SPE frames, multi-accumulation .spc spectra).
+fn select_spectrum(
+    cli: &Cli,
+    input_path: &Path,
+    mut spectra: Vec<SpcFile>,
+    noun: &str,
+) -> Result<SpcFile, Box<dyn std::error::Error>> {
+    let index = cli.subfile.unwrap_or(0);
+    if index >= spectra.len() {
+        return Err(format!("{} {} requested, but the file has {} {}(s)", noun, index, spectra.len(), noun).into());
+    }
+    if spectra.len() > 1 && cli.subfile.is_none() {
+        log::warn!(
+            file:% = input_path.display();
+            "{} {}(s); converting {} 0 (select another with --{}, or all with --all-spectra)",
+            spectra.len(),
+            noun,
+            noun,
+            noun
+        );
+    }
+    Ok(spectra.swap_remove(index))
+}
+
+/// Salvage a file that failed to parse, reporting what was recovered.
+fn recover_spectra(
+    cli: &Cli,
+    reader: &dyn SpectrumReader,
+    input_path: &Path,
+    read_options: &ReadOptions,
+    error: ParseError,
+) -> Result<Vec<SpcFile>, Box<dyn std::error::Error>> {
+    // The failed read may have modified the bytes
+    let bytes = read_input(cli, input_path)?;
+    let Some(recovered) = reader.recover(&bytes, read_options) else {
+        return Err(error.into());
+    };
+    let (mut spectra, report) = recovered?;
+
+    let path = input_path.display();
+    log::warn!(file:% = path; "{}; recovered {}", error, report.salvaged.join(", "));
+    for problem in &report.problems {
+        log::warn!(file:% = path; "{}", problem);
+    }
+    for lost in &report.lost {
+        log::warn!(file:% = path; "lost {}", lost);
+    }
+
+    let step = processing::ProcessingStep::new("recover")
+        .param("error", error.to_string())
+        .param("lost", report.lost)
+        .param("problems", report.problems);
+    for spc in &mut spectra {
+        spc.history.push(step.clone());
+    }
+    Ok(spectra)
+}
+
+/// Write the converted spectrum in the selected format.
+fn write_output(cli: &Cli, spc: &SpcFile, output_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let options = write_options(cli);
+    // Fail on an unavailable --axis before creating the file
+    if options.axis.is_some() {
+        output::select_axis(spc, options.axis)?;
+    }
+    if is_stdout(output_path) {
+        let mut stdout = std::io::stdout().lock();
+        output_writer(cli).write(spc, &mut stdout, &options).map_err(|e| e as Box<dyn std::error::Error>)?;
+        return Ok(stdout.flush()?);
+    }
+    output_writer(cli)
+        .write_file(spc, output_path, &options)
+        .map_err(|e| e as Box<dyn std::error::Error>)
+}
+
+/// Whether an output path is `-`, for stdout.
+fn is_stdout(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+/// Writer options from the command line.
+fn write_options(cli: &Cli) -> WriteOptions {
+    WriteOptions {
+        pretty: cli.pretty,
+        axis: cli.axis.map(AxisType::from),
+        plot: plot_options(cli),
+        csv: csv_options(cli),
+        pairs: pairs_options(cli),
+        append: cli.append,
+        single_precision: cli.export_precision == PrecisionArg::F32,
+    }
+}
+
+/// Apply the requested processing steps in a fixed order.
+fn apply_processing(cli: &Cli, spc: &mut SpcFile) -> Result<(), String> {
+    if cli.subtract_blank {
+        processing::subtract_blank(spc, cli.clip_negative);
+    }
+    if let (Some(curve), Some(path)) = (&cli.response_curve, &cli.response) {
+        processing::correct_response(spc, curve, &path.display().to_string())?;
+    }
+    if cli.despike {
+        processing::despike(spc, processing::DEFAULT_DESPIKE_WINDOW, cli.despike_threshold);
+    }
+    if cli.apply_config {
+        processing::apply_config(spc)?;
+    }
+    if cli.apply_sgolay {
+        match spc.config.as_ref().and_then(processing::SavitzkyGolay::from_config) {
+            Some(filter) => processing::apply_savitzky_golay(spc, &filter)
+                .map_err(|e| format!("stored Savitzky-Golay settings: {}", e))?,
+            None if cli.verbose => log::info!("  No enabled Savitzky-Golay filter stored; skipped"),
+            None => {}
         }
-        
-        output::write_plot_default(&spc, &plot_path)?;
-        
-        if cli.verbose {
-            eprintln!("  -> \"{}\"", plot_path.display());
+    }
+    if let Some(kernel) = cli.smooth {
+        let kernel = match kernel {
+            SmoothArg::Boxcar => processing::SmoothKernel::Boxcar {
+                width: cli.smooth_width.round().max(1.0) as usize,
+            },
+            SmoothArg::Gaussian => processing::SmoothKernel::Gaussian { fwhm: cli.smooth_width },
+        };
+        processing::smooth(spc, kernel);
+    }
+    if let Some(method) = cli.baseline {
+        let method = match method {
+            BaselineArg::Als => processing::BaselineMethod::Als {
+                lambda: cli.baseline_lambda,
+                asymmetry: processing::DEFAULT_ALS_ASYMMETRY,
+            },
+            BaselineArg::Polynomial => processing::BaselineMethod::Polynomial {
+                degree: cli.baseline_degree,
+            },
+        };
+        processing::subtract_baseline(spc, method);
+    }
+    if cli.xmin.is_some() || cli.xmax.is_some() {
+        processing::crop(spc, cli.axis.map(Into::into), cli.xmin, cli.xmax)?;
+    }
+    if let Some(mode) = cli.normalize {
+        processing::normalize(spc, mode.into());
+    }
+    if cli.export_precision == PrecisionArg::F32 {
+        processing::single_precision(spc);
+    }
+
+    if cli.verbose {
+        for step in &spc.history {
+            log::info!("  Applied: {}", step.summary());
         }
     }
+    Ok(())
+}
 
-    Ok(output_path)
+/// Parse a `KEY=VALUE` metadata argument.
+fn parse_key_value(s: &str) -> Result<(String, String), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("invalid KEY=VALUE: no `=` found in `{}`", s))?;
+    if key.trim().is_empty() {
+        return Err(format!("invalid KEY=VALUE: empty key in `{}`", s));
+    }
+    Ok((key.trim().to_string(), value.to_string()))
 }
 
-fn get_output_path(cli: &Cli, input_path: &PathBuf) -> PathBuf {
-    let extension = match cli.format {
-        OutputFormat::Json => "json",
-        OutputFormat::Csv => "csv",
-        OutputFormat::Pairs => "txt",
+fn parse_range(s: &str) -> Result<(f64, f64), String> {
+    let (min, max) = s
+        .split_once(':')
+        .ok_or_else(|| format!("invalid MIN:MAX: no `:` found in `{}`", s))?;
+    let parse = |v: &str| {
+        v.trim()
+            .parse::<f64>()
+            .map_err(|_| format!("invalid MIN:MAX: `{}` is not a number", v))
     };
+    Ok((parse(min)?, parse(max)?))
+}
+
+fn parse_laser_nm(s: &str) -> Result<f64, String> {
+    match s.trim().parse::<f64>() {
+        Ok(nm) if nm.is_finite() && nm > 0.0 => Ok(nm),
+        _ => Err(format!("invalid wavelength `{}`: expected a positive number of nm", s)),
+    }
+}
+
+fn parse_smooth_width(s: &str) -> Result<f64, String> {
+    match s.trim().parse::<f64>() {
+        Ok(width) if width.is_finite() && width > 0.0 && width <= processing::MAX_SMOOTH_WIDTH => Ok(width),
+        _ => Err(format!(
+            "invalid width `{}`: expected a positive number of points up to {}",
+            s,
+            processing::MAX_SMOOTH_WIDTH
+        )),
+    }
+}
+
+/// Output path for an input, or for spectrum `index` of an input holding
+/// several.
+fn get_output_path(cli: &Cli, input_path: &Path, index: Option<usize>) -> PathBuf {
+    let extension = output_writer(cli).extension();
 
-    if let Some(ref output) = cli.output {
-        if cli.input.len() == 1 {
+    if let Some(output) = cli.staging.as_ref().or(cli.output.as_ref()) {
+        if is_stdout(output) && cli.staging.is_none() {
+            output.clone()
+        } else if cli.input.len() == 1 && cli.staging.is_none() {
             // Single file: use output as-is if it has an extension, otherwise add one
-            if output.extension().is_some() {
-                output.clone()
-            } else {
-                output.with_extension(extension)
+            match output.extension() {
+                Some(output_extension) => numbered_path(output, index).with_extension(output_extension),
+                None => numbered_path(output, index).with_extension(extension),
             }
         } else {
-            // Multiple files: output is a directory
-            let filename = input_path
-                .file_stem()
-                .unwrap_or_default()
-                .to_string_lossy();
-            output.join(format!("{}.{}", filename, extension))
+            // Multiple files: output is a directory, mirroring any input tree
+            let relative = match cli.relative_input.get(input_path) {
+                Some(relative) => relative.clone(),
+                None => PathBuf::from(input_path.file_name().unwrap_or_default()),
+            };
+            numbered_path(&output.join(relative), index).with_extension(extension)
         }
     } else {
         // No output specified: create alongside input
-        input_path.with_extension(extension)
+        numbered_path(&beside(cli, input_path), index).with_extension(extension)
+    }
+}
+
+/// The id of each input in batch outputs: its path below the directory or
+/// glob it was found in, without the extension (`sub/a` for `data/sub/a.spc`
+/// found in `data/`). Inputs that would share an id, such as `a/x.spc` and
+/// `b/x.spc` given as files, use their path below the inputs' common
+/// directory instead.
+fn file_ids(cli: &Cli) -> HashMap<&Path, PathBuf> {
+    let relative = |input: &Path| match cli.relative_input.get(input) {
+        Some(relative) => relative.clone(),
+        None => PathBuf::from(input.file_name().unwrap_or_default()),
+    };
+    let mut counts: HashMap<PathBuf, usize> = HashMap::new();
+    for input in &cli.input {
+        *counts.entry(relative(input)).or_default() += 1;
+    }
+    let mut root = cli.input.first().and_then(|input| input.parent()).unwrap_or(Path::new(""));
+    for input in &cli.input {
+        while !input.starts_with(root) {
+            root = root.parent().unwrap_or(Path::new(""));
+        }
+    }
+
+    cli.input
+        .iter()
+        .map(|input| {
+            let id = relative(input);
+            let id = match counts[&id] {
+                1 => id,
+                _ => input.strip_prefix(root).unwrap_or(input).to_path_buf(),
+            };
+            (input.as_path(), id.with_extension(""))
+        })
+        .collect()
+}
+
+/// Where outputs placed alongside an input go: next to the input itself,
+/// or for a file read from an archive, into a directory named after the
+/// archive (`data/session/sub/a.spc` for `sub/a.spc` in `data/session.zip`).
+fn beside(cli: &Cli, input_path: &Path) -> PathBuf {
+    let Some(relative) = cli.relative_input.get(input_path).filter(|_| cli.archived.contains_key(input_path)) else {
+        return input_path.to_path_buf();
+    };
+    let archive = input_path.ancestors().nth(relative.components().count()).unwrap_or(input_path);
+    archive.with_file_name(inputs::archive_stem(archive)).join(relative)
+}
+
+/// The bytes of an input, from disk or from the archive it was read from.
+fn read_input(cli: &Cli, input_path: &Path) -> std::io::Result<Vec<u8>> {
+    match cli.archived.get(input_path) {
+        Some(archived) => archived.read(),
+        None => std::fs::read(input_path),
+    }
+}
+
+/// A path as the user sees it: staged outputs as their place in the
+/// archive, e.g. `batch.zip/sub/a.json`.
+fn shown_path(cli: &Cli, path: &Path) -> PathBuf {
+    match (&cli.staging, &cli.output) {
+        (Some(staging), Some(archive)) => archive.join(path.strip_prefix(staging).unwrap_or(path)),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// `dir/name_<index>` for spectrum `index` of a file holding several;
+/// the path itself otherwise.
+fn numbered_path(path: &Path, index: Option<usize>) -> PathBuf {
+    match index {
+        Some(index) => {
+            path.with_file_name(format!("{}_{}", path.file_stem().unwrap_or_default().to_string_lossy(), index))
+        }
+        None => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The CLI with `flags`, and an output of it that already exists.
+    fn existing_output(name: &str, flags: &[&str]) -> (Cli, PathBuf) {
+        let path = std::env::temp_dir().join(format!("spc_may_write_{}_{}.json", name, std::process::id()));
+        std::fs::write(&path, "old").unwrap();
+        let cli = Cli::parse_from(["spc-convert", "input.spc"].iter().chain(flags));
+        (cli, path)
+    }
+
+    #[test]
+    fn test_skip_existing() {
+        let (cli, path) = existing_output("skip", &["--skip-existing"]);
+        assert!(!may_write(&cli, &path).unwrap());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "old");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_no_clobber() {
+        let (cli, path) = existing_output("no_clobber", &["--no-clobber"]);
+        assert!(may_write(&cli, &path).unwrap_err().to_string().contains("already exists"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "old");
+
+        // A new output is claimed, so a second writer to it is refused
+        std::fs::remove_file(&path).unwrap();
+        assert!(may_write(&cli, &path).unwrap());
+        assert!(may_write(&cli, &path).is_err());
+        discard_unwritten(&path);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_overwrite() {
+        let (cli, path) = existing_output("overwrite", &["--overwrite"]);
+        assert!(may_write(&cli, &path).unwrap());
+        // Replaced by the writer, not here
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "old");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_stdout_output() {
+        let cli = Cli::parse_from(["spc-convert", "input.spc", "-f", "csv", "-o", "-"]);
+        let path = get_output_path(&cli, Path::new("input.spc"), None);
+        assert_eq!(path, Path::new("-"));
+        assert_eq!(get_output_path(&cli, Path::new("input.spc"), Some(1)), Path::new("-"));
+        assert!(may_write(&cli, &path).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_given_options() {
+        use std::os::unix::ffi::OsStrExt;
+        let output = std::ffi::OsStr::from_bytes(b"out\xff/");
+        let args = ["spc-convert".as_ref(), "data/".as_ref(), "--normalize=max".as_ref(), "-o".as_ref(), output];
+        let command = Cli::command();
+        let matches = command.clone().try_get_matches_from(args).unwrap();
+        // Inputs are left out and non-UTF-8 values are kept lossily
+        assert_eq!(given_options(&command, &matches), ["--output", "out\u{fffd}/", "--normalize", "max"]);
+    }
+
+    #[test]
+    fn test_file_ids() {
+        let mut cli = Cli::parse_from(["spc-convert", "data/a/x.spc", "data/b/x.spc", "data/b/y.spc"]);
+        let ids = file_ids(&cli);
+        assert_eq!(ids[Path::new("data/a/x.spc")], Path::new("a/x"));
+        assert_eq!(ids[Path::new("data/b/x.spc")], Path::new("b/x"));
+        assert_eq!(ids[Path::new("data/b/y.spc")], Path::new("y"));
+
+        // Inputs found in a directory keep their path below it
+        cli.relative_input.insert(PathBuf::from("data/b/y.spc"), PathBuf::from("b/y.spc"));
+        assert_eq!(file_ids(&cli)[Path::new("data/b/y.spc")], Path::new("b/y"));
+    }
+
+    #[test]
+    fn test_failed_batch_write() {
+        let dir = std::env::temp_dir().join(format!("spc_batch_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("a.spc");
+        spc_converter::spectre::SpcFileBuilder::new("CAM-1", vec![1.0, 2.0, 3.0]).build().write_file(&input).unwrap();
+        let output = dir.join("missing").join("all.csv");
+        let cli = Cli::parse_from([
+            "spc-convert".as_ref(),
+            input.as_os_str(),
+            "-f".as_ref(),
+            "csv-matrix".as_ref(),
+            "-o".as_ref(),
+            output.as_os_str(),
+        ]);
+
+        let mut run_report = report::RunReport::new(&cli.format);
+        assert!(convert_batch(&cli, &mut run_report).is_err());
+        let json = serde_json::to_value(&run_report).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(json["error"].is_string());
+        assert_eq!(json["succeeded"], 0);
+        // The input was read even though the output could not be written
+        assert_eq!(json["files"][0]["status"], "loaded");
+        assert_eq!(json["files"][0]["uid"], "CAM-1");
     }
 }
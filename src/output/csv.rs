@@ -1,6 +1,8 @@
 //! CSV output format.
 
-use crate::spectre::{SpectreFile, SpcFile};
+use super::{axis_info, select_axis};
+use crate::processing::unique_names;
+use crate::spectre::{AxisType, Instrument, SpectreFile, SpcFile};
 use std::io::{self, Write};
 
 /// Write SpectreFile as CSV to a writer.
@@ -29,62 +31,124 @@ pub fn to_csv_string(spectre: &SpectreFile) -> io::Result<String> {
     String::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
 }
 
+/// Options for [`write_csv_spc_with`].
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    /// X-axis column after the index (none for [`AxisType::Pixels`]); the
+    /// axis must be available [default: every available axis]
+    pub axis: Option<AxisType>,
+    /// Field separator, e.g. `,`, `;` or `\t`
+    pub delimiter: char,
+    /// Decimal separator, `.` or `,`
+    pub decimal: char,
+    /// Digits after the decimal point [default: as many as needed]
+    pub precision: Option<usize>,
+    /// Write the index column
+    pub index: bool,
+    /// Start with `# key: value` lines for the uid, laser wavelength,
+    /// exposure, gain and calibration
+    pub header: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            axis: None,
+            delimiter: ',',
+            decimal: '.',
+            precision: None,
+            index: true,
+            header: false,
+        }
+    }
+}
+
+impl CsvOptions {
+    pub(super) fn format(&self, value: f64) -> String {
+        let text = fixed(value, self.precision);
+        if self.decimal == '.' {
+            text
+        } else {
+            text.replace('.', &self.decimal.to_string())
+        }
+    }
+}
+
+/// `value` with `precision` digits after the decimal point, else as many
+/// as needed.
+pub(super) fn fixed(value: f64, precision: Option<usize>) -> String {
+    match precision {
+        Some(precision) => format!("{:.*}", precision, value),
+        None => value.to_string(),
+    }
+}
+
 /// Write SpcFile as CSV to a writer.
 ///
 /// If calibration is present, includes wavelength/wavenumber columns.
+/// The acquisition time, operator, sample, user metadata and processing
+/// history, if any, are written first as `# key: value` comment lines.
 /// Format: index,wavelength,raman_shift,intensity,blank
-pub fn write_csv_spc<W: Write>(spc: &SpcFile, mut writer: W) -> io::Result<()> {
+pub fn write_csv_spc<W: Write>(spc: &SpcFile, writer: W) -> io::Result<()> {
+    write_csv_spc_with(spc, &CsvOptions::default(), writer)
+}
+
+/// Write SpcFile as CSV with the given columns and number format.
+pub fn write_csv_spc_with<W: Write>(spc: &SpcFile, options: &CsvOptions, mut writer: W) -> io::Result<()> {
+    let axis = options.axis;
+    if let Some(axis) = axis {
+        select_axis(spc, Some(axis))?;
+    }
+    check_separators(options)?;
+
+    if options.header {
+        write_header(spc, &mut writer)?;
+    }
+    write_comments(spc, &mut writer)?;
+
     // Determine what columns we have
-    let has_wavelength = spc.wavelength_axis.is_some();
-    let has_raman = spc.raman_shift_axis.is_some();
-    
+    let shown = |a: AxisType| axis.is_none_or(|axis| axis == a);
+    let wavelengths = shown(AxisType::Wavelengths).then(|| spc.wavelengths()).flatten();
+    let raman_shifts = shown(AxisType::RamanShifts).then(|| spc.raman_shifts()).flatten();
+    let blank = Some(&spc.blank[..]).filter(|blank| !blank.is_empty());
+
     // Write header
-    let mut header = String::from("index");
-    if has_wavelength {
-        header.push_str(",wavelength_nm");
+    let mut header = Vec::new();
+    if options.index {
+        header.push("index");
+    }
+    if wavelengths.is_some() {
+        header.push("wavelength_nm");
     }
-    if has_raman {
-        header.push_str(",raman_shift_cm-1");
+    if raman_shifts.is_some() {
+        header.push("raman_shift_cm-1");
     }
-    header.push_str(",intensity");
-    if !spc.blank.is_empty() {
-        header.push_str(",blank");
+    header.push("intensity");
+    if blank.is_some() {
+        header.push("blank");
     }
-    writeln!(writer, "{}", header)?;
+    writeln!(writer, "{}", header.join(&options.delimiter.to_string()))?;
 
     // Determine max length
     let max_len = spc.data.len().max(spc.blank.len());
-    
-    let wavelengths = spc.wavelength_axis.as_ref();
-    let raman_shifts = spc.raman_shift_axis.as_ref();
+    let value = |column: Option<&[f64]>, i: usize| options.format(column.and_then(|v| v.get(i)).copied().unwrap_or(f64::NAN));
 
     for i in 0..max_len {
-        // Index
-        write!(writer, "{}", i)?;
-        
-        // Wavelength
-        if has_wavelength {
-            let wl = wavelengths.and_then(|v| v.get(i)).copied().unwrap_or(f64::NAN);
-            write!(writer, ",{}", wl)?;
+        let mut row = Vec::with_capacity(header.len());
+        if options.index {
+            row.push(i.to_string());
         }
-        
-        // Raman shift
-        if has_raman {
-            let rs = raman_shifts.and_then(|v| v.get(i)).copied().unwrap_or(f64::NAN);
-            write!(writer, ",{}", rs)?;
+        if wavelengths.is_some() {
+            row.push(value(wavelengths.as_deref(), i));
         }
-        
-        // Intensity
-        let intensity = spc.data.get(i).copied().unwrap_or(f64::NAN);
-        write!(writer, ",{}", intensity)?;
-        
-        // Blank
-        if !spc.blank.is_empty() {
-            let blank = spc.blank.get(i).copied().unwrap_or(f64::NAN);
-            write!(writer, ",{}", blank)?;
+        if raman_shifts.is_some() {
+            row.push(value(raman_shifts.as_deref(), i));
         }
-        
-        writeln!(writer)?;
+        row.push(value(Some(&spc.data[..]), i));
+        if blank.is_some() {
+            row.push(value(blank, i));
+        }
+        writeln!(writer, "{}", row.join(&options.delimiter.to_string()))?;
     }
 
     Ok(())
@@ -96,3 +160,231 @@ pub fn to_csv_string_spc(spc: &SpcFile) -> io::Result<String> {
     write_csv_spc(spc, &mut buf)?;
     String::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
 }
+
+/// Write spectra as a wide CSV matrix: the x column, then one intensity
+/// column per spectrum, headed by its uid (numbered by position where uids
+/// repeat). See [`write_csv_matrix_named`].
+pub fn write_csv_matrix<W: Write>(spectra: &[SpcFile], options: &CsvOptions, writer: W) -> io::Result<()> {
+    let names = unique_names(spectra);
+    let columns: Vec<(&str, &SpcFile)> = names.iter().map(String::as_str).zip(spectra).collect();
+    write_csv_matrix_named(&columns, options, writer)
+}
+
+/// Write `(name, spectrum)` pairs as a wide CSV matrix, the layout
+/// chemometrics packages expect: an `index` column (unless turned off),
+/// the x column, then one intensity column per spectrum headed by its
+/// name.
+///
+/// The x-axis is `options.axis`, else the first spectrum's (see
+/// [`select_axis`]). Every spectrum must have that axis with the same
+/// values; spectra on different axes need resampling first.
+pub fn write_csv_matrix_named<W: Write>(
+    columns: &[(&str, &SpcFile)],
+    options: &CsvOptions,
+    mut writer: W,
+) -> io::Result<()> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+    let Some((_, first)) = columns.first() else {
+        return Err(invalid("no spectra for the matrix".to_string()));
+    };
+    check_separators(options)?;
+
+    let x = select_axis(first, options.axis)?;
+    let (axis, heading) = match x.name {
+        "Raman Shift" => (AxisType::RamanShifts, "raman_shift_cm-1"),
+        "Wavelength" => (AxisType::Wavelengths, "wavelength_nm"),
+        _ => (AxisType::Pixels, "index"),
+    };
+    for (name, spc) in &columns[1..] {
+        let compatible = axis_info(spc, axis).is_some_and(|other| {
+            other.values.len() == x.values.len()
+                && other.values.iter().zip(x.values.iter()).all(|(a, b)| (a - b).abs() <= 1e-6 * a.abs().max(b.abs()).max(1.0))
+        });
+        if !compatible {
+            return Err(invalid(format!("{} is not on the {} axis of {}", name, x.name, columns[0].0)));
+        }
+    }
+
+    let delimiter = options.delimiter.to_string();
+    let index = options.index && axis != AxisType::Pixels;
+    let mut header: Vec<String> = Vec::with_capacity(columns.len() + 2);
+    if index {
+        header.push("index".to_string());
+    }
+    header.push(heading.to_string());
+    header.extend(columns.iter().map(|(name, _)| quote(name, options.delimiter)));
+    writeln!(writer, "{}", header.join(&delimiter))?;
+
+    for (i, x) in x.values.iter().enumerate() {
+        let mut row = Vec::with_capacity(header.len());
+        if index {
+            row.push(i.to_string());
+        }
+        row.push(if axis == AxisType::Pixels { i.to_string() } else { options.format(*x) });
+        row.extend(columns.iter().map(|(_, spc)| options.format(spc.data.get(i).copied().unwrap_or(f64::NAN))));
+        writeln!(writer, "{}", row.join(&delimiter))?;
+    }
+    Ok(())
+}
+
+pub(super) fn check_separators(options: &CsvOptions) -> io::Result<()> {
+    if options.delimiter == options.decimal || !['.', ','].contains(&options.decimal) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("cannot write CSV with delimiter {:?} and decimal separator {:?}", options.delimiter, options.decimal),
+        ));
+    }
+    Ok(())
+}
+
+/// A heading, quoted if it contains the delimiter or a quote.
+pub(super) fn quote(name: &str, delimiter: char) -> String {
+    if name.contains(delimiter) || name.contains('"') {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    } else {
+        name.to_string()
+    }
+}
+
+/// `# key: value` lines describing the acquisition: `uid`, `laser_nm`,
+/// `exposure`, `gain`, `calibration` (the model) and
+/// `calibration_coefficients`, where known.
+fn write_header<W: Write>(spc: &SpcFile, mut writer: W) -> io::Result<()> {
+    writeln!(writer, "# uid: {}", spc.uid)?;
+    if let Some(config) = &spc.config {
+        let settings = [("laser_nm", config.raman_wavelength), ("exposure", config.exposure), ("gain", config.gain)];
+        for (key, value) in settings.into_iter().filter_map(|(key, value)| Some((key, value?))) {
+            writeln!(writer, "# {}: {}", key, value)?;
+        }
+    }
+    if let Some(calibration) = &spc.calibration {
+        writeln!(writer, "# calibration: {}", format!("{:?}", calibration.model).to_lowercase())?;
+        if !calibration.coefficients.is_empty() {
+            let coefficients: Vec<String> = calibration.coefficients.iter().map(f64::to_string).collect();
+            writeln!(writer, "# calibration_coefficients: {}", coefficients.join(", "))?;
+        }
+    }
+    Ok(())
+}
+
+/// `# key: value` comment lines of the text formats: `acquired`,
+/// `operator`, `sample` and `instrument.*` if known, then metadata and
+/// processing steps.
+pub(super) fn write_comments<W: Write>(spc: &SpcFile, mut writer: W) -> io::Result<()> {
+    let labels = [("acquired", &spc.acquired), ("operator", &spc.operator), ("sample", &spc.sample)];
+    for (key, value) in labels.into_iter().filter_map(|(key, value)| Some((key, value.as_ref()?))) {
+        writeln!(writer, "# {}: {}", key, value)?;
+    }
+    for (key, value) in spc.instrument.iter().flat_map(Instrument::entries) {
+        writeln!(writer, "# {}: {}", key, value)?;
+    }
+    for (key, value) in &spc.metadata {
+        writeln!(writer, "# {}: {}", key, value)?;
+    }
+    for step in &spc.history {
+        writeln!(writer, "# processing: {}", step.summary())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_options() {
+        let spc = SpcFile {
+            uid: "CAM-1".to_string(),
+            data: vec![1.25, 2.5],
+            blank: Vec::new(),
+            calibration: None,
+            config: None,
+            wavelength_axis: Some(vec![800.0, 800.5]),
+            raman_shift_axis: None,
+            acquired: None,
+            operator: None,
+            sample: None,
+            instrument: None,
+            provenance: None,
+            stats: None,
+            metadata: Default::default(),
+            history: Vec::new(),
+            extras: Vec::new(),
+        };
+        let write = |options: &CsvOptions| {
+            let mut buf = Vec::new();
+            write_csv_spc_with(&spc, options, &mut buf).map(|_| String::from_utf8(buf).unwrap())
+        };
+
+        assert_eq!(write(&CsvOptions::default()).unwrap(), "index,wavelength_nm,intensity\n0,800,1.25\n1,800.5,2.5\n");
+        let excel = CsvOptions {
+            delimiter: ';',
+            decimal: ',',
+            precision: Some(1),
+            index: false,
+            ..Default::default()
+        };
+        assert_eq!(write(&excel).unwrap(), "wavelength_nm;intensity\n800,0;1,2\n800,5;2,5\n");
+        let ambiguous = CsvOptions {
+            decimal: ',',
+            ..Default::default()
+        };
+        assert!(write(&ambiguous).is_err());
+
+        // The acquisition header reads back
+        let mut spc = spc.clone();
+        spc.config = Some(crate::spectre::Config {
+            exposure: Some(100.0),
+            ..Default::default()
+        });
+        spc.calibration = Some(crate::Calibration::legendre(vec![800.25, 0.5]));
+        let header = CsvOptions {
+            header: true,
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        write_csv_spc_with(&spc, &header, &mut buf).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        assert!(text.starts_with("# uid: CAM-1\n# exposure: 100\n# calibration: legendre\n# calibration_coefficients: 800.25, 0.5\n"));
+        let read = crate::import::read_text(&text, None).unwrap();
+        assert_eq!(read.uid, "CAM-1");
+        assert_eq!(read.config.unwrap().exposure, Some(100.0));
+        assert!(read.metadata.is_empty());
+    }
+
+    #[test]
+    fn test_csv_matrix() {
+        let spectrum = |uid: &str, data: Vec<f64>, wavelengths: Vec<f64>| SpcFile {
+            uid: uid.to_string(),
+            data,
+            blank: Vec::new(),
+            calibration: None,
+            config: None,
+            wavelength_axis: Some(wavelengths),
+            raman_shift_axis: None,
+            acquired: None,
+            operator: None,
+            sample: None,
+            instrument: None,
+            provenance: None,
+            stats: None,
+            metadata: Default::default(),
+            history: Vec::new(),
+            extras: Vec::new(),
+        };
+        let spectra = [
+            spectrum("CAM-1", vec![1.0, 2.0], vec![500.0, 501.0]),
+            spectrum("CAM-1", vec![3.0, 4.0], vec![500.0, 501.0]),
+            spectrum("CAM-2", vec![5.0, 6.0], vec![500.0, 501.0]),
+        ];
+        let mut buf = Vec::new();
+        write_csv_matrix(&spectra, &CsvOptions::default(), &mut buf).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "index,wavelength_nm,CAM-1_0,CAM-1_1,CAM-2\n0,500,1,3,5\n1,501,2,4,6\n"
+        );
+
+        let shifted = [spectra[0].clone(), spectrum("CAM-3", vec![7.0, 8.0], vec![510.0, 511.0])];
+        assert!(write_csv_matrix(&shifted, &CsvOptions::default(), &mut Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,192 @@
+//! Thermo Galactic SPC output (GRAMS "new format", version 0x4B).
+//!
+//! Not to be confused with the Spectrum Analyzer Suite .spc container this
+//! crate reads. Writes a single-subfile, IEEE float file with an explicit
+//! x-array so the calibrated (non-uniform) axis is preserved.
+
+use super::select_best_axis;
+use crate::spectre::timestamp::parse_rfc3339;
+use crate::spectre::SpcFile;
+use std::io::{self, Write};
+
+/// Main header size.
+pub(crate) const HEADER_SIZE: usize = 512;
+/// Subfile header size.
+pub(crate) const SUBHEADER_SIZE: usize = 32;
+
+/// `ftflgs`: per-file x values follow the main header.
+pub(crate) const TXVALS: u8 = 0x80;
+/// File format version byte for the new (LSB) format.
+pub(crate) const VERSION_NEW_LSB: u8 = 0x4B;
+/// Exponent byte marking IEEE 32-bit float y values.
+pub(crate) const FLOAT_DATA: u8 = 0x80;
+
+/// `fexper` experiment types.
+const EXPERIMENT_GENERAL: u8 = 0;
+const EXPERIMENT_RAMAN: u8 = 11;
+
+/// `fxtype` axis units.
+const X_ARBITRARY: u8 = 0;
+pub(crate) const X_NANOMETERS: u8 = 3;
+pub(crate) const X_RAMAN_SHIFT: u8 = 13;
+const X_POINTS: u8 = 22;
+
+/// `fytype` intensity units.
+const Y_COUNTS: u8 = 4;
+
+/// `fdate` of an acquisition time: minute, hour, day, month and year in
+/// 6, 5, 5, 4 and 12 bits. Zero when the time is not an RFC 3339 date.
+pub(crate) fn pack_date(acquired: &str) -> u32 {
+    match parse_rfc3339(acquired) {
+        Some([year, month, day, hour, minute, _]) if year < 4096 => {
+            year << 20 | month << 16 | day << 11 | hour << 6 | minute
+        }
+        _ => 0,
+    }
+}
+
+/// The acquisition time of a nonzero `fdate`, e.g. `2024-03-01T12:30`
+/// (Galactic files store no seconds or time zone).
+pub(crate) fn unpack_date(fdate: u32) -> Option<String> {
+    let (year, month, day) = (fdate >> 20, fdate >> 16 & 0xF, fdate >> 11 & 0x1F);
+    let (hour, minute) = (fdate >> 6 & 0x1F, fdate & 0x3F);
+    let valid = (1..=12).contains(&month) && (1..=31).contains(&day) && hour < 24 && minute < 60;
+    valid.then(|| format!("{:04}-{:02}-{:02}T{:02}:{:02}", year, month, day, hour, minute))
+}
+
+/// Write SpcFile as a Thermo Galactic SPC file.
+///
+/// Uses the best available x-axis (Raman shift, wavelength, or pixel index).
+/// An RFC 3339 acquisition time is stored to the minute.
+pub fn write_galactic<W: Write>(spc: &SpcFile, mut writer: W) -> io::Result<()> {
+    let axis = select_best_axis(spc);
+    let n = spc.data.len();
+    let npts = u32::try_from(n)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many points for Galactic SPC"))?;
+
+    let (experiment, x_type) = match axis.name {
+        "Raman Shift" => (EXPERIMENT_RAMAN, X_RAMAN_SHIFT),
+        "Wavelength" => (EXPERIMENT_GENERAL, X_NANOMETERS),
+        "Pixel Index" => (EXPERIMENT_GENERAL, X_POINTS),
+        _ => (EXPERIMENT_GENERAL, X_ARBITRARY),
+    };
+
+    let first = axis.values.first().copied().unwrap_or(0.0);
+    let last = axis.values.last().copied().unwrap_or(0.0);
+
+    let mut header = [0u8; HEADER_SIZE];
+    header[0] = TXVALS;
+    header[1] = VERSION_NEW_LSB;
+    header[2] = experiment;
+    header[3] = FLOAT_DATA;
+    header[4..8].copy_from_slice(&npts.to_le_bytes());
+    header[8..16].copy_from_slice(&first.to_le_bytes());
+    header[16..24].copy_from_slice(&last.to_le_bytes());
+    header[24..28].copy_from_slice(&1u32.to_le_bytes()); // fnsub
+    header[28] = x_type;
+    header[29] = Y_COUNTS;
+    let fdate = spc.acquired.as_deref().map_or(0, pack_date);
+    header[32..36].copy_from_slice(&fdate.to_le_bytes());
+
+    // fcmnt: 130-byte NUL-terminated comment
+    let mut comment = format!("UID: {}", spc.uid);
+    if let Some(laser) = spc.config.as_ref().and_then(|c| c.raman_wavelength) {
+        comment.push_str(&format!("; Laser: {} nm", laser));
+    }
+    let comment = comment.as_bytes();
+    let len = comment.len().min(129);
+    header[88..88 + len].copy_from_slice(&comment[..len]);
+
+    writer.write_all(&header)?;
+
+    // X array (float32)
+    for i in 0..n {
+        let x = axis.values.get(i).copied().unwrap_or(i as f64) as f32;
+        writer.write_all(&x.to_le_bytes())?;
+    }
+
+    // Subfile header
+    let mut subheader = [0u8; SUBHEADER_SIZE];
+    subheader[1] = FLOAT_DATA;
+    subheader[16..20].copy_from_slice(&npts.to_le_bytes());
+    writer.write_all(&subheader)?;
+
+    // Y values (float32)
+    for &y in &spc.data {
+        writer.write_all(&(y as f32).to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Write SpcFile as Thermo Galactic SPC bytes.
+pub fn to_galactic_bytes(spc: &SpcFile) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    write_galactic(spc, &mut buf)?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spectre::SpcFileBuilder;
+    use crate::Calibration;
+
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn f64_at(bytes: &[u8], offset: usize) -> f64 {
+        f64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_header_fields() {
+        let spc = SpcFileBuilder::new("CAM-1", vec![10.0, 20.5, 30.25])
+            .calibration(Calibration::legendre(vec![800.0, 20.0]))
+            .laser_wavelength(785.0)
+            .acquired("2024-03-01T12:30:45Z")
+            .build();
+        let shifts = spc.raman_shift_axis.clone().unwrap();
+        let bytes = to_galactic_bytes(&spc).unwrap();
+        assert_eq!(bytes.len(), HEADER_SIZE + 3 * 4 + SUBHEADER_SIZE + 3 * 4);
+        assert_eq!(bytes[..4], [TXVALS, VERSION_NEW_LSB, EXPERIMENT_RAMAN, FLOAT_DATA]);
+        assert_eq!(u32_at(&bytes, 4), 3);
+        assert_eq!(f64_at(&bytes, 8), shifts[0]);
+        assert_eq!(f64_at(&bytes, 16), shifts[2]);
+        assert_eq!(u32_at(&bytes, 24), 1);
+        assert_eq!(bytes[28..30], [X_RAMAN_SHIFT, Y_COUNTS]);
+        assert_eq!(unpack_date(u32_at(&bytes, 32)).as_deref(), Some("2024-03-01T12:30"));
+        assert!(bytes[88..].starts_with(b"UID: CAM-1; Laser: 785 nm\0"));
+
+        let x = f32::from_le_bytes(bytes[HEADER_SIZE..HEADER_SIZE + 4].try_into().unwrap());
+        assert_eq!(x, shifts[0] as f32);
+        let subheader = &bytes[HEADER_SIZE + 12..HEADER_SIZE + 12 + SUBHEADER_SIZE];
+        assert_eq!(subheader[1], FLOAT_DATA);
+        assert_eq!(u32_at(subheader, 16), 3);
+        let y = f32::from_le_bytes(bytes[bytes.len() - 4..].try_into().unwrap());
+        assert_eq!(y, 30.25);
+    }
+
+    #[test]
+    fn test_uncalibrated_header() {
+        let spc = SpcFileBuilder::new("X".repeat(200), vec![1.0, 2.0]).acquired("yesterday").build();
+        let bytes = to_galactic_bytes(&spc).unwrap();
+        assert_eq!(bytes[2], EXPERIMENT_GENERAL);
+        assert_eq!(bytes[28], X_POINTS);
+        assert_eq!((f64_at(&bytes, 8), f64_at(&bytes, 16)), (0.0, 1.0));
+        assert_eq!(u32_at(&bytes, 32), 0);
+        // The comment is cut to 129 bytes and stays NUL-terminated
+        assert_eq!(bytes[88 + 128], b'X');
+        assert_eq!(bytes[88 + 129], 0);
+    }
+
+    #[test]
+    fn test_pack_date() {
+        let fdate = pack_date("2024-03-01T12:30:45Z");
+        assert_eq!(fdate, 2024 << 20 | 3 << 16 | 1 << 11 | 12 << 6 | 30);
+        assert_eq!(unpack_date(fdate).as_deref(), Some("2024-03-01T12:30"));
+        assert_eq!(pack_date("not a date"), 0);
+        assert_eq!(unpack_date(0), None);
+    }
+}
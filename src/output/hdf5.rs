@@ -0,0 +1,177 @@
+//! HDF5 output format (requires the `hdf5` feature and a system libhdf5).
+//!
+//! Layout of one spectrum (at the file root, or in a group for batches):
+//!
+//! ```text
+//! /data                      f64[n]   intensity
+//! /blank                     f64[n]   blank/reference (if present)
+//! /wavelength_axis           f64[n]   (if calibrated)
+//! /raman_shift_axis          f64[n]   (if calibrated and laser known)
+//! /calibration_coefficients  f64[k]   (if present)
+//! /config                    group, one attribute per config field
+//! /instrument                group, model/serial/firmware and detector_temperature attributes (if known)
+//! /provenance                group, one string attribute per field (if requested); options joined by spaces
+//! /metadata                  group, one string attribute per user field
+//! @uid                       string attribute
+//! @acquired, @operator, @sample  string attributes (if known)
+//! ```
+//!
+//! [`write_series_hdf5`] writes a time series as a matrix instead.
+
+use crate::spectre::{AxisType, ConfigValue, SpcFile, TimeSeries};
+use ::hdf5::types::VarLenUnicode;
+use ::hdf5::{File, Group, Location};
+use std::path::Path;
+
+/// Write SpcFile as an HDF5 file.
+pub fn write_hdf5<P: AsRef<Path>>(spc: &SpcFile, path: P) -> ::hdf5::Result<()> {
+    let file = File::create(path)?;
+    write_hdf5_group(spc, &file)
+}
+
+/// Write many spectra into one HDF5 file, one group per `(name, spectrum)`.
+pub fn write_hdf5_batch<P: AsRef<Path>>(spectra: &[(&str, &SpcFile)], path: P) -> ::hdf5::Result<()> {
+    let file = File::create(path)?;
+    for (name, spc) in spectra {
+        let group = file.create_group(name)?;
+        write_hdf5_group(spc, &group)?;
+    }
+    Ok(())
+}
+
+/// Write SpcFile datasets and attributes into an existing group.
+pub fn write_hdf5_group(spc: &SpcFile, group: &Group) -> ::hdf5::Result<()> {
+    write_string_attr(group, "uid", &spc.uid)?;
+    for (name, label) in [("acquired", &spc.acquired), ("operator", &spc.operator), ("sample", &spc.sample)] {
+        if let Some(label) = label {
+            write_string_attr(group, name, label)?;
+        }
+    }
+    if let Some(ref instrument) = spc.instrument {
+        let location = group.create_group("instrument")?;
+        for (name, value) in [
+            ("model", &instrument.model),
+            ("serial", &instrument.serial),
+            ("firmware", &instrument.firmware),
+        ] {
+            if let Some(value) = value {
+                write_string_attr(&location, name, value)?;
+            }
+        }
+        if let Some(temperature) = instrument.detector_temperature {
+            location.new_attr::<f64>().create("detector_temperature")?.write_scalar(&temperature)?;
+        }
+    }
+    if let Some(ref provenance) = spc.provenance {
+        let location = group.create_group("provenance")?;
+        write_string_attr(&location, "converter_version", &provenance.converter_version)?;
+        write_string_attr(&location, "source", &provenance.source)?;
+        write_string_attr(&location, "source_sha256", &provenance.source_sha256)?;
+        write_string_attr(&location, "converted", &provenance.converted)?;
+        write_string_attr(&location, "options", &provenance.options.join(" "))?;
+    }
+
+    write_dataset(group, "data", &spc.data)?;
+    if !spc.blank.is_empty() {
+        write_dataset(group, "blank", &spc.blank)?;
+    }
+    if let Some(axis) = spc.wavelengths() {
+        write_dataset(group, "wavelength_axis", &axis)?;
+    }
+    if let Some(axis) = spc.raman_shifts() {
+        write_dataset(group, "raman_shift_axis", &axis)?;
+    }
+    if let Some(ref cal) = spc.calibration {
+        write_dataset(group, "calibration_coefficients", &cal.coefficients)?;
+    }
+
+    if let Some(ref cfg) = spc.config {
+        let config = group.create_group("config")?;
+        let doubles = [
+            ("raman_wavelength", cfg.raman_wavelength),
+            ("exposure", cfg.exposure),
+            ("gain", cfg.gain),
+        ];
+        for (name, value) in doubles {
+            if let Some(v) = value {
+                config.new_attr::<f64>().create(name)?.write_scalar(&v)?;
+            }
+        }
+        let ints = [
+            ("smoothing", cfg.smoothing),
+            ("average", cfg.average),
+            ("sgolay_window", cfg.sgolay_window),
+            ("sgolay_order", cfg.sgolay_order),
+            ("sgolay_deriv", cfg.sgolay_deriv),
+            ("axis", cfg.axis.map(|a| a as i32)),
+        ];
+        for (name, value) in ints {
+            if let Some(v) = value {
+                config.new_attr::<i32>().create(name)?.write_scalar(&v)?;
+            }
+        }
+        let bools = [
+            ("medfilt", cfg.medfilt),
+            ("baseline", cfg.baseline),
+            ("sgolay", cfg.sgolay),
+        ];
+        for (name, value) in bools {
+            if let Some(v) = value {
+                config.new_attr::<bool>().create(name)?.write_scalar(&v)?;
+            }
+        }
+        for (name, value) in &cfg.other {
+            match value {
+                ConfigValue::Bool(v) => config.new_attr::<bool>().create(name.as_str())?.write_scalar(v)?,
+                ConfigValue::Int(v) => config.new_attr::<i64>().create(name.as_str())?.write_scalar(v)?,
+                ConfigValue::Double(v) => config.new_attr::<f64>().create(name.as_str())?.write_scalar(v)?,
+                _ => write_string_attr(&config, name, &value.to_string())?,
+            }
+        }
+    }
+
+    if !spc.metadata.is_empty() {
+        let metadata = group.create_group("metadata")?;
+        for (key, value) in &spc.metadata {
+            write_string_attr(&metadata, key, value)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Write a time series as an HDF5 file: `time` (f64[t]), `x` (f64[n]) and
+/// the `intensity` matrix (f64[t, n]), with `time_base` and `axis` string
+/// attributes.
+pub fn write_series_hdf5<P: AsRef<Path>>(series: &TimeSeries, path: P) -> ::hdf5::Result<()> {
+    let file = File::create(path)?;
+    write_string_attr(&file, "time_base", series.time_base.name())?;
+    let axis = match series.axis {
+        AxisType::RamanShifts => "raman_shift",
+        AxisType::Wavelengths => "wavelength",
+        AxisType::Pixels => "pixel",
+    };
+    write_string_attr(&file, "axis", axis)?;
+    write_dataset(&file, "time", &series.times)?;
+    write_dataset(&file, "x", &series.x)?;
+    let flat: Vec<f64> = series.intensities.concat();
+    file.new_dataset::<f64>()
+        .shape([series.times.len(), series.x.len()])
+        .create("intensity")?
+        .write_raw(&flat)
+}
+
+fn write_dataset(group: &Group, name: &str, values: &[f64]) -> ::hdf5::Result<()> {
+    group.new_dataset_builder().with_data(values).create(name)?;
+    Ok(())
+}
+
+fn write_string_attr(location: &Location, name: &str, value: &str) -> ::hdf5::Result<()> {
+    let value: VarLenUnicode = value
+        .parse()
+        .map_err(|e| ::hdf5::Error::from(format!("invalid string attribute {}: {}", name, e)))?;
+    location
+        .new_attr::<VarLenUnicode>()
+        .create(name)?
+        .write_scalar(&value)
+}
@@ -64,25 +64,207 @@ pub fn to_json_string(spectre: &SpectreFile, pretty: bool) -> Result<String, ser
     }
 }
 
-/// Write SpcFile (with calibration) as JSON to a writer.
+/// Version of the SpcFile JSON layout, written as `schema_version`. Files
+/// without it are version 1. Bumped on breaking changes: a removed or
+/// renamed field, or a changed type.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// JSON Schema (draft 2020-12) of the SpcFile JSON output.
+pub const JSON_SCHEMA: &str = include_str!("spc_file.schema.json");
+
+/// SpcFile JSON: the schema version, then the SpcFile fields.
+#[derive(Serialize)]
+pub(super) struct VersionedSpcFile<'a> {
+    schema_version: u32,
+    #[serde(flatten)]
+    spc: &'a SpcFile,
+}
+
+impl<'a> VersionedSpcFile<'a> {
+    pub(super) fn new(spc: &'a SpcFile) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            spc,
+        }
+    }
+}
+
+/// Write SpcFile (with calibration) as JSON to a writer, following
+/// [`JSON_SCHEMA`].
 pub fn write_json_spc<W: Write>(
     spc: &SpcFile,
     writer: W,
     pretty: bool,
 ) -> Result<(), serde_json::Error> {
-    // SpcFile is already Serialize, so we output it directly
     if pretty {
-        serde_json::to_writer_pretty(writer, spc)
+        serde_json::to_writer_pretty(writer, &VersionedSpcFile::new(spc))
     } else {
-        serde_json::to_writer(writer, spc)
+        serde_json::to_writer(writer, &VersionedSpcFile::new(spc))
     }
 }
 
 /// Write SpcFile as JSON string.
 pub fn to_json_string_spc(spc: &SpcFile, pretty: bool) -> Result<String, serde_json::Error> {
     if pretty {
-        serde_json::to_string_pretty(spc)
+        serde_json::to_string_pretty(&VersionedSpcFile::new(spc))
     } else {
-        serde_json::to_string(spc)
+        serde_json::to_string(&VersionedSpcFile::new(spc))
+    }
+}
+
+/// One NDJSON line or array element: the input file id, then the SpcFile
+/// JSON.
+#[derive(Serialize)]
+struct NdjsonLine<'a> {
+    file_id: &'a str,
+    #[serde(flatten)]
+    spc: VersionedSpcFile<'a>,
+}
+
+/// Write `(file_id, spectrum)` pairs as newline-delimited JSON: one
+/// compact [`JSON_SCHEMA`] document per line, with a leading `file_id`.
+pub fn write_ndjson<W: Write>(spectra: &[(&str, &SpcFile)], mut writer: W) -> std::io::Result<()> {
+    for (file_id, spc) in spectra {
+        let line = NdjsonLine {
+            file_id,
+            spc: VersionedSpcFile::new(spc),
+        };
+        serde_json::to_writer(&mut writer, &line)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()
+}
+
+/// Write `(file_id, spectrum)` pairs as one JSON array of [`JSON_SCHEMA`]
+/// documents, each with a leading `file_id` as in [`write_ndjson`].
+pub fn write_json_array<W: Write>(spectra: &[(&str, &SpcFile)], writer: W, pretty: bool) -> std::io::Result<()> {
+    let lines: Vec<NdjsonLine> = spectra
+        .iter()
+        .map(|(file_id, spc)| NdjsonLine {
+            file_id,
+            spc: VersionedSpcFile::new(spc),
+        })
+        .collect();
+    write_document(&lines, writer, pretty)
+}
+
+/// Write `(file_id, spectrum)` pairs as one JSON object mapping each file
+/// id to its [`JSON_SCHEMA`] document. File ids must be unique.
+pub fn write_json_object<W: Write>(spectra: &[(&str, &SpcFile)], writer: W, pretty: bool) -> std::io::Result<()> {
+    let mut object = serde_json::Map::with_capacity(spectra.len());
+    for (file_id, spc) in spectra {
+        let document = serde_json::to_value(VersionedSpcFile::new(spc))?;
+        if object.insert(file_id.to_string(), document).is_some() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("file id {} occurs more than once", file_id),
+            ));
+        }
+    }
+    write_document(&object, writer, pretty)
+}
+
+fn write_document<W: Write, T: Serialize>(document: &T, mut writer: W, pretty: bool) -> std::io::Result<()> {
+    if pretty {
+        serde_json::to_writer_pretty(&mut writer, document)?;
+    } else {
+        serde_json::to_writer(&mut writer, document)?;
+    }
+    writer.write_all(b"\n")?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spectre::{Calibration, Config, Instrument};
+
+    #[test]
+    fn test_schema_covers_output() {
+        let schema: serde_json::Value = serde_json::from_str(JSON_SCHEMA).unwrap();
+        assert_eq!(schema["properties"]["schema_version"]["const"], SCHEMA_VERSION);
+
+        let mut spc = SpcFile {
+            uid: "CAM-1".to_string(),
+            data: vec![1.0, 2.0],
+            blank: vec![0.0; 2],
+            calibration: Some(Calibration::legendre(vec![800.0, 50.0])),
+            config: Some(Config {
+                raman_wavelength: Some(785.0),
+                ..Default::default()
+            }),
+            wavelength_axis: None,
+            raman_shift_axis: None,
+            acquired: Some("2024-03-01T12:30:05Z".to_string()),
+            operator: Some("J. Doe".to_string()),
+            sample: Some("A1".to_string()),
+            instrument: Some(Instrument {
+                model: Some("Pulsar".to_string()),
+                ..Default::default()
+            }),
+            provenance: Some(crate::spectre::Provenance::new(std::path::Path::new("a.spc"), b"")),
+            stats: None,
+            metadata: [("room".to_string(), "B12".to_string())].into(),
+            history: vec![crate::processing::ProcessingStep::new("normalize")],
+            extras: Vec::new(),
+        };
+        spc.set_laser_wavelength(785.0);
+        spc.stats = Some(crate::processing::summarize(&spc, 65535.0));
+
+        // Every field written is described, and the output reads back
+        let json = to_json_string_spc(&spc, false).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["schema_version"], SCHEMA_VERSION);
+        for key in value.as_object().unwrap().keys() {
+            assert!(schema["properties"].get(key).is_some(), "{} is not in the schema", key);
+        }
+        let read: SpcFile = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.sample, spc.sample);
+    }
+
+    #[test]
+    fn test_ndjson() {
+        let spc = |uid: &str| SpcFile {
+            uid: uid.to_string(),
+            data: vec![1.0, 2.0],
+            blank: Vec::new(),
+            calibration: None,
+            config: None,
+            wavelength_axis: None,
+            raman_shift_axis: None,
+            acquired: None,
+            operator: None,
+            sample: None,
+            instrument: None,
+            provenance: None,
+            stats: None,
+            metadata: Default::default(),
+            history: Vec::new(),
+            extras: Vec::new(),
+        };
+        let (a, b) = (spc("CAM-1"), spc("CAM-2"));
+        let mut out = Vec::new();
+        write_ndjson(&[("a.spc", &a), ("b.spc#1", &b)], &mut out).unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<serde_json::Value> = text.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert!(text.ends_with('\n'));
+        assert_eq!(lines[1]["file_id"], "b.spc#1");
+        assert_eq!(lines[1]["uid"], "CAM-2");
+        assert_eq!(lines[0]["schema_version"], SCHEMA_VERSION);
+
+        let mut out = Vec::new();
+        write_json_array(&[("a", &a), ("b", &b)], &mut out, false).unwrap();
+        let array: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(array[1]["file_id"], "b");
+        assert_eq!(array[1]["uid"], "CAM-2");
+
+        let mut out = Vec::new();
+        write_json_object(&[("a", &a), ("b", &b)], &mut out, true).unwrap();
+        let object: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(object["a"]["uid"], "CAM-1");
+        assert_eq!(object["b"]["schema_version"], SCHEMA_VERSION);
+        assert!(write_json_object(&[("a", &a), ("a", &b)], Vec::new(), false).is_err());
     }
 }
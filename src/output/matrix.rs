@@ -0,0 +1,135 @@
+//! Sample × point matrix output: CSV, NumPy `.npy` and `.npz`.
+
+use super::csv::{check_separators, quote};
+use super::{CsvOptions, ZipWriter};
+use crate::processing::SpectraMatrix;
+use crate::spectre::AxisType;
+use std::io::{self, Write};
+
+/// Write a matrix as CSV, one row per sample: a heading row with `sample`
+/// and the x values, then each sample's name and intensities.
+///
+/// Only the delimiter, decimal separator and precision of `options` apply.
+pub fn write_matrix_csv<W: Write>(matrix: &SpectraMatrix, options: &CsvOptions, mut writer: W) -> io::Result<()> {
+    check_separators(options)?;
+    let delimiter = options.delimiter.to_string();
+    let mut header = Vec::with_capacity(matrix.x.len() + 1);
+    header.push("sample".to_string());
+    header.extend(matrix.x.iter().map(|&x| match matrix.axis {
+        AxisType::Pixels => x.to_string(),
+        _ => options.format(x),
+    }));
+    writeln!(writer, "{}", header.join(&delimiter))?;
+
+    for (name, row) in matrix.samples.iter().zip(&matrix.intensities) {
+        let mut fields = Vec::with_capacity(row.len() + 1);
+        fields.push(quote(name, options.delimiter));
+        fields.extend(row.iter().map(|&value| options.format(value)));
+        writeln!(writer, "{}", fields.join(&delimiter))?;
+    }
+    Ok(())
+}
+
+/// Write the intensities as a NumPy `.npy` array of little-endian `float64`,
+/// shaped `(samples, points)`; `numpy.load` reads it.
+pub fn write_matrix_npy<W: Write>(matrix: &SpectraMatrix, mut writer: W) -> io::Result<()> {
+    let (rows, columns) = matrix.shape();
+    writer.write_all(&npy_f64(&[rows, columns], matrix.intensities.iter().flatten()))
+}
+
+/// Write a matrix as a NumPy `.npz` archive of the arrays `X` (the
+/// intensities, `(samples, points)`), `x` (the axis), `samples` (the names)
+/// and, when mean centered, `mean`.
+pub fn write_matrix_npz<W: Write>(matrix: &SpectraMatrix, writer: W) -> io::Result<W> {
+    let (rows, columns) = matrix.shape();
+    let mut zip = ZipWriter::new(writer);
+    zip.add("X.npy", &npy_f64(&[rows, columns], matrix.intensities.iter().flatten()))?;
+    zip.add("x.npy", &npy_f64(&[columns], &matrix.x))?;
+    zip.add("samples.npy", &npy_strings(&matrix.samples))?;
+    if let Some(ref mean) = matrix.mean {
+        zip.add("mean.npy", &npy_f64(&[columns], mean))?;
+    }
+    zip.finish()
+}
+
+/// A version 1.0 `.npy` header, padded so the data starts at a multiple of
+/// 64 bytes.
+fn npy_header(descr: &str, shape: &[usize]) -> Vec<u8> {
+    let shape = match shape {
+        [length] => format!("({},)", length),
+        _ => format!("({})", shape.iter().map(usize::to_string).collect::<Vec<_>>().join(", ")),
+    };
+    let mut dict = format!("{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}", descr, shape);
+    // Magic, version and header length take 10 bytes; the dict ends in a newline
+    dict.push_str(&" ".repeat(63 - (10 + dict.len()) % 64));
+    dict.push('\n');
+
+    let mut header = b"\x93NUMPY\x01\x00".to_vec();
+    header.extend_from_slice(&(dict.len() as u16).to_le_bytes());
+    header.extend_from_slice(dict.as_bytes());
+    header
+}
+
+fn npy_f64<'a>(shape: &[usize], values: impl IntoIterator<Item = &'a f64>) -> Vec<u8> {
+    let mut bytes = npy_header("<f8", shape);
+    for value in values {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    bytes
+}
+
+/// Strings as fixed-width UTF-32 (`<U<n>`), the width of the longest.
+fn npy_strings(values: &[String]) -> Vec<u8> {
+    let width = values.iter().map(|value| value.chars().count()).max().unwrap_or(0).max(1);
+    let mut bytes = npy_header(&format!("<U{}", width), &[values.len()]);
+    for value in values {
+        let chars = value.chars().map(u32::from).chain(std::iter::repeat(0));
+        for c in chars.take(width) {
+            bytes.extend_from_slice(&c.to_le_bytes());
+        }
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_matrix() {
+        let matrix = SpectraMatrix {
+            axis: AxisType::RamanShifts,
+            x: vec![400.5, 401.0],
+            samples: vec!["a".to_string(), "b;c".to_string()],
+            intensities: vec![vec![1.0, 2.0], vec![3.0, f64::NAN]],
+            mean: None,
+        };
+        let options = CsvOptions {
+            delimiter: ';',
+            decimal: ',',
+            ..Default::default()
+        };
+        let mut csv = Vec::new();
+        write_matrix_csv(&matrix, &options, &mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), "sample;400,5;401\na;1;2\n\"b;c\";3;NaN\n");
+
+        let mut npy = Vec::new();
+        write_matrix_npy(&matrix, &mut npy).unwrap();
+        let header_len = u16::from_le_bytes([npy[8], npy[9]]) as usize;
+        let header = std::str::from_utf8(&npy[10..10 + header_len]).unwrap();
+        assert_eq!((10 + header_len) % 64, 0);
+        assert!(header.starts_with("{'descr': '<f8', 'fortran_order': False, 'shape': (2, 2), }"));
+        assert!(header.ends_with(" \n"));
+        assert_eq!(npy.len(), 10 + header_len + 4 * 8);
+        assert_eq!(npy[10 + header_len + 16..10 + header_len + 24], 3.0f64.to_le_bytes());
+
+        let strings = npy_strings(&matrix.samples);
+        assert!(String::from_utf8_lossy(&strings).contains("'descr': '<U3', 'fortran_order': False, 'shape': (2,), }"));
+        assert_eq!(strings.len() % 64, 2 * 3 * 4);
+
+        let npz = write_matrix_npz(&matrix, Vec::new()).unwrap();
+        let names = crate::import::archive::read_archive(&npz, |_| true).unwrap();
+        let names: Vec<&str> = names.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, ["X.npy", "x.npy", "samples.npy"]);
+    }
+}
@@ -2,10 +2,38 @@
 
 mod json;
 mod csv;
+pub mod galactic;
+#[cfg(feature = "hdf5")]
+mod hdf5;
+mod matrix;
 mod pairs;
+#[cfg(feature = "plot")]
+mod pdf;
+#[cfg(feature = "parquet")]
+pub mod parquet;
 mod plot;
+mod series;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+mod xy;
+mod yaml;
+mod zip;
 
 pub use self::json::*;
 pub use self::csv::*;
+pub use self::galactic::*;
+#[cfg(feature = "hdf5")]
+pub use self::hdf5::*;
+pub use self::matrix::*;
 pub use self::pairs::*;
+#[cfg(feature = "plot")]
+pub use self::pdf::*;
+#[cfg(feature = "parquet")]
+pub use self::parquet::*;
 pub use self::plot::*;
+pub use self::series::*;
+#[cfg(feature = "sqlite")]
+pub use self::sqlite::*;
+pub use self::xy::*;
+pub use self::yaml::*;
+pub use self::zip::ZipWriter;
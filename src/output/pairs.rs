@@ -1,8 +1,42 @@
 //! Pairs output format - LLM-friendly x,y pairs with minimal context header.
 
-use crate::spectre::SpcFile;
+use super::csv::fixed;
+use super::select_axis;
+use crate::processing::{downsample, DownsampleMode};
+use crate::spectre::{AxisType, SpcFile};
+use std::borrow::Cow;
 use std::io::{self, Write};
 
+/// Options for [`write_pairs_with`].
+#[derive(Debug, Clone)]
+pub struct PairsOptions {
+    /// X-axis; must be available [default: see [`write_pairs`]]
+    pub axis: Option<AxisType>,
+    /// Separator between the values of a line
+    pub delimiter: String,
+    /// Digits after the decimal point [default: as many as needed]
+    pub precision: Option<usize>,
+    /// Add the blank as a third value, where there is one
+    pub blank: bool,
+    /// Reduce the output to this many points [default: every point]
+    pub points: Option<usize>,
+    /// How runs of points are reduced with `points`
+    pub downsample: DownsampleMode,
+}
+
+impl Default for PairsOptions {
+    fn default() -> Self {
+        Self {
+            axis: None,
+            delimiter: ", ".to_string(),
+            precision: None,
+            blank: false,
+            points: None,
+            downsample: DownsampleMode::Mean,
+        }
+    }
+}
+
 /// Write SpcFile as LLM-friendly pairs format.
 ///
 /// Format:
@@ -15,42 +49,80 @@ use std::io::{self, Write};
 /// 180.2, 1089.7
 /// ...
 /// ```
-pub fn write_pairs<W: Write>(spc: &SpcFile, mut writer: W) -> io::Result<()> {
-    // Determine which x-axis to use (prefer Raman shift, then wavelength, then index)
-    let (x_axis_name, x_axis_unit, x_values): (&str, &str, Vec<f64>) =
-        if let Some(ref raman) = spc.raman_shift_axis {
-            ("Raman Shift", "cm⁻¹", raman.clone())
-        } else if let Some(ref wavelength) = spc.wavelength_axis {
-            ("Wavelength", "nm", wavelength.clone())
-        } else {
-            ("Pixel Index", "", (0..spc.data.len()).map(|i| i as f64).collect())
-        };
+///
+/// The x-axis is the one stored in the file's config if available, otherwise
+/// Raman shift, then wavelength, then pixel index.
+pub fn write_pairs<W: Write>(spc: &SpcFile, writer: W) -> io::Result<()> {
+    write_pairs_with(spc, &PairsOptions::default(), writer)
+}
+
+/// Write SpcFile as pairs format with the given axis and number format.
+/// With `options.blank`, lines are `x, y, blank` and the header says so.
+/// With `options.points`, x, y and blank are downsampled alike (see
+/// [`downsample`]) and the `Points` line gives the original count.
+pub fn write_pairs_with<W: Write>(spc: &SpcFile, options: &PairsOptions, mut writer: W) -> io::Result<()> {
+    let axis = select_axis(spc, options.axis)?;
+    let (x_axis_name, x_axis_unit, x_values) = (axis.name, axis.unit, axis.values);
+    let blank = options.blank && !spc.blank.is_empty();
+    let y_axis = if blank { "Intensity, Blank" } else { "Intensity" };
 
     // Write header comments
     writeln!(writer, "# Raman Spectrum")?;
     
     if x_axis_unit.is_empty() {
-        writeln!(writer, "# X-axis: {}, Y-axis: Intensity", x_axis_name)?;
+        writeln!(writer, "# X-axis: {}, Y-axis: {}", x_axis_name, y_axis)?;
     } else {
-        writeln!(writer, "# X-axis: {} ({}), Y-axis: Intensity", x_axis_name, x_axis_unit)?;
+        writeln!(writer, "# X-axis: {} ({}), Y-axis: {}", x_axis_name, x_axis_unit, y_axis)?;
+    }
+
+    // Downsample x, y and blank alike
+    let n = x_values.len().min(spc.data.len());
+    fn reduce<'a>(values: &'a [f64], options: &PairsOptions) -> Cow<'a, [f64]> {
+        match options.points {
+            Some(points) => Cow::Owned(downsample(values, points, options.downsample)),
+            None => Cow::Borrowed(values),
+        }
     }
+    let x_values = reduce(&x_values[..n], options);
+    let data = reduce(&spc.data[..n], options);
+    let blanks = if blank {
+        reduce(&(0..n).map(|i| spc.blank.get(i).copied().unwrap_or(f64::NAN)).collect::<Vec<_>>(), options).into_owned()
+    } else {
+        Vec::new()
+    };
+    let points = if data.len() < spc.data.len() {
+        let mode = match options.downsample {
+            DownsampleMode::Mean => "mean",
+            DownsampleMode::Decimate => "decimated",
+        };
+        format!("{} ({} of {})", data.len(), mode, spc.data.len())
+    } else {
+        spc.data.len().to_string()
+    };
 
     // Add laser wavelength if available
     if let Some(ref cfg) = spc.config {
         if let Some(laser) = cfg.raman_wavelength {
-            writeln!(writer, "# Laser: {}nm, Points: {}", laser, spc.data.len())?;
+            writeln!(writer, "# Laser: {}nm, Points: {}", laser, points)?;
         } else {
-            writeln!(writer, "# Points: {}", spc.data.len())?;
+            writeln!(writer, "# Points: {}", points)?;
         }
     } else {
-        writeln!(writer, "# Points: {}", spc.data.len())?;
+        writeln!(writer, "# Points: {}", points)?;
     }
 
+    super::csv::write_comments(spc, &mut writer)?;
+
     writeln!(writer)?; // Blank line before data
 
     // Write x,y pairs
-    for (x, y) in x_values.iter().zip(spc.data.iter()) {
-        writeln!(writer, "{}, {}", x, y)?;
+    let format = |value: f64| fixed(value, options.precision);
+    for (i, (x, y)) in x_values.iter().zip(data.iter()).enumerate() {
+        write!(writer, "{}{}{}", format(*x), options.delimiter, format(*y))?;
+        if let Some(value) = blanks.get(i) {
+            write!(writer, "{}{}", options.delimiter, format(*value))?;
+        }
+        writeln!(writer)?;
     }
 
     Ok(())
@@ -62,3 +134,56 @@ pub fn to_pairs_string(spc: &SpcFile) -> io::Result<String> {
     write_pairs(spc, &mut buf)?;
     String::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pairs_options() {
+        let spc = SpcFile {
+            uid: "CAM-1".to_string(),
+            data: vec![10.25, 11.0],
+            blank: vec![1.0, 2.0],
+            calibration: None,
+            config: None,
+            wavelength_axis: Some(vec![800.0, 800.5]),
+            raman_shift_axis: None,
+            acquired: None,
+            operator: None,
+            sample: None,
+            instrument: None,
+            provenance: None,
+            stats: None,
+            metadata: Default::default(),
+            history: Vec::new(),
+            extras: Vec::new(),
+        };
+        let options = PairsOptions {
+            delimiter: "\t".to_string(),
+            precision: Some(1),
+            blank: true,
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        write_pairs_with(&spc, &options, &mut buf).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        assert!(text.contains("# X-axis: Wavelength (nm), Y-axis: Intensity, Blank\n"));
+        assert!(text.ends_with("\n800.0\t10.2\t1.0\n800.5\t11.0\t2.0\n"));
+
+        // The blank column reads back as such
+        let read = crate::import::read_text(&text, None).unwrap();
+        assert_eq!(read.data, vec![10.2, 11.0]);
+        assert_eq!(read.blank, vec![1.0, 2.0]);
+
+        let options = PairsOptions {
+            points: Some(1),
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        write_pairs_with(&spc, &options, &mut buf).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        assert!(text.contains("# Points: 1 (mean of 2)\n"));
+        assert!(text.ends_with("\n800.25, 10.625\n"));
+    }
+}
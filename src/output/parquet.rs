@@ -0,0 +1,151 @@
+//! Parquet output format (requires the `parquet` feature).
+//!
+//! Writes one long-format table for one or many spectra:
+//!
+//! | column      | type             | notes                                  |
+//! |-------------|------------------|----------------------------------------|
+//! | `file_id`   | string           | caller-supplied identifier             |
+//! | `index`     | int64            | pixel index                            |
+//! | `x`         | double, nullable | best available axis value              |
+//! | `intensity` | double           |                                        |
+//! | `blank`     | double, nullable | null where the blank is shorter/absent |
+//!
+//! With single precision ([`write_parquet_with`]), `intensity` and `blank`
+//! are floats, halving their size.
+//!
+//! Each spectrum is one row group. The `x_axis` key-value metadata entry maps
+//! each `file_id` to the axis used for `x` (as a JSON object), and `labels`
+//! maps those with a known acquisition time, operator, sample or instrument
+//! to them.
+
+use super::select_best_axis;
+use crate::spectre::SpcFile;
+use parquet::basic::Compression;
+use parquet::data_type::{ByteArray, ByteArrayType, DoubleType, FloatType, Int64Type};
+use parquet::errors::Result;
+use parquet::file::metadata::KeyValue;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use std::io::Write;
+use std::sync::Arc;
+
+const SCHEMA: &str = "
+message spectrum {
+    REQUIRED BYTE_ARRAY file_id (UTF8);
+    REQUIRED INT64 index;
+    OPTIONAL DOUBLE x;
+    REQUIRED DOUBLE intensity;
+    OPTIONAL DOUBLE blank;
+}
+";
+
+/// Write spectra as one Parquet table, one row group per `(file_id, spectrum)`.
+pub fn write_parquet<W: Write + Send>(spectra: &[(&str, &SpcFile)], writer: W) -> Result<()> {
+    write_parquet_with(spectra, writer, false)
+}
+
+/// Like [`write_parquet`]; with `single_precision`, intensities and blank
+/// are stored as 32-bit floats.
+pub fn write_parquet_with<W: Write + Send>(spectra: &[(&str, &SpcFile)], writer: W, single_precision: bool) -> Result<()> {
+    let schema = if single_precision {
+        SCHEMA.replace("DOUBLE intensity", "FLOAT intensity").replace("DOUBLE blank", "FLOAT blank")
+    } else {
+        SCHEMA.to_string()
+    };
+    let schema = Arc::new(parse_message_type(&schema)?);
+
+    let axes: Vec<_> = spectra.iter().map(|(_, spc)| select_best_axis(spc)).collect();
+    let axis_names: serde_json::Map<String, serde_json::Value> = spectra
+        .iter()
+        .zip(&axes)
+        .map(|((id, _), axis)| {
+            let name = if axis.unit.is_empty() {
+                axis.name.to_string()
+            } else {
+                format!("{} ({})", axis.name, axis.unit)
+            };
+            (id.to_string(), serde_json::Value::String(name))
+        })
+        .collect();
+    let labels: serde_json::Map<String, serde_json::Value> = spectra
+        .iter()
+        .filter(|(_, spc)| {
+            spc.acquired.is_some() || spc.operator.is_some() || spc.sample.is_some() || spc.instrument.is_some()
+        })
+        .map(|(id, spc)| {
+            let labels = serde_json::json!({
+                "acquired": spc.acquired,
+                "operator": spc.operator,
+                "sample": spc.sample,
+                "instrument": spc.instrument,
+            });
+            (id.to_string(), labels)
+        })
+        .collect();
+
+    let mut key_value = vec![KeyValue::new("x_axis".to_string(), serde_json::Value::Object(axis_names).to_string())];
+    if !labels.is_empty() {
+        key_value.push(KeyValue::new("labels".to_string(), serde_json::Value::Object(labels).to_string()));
+    }
+    let props = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .set_key_value_metadata(Some(key_value))
+        .build();
+    let mut file_writer = SerializedFileWriter::new(writer, schema, Arc::new(props))?;
+
+    for ((file_id, spc), axis) in spectra.iter().zip(&axes) {
+        let n = spc.data.len();
+
+        let ids = vec![ByteArray::from(*file_id); n];
+        let indices: Vec<i64> = (0..n as i64).collect();
+        let (x, x_levels) = optional_column(&axis.values, n);
+        let (blank, blank_levels) = optional_column(&spc.blank, n);
+
+        let mut row_group = file_writer.next_row_group()?;
+        let mut column_index = 0;
+        while let Some(mut column) = row_group.next_column()? {
+            match column_index {
+                0 => {
+                    column.typed::<ByteArrayType>().write_batch(&ids, None, None)?;
+                }
+                1 => {
+                    column.typed::<Int64Type>().write_batch(&indices, None, None)?;
+                }
+                2 => {
+                    column.typed::<DoubleType>().write_batch(&x, Some(&x_levels), None)?;
+                }
+                3 if single_precision => {
+                    column.typed::<FloatType>().write_batch(&singles(&spc.data), None, None)?;
+                }
+                3 => {
+                    column.typed::<DoubleType>().write_batch(&spc.data, None, None)?;
+                }
+                _ if single_precision => {
+                    column.typed::<FloatType>().write_batch(&singles(&blank), Some(&blank_levels), None)?;
+                }
+                _ => {
+                    column.typed::<DoubleType>().write_batch(&blank, Some(&blank_levels), None)?;
+                }
+            }
+            column.close()?;
+            column_index += 1;
+        }
+        row_group.close()?;
+    }
+
+    file_writer.close()?;
+    Ok(())
+}
+
+fn singles(values: &[f64]) -> Vec<f32> {
+    values.iter().map(|&value| value as f32).collect()
+}
+
+/// Values and definition levels for a nullable column of `n` rows.
+fn optional_column(values: &[f64], n: usize) -> (Vec<f64>, Vec<i16>) {
+    let present = values.len().min(n);
+    let mut levels = vec![1i16; present];
+    levels.resize(n, 0);
+    (values[..present].to_vec(), levels)
+}
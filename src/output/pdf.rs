@@ -0,0 +1,362 @@
+//! PDF report output: one measurement as a fixed document for archiving
+//! and QA sign-off.
+//!
+//! The report holds the plot (as rendered for PNG output), the acquisition
+//! details, provenance, statistics and processing history, and a sign-off
+//! block. It is written directly as PDF 1.4 with the standard Helvetica
+//! fonts, so no font is embedded and no layout library is needed.
+
+use super::plot::plot_pixels;
+use super::PlotOptions;
+use crate::processing::{summarize, DEFAULT_SATURATION_LEVEL};
+use crate::spectre::{Instrument, SpcFile};
+use std::io::{self, Write};
+
+/// A4 page size in points.
+const PAGE_WIDTH: f64 = 595.0;
+const PAGE_HEIGHT: f64 = 842.0;
+const MARGIN: f64 = 40.0;
+const LINE_HEIGHT: f64 = 13.0;
+/// Characters per detail line before it wraps.
+const LINE_CHARS: usize = 100;
+/// Offset of the value column; keys wrap to fit in front of it.
+const KEY_COLUMN: f64 = 130.0;
+const KEY_SIZE: f64 = 9.0;
+/// Helvetica-Bold advance widths of ' '..='~', in 1/1000 em.
+const BOLD_WIDTHS: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556, 556, 556, 556, 556,
+    556, 556, 556, 333, 333, 584, 584, 584, 611, 975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833,
+    722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556, 333, 556, 611, 556, 611,
+    556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611, 611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389,
+    280, 389, 584,
+];
+
+/// Write a one-spectrum PDF report. The plot is drawn with `options` and
+/// scaled to the page width.
+pub fn write_pdf<W: Write>(spc: &SpcFile, options: &PlotOptions, mut writer: W) -> io::Result<()> {
+    let pixels = plot_pixels(spc, options)?;
+    let mut zlib = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    zlib.write_all(&pixels)?;
+    let image = zlib.finish()?;
+
+    // Title and plot on the first page, then the details, flowing onto
+    // further pages as needed
+    let plot_width = PAGE_WIDTH - 2.0 * MARGIN;
+    let plot_height = plot_width * options.height as f64 / options.width as f64;
+    let mut pages = vec![Page::default()];
+    let mut y = PAGE_HEIGHT - MARGIN - 18.0;
+    pages[0].text(MARGIN, y, "F2", 18.0, "Spectrum Report");
+    y -= 22.0;
+    let source = spc.provenance.as_ref().map_or(spc.uid.as_str(), |provenance| provenance.source.as_str());
+    pages[0].text(MARGIN, y, "F1", 11.0, source);
+    y -= 10.0 + plot_height;
+    pages[0].image(MARGIN, y, plot_width, plot_height);
+    y -= 24.0;
+
+    let sign_off = ["Reviewed by: ________________________", "Date: ______________", "Signature: ________________________"];
+    let lines = report_lines(spc);
+    let next_line = |pages: &mut Vec<Page>, y: &mut f64, height: f64| {
+        if *y - height < MARGIN + LINE_HEIGHT {
+            pages.push(Page::default());
+            *y = PAGE_HEIGHT - MARGIN - LINE_HEIGHT;
+        }
+        *y -= height;
+    };
+    for line in &lines {
+        match line {
+            Line::Heading(text) => {
+                next_line(&mut pages, &mut y, LINE_HEIGHT * 1.5);
+                pages.last_mut().unwrap().text(MARGIN, y, "F2", 11.0, text);
+            }
+            Line::Entry(key, value) => {
+                let keys = wrap(key, |text| bold_width(text, KEY_SIZE) <= KEY_COLUMN - 6.0);
+                let chunks = wrap(value, |text| text.chars().count() <= LINE_CHARS - 24);
+                for i in 0..keys.len().max(chunks.len()) {
+                    next_line(&mut pages, &mut y, LINE_HEIGHT);
+                    let page = pages.last_mut().unwrap();
+                    if let Some(key) = keys.get(i) {
+                        page.text(MARGIN, y, "F2", KEY_SIZE, key);
+                    }
+                    if let Some(chunk) = chunks.get(i) {
+                        page.text(MARGIN + KEY_COLUMN, y, "F1", 9.0, chunk);
+                    }
+                }
+            }
+        }
+    }
+    next_line(&mut pages, &mut y, LINE_HEIGHT * 3.0);
+    for (i, field) in sign_off.iter().enumerate() {
+        pages.last_mut().unwrap().text(MARGIN + [0.0, 215.0, 330.0][i], y, "F1", 9.0, field);
+    }
+
+    let page_count = pages.len();
+    for (i, page) in pages.iter_mut().enumerate() {
+        let footer = format!("spc-converter {} - page {} of {}", env!("CARGO_PKG_VERSION"), i + 1, page_count);
+        page.text(MARGIN, MARGIN / 2.0, "F1", 8.0, &footer);
+    }
+
+    writer.write_all(&assemble(&pages, &image, options.width, options.height))
+}
+
+/// A line of the details section.
+enum Line {
+    Heading(String),
+    Entry(String, String),
+}
+
+/// Acquisition details, provenance, statistics, metadata and history.
+fn report_lines(spc: &SpcFile) -> Vec<Line> {
+    let mut lines = vec![Line::Heading("Acquisition".to_string())];
+    let entry = |lines: &mut Vec<Line>, key: &str, value: String| lines.push(Line::Entry(key.to_string(), value));
+
+    entry(&mut lines, "UID", spc.uid.clone());
+    for (key, value) in [("Acquired", &spc.acquired), ("Operator", &spc.operator), ("Sample", &spc.sample)] {
+        if let Some(value) = value {
+            entry(&mut lines, key, value.clone());
+        }
+    }
+    for (key, value) in spc.instrument.iter().flat_map(Instrument::entries) {
+        entry(&mut lines, key, value);
+    }
+    if let Some(config) = &spc.config {
+        let settings = [("Laser (nm)", config.raman_wavelength), ("Exposure", config.exposure), ("Gain", config.gain)];
+        for (key, value) in settings {
+            if let Some(value) = value {
+                entry(&mut lines, key, value.to_string());
+            }
+        }
+    }
+    if let Some(calibration) = &spc.calibration {
+        let coefficients: Vec<String> = calibration.coefficients.iter().map(f64::to_string).collect();
+        let model = format!("{:?}", calibration.model).to_lowercase();
+        entry(&mut lines, "Calibration", format!("{} {}", model, coefficients.join(", ")).trim_end().to_string());
+    }
+
+    let stats = spc.stats.clone().unwrap_or_else(|| summarize(spc, DEFAULT_SATURATION_LEVEL));
+    lines.push(Line::Heading("Statistics".to_string()));
+    entry(&mut lines, "Points", stats.points.to_string());
+    entry(&mut lines, "Intensity", format!("min {:.4}, max {:.4}, mean {:.4}, std {:.4}", stats.min, stats.max, stats.mean, stats.std));
+    entry(&mut lines, "Noise / SNR", format!("{:.4} / {:.1}", stats.noise, stats.snr));
+    entry(&mut lines, "Saturated points", stats.saturated.to_string());
+    entry(&mut lines, stats.axis, format!("{:.4} to {:.4}", stats.axis_min, stats.axis_max));
+
+    if let Some(provenance) = &spc.provenance {
+        lines.push(Line::Heading("Provenance".to_string()));
+        entry(&mut lines, "Source", provenance.source.clone());
+        entry(&mut lines, "SHA-256", provenance.source_sha256.clone());
+        entry(&mut lines, "Converted", format!("{} (spc-converter {})", provenance.converted, provenance.converter_version));
+        if !provenance.options.is_empty() {
+            entry(&mut lines, "Options", provenance.options.join(" "));
+        }
+    }
+    if !spc.metadata.is_empty() {
+        lines.push(Line::Heading("Metadata".to_string()));
+        for (key, value) in &spc.metadata {
+            entry(&mut lines, key, value.clone());
+        }
+    }
+    if !spc.history.is_empty() {
+        lines.push(Line::Heading("Processing".to_string()));
+        for (i, step) in spc.history.iter().enumerate() {
+            entry(&mut lines, &format!("{}.", i + 1), step.summary());
+        }
+    }
+    lines
+}
+
+/// Split text into chunks that each `fit`, at spaces where possible. A
+/// chunk holds at least one character even if that does not fit.
+fn wrap(text: &str, fits: impl Fn(&str) -> bool) -> Vec<String> {
+    let mut chunks = vec![String::new()];
+    for word in text.split(' ') {
+        let current = chunks.last_mut().unwrap();
+        if !current.is_empty() && !fits(&format!("{} {}", current, word)) {
+            chunks.push(String::new());
+        }
+        let mut separate = !chunks.last().unwrap().is_empty();
+        for c in word.chars() {
+            let current = chunks.last_mut().unwrap();
+            let mut extended = current.clone();
+            if separate {
+                extended.push(' ');
+            }
+            extended.push(c);
+            if current.is_empty() || fits(&extended) {
+                *current = extended;
+            } else {
+                chunks.push(c.to_string());
+            }
+            separate = false;
+        }
+    }
+    chunks
+}
+
+/// Width of text set in Helvetica-Bold at `size` points. Characters outside
+/// ASCII are counted as a full em.
+fn bold_width(text: &str, size: f64) -> f64 {
+    let units: u32 = text
+        .chars()
+        .map(|c| match c {
+            ' '..='~' => BOLD_WIDTHS[c as usize - 32] as u32,
+            _ => 1000,
+        })
+        .sum();
+    units as f64 * size / 1000.0
+}
+
+/// Content stream of one page.
+#[derive(Default)]
+struct Page {
+    content: String,
+}
+
+impl Page {
+    fn text(&mut self, x: f64, y: f64, font: &str, size: f64, text: &str) {
+        self.content.push_str(&format!("BT /{} {} Tf {:.2} {:.2} Td ({}) Tj ET\n", font, size, x, y, escape(text)));
+    }
+
+    fn image(&mut self, x: f64, y: f64, width: f64, height: f64) {
+        self.content.push_str(&format!("q {:.2} 0 0 {:.2} {:.2} {:.2} cm /Im1 Do Q\n", width, height, x, y));
+    }
+}
+
+/// A PDF literal string body in WinAnsi (Latin-1) encoding. Characters
+/// outside it are approximated or replaced by `?`.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '(' | ')' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            ' '..='~' => escaped.push(c),
+            '⁻' | '−' | '–' => escaped.push('-'),
+            _ if (0xA0..=0xFF).contains(&(c as u32)) => escaped.push_str(&format!("\\{:03o}", c as u32)),
+            _ => escaped.push('?'),
+        }
+    }
+    escaped
+}
+
+/// The PDF file: catalog, page tree, fonts, the plot image, then one page
+/// and content stream per page, with the cross-reference table.
+fn assemble(pages: &[Page], image: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let page_ids: Vec<usize> = (0..pages.len()).map(|i| 6 + 2 * i).collect();
+    let kids: Vec<String> = page_ids.iter().map(|id| format!("{} 0 R", id)).collect();
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), pages.len()).into_bytes(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_vec(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>".to_vec(),
+    ];
+    let mut image_object = format!(
+        "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB /BitsPerComponent 8 \
+         /Filter /FlateDecode /Length {} >>\nstream\n",
+        width,
+        height,
+        image.len()
+    )
+    .into_bytes();
+    image_object.extend_from_slice(image);
+    image_object.extend_from_slice(b"\nendstream");
+    objects.push(image_object);
+    for (page, id) in pages.iter().zip(&page_ids) {
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Contents {} 0 R \
+                 /Resources << /Font << /F1 3 0 R /F2 4 0 R >> /XObject << /Im1 5 0 R >> >> >>",
+                PAGE_WIDTH,
+                PAGE_HEIGHT,
+                id + 1
+            )
+            .into_bytes(),
+        );
+        objects.push(format!("<< /Length {} >>\nstream\n{}endstream", page.content.len(), page.content).into_bytes());
+    }
+
+    let mut pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+        pdf.extend_from_slice(object);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+    let xref = pdf.len();
+    pdf.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref).as_bytes(),
+    );
+    pdf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_pdf() {
+        let spc = SpcFile {
+            uid: "CAM-1".to_string(),
+            data: (0..50).map(|i| (i as f64 / 5.0).sin() * 100.0 + 200.0).collect(),
+            blank: Vec::new(),
+            calibration: None,
+            config: None,
+            wavelength_axis: None,
+            raman_shift_axis: None,
+            acquired: None,
+            operator: Some("J. Doe (QA)".to_string()),
+            sample: None,
+            instrument: None,
+            provenance: None,
+            stats: None,
+            // Enough entries to flow onto a second page
+            metadata: (0..40).map(|i| (format!("key{}", i), "value".to_string())).collect(),
+            history: Vec::new(),
+            extras: Vec::new(),
+        };
+        let options = PlotOptions {
+            width: 300,
+            height: 150,
+            ..Default::default()
+        };
+        let mut pdf = Vec::new();
+        write_pdf(&spc, &options, &mut pdf).unwrap();
+
+        assert!(pdf.starts_with(b"%PDF-1.4\n") && pdf.ends_with(b"%%EOF\n"));
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.contains("/Count 2"));
+        assert!(text.contains("(J. Doe \\(QA\\)) Tj"));
+        // The cross-reference table points at each object
+        let start: usize = text.rsplit("startxref\n").next().unwrap().lines().next().unwrap().parse().unwrap();
+        let xref = std::str::from_utf8(&pdf[start..]).unwrap();
+        assert!(xref.starts_with("xref\n"));
+        let entries: Vec<&str> = xref.lines().skip(3).take_while(|line| line.ends_with(" n ")).collect();
+        for (i, entry) in entries.iter().enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj", i + 1).as_bytes()));
+        }
+        assert_eq!(entries.len(), 9);
+    }
+
+    #[test]
+    fn test_wrap() {
+        let by_chars = |text: &str| wrap(text, |chunk| chunk.chars().count() <= 8);
+        assert_eq!(by_chars("one two three"), ["one two", "three"]);
+        assert_eq!(by_chars("abcdefghijkl x"), ["abcdefgh", "ijkl x"]);
+        assert_eq!(by_chars(""), [""]);
+
+        // Keys wrap to the key column
+        assert_eq!(bold_width("W", 10.0), 9.44);
+        let key = "instrument.detector.temperature_setpoint_celsius";
+        let keys = wrap(key, |text| bold_width(text, KEY_SIZE) <= KEY_COLUMN - 6.0);
+        assert!(keys.len() > 1);
+        assert_eq!(keys.concat(), key);
+        assert!(keys.iter().all(|chunk| bold_width(chunk, KEY_SIZE) <= KEY_COLUMN - 6.0));
+    }
+}
@@ -1,52 +1,118 @@
 //! Plot output format - PNG spectrum visualization.
 
-use crate::spectre::SpcFile;
+use crate::spectre::{AxisType, SpcFile};
+use std::borrow::Cow;
 use std::io;
+#[cfg(feature = "plot")]
 use std::path::Path;
 
-use plotters::prelude::*;
-use plotters::backend::BitMapBackend;
+#[cfg(feature = "plot")]
+use plotters::{
+    backend::BitMapBackend,
+    coord::{ranged1d::ValueFormatter, types::RangedCoordf64, Shift},
+    prelude::*,
+};
 
-/// Axis type selected for plotting, with descriptive information.
+/// Axis type selected for plotting, with descriptive information. Stored
+/// axes are borrowed from the spectrum; others are generated.
 #[derive(Debug, Clone)]
-pub struct PlotAxisInfo {
+pub struct PlotAxisInfo<'a> {
     pub name: &'static str,
     pub unit: &'static str,
-    pub values: Vec<f64>,
+    pub values: Cow<'a, [f64]>,
     /// Whether the x-axis should be reversed (high to low, spectroscopy convention)
     pub reversed: bool,
 }
 
 /// Determines the best axis to use for plotting based on available data.
 /// Priority: Raman Shift > Wavelength > Pixel Index
-pub fn select_best_axis(spc: &SpcFile) -> PlotAxisInfo {
-    if let Some(ref raman) = spc.raman_shift_axis {
-        PlotAxisInfo {
+pub fn select_best_axis(spc: &SpcFile) -> PlotAxisInfo<'_> {
+    axis_info(spc, AxisType::RamanShifts)
+        .or_else(|| axis_info(spc, AxisType::Wavelengths))
+        .unwrap_or_else(|| pixel_axis(spc))
+}
+
+/// The requested axis, if the spectrum has it.
+pub fn axis_info(spc: &SpcFile, axis: AxisType) -> Option<PlotAxisInfo<'_>> {
+    match axis {
+        AxisType::RamanShifts => spc.raman_shifts().map(|raman| PlotAxisInfo {
             name: "Raman Shift",
             unit: "cm⁻¹",
-            values: raman.clone(),
+            values: raman,
             reversed: true, // Spectroscopy convention: high to low
-        }
-    } else if let Some(ref wavelength) = spc.wavelength_axis {
-        PlotAxisInfo {
+        }),
+        AxisType::Wavelengths => spc.wavelengths().map(|wavelength| PlotAxisInfo {
             name: "Wavelength",
             unit: "nm",
-            values: wavelength.clone(),
-            reversed: false,
-        }
-    } else {
-        PlotAxisInfo {
-            name: "Pixel Index",
-            unit: "",
-            values: (0..spc.data.len()).map(|i| i as f64).collect(),
+            values: wavelength,
             reversed: false,
+        }),
+        AxisType::Pixels => Some(pixel_axis(spc)),
+    }
+}
+
+fn pixel_axis(spc: &SpcFile) -> PlotAxisInfo<'_> {
+    PlotAxisInfo {
+        name: "Pixel Index",
+        unit: "",
+        values: Cow::Owned((0..spc.data.len()).map(|i| i as f64).collect()),
+        reversed: false,
+    }
+}
+
+/// Selects the x-axis for output.
+///
+/// A `requested` axis must be available. Otherwise the axis stored in the
+/// file's config is used if available, falling back to [`select_best_axis`].
+pub fn select_axis(spc: &SpcFile, requested: Option<AxisType>) -> io::Result<PlotAxisInfo<'_>> {
+    if let Some(axis) = requested {
+        return axis_info(spc, axis).ok_or_else(|| {
+            let message = match axis {
+                AxisType::RamanShifts => "no Raman shift axis (needs a calibration and laser wavelength)",
+                _ => "no wavelength axis (needs a calibration)",
+            };
+            io::Error::new(io::ErrorKind::InvalidInput, message)
+        });
+    }
+    Ok(spc
+        .config
+        .as_ref()
+        .and_then(|cfg| cfg.axis)
+        .and_then(|axis| axis_info(spc, axis))
+        .unwrap_or_else(|| select_best_axis(spc)))
+}
+
+/// Options for [`write_plot_with`].
+#[derive(Debug, Clone)]
+pub struct PlotOptions {
+    /// Image width in pixels
+    pub width: u32,
+    /// Image height in pixels
+    pub height: u32,
+    /// Use a logarithmic intensity axis (non-positive values are clipped)
+    pub log_y: bool,
+    /// Only plot points with x in this range (in axis units, either order)
+    pub x_range: Option<(f64, f64)>,
+    /// X-axis to plot [default: the file's stored preference, see [`select_axis`]]
+    pub axis: Option<AxisType>,
+}
+
+impl Default for PlotOptions {
+    fn default() -> Self {
+        Self {
+            width: 1200,
+            height: 600,
+            log_y: false,
+            x_range: None,
+            axis: None,
         }
     }
 }
 
 /// Generate a PNG plot of the spectrum.
 ///
-/// The plot will intelligently select the best available x-axis:
+/// The plot uses the x-axis stored in the file's config if available, and
+/// otherwise selects the best available one:
 /// - Raman shift (cm⁻¹) if laser wavelength and calibration are present
 /// - Wavelength (nm) if only calibration is present
 /// - Pixel index as fallback
@@ -56,25 +122,71 @@ pub fn select_best_axis(spc: &SpcFile) -> PlotAxisInfo {
 /// * `output_path` - Output path for the PNG file
 /// * `width` - Image width in pixels (default: 1200)
 /// * `height` - Image height in pixels (default: 600)
+#[cfg(feature = "plot")]
 pub fn write_plot<P: AsRef<Path>>(
     spc: &SpcFile,
     output_path: P,
     width: u32,
     height: u32,
 ) -> io::Result<()> {
-    let axis = select_best_axis(spc);
-    
-    // Calculate data ranges with padding
-    let x_min = axis.values.iter().cloned().fold(f64::INFINITY, f64::min);
-    let x_max = axis.values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
-    let y_min = spc.data.iter().cloned().fold(f64::INFINITY, f64::min);
-    let y_max = spc.data.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
-    
-    // Add padding to y-axis
-    let y_padding = (y_max - y_min) * 0.05;
-    let y_min = y_min - y_padding;
-    let y_max = y_max + y_padding;
-    
+    let options = PlotOptions {
+        width,
+        height,
+        ..Default::default()
+    };
+    write_plot_with(spc, output_path, &options)
+}
+
+/// Generate a PNG plot of the spectrum with a log intensity axis and/or a
+/// restricted x-range.
+#[cfg(feature = "plot")]
+pub fn write_plot_with<P: AsRef<Path>>(
+    spc: &SpcFile,
+    output_path: P,
+    options: &PlotOptions,
+) -> io::Result<()> {
+    let root = BitMapBackend::new(output_path.as_ref(), (options.width, options.height)).into_drawing_area();
+    plot_on(spc, root, options)
+}
+
+/// Render the plot of [`write_plot_with`] into RGB pixels, row by row.
+#[cfg(feature = "plot")]
+pub(crate) fn plot_pixels(spc: &SpcFile, options: &PlotOptions) -> io::Result<Vec<u8>> {
+    let mut pixels = vec![0u8; options.width as usize * options.height as usize * 3];
+    let root = BitMapBackend::with_buffer(&mut pixels, (options.width, options.height)).into_drawing_area();
+    plot_on(spc, root, options)?;
+    Ok(pixels)
+}
+
+#[cfg(feature = "plot")]
+fn plot_on<DB: DrawingBackend>(spc: &SpcFile, root: DrawingArea<DB, Shift>, options: &PlotOptions) -> io::Result<()> {
+    let axis = select_axis(spc, options.axis)?;
+
+    // Points inside the requested x-range
+    let mut data_points: Vec<(f64, f64)> = axis.values
+        .iter()
+        .zip(spc.data.iter())
+        .map(|(&x, &y)| (x, y))
+        .collect();
+    if let Some((a, b)) = options.x_range {
+        let (lo, hi) = if a <= b { (a, b) } else { (b, a) };
+        data_points.retain(|&(x, _)| x >= lo && x <= hi);
+    }
+    if data_points.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "no points in the plot range"));
+    }
+
+    // Calculate data ranges
+    let (x_min, x_max) = match options.x_range {
+        Some((a, b)) => (a.min(b), a.max(b)),
+        None => (
+            data_points.iter().map(|p| p.0).fold(f64::INFINITY, f64::min),
+            data_points.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max),
+        ),
+    };
+    let y_min = data_points.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+    let y_max = data_points.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+
     // Build axis label
     let x_label = if axis.unit.is_empty() {
         axis.name.to_string()
@@ -94,11 +206,8 @@ pub fn write_plot<P: AsRef<Path>>(
     };
     
     // Create the chart
-    let root = BitMapBackend::new(output_path.as_ref(), (width, height))
-        .into_drawing_area();
-    
     root.fill(&WHITE)
-        .map_err(|e: DrawingAreaErrorKind<_>| io::Error::new(io::ErrorKind::Other, format!("{:?}", e)))?;
+        .map_err(|e: DrawingAreaErrorKind<_>| io::Error::other(format!("{:?}", e)))?;
     
     // Build x-axis range (reversed for Raman shift - spectroscopy convention)
     let (x_start, x_end) = if axis.reversed {
@@ -107,42 +216,78 @@ pub fn write_plot<P: AsRef<Path>>(
         (x_min, x_max)  // Normal: low to high
     };
     
-    let mut chart = ChartBuilder::on(&root)
+    let mut builder = ChartBuilder::on(&root);
+    builder
         .caption(&title, ("sans-serif", 24).into_font())
         .margin(20)
         .x_label_area_size(50)
-        .y_label_area_size(70)
-        .build_cartesian_2d(x_start..x_end, y_min..y_max)
-        .map_err(|e: DrawingAreaErrorKind<_>| io::Error::new(io::ErrorKind::Other, format!("{:?}", e)))?;
+        .y_label_area_size(70);
+
+    if options.log_y {
+        // Clip to the smallest positive intensity
+        let floor = data_points
+            .iter()
+            .map(|p| p.1)
+            .filter(|&y| y > 0.0)
+            .fold(f64::INFINITY, f64::min);
+        let floor = if floor.is_finite() { floor } else { 1.0 };
+        let points: Vec<(f64, f64)> = data_points.into_iter().map(|(x, y)| (x, y.max(floor))).collect();
+
+        // Whole decades so the axis always gets labelled ticks
+        let y_bottom = 10f64.powf(floor.log10().floor());
+        let y_top = 10f64.powf(y_max.max(floor).log10().floor() + 1.0);
+
+        let mut chart = builder
+            .build_cartesian_2d(x_start..x_end, (y_bottom..y_top).log_scale())
+            .map_err(|e: DrawingAreaErrorKind<_>| io::Error::other(format!("{:?}", e)))?;
+        draw_spectrum(&mut chart, &x_label, points)?;
+    } else {
+        // Add padding to y-axis
+        let y_padding = (y_max - y_min) * 0.05;
+        let mut chart = builder
+            .build_cartesian_2d(x_start..x_end, (y_min - y_padding)..(y_max + y_padding))
+            .map_err(|e: DrawingAreaErrorKind<_>| io::Error::other(format!("{:?}", e)))?;
+        draw_spectrum(&mut chart, &x_label, data_points)?;
+    }
+    
+    // Render to the backend
+    root.present()
+        .map_err(|e: DrawingAreaErrorKind<_>| io::Error::other(format!("{:?}", e)))?;
     
+    Ok(())
+}
+
+/// Draw the mesh, labels and spectrum line onto a chart.
+#[cfg(feature = "plot")]
+fn draw_spectrum<DB, Y, I>(
+    chart: &mut ChartContext<'_, DB, Cartesian2d<RangedCoordf64, Y>>,
+    x_label: &str,
+    points: I,
+) -> io::Result<()>
+where
+    DB: DrawingBackend,
+    Y: Ranged<ValueType = f64> + ValueFormatter<f64>,
+    I: IntoIterator<Item = (f64, f64)>,
+{
     chart
         .configure_mesh()
-        .x_desc(&x_label)
+        .x_desc(x_label)
         .y_desc("Intensity")
         .axis_desc_style(("sans-serif", 16))
         .label_style(("sans-serif", 12))
         .draw()
-        .map_err(|e: DrawingAreaErrorKind<_>| io::Error::new(io::ErrorKind::Other, format!("{:?}", e)))?;
+        .map_err(|e: DrawingAreaErrorKind<_>| io::Error::other(format!("{:?}", e)))?;
     
     // Draw the spectrum line
-    let data_points: Vec<(f64, f64)> = axis.values
-        .iter()
-        .zip(spc.data.iter())
-        .map(|(&x, &y)| (x, y))
-        .collect();
-    
     chart
-        .draw_series(LineSeries::new(data_points, &BLUE))
-        .map_err(|e: DrawingAreaErrorKind<_>| io::Error::new(io::ErrorKind::Other, format!("{:?}", e)))?;
-    
-    // Render to file
-    root.present()
-        .map_err(|e: DrawingAreaErrorKind<_>| io::Error::new(io::ErrorKind::Other, format!("{:?}", e)))?;
-    
+        .draw_series(LineSeries::new(points, &BLUE))
+        .map_err(|e: DrawingAreaErrorKind<_>| io::Error::other(format!("{:?}", e)))?;
+
     Ok(())
 }
 
 /// Generate a PNG plot with default dimensions (1200x600).
+#[cfg(feature = "plot")]
 pub fn write_plot_default<P: AsRef<Path>>(spc: &SpcFile, output_path: P) -> io::Result<()> {
-    write_plot(spc, output_path, 1200, 600)
+    write_plot_with(spc, output_path, &PlotOptions::default())
 }
@@ -0,0 +1,121 @@
+//! Time-series output: time × x matrix and waterfall plot.
+
+#[cfg(feature = "plot")]
+use super::PlotOptions;
+#[cfg(feature = "plot")]
+use crate::spectre::AxisType;
+use crate::spectre::TimeSeries;
+use std::io::{self, Write};
+#[cfg(feature = "plot")]
+use std::path::Path;
+
+#[cfg(feature = "plot")]
+use plotters::prelude::*;
+
+/// Write a time series as a CSV matrix: a heading row with the time column
+/// name (`time_s`, `sequence` or `index`) and the x values, then one row
+/// per spectrum.
+pub fn write_series_csv<W: Write>(series: &TimeSeries, mut writer: W) -> io::Result<()> {
+    write!(writer, "{}", series.time_base.name())?;
+    for x in &series.x {
+        write!(writer, ",{}", x)?;
+    }
+    writeln!(writer)?;
+    for (time, row) in series.times.iter().zip(&series.intensities) {
+        write!(writer, "{}", time)?;
+        for value in row {
+            write!(writer, ",{}", value)?;
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+/// Plot a time series as a waterfall: one line per spectrum, each raised
+/// above the previous one and colored from blue (first) to red (last).
+///
+/// `options.log_y` and `options.axis` are ignored; the series has one axis.
+#[cfg(feature = "plot")]
+pub fn write_waterfall<P: AsRef<Path>>(series: &TimeSeries, output_path: P, options: &PlotOptions) -> io::Result<()> {
+    let in_range = |x: f64| match options.x_range {
+        Some((a, b)) => x >= a.min(b) && x <= a.max(b),
+        None => true,
+    };
+    let points: Vec<Vec<(f64, f64)>> = series
+        .intensities
+        .iter()
+        .map(|row| series.x.iter().copied().zip(row.iter().copied()).filter(|&(x, _)| in_range(x)).collect())
+        .collect();
+    let values = || points.iter().flatten();
+    if values().next().is_none() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "no points in the plot range"));
+    }
+
+    let x_min = values().map(|p| p.0).fold(f64::INFINITY, f64::min);
+    let x_max = values().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+    let y_min = values().map(|p| p.1).fold(f64::INFINITY, f64::min);
+    let y_max = values().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+    // Traces overlap by a third of the intensity span when there are many
+    let span = (y_max - y_min).max(f64::EPSILON);
+    let step = span * (3.0 / points.len() as f64).min(1.0);
+    let top = y_max + step * (points.len() - 1) as f64;
+
+    let (x_label, reversed) = match series.axis {
+        AxisType::RamanShifts => ("Raman Shift (cm⁻¹)", true),
+        AxisType::Wavelengths => ("Wavelength (nm)", false),
+        AxisType::Pixels => ("Pixel Index", false),
+    };
+    let (x_start, x_end) = if reversed { (x_max, x_min) } else { (x_min, x_max) };
+    let plot_error = |e: DrawingAreaErrorKind<_>| io::Error::other(format!("{:?}", e));
+
+    let root = BitMapBackend::new(output_path.as_ref(), (options.width, options.height)).into_drawing_area();
+    root.fill(&WHITE).map_err(plot_error)?;
+    let title = format!("Time Series ({} spectra)", points.len());
+    let mut chart = ChartBuilder::on(&root)
+        .caption(&title, ("sans-serif", 24).into_font())
+        .margin(20)
+        .x_label_area_size(50)
+        .y_label_area_size(70)
+        .build_cartesian_2d(x_start..x_end, (y_min - span * 0.05)..(top + span * 0.05))
+        .map_err(plot_error)?;
+    chart
+        .configure_mesh()
+        .x_desc(x_label)
+        .y_desc("Intensity (offset)")
+        .axis_desc_style(("sans-serif", 16))
+        .label_style(("sans-serif", 12))
+        .draw()
+        .map_err(plot_error)?;
+
+    let last = (points.len() - 1).max(1) as f64;
+    for (i, trace) in points.into_iter().enumerate() {
+        let offset = step * i as f64;
+        let color = HSLColor(0.66 * (1.0 - i as f64 / last), 0.8, 0.45);
+        chart
+            .draw_series(LineSeries::new(trace.into_iter().map(|(x, y)| (x, y + offset)), &color))
+            .map_err(plot_error)?;
+    }
+
+    root.present().map_err(plot_error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spectre::{AxisType, TimeBase};
+
+    #[test]
+    fn test_write_series_csv() {
+        let series = TimeSeries {
+            time_base: TimeBase::Seconds,
+            times: vec![0.0, 1.5],
+            axis: AxisType::Wavelengths,
+            x: vec![500.0, 501.0],
+            intensities: vec![vec![1.0, 2.0], vec![3.0, 4.0]],
+        };
+        let mut csv = Vec::new();
+        write_series_csv(&series, &mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), "time_s,500,501\n0,1,2\n1.5,3,4\n");
+    }
+}
@@ -0,0 +1,146 @@
+//! SQLite output (requires the `sqlite` feature).
+//!
+//! Each spectrum becomes one row in `spectra` plus one row per point in
+//! `points`; user metadata goes to `metadata` as key/value rows. SQLite has
+//! no NaN, so NaN values are stored as NULL:
+//!
+//! ```sql
+//! SELECT s.file_id, p.x, p.intensity
+//! FROM spectra s JOIN points p ON p.spectrum_id = s.id
+//! WHERE s.uid = 'CAM-42';
+//! ```
+
+use super::select_best_axis;
+use crate::spectre::SpcFile;
+use rusqlite::{params, Connection, Result};
+use std::path::Path;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS spectra (
+    id INTEGER PRIMARY KEY,
+    file_id TEXT NOT NULL,
+    uid TEXT NOT NULL,
+    acquired TEXT,
+    operator TEXT,
+    sample TEXT,
+    instrument TEXT,
+    laser_nm REAL,
+    axis TEXT NOT NULL,
+    axis_unit TEXT NOT NULL,
+    num_points INTEGER NOT NULL,
+    calibration TEXT,
+    config TEXT,
+    history TEXT
+);
+CREATE TABLE IF NOT EXISTS points (
+    spectrum_id INTEGER NOT NULL REFERENCES spectra(id),
+    idx INTEGER NOT NULL,
+    x REAL,
+    intensity REAL,
+    blank REAL,
+    PRIMARY KEY (spectrum_id, idx)
+);
+CREATE TABLE IF NOT EXISTS metadata (
+    spectrum_id INTEGER NOT NULL REFERENCES spectra(id),
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (spectrum_id, key)
+);
+";
+
+/// Columns added to `spectra` since its first version, created when
+/// appending to an older database.
+const ADDED_COLUMNS: [&str; 4] = ["acquired", "operator", "sample", "instrument"];
+
+/// Write spectra into a SQLite database, one `(file_id, spectrum)` per entry.
+///
+/// With `append` the rows are added to an existing database; otherwise any
+/// existing tables are dropped first. All rows go in one transaction.
+pub fn write_sqlite(spectra: &[(&str, &SpcFile)], path: &Path, append: bool) -> Result<()> {
+    let mut conn = Connection::open(path)?;
+    if !append {
+        conn.execute_batch("DROP TABLE IF EXISTS metadata; DROP TABLE IF EXISTS points; DROP TABLE IF EXISTS spectra;")?;
+    }
+    conn.execute_batch(SCHEMA)?;
+    let columns: Vec<String> = conn
+        .prepare("SELECT name FROM pragma_table_info('spectra')")?
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<_>>()?;
+    for column in ADDED_COLUMNS.iter().filter(|c| !columns.iter().any(|name| name == *c)) {
+        conn.execute_batch(&format!("ALTER TABLE spectra ADD COLUMN {} TEXT;", column))?;
+    }
+
+    let tx = conn.transaction()?;
+    for (file_id, spc) in spectra {
+        insert_spectrum(&tx, file_id, spc)?;
+    }
+    tx.commit()
+}
+
+/// Insert one spectrum into a database with the tables created, returning its id.
+pub fn insert_spectrum(conn: &Connection, file_id: &str, spc: &SpcFile) -> Result<i64> {
+    let axis = select_best_axis(spc);
+    let to_json = |value: serde_json::Value| (!value.is_null()).then(|| value.to_string());
+
+    conn.execute(
+        "INSERT INTO spectra (file_id, uid, acquired, operator, sample, instrument, laser_nm, axis, axis_unit,
+                              num_points, calibration, config, history)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        params![
+            file_id,
+            spc.uid,
+            spc.acquired,
+            spc.operator,
+            spc.sample,
+            to_json(serde_json::json!(spc.instrument)),
+            spc.config.as_ref().and_then(|c| c.raman_wavelength),
+            axis.name,
+            axis.unit,
+            spc.data.len() as i64,
+            to_json(serde_json::json!(spc.calibration)),
+            to_json(serde_json::json!(spc.config)),
+            (!spc.history.is_empty()).then(|| serde_json::json!(spc.history).to_string()),
+        ],
+    )?;
+    let id = conn.last_insert_rowid();
+
+    let mut insert_point = conn.prepare_cached(
+        "INSERT INTO points (spectrum_id, idx, x, intensity, blank) VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    for (i, &y) in spc.data.iter().enumerate() {
+        insert_point.execute(params![id, i as i64, axis.values.get(i), y, spc.blank.get(i)])?;
+    }
+
+    let mut insert_meta =
+        conn.prepare_cached("INSERT INTO metadata (spectrum_id, key, value) VALUES (?1, ?2, ?3)")?;
+    for (key, value) in &spc.metadata {
+        insert_meta.execute(params![id, key, value])?;
+    }
+
+    Ok(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spectre::SpcFileBuilder;
+
+    #[test]
+    fn test_nan_intensity() {
+        let path = std::env::temp_dir().join(format!("spc_sqlite_nan_{}.db", std::process::id()));
+        let spc = SpcFileBuilder::new("CAM-1", vec![1.0, f64::NAN, 3.0]).build();
+        write_sqlite(&[("a", &spc)], &path, false).unwrap();
+
+        let conn = Connection::open(&path).unwrap();
+        let intensities: Vec<Option<f64>> = conn
+            .prepare("SELECT intensity FROM points ORDER BY idx")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        drop(conn);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(intensities, [Some(1.0), None, Some(3.0)]);
+    }
+}
@@ -0,0 +1,48 @@
+//! Plain two-column XY text, as legacy spectroscopy tools import it.
+
+use super::select_axis;
+use crate::spectre::{AxisType, SpcFile};
+use std::io::{self, Write};
+
+/// Write SpcFile as `x<TAB>y` lines, without a header or other columns.
+///
+/// The x-axis is `axis`, which must be available, else selected as for
+/// [`write_pairs`](super::write_pairs).
+pub fn write_xy<W: Write>(spc: &SpcFile, axis: Option<AxisType>, mut writer: W) -> io::Result<()> {
+    let axis = select_axis(spc, axis)?;
+    for (x, y) in axis.values.iter().zip(&spc.data) {
+        writeln!(writer, "{}\t{}", x, y)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_xy() {
+        let spc = SpcFile {
+            uid: "CAM-1".to_string(),
+            data: vec![10.0, 11.5],
+            blank: vec![1.0, 1.0],
+            calibration: None,
+            config: None,
+            wavelength_axis: Some(vec![800.0, 800.5]),
+            raman_shift_axis: None,
+            acquired: None,
+            operator: None,
+            sample: None,
+            instrument: None,
+            provenance: None,
+            stats: None,
+            metadata: [("sample".to_string(), "A1".to_string())].into(),
+            history: Vec::new(),
+            extras: Vec::new(),
+        };
+        let mut buf = Vec::new();
+        write_xy(&spc, None, &mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "800\t10\n800.5\t11.5\n");
+        assert!(write_xy(&spc, Some(AxisType::RamanShifts), &mut Vec::new()).is_err());
+    }
+}
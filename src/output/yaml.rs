@@ -0,0 +1,135 @@
+//! YAML output format.
+//!
+//! The same document as the JSON output (see [`JSON_SCHEMA`](super::JSON_SCHEMA)),
+//! in block style so small spectra can be embedded in YAML manifests and
+//! reviewed line by line.
+
+use super::json::VersionedSpcFile;
+use crate::spectre::SpcFile;
+use serde_json::Value;
+use std::io::{self, Write};
+
+/// Write SpcFile as a YAML document.
+pub fn write_yaml<W: Write>(spc: &SpcFile, mut writer: W) -> io::Result<()> {
+    let value = serde_json::to_value(VersionedSpcFile::new(spc))?;
+    let mut text = String::new();
+    emit(&value, 0, &mut text);
+    writer.write_all(text.as_bytes())
+}
+
+/// Write SpcFile as a YAML string.
+pub fn to_yaml_string(spc: &SpcFile) -> io::Result<String> {
+    let mut buf = Vec::new();
+    write_yaml(spc, &mut buf)?;
+    String::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Emit a mapping or sequence as block lines at `indent`.
+fn emit(value: &Value, indent: usize, out: &mut String) {
+    let pad = " ".repeat(indent);
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                out.push_str(&format!("{}{}:", pad, key_text(key)));
+                nested(value, indent + 2, out);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                out.push_str(&format!("{}-", pad));
+                match item {
+                    // The first entry follows the dash, the rest align with it
+                    Value::Object(map) if !map.is_empty() => {
+                        let mut entry = String::new();
+                        emit(item, indent + 2, &mut entry);
+                        out.push(' ');
+                        out.push_str(&entry[indent + 2..]);
+                    }
+                    Value::Array(inner) if inner.iter().all(is_scalar) => {
+                        out.push_str(&format!(" {}\n", flow(item)));
+                    }
+                    _ => nested(item, indent + 2, out),
+                }
+            }
+        }
+        scalar => out.push_str(&format!("{}{}\n", pad, flow(scalar))),
+    }
+}
+
+/// The rest of a `key:` or `-` line: a scalar or empty collection inline,
+/// else a block on the following lines.
+fn nested(value: &Value, indent: usize, out: &mut String) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            out.push('\n');
+            emit(value, indent, out);
+        }
+        Value::Array(items) if !items.is_empty() => {
+            out.push('\n');
+            emit(value, indent, out);
+        }
+        _ => out.push_str(&format!(" {}\n", flow(value))),
+    }
+}
+
+/// A value in flow style. JSON scalars are valid YAML; strings are always
+/// double-quoted so none reads back as a number, boolean or null.
+fn flow(value: &Value) -> String {
+    match value {
+        Value::Object(map) if map.is_empty() => "{}".to_string(),
+        Value::Array(items) => format!("[{}]", items.iter().map(flow).collect::<Vec<_>>().join(", ")),
+        other => other.to_string(),
+    }
+}
+
+fn is_scalar(value: &Value) -> bool {
+    !matches!(value, Value::Array(_) | Value::Object(_))
+}
+
+/// A mapping key: plain when it is a simple identifier, else quoted.
+fn key_text(key: &str) -> String {
+    let plain = key.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || "_.-".contains(c))
+        && !["true", "false", "null", "yes", "no", "on", "off", "y", "n"].contains(&key.to_lowercase().as_str());
+    if plain {
+        key.to_string()
+    } else {
+        Value::String(key.to_string()).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spectre::Calibration;
+
+    #[test]
+    fn test_write_yaml() {
+        let spc = SpcFile {
+            uid: "CAM-1".to_string(),
+            data: vec![1.0, 2.5],
+            blank: Vec::new(),
+            calibration: Some(Calibration::legendre(vec![800.0, 50.0])),
+            config: None,
+            wavelength_axis: None,
+            raman_shift_axis: None,
+            acquired: None,
+            operator: None,
+            sample: Some("true".to_string()),
+            instrument: None,
+            provenance: None,
+            stats: None,
+            metadata: [("room no".to_string(), "B12".to_string())].into(),
+            history: vec![crate::processing::ProcessingStep::new("normalize")],
+            extras: Vec::new(),
+        };
+        let yaml = to_yaml_string(&spc).unwrap();
+        let expected = format!(
+            "schema_version: 2\nuid: \"CAM-1\"\ndata:\n  - 1.0\n  - 2.5\nblank: []\ncalibration:\n  coefficients:\n    \
+             - 800.0\n    - 50.0\nsample: \"true\"\nmetadata:\n  \"room no\": \"B12\"\nhistory:\n  - step: \"normalize\"\n    \
+             version: \"{}\"\n",
+            env!("CARGO_PKG_VERSION")
+        );
+        assert_eq!(yaml, expected);
+    }
+}
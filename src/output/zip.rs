@@ -0,0 +1,193 @@
+//! ZIP archive output: converted files of a batch in one archive.
+//!
+//! Entries are deflated (or stored, when that is smaller) and written as
+//! they are added; the central directory follows on [`ZipWriter::finish`].
+//! Archives are limited to 4 GiB and 65535 entries (no ZIP64).
+
+use crate::spectre::timestamp::{parse_rfc3339, rfc3339};
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+/// Version 2.0: deflate and directories
+const VERSION: u16 = 20;
+/// Names are UTF-8
+const UTF8_NAMES: u16 = 1 << 11;
+
+/// A central directory entry, kept until [`ZipWriter::finish`].
+struct Entry {
+    name: String,
+    method: u16,
+    crc: u32,
+    compressed_size: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Writes a ZIP archive entry by entry.
+///
+/// ```
+/// # use spc_converter::output::ZipWriter;
+/// let mut zip = ZipWriter::new(Vec::new());
+/// zip.add("spectra/a.json", br#"{"uid":"CAM-1"}"#)?;
+/// let bytes = zip.finish()?;
+/// assert_eq!(&bytes[..4], b"PK\x03\x04");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct ZipWriter<W: Write> {
+    writer: W,
+    offset: u64,
+    entries: Vec<Entry>,
+    /// DOS time and date of all entries
+    modified: (u16, u16),
+}
+
+impl<W: Write> ZipWriter<W> {
+    /// Start an archive; entries are dated now.
+    pub fn new(writer: W) -> Self {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+        Self {
+            writer,
+            offset: 0,
+            entries: Vec::new(),
+            modified: dos_time(now as i64),
+        }
+    }
+
+    /// Add a file. `name` is its path inside the archive, with `/` between
+    /// directories.
+    pub fn add(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        let mut crc = Crc::new();
+        crc.update(data);
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data)?;
+        let deflated = encoder.finish()?;
+        let (method, contents) = if deflated.len() < data.len() { (DEFLATED, &deflated[..]) } else { (STORED, data) };
+
+        let entry = Entry {
+            name: name.trim_start_matches('/').to_string(),
+            method,
+            crc: crc.sum(),
+            compressed_size: limit(contents.len() as u64)?,
+            size: limit(data.len() as u64)?,
+            offset: limit(self.offset)?,
+        };
+        if self.entries.len() == usize::from(u16::MAX) {
+            return Err(io::Error::other("too many entries for a ZIP archive"));
+        }
+
+        let mut header = Vec::with_capacity(30 + entry.name.len());
+        header.extend_from_slice(&LOCAL_HEADER.to_le_bytes());
+        header.extend_from_slice(&VERSION.to_le_bytes());
+        self.common_fields(&mut header, &entry);
+        header.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+        header.extend_from_slice(entry.name.as_bytes());
+        self.writer.write_all(&header)?;
+        self.writer.write_all(contents)?;
+        self.offset += header.len() as u64 + contents.len() as u64;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Flags, method, time, checksum, sizes and name length, shared by the
+    /// local and central headers.
+    fn common_fields(&self, header: &mut Vec<u8>, entry: &Entry) {
+        header.extend_from_slice(&UTF8_NAMES.to_le_bytes());
+        header.extend_from_slice(&entry.method.to_le_bytes());
+        header.extend_from_slice(&self.modified.0.to_le_bytes());
+        header.extend_from_slice(&self.modified.1.to_le_bytes());
+        header.extend_from_slice(&entry.crc.to_le_bytes());
+        header.extend_from_slice(&entry.compressed_size.to_le_bytes());
+        header.extend_from_slice(&entry.size.to_le_bytes());
+        header.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+    }
+
+    /// Write the central directory and return the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let start = limit(self.offset)?;
+        let mut directory = Vec::new();
+        for entry in &self.entries {
+            directory.extend_from_slice(&CENTRAL_HEADER.to_le_bytes());
+            directory.extend_from_slice(&VERSION.to_le_bytes()); // made by
+            directory.extend_from_slice(&VERSION.to_le_bytes()); // needed
+            self.common_fields(&mut directory, entry);
+            // Extra field and comment length, disk number, internal and external attributes
+            directory.extend_from_slice(&[0; 12]);
+            directory.extend_from_slice(&entry.offset.to_le_bytes());
+            directory.extend_from_slice(entry.name.as_bytes());
+        }
+        let count = self.entries.len() as u16;
+        let size = limit(directory.len() as u64)?;
+        directory.extend_from_slice(&END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+        directory.extend_from_slice(&[0; 4]); // disk numbers
+        directory.extend_from_slice(&count.to_le_bytes());
+        directory.extend_from_slice(&count.to_le_bytes());
+        directory.extend_from_slice(&size.to_le_bytes());
+        directory.extend_from_slice(&start.to_le_bytes());
+        directory.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        self.writer.write_all(&directory)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+fn limit(value: u64) -> io::Result<u32> {
+    u32::try_from(value).map_err(|_| io::Error::other("ZIP archive larger than 4 GiB"))
+}
+
+/// MS-DOS time and date (UTC) of a Unix time; 1980 at the earliest.
+fn dos_time(unix_seconds: i64) -> (u16, u16) {
+    let [year, month, day, hour, minute, second] = parse_rfc3339(&rfc3339(unix_seconds)).unwrap_or([1980, 1, 1, 0, 0, 0]);
+    if year < 1980 {
+        return (0, (1 << 5) | 1);
+    }
+    let time = (hour << 11) | (minute << 5) | (second / 2);
+    let date = ((year.min(2107) - 1980) << 9) | (month << 5) | day;
+    (time as u16, date as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::DeflateDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn test_zip_writer() {
+        let json = br#"{"data":[1.0,1.0,1.0,1.0,1.0,1.0,1.0,1.0,1.0,1.0]}"#;
+        let mut zip = ZipWriter::new(Vec::new());
+        zip.add("out/a.json", json).unwrap();
+        zip.add("b.bin", &[7]).unwrap();
+        let bytes = zip.finish().unwrap();
+
+        // End of central directory: two entries, directory right after the data
+        let end = &bytes[bytes.len() - 22..];
+        assert_eq!(end[..4], END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+        assert_eq!(u16::from_le_bytes([end[10], end[11]]), 2);
+        let size = u32::from_le_bytes(end[12..16].try_into().unwrap()) as usize;
+        let start = u32::from_le_bytes(end[16..20].try_into().unwrap()) as usize;
+        assert_eq!(start + size, bytes.len() - 22);
+        assert_eq!(bytes[start..start + 4], CENTRAL_HEADER.to_le_bytes());
+
+        // First entry: deflated, decompresses to the input
+        assert_eq!(u16::from_le_bytes([bytes[8], bytes[9]]), DEFLATED);
+        let compressed = u32::from_le_bytes(bytes[18..22].try_into().unwrap()) as usize;
+        assert_eq!(&bytes[30..40], b"out/a.json");
+        let mut inflated = Vec::new();
+        DeflateDecoder::new(&bytes[40..40 + compressed]).read_to_end(&mut inflated).unwrap();
+        assert_eq!(inflated, json);
+
+        // Second entry: too small to compress, stored
+        let second = 40 + compressed;
+        assert_eq!(u16::from_le_bytes([bytes[second + 8], bytes[second + 9]]), STORED);
+        assert_eq!(bytes[second + 30 + 5], 7);
+
+        assert_eq!(dos_time(1_709_296_205), ((12 << 11) | (30 << 5) | 2, (44 << 9) | (3 << 5) | 1));
+    }
+}
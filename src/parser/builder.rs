@@ -0,0 +1,151 @@
+//! Building StorageObjects to serialize.
+
+use super::object::{StorageObject, Variable};
+
+/// Type name of a `storage_vector<double>`, as written by the Spectrum
+/// Analyzer Suite.
+pub const DOUBLE_VECTOR_TYPE: &str = "class storage_vector<double>";
+/// Type name of a `storage_string`.
+pub const STRING_TYPE: &str = "class storage_string";
+
+/// Builds a [`StorageObject`] field by field.
+///
+/// Variables are owned by the object's type, and children added with
+/// [`child`](Self::child) (or the [`string`](Self::string) and
+/// [`double_vec`](Self::double_vec) shorthands) are owned by it.
+///
+/// ```
+/// # use spc_converter::parser::StorageObjectBuilder;
+/// let bytes = StorageObjectBuilder::new("class SpectreFile")
+///     .name("data")
+///     .double_vec("m_data", &[1.0, 2.0, 3.0])
+///     .double_vec("m_blank", &[])
+///     .string("m_uid", "CAM-1")
+///     .to_bytes();
+/// ```
+#[derive(Debug, Clone)]
+pub struct StorageObjectBuilder {
+    object: StorageObject,
+}
+
+impl StorageObjectBuilder {
+    /// Start an object of the given type, with empty owner and variable
+    /// names.
+    pub fn new(type_name: impl Into<String>) -> Self {
+        Self {
+            object: StorageObject {
+                type_name: type_name.into(),
+                owner_name: String::new(),
+                var_name: String::new(),
+                variables: Vec::new(),
+                children: Vec::new(),
+            },
+        }
+    }
+
+    /// Set the variable name the object is stored under (e.g. `data`).
+    pub fn name(mut self, var_name: impl Into<String>) -> Self {
+        self.object.var_name = var_name.into();
+        self
+    }
+
+    /// Set the type name of the owning object.
+    pub fn owner(mut self, owner_name: impl Into<String>) -> Self {
+        self.object.owner_name = owner_name.into();
+        self
+    }
+
+    /// Add a variable with raw value bytes.
+    pub fn variable(mut self, name: impl Into<String>, type_name: impl Into<String>, data: Vec<u8>) -> Self {
+        self.object.variables.push(Variable {
+            owner: self.object.type_name.clone(),
+            name: name.into(),
+            type_name: type_name.into(),
+            data,
+        });
+        self
+    }
+
+    /// Add a `double` variable.
+    pub fn f64(self, name: impl Into<String>, value: f64) -> Self {
+        self.variable(name, "double", value.to_le_bytes().to_vec())
+    }
+
+    /// Add an `int` (32-bit) variable.
+    pub fn i32(self, name: impl Into<String>, value: i32) -> Self {
+        self.variable(name, "int", value.to_le_bytes().to_vec())
+    }
+
+    /// Add a `bool` (one byte) variable.
+    pub fn bool(self, name: impl Into<String>, value: bool) -> Self {
+        self.variable(name, "bool", vec![value as u8])
+    }
+
+    /// Add one unnamed `double` variable per value, as a
+    /// `storage_vector<double>` stores its elements.
+    pub fn doubles(self, values: &[f64]) -> Self {
+        values.iter().fold(self, |builder, &value| builder.f64("", value))
+    }
+
+    /// Add a child object, owned by this one.
+    pub fn child(mut self, mut child: StorageObject) -> Self {
+        child.owner_name = self.object.type_name.clone();
+        self.object.children.push(child);
+        self
+    }
+
+    /// Add a `storage_string` child: `size` (64-bit, including the NUL) and
+    /// the NUL-terminated `data`.
+    pub fn string(self, name: impl Into<String>, value: &str) -> Self {
+        let mut data = value.as_bytes().to_vec();
+        data.push(0);
+        let child = StorageObjectBuilder::new(STRING_TYPE)
+            .name(name)
+            .variable("size", "size_t", (data.len() as u64).to_le_bytes().to_vec())
+            .variable("data", "char", data)
+            .build();
+        self.child(child)
+    }
+
+    /// Add a `storage_vector<double>` child.
+    pub fn double_vec(self, name: impl Into<String>, values: &[f64]) -> Self {
+        let child = StorageObjectBuilder::new(DOUBLE_VECTOR_TYPE).name(name).doubles(values).build();
+        self.child(child)
+    }
+
+    /// Finish the object.
+    pub fn build(self) -> StorageObject {
+        self.object
+    }
+
+    /// Serialize the object to the packed binary layout.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.object.to_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ObjectView;
+
+    #[test]
+    fn test_builder_round_trip() {
+        let bytes = StorageObjectBuilder::new("class SpectreFile")
+            .name("data")
+            .double_vec("m_data", &[1.0, 2.5])
+            .string("m_uid", "CAM-1")
+            .i32("version", 3)
+            .to_bytes();
+
+        let parsed = StorageObject::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed.var_name, "data");
+        assert_eq!(parsed.find_var("version").unwrap().data, 3i32.to_le_bytes());
+        let uid = parsed.find_child("m_uid").unwrap();
+        assert_eq!(uid.owner_name, "class SpectreFile");
+        assert_eq!(uid.find_var("data").unwrap().data, b"CAM-1\0");
+
+        let view = ObjectView::parse(&bytes).unwrap();
+        assert_eq!(view.find_child("m_data").unwrap().unwrap().f64_values().unwrap(), vec![1.0, 2.5]);
+    }
+}
@@ -1,6 +1,10 @@
 //! Container layer: encryption and compression wrapper.
 
 use super::header::ParseError;
+use super::options::Limits;
+use super::reader::ContainerReader;
+use std::borrow::Cow;
+use std::io::{Read, Write};
 
 /// Container header (packed, 80 bytes total with alignment).
 #[derive(Debug)]
@@ -13,10 +17,42 @@ pub struct ContainerHeader {
     pub buffers_data_ofs: u64,
 }
 
+/// Container versions this crate can read (the digit in the "SPC0" magic).
+pub const SUPPORTED_VERSIONS: &[u8] = &[0];
+
 impl ContainerHeader {
     pub const MAGIC: u32 = 0x53504330; // "0CPS" as stored bytes (reads as "SPC0")
+    /// Magic of any version: "SPC" followed by the version digit.
+    const MAGIC_PREFIX: u32 = 0x53504300;
     pub const SIZE: usize = 80; // 4+4+8+8+4+8+10*4 = 80 bytes with reserved
 
+    /// The container version in a magic ("SPC1" is version 1), or `None`
+    /// if it is not a container magic at all.
+    pub fn version_of(ident: u32) -> Option<u8> {
+        let digit = (ident & 0xFF) as u8;
+        (ident & 0xFFFF_FF00 == Self::MAGIC_PREFIX && digit.is_ascii_digit()).then(|| digit - b'0')
+    }
+
+    /// Parse the header, dispatching on the container version in its magic.
+    ///
+    /// Unsupported versions are reported as such rather than as a bad magic,
+    /// so that files from newer software releases are recognizable.
+    pub fn parse(data: &[u8]) -> Result<Self, ParseError> {
+        let ident = data
+            .get(0..4)
+            .map(|ident| u32::from_le_bytes(ident.try_into().unwrap()))
+            .ok_or(ParseError::FileTooSmall {
+                expected: Self::SIZE,
+                actual: data.len(),
+            })?;
+        match Self::version_of(ident) {
+            Some(0) => Self::from_bytes(data),
+            Some(version) => Err(ParseError::UnsupportedVersion(version)),
+            None => Err(ParseError::BadMagic { found: ident }),
+        }
+    }
+
+    /// Parse the version 0 header layout, without checking the magic.
     pub fn from_bytes(data: &[u8]) -> Result<Self, ParseError> {
         if data.len() < Self::SIZE {
             return Err(ParseError::FileTooSmall {
@@ -36,6 +72,17 @@ impl ContainerHeader {
             // Reserved: 40-80
         })
     }
+
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut out = [0u8; Self::SIZE];
+        out[0..4].copy_from_slice(&self.ident.to_le_bytes());
+        out[4..8].copy_from_slice(&self.checksum.to_le_bytes());
+        out[8..16].copy_from_slice(&self.num_buffers.to_le_bytes());
+        out[16..24].copy_from_slice(&self.buffers_table_ofs.to_le_bytes());
+        out[24..28].copy_from_slice(&self.seed.to_le_bytes());
+        out[32..40].copy_from_slice(&self.buffers_data_ofs.to_le_bytes());
+        out
+    }
 }
 
 /// Buffer entry in the table (24 bytes with 8-byte alignment).
@@ -58,8 +105,75 @@ impl BufferEntry {
             size: u64::from_le_bytes(data[16..24].try_into().unwrap()),
         }
     }
+
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut out = [0u8; Self::SIZE];
+        out[0] = self.encoding;
+        out[8..16].copy_from_slice(&self.offset.to_le_bytes());
+        out[16..24].copy_from_slice(&self.size.to_le_bytes());
+        out
+    }
+}
+
+/// Buffer encodings (the first byte of a [`BufferEntry`]).
+pub const ENCODING_NONE: u8 = 0;
+pub const ENCODING_RLE8: u8 = 1;
+pub const ENCODING_RLE0: u8 = 2;
+/// Deflate, written by newer Spectrum Analyzer Suite builds
+pub const ENCODING_ZLIB: u8 = 3;
+/// LZ4 frame (needs the `lz4` feature)
+pub const ENCODING_LZ4: u8 = 4;
+/// Zstandard frame (needs the `zstd` feature)
+pub const ENCODING_ZSTD: u8 = 5;
+
+/// Decoder for one buffer encoding, given the maximum decoded size.
+pub type Decoder = fn(&[u8], usize) -> Result<Vec<u8>, ParseError>;
+
+/// Encoder for one buffer encoding.
+pub type Encoder = fn(&[u8]) -> Vec<u8>;
+
+/// A known buffer encoding.
+#[derive(Debug, Clone, Copy)]
+pub struct Encoding {
+    pub id: u8,
+    pub name: &'static str,
+    /// Cargo feature that provides the decoder, if optional
+    pub feature: Option<&'static str>,
+    /// `None` if the feature is not enabled in this build
+    pub decoder: Option<Decoder>,
+    /// `None` if buffers cannot be written with this encoding
+    pub encoder: Option<Encoder>,
+}
+
+#[cfg(feature = "lz4")]
+const LZ4_DECODER: Option<Decoder> = Some(lz4_decode);
+#[cfg(not(feature = "lz4"))]
+const LZ4_DECODER: Option<Decoder> = None;
+#[cfg(feature = "zstd")]
+const ZSTD_DECODER: Option<Decoder> = Some(zstd_decode);
+#[cfg(not(feature = "zstd"))]
+const ZSTD_DECODER: Option<Decoder> = None;
+
+/// Registry of buffer encodings, keyed by the encoding byte.
+pub const ENCODINGS: &[Encoding] = &[
+    Encoding { id: ENCODING_NONE, name: "none", feature: None, decoder: Some(none_decode), encoder: Some(<[u8]>::to_vec) },
+    Encoding { id: ENCODING_RLE8, name: "RLE8", feature: None, decoder: Some(rle8_decode), encoder: Some(rle8_encode) },
+    Encoding { id: ENCODING_RLE0, name: "RLE0", feature: None, decoder: Some(rle0_decode), encoder: Some(rle0_encode) },
+    Encoding { id: ENCODING_ZLIB, name: "zlib", feature: None, decoder: Some(zlib_decode), encoder: Some(zlib_encode) },
+    Encoding { id: ENCODING_LZ4, name: "LZ4", feature: Some("lz4"), decoder: LZ4_DECODER, encoder: None },
+    Encoding { id: ENCODING_ZSTD, name: "Zstandard", feature: Some("zstd"), decoder: ZSTD_DECODER, encoder: None },
+];
+
+/// Look up an encoding byte in [`ENCODINGS`].
+pub fn encoding(id: u8) -> Option<&'static Encoding> {
+    ENCODINGS.iter().find(|e| e.id == id)
 }
 
+/// Encryption key used by the Spectrum Analyzer Suite.
+pub const ENCRYPTION_KEY: u32 = 0xfeedbeef;
+/// Encryption block size (not stored in the file).
+pub const BLOCK_SIZE: usize = 4;
+
 /// Decrypt the data (XOR-based with avalanche).
 pub fn decrypt(data: &mut [u8], encryption_key: u32, seed: u32, block_size: usize) {
     if block_size == 0 || data.len() < 4 {
@@ -79,17 +193,48 @@ pub fn decrypt(data: &mut [u8], encryption_key: u32, seed: u32, block_size: usiz
 
     let mut current_key = key.wrapping_add(repmat(num_elements as u32));
 
-    // Process as u32 words
-    let words: &mut [u32] = unsafe {
-        std::slice::from_raw_parts_mut(data.as_mut_ptr() as *mut u32, num_elements)
+    // Process as little-endian u32 words; `data` need not be aligned
+    for j in 0..block_size {
+        let mut i = j;
+        while i < num_elements {
+            let word = &mut data[i * 4..i * 4 + 4];
+            let encrypted = u32::from_le_bytes(word.try_into().unwrap());
+            word.copy_from_slice(&(encrypted ^ current_key).to_le_bytes());
+            current_key = current_key.wrapping_add(!encrypted);
+            current_key = current_key.wrapping_add(repmat(i as u32));
+            i += block_size;
+        }
+    }
+}
+
+/// Encrypt the data (inverse of [`decrypt`]).
+///
+/// Same key schedule as decryption, but the key is advanced with the
+/// complement of the encrypted (post-XOR) word.
+pub fn encrypt(data: &mut [u8], encryption_key: u32, seed: u32, block_size: usize) {
+    if block_size == 0 || data.len() < 4 {
+        return;
+    }
+
+    let num_elements = data.len() / 4;
+    let key = encryption_key ^ seed;
+
+    let repmat = |value: u32| -> u32 {
+        let v = value & 0xFF;
+        let v = v | (v << 8);
+        let v = v | (v << 16);
+        !v
     };
 
+    let mut current_key = key.wrapping_add(repmat(num_elements as u32));
+
     for j in 0..block_size {
         let mut i = j;
         while i < num_elements {
-            let temp = !words[i];
-            words[i] ^= current_key;
-            current_key = current_key.wrapping_add(temp);
+            let word = &mut data[i * 4..i * 4 + 4];
+            let encrypted = u32::from_le_bytes(word.try_into().unwrap()) ^ current_key;
+            word.copy_from_slice(&encrypted.to_le_bytes());
+            current_key = current_key.wrapping_add(!encrypted);
             current_key = current_key.wrapping_add(repmat(i as u32));
             i += block_size;
         }
@@ -124,23 +269,85 @@ pub fn checksum(data: &[u8]) -> u32 {
     !sum
 }
 
-/// RLE8 decode: pairs of (count, byte).
-pub fn rle8_decode(data: &[u8]) -> Vec<u8> {
+/// Error for decoded data exceeding `max_size` bytes.
+fn too_large(encoding: u8, max_size: usize) -> ParseError {
+    let name = self::encoding(encoding).map_or("unknown", |e| e.name);
+    ParseError::LimitExceeded {
+        what: format!("decoded {} buffer", name),
+        limit: max_size as u64,
+    }
+}
+
+fn none_decode(data: &[u8], max_size: usize) -> Result<Vec<u8>, ParseError> {
+    if data.len() > max_size {
+        return Err(too_large(ENCODING_NONE, max_size));
+    }
+    Ok(data.to_vec())
+}
+
+/// RLE8 decode: pairs of (count, byte), up to `max_size` bytes.
+pub fn rle8_decode(data: &[u8], max_size: usize) -> Result<Vec<u8>, ParseError> {
     let mut result = Vec::new();
     let mut i = 0;
 
     while i + 1 < data.len() {
         let count = data[i] as usize;
         let symbol = data[i + 1];
-        result.extend(std::iter::repeat(symbol).take(count));
+        if result.len() + count > max_size {
+            return Err(too_large(ENCODING_RLE8, max_size));
+        }
+        result.extend(std::iter::repeat_n(symbol, count));
         i += 2;
     }
 
+    Ok(result)
+}
+
+/// RLE8 encode: runs of up to 255 equal bytes as (count, byte) pairs.
+pub fn rle8_encode(data: &[u8]) -> Vec<u8> {
+    let mut result = Vec::new();
+    for run in data.chunk_by(|a, b| a == b) {
+        for part in run.chunks(255) {
+            result.extend([part.len() as u8, part[0]]);
+        }
+    }
+    result
+}
+
+/// RLE0 encode: runs of equal 8-byte blocks (one `f64` each), then any
+/// remaining bytes one by one.
+pub fn rle0_encode(data: &[u8]) -> Vec<u8> {
+    let mut result = Vec::new();
+    // The decoder starts with a block size of 1
+    let mut block_size = 1;
+    let mut emit = |block: &[u8], occurrence: usize| {
+        if block.len() == block_size {
+            result.push(occurrence as u8);
+        } else {
+            block_size = block.len();
+            result.extend([0, block_size as u8, occurrence as u8]);
+        }
+        result.extend_from_slice(block);
+    };
+
+    let blocks = data.chunks_exact(8);
+    let rest = blocks.remainder();
+    let mut blocks = blocks.peekable();
+    while let Some(block) = blocks.next() {
+        let mut occurrence = 1;
+        while occurrence < 255 && blocks.next_if_eq(&block).is_some() {
+            occurrence += 1;
+        }
+        emit(block, occurrence);
+    }
+    for byte in rest.chunks(1) {
+        emit(byte, 1);
+    }
     result
 }
 
-/// RLE0 decode: variable block size RLE.
-pub fn rle0_decode(data: &[u8]) -> Vec<u8> {
+/// RLE0 decode: variable block size RLE, up to `max_size` bytes.
+pub fn rle0_decode(data: &[u8], max_size: usize) -> Result<Vec<u8>, ParseError> {
     let mut result = Vec::new();
     let mut block_size: usize = 1;
     let mut i = 0;
@@ -167,6 +374,9 @@ pub fn rle0_decode(data: &[u8]) -> Vec<u8> {
                 break;
             }
             let block = &data[i..i + block_size];
+            if result.len() + occurrence * block_size > max_size {
+                return Err(too_large(ENCODING_RLE0, max_size));
+            }
             for _ in 0..occurrence {
                 result.extend_from_slice(block);
             }
@@ -176,6 +386,9 @@ pub fn rle0_decode(data: &[u8]) -> Vec<u8> {
                 break;
             }
             let block = &data[i..i + block_size];
+            if result.len() + occurrence * block_size > max_size {
+                return Err(too_large(ENCODING_RLE0, max_size));
+            }
             for _ in 0..occurrence {
                 result.extend_from_slice(block);
             }
@@ -183,89 +396,319 @@ pub fn rle0_decode(data: &[u8]) -> Vec<u8> {
         }
     }
 
-    result
+    Ok(result)
 }
 
-/// Decode based on encoding type.
-pub fn decode(data: &[u8], encoding: u8) -> Vec<u8> {
-    match encoding {
-        0 => data.to_vec(), // ENCODING_NONE
-        1 => rle8_decode(data), // ENCODING_RLE8
-        2 => rle0_decode(data), // ENCODING_RLE0
-        _ => data.to_vec(), // Unknown, return as-is
+/// Read a decompressing stream to the end, stopping once it produces more
+/// than `max_size` bytes.
+fn read_limited(reader: impl Read, encoding: u8, max_size: usize) -> Result<Vec<u8>, ParseError> {
+    let mut result = Vec::new();
+    reader
+        .take(max_size as u64 + 1)
+        .read_to_end(&mut result)
+        .map_err(|e| ParseError::Decode {
+            encoding,
+            message: e.to_string(),
+        })?;
+    if result.len() > max_size {
+        return Err(too_large(encoding, max_size));
     }
+    Ok(result)
 }
 
-/// Unpack a container: decrypt, decompress, and return StorageObject data.
-pub fn unpack_container(data: &[u8]) -> Result<Vec<Vec<u8>>, ParseError> {
-    const ENCRYPTION_KEY: u32 = 0xfeedbeef;
-    const BLOCK_SIZE: usize = 4;
+/// Zlib encode: a zlib stream at the default compression level.
+pub fn zlib_encode(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).expect("writing to a Vec cannot fail");
+    encoder.finish().expect("writing to a Vec cannot fail")
+}
+
+/// Zlib decode: a zlib stream, or raw deflate data without the zlib
+/// wrapper, up to `max_size` bytes.
+pub fn zlib_decode(data: &[u8], max_size: usize) -> Result<Vec<u8>, ParseError> {
+    match read_limited(flate2::read::ZlibDecoder::new(data), ENCODING_ZLIB, max_size) {
+        Err(ParseError::Decode { .. }) => read_limited(flate2::read::DeflateDecoder::new(data), ENCODING_ZLIB, max_size),
+        result => result,
+    }
+}
+
+/// LZ4 decode: an LZ4 frame, up to `max_size` bytes.
+#[cfg(feature = "lz4")]
+pub fn lz4_decode(data: &[u8], max_size: usize) -> Result<Vec<u8>, ParseError> {
+    read_limited(lz4_flex::frame::FrameDecoder::new(data), ENCODING_LZ4, max_size)
+}
 
-    let header = ContainerHeader::from_bytes(data)?;
+/// Zstandard decode: one or more Zstandard frames, up to `max_size` bytes.
+#[cfg(feature = "zstd")]
+pub fn zstd_decode(data: &[u8], max_size: usize) -> Result<Vec<u8>, ParseError> {
+    let decoder = zstd::stream::read::Decoder::new(data).map_err(|e| ParseError::Decode {
+        encoding: ENCODING_ZSTD,
+        message: e.to_string(),
+    })?;
+    read_limited(decoder, ENCODING_ZSTD, max_size)
+}
+
+/// Decode based on encoding type, up to the default
+/// [`Limits::max_buffer_size`].
+///
+/// Unknown encodings, and encodings whose feature is not enabled, are an
+/// error rather than being passed through as raw data.
+pub fn decode(data: &[u8], encoding_id: u8) -> Result<Vec<u8>, ParseError> {
+    decode_limited(data, encoding_id, Limits::DEFAULT_MAX_BUFFER_SIZE)
+}
 
-    if header.ident != ContainerHeader::MAGIC {
-        return Err(ParseError::TypeMismatch {
-            expected: format!("SPC0 magic (0x{:08X})", ContainerHeader::MAGIC),
-            actual: format!("0x{:08X}", header.ident),
+/// Decode based on encoding type, failing if the result would exceed
+/// `max_size` bytes.
+pub fn decode_limited(data: &[u8], encoding_id: u8, max_size: usize) -> Result<Vec<u8>, ParseError> {
+    let Some(encoding) = encoding(encoding_id) else {
+        return Err(ParseError::Decode {
+            encoding: encoding_id,
+            message: "unknown encoding".to_string(),
         });
+    };
+    match encoding.decoder {
+        Some(decoder) => decoder(data, max_size),
+        None => Err(ParseError::Decode {
+            encoding: encoding_id,
+            message: format!(
+                "{} support is not enabled (build with --features {})",
+                encoding.name,
+                encoding.feature.unwrap_or_default()
+            ),
+        }),
     }
+}
+
+/// Decode one buffer of a container within what is left of
+/// `limits.max_total_size`, adding the decoded size to `decoded_total`.
+///
+/// Unencoded buffers are bounded by the file itself and not counted, as in
+/// [`unpack_container_in_place_with`].
+pub(crate) fn decode_counted(
+    data: &[u8],
+    encoding_id: u8,
+    limits: &Limits,
+    decoded_total: &mut usize,
+) -> Result<Vec<u8>, ParseError> {
+    if encoding_id == ENCODING_NONE {
+        return decode_limited(data, encoding_id, limits.max_buffer_size);
+    }
+    let max_size = limits.max_buffer_size.min(limits.max_total_size.saturating_sub(*decoded_total));
+    let decoded = decode_limited(data, encoding_id, max_size).map_err(|e| match e {
+        ParseError::LimitExceeded { .. } if max_size < limits.max_buffer_size => ParseError::LimitExceeded {
+            what: "decoded container".to_string(),
+            limit: limits.max_total_size as u64,
+        },
+        e => e,
+    })?;
+    *decoded_total += decoded.len();
+    Ok(decoded)
+}
 
-    // Make a mutable copy for decryption
+/// Unpack a container: decrypt, decompress, and return StorageObject data.
+pub fn unpack_container(data: &[u8]) -> Result<Vec<Vec<u8>>, ParseError> {
     let mut data = data.to_vec();
+    let buffers = unpack_container_in_place(&mut data)?;
+    Ok(buffers.into_iter().map(Cow::into_owned).collect())
+}
 
-    // Zero out checksum for verification
-    data[4..8].copy_from_slice(&[0, 0, 0, 0]);
-
-    // Decrypt everything after header
-    if data.len() > ContainerHeader::SIZE {
-        decrypt(
-            &mut data[ContainerHeader::SIZE..],
-            ENCRYPTION_KEY,
-            header.seed,
-            BLOCK_SIZE,
-        );
+/// Unpack a container without copying it: `data` is decrypted in place,
+/// and unencoded buffers are returned as slices of it. Only compressed
+/// buffers are allocated.
+///
+/// On success `data` holds the decrypted container with the checksum field
+/// zeroed. Decompression is bounded by the default [`Limits`].
+pub fn unpack_container_in_place(data: &mut [u8]) -> Result<Vec<Cow<'_, [u8]>>, ParseError> {
+    unpack_container_in_place_with(data, &Limits::default())
+}
+
+/// Like [`unpack_container_in_place`], with the given decompression limits.
+pub fn unpack_container_in_place_with<'a>(
+    data: &'a mut [u8],
+    limits: &Limits,
+) -> Result<Vec<Cow<'a, [u8]>>, ParseError> {
+    let buffers = unpack_buffers_in_place(data, limits)?;
+    Ok(buffers.into_iter().map(|(_, buffer)| buffer).collect())
+}
+
+/// A decoded buffer with its file offset if it is stored unencoded.
+pub(crate) type LocatedBuffer<'a> = (Option<u64>, Cow<'a, [u8]>);
+
+/// Like [`unpack_container_in_place`], also returning the file offset of
+/// each buffer that is stored unencoded, for locating errors.
+#[tracing::instrument(name = "unpack_container", level = "debug", skip_all, fields(bytes = data.len()))]
+pub(crate) fn unpack_buffers_in_place<'a>(
+    data: &'a mut [u8],
+    limits: &Limits,
+) -> Result<Vec<LocatedBuffer<'a>>, ParseError> {
+    let reader = ContainerReader::new(data)?.with_limits(*limits);
+    reader
+        .decode_all()?
+        .into_iter()
+        .map(|(entry, buffer)| {
+            let file_offset = (entry.encoding == ENCODING_NONE).then(|| reader.encoded(&entry).map(|(start, _)| start));
+            Ok((file_offset.transpose()?, buffer))
+        })
+        .collect()
+}
+
+/// Pack StorageObject buffers into an encrypted container (inverse of [`unpack_container`]).
+///
+/// Buffers are stored unencoded. The body is zero-padded to a multiple of
+/// 4 bytes, checksummed, then encrypted.
+pub fn pack_container(buffers: &[Vec<u8>], seed: u32) -> Vec<u8> {
+    pack_container_encoded(buffers, seed, ENCODING_NONE).expect("unencoded buffers can always be written")
+}
+
+/// Like [`pack_container`], storing every buffer with the given encoding.
+/// `None` if the encoding has no encoder.
+pub fn pack_container_encoded(buffers: &[Vec<u8>], seed: u32, encoding_id: u8) -> Option<Vec<u8>> {
+    let encoder = encoding(encoding_id)?.encoder?;
+    let table_ofs = ContainerHeader::SIZE as u64;
+    let data_ofs = table_ofs + (buffers.len() * BufferEntry::SIZE) as u64;
+
+    let mut table = Vec::with_capacity(buffers.len() * BufferEntry::SIZE);
+    let mut body = Vec::new();
+    for buffer in buffers {
+        let encoded = encoder(buffer);
+        let entry = BufferEntry {
+            encoding: encoding_id,
+            offset: body.len() as u64,
+            size: encoded.len() as u64,
+        };
+        table.extend_from_slice(&entry.to_bytes());
+        body.extend_from_slice(&encoded);
     }
 
-    // Verify checksum
-    let computed = checksum(&data);
-    if computed != header.checksum {
-        return Err(ParseError::TypeMismatch {
-            expected: format!("checksum 0x{:08X}", header.checksum),
-            actual: format!("0x{:08X}", computed),
-        });
+    let mut header = ContainerHeader {
+        ident: ContainerHeader::MAGIC,
+        checksum: 0,
+        num_buffers: buffers.len() as u64,
+        buffers_table_ofs: table_ofs,
+        seed,
+        buffers_data_ofs: data_ofs,
+    };
+
+    let mut data = Vec::with_capacity(data_ofs as usize + body.len() + 3);
+    data.extend_from_slice(&header.to_bytes());
+    data.extend_from_slice(&table);
+    data.extend_from_slice(&body);
+    while !(data.len() - ContainerHeader::SIZE).is_multiple_of(4) {
+        data.push(0);
     }
 
-    // Parse buffer table
-    let table_start = header.buffers_table_ofs as usize;
-    let data_start = header.buffers_data_ofs as usize;
-    
-    let mut buffers = Vec::new();
-
-    for i in 0..header.num_buffers as usize {
-        let entry_start = table_start + i * BufferEntry::SIZE;
-        if entry_start + BufferEntry::SIZE > data.len() {
-            return Err(ParseError::InvalidOffset {
-                offset: entry_start as u64,
-                size: data.len(),
-            });
+    // Checksum covers the plaintext with the checksum field zeroed
+    header.checksum = checksum(&data);
+    data[4..8].copy_from_slice(&header.checksum.to_le_bytes());
+
+    encrypt(&mut data[ContainerHeader::SIZE..], ENCRYPTION_KEY, seed, BLOCK_SIZE);
+    Some(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_decode_compressed() {
+        let plain: Vec<u8> = (0..1000u32).flat_map(|i| (i % 7).to_le_bytes()).collect();
+
+        let mut zlib = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        zlib.write_all(&plain).unwrap();
+        assert_eq!(decode(&zlib.finish().unwrap(), ENCODING_ZLIB).unwrap(), plain);
+
+        let mut deflate = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        deflate.write_all(&plain).unwrap();
+        assert_eq!(decode(&deflate.finish().unwrap(), ENCODING_ZLIB).unwrap(), plain);
+
+        assert!(decode(&[0xFF; 16], ENCODING_ZLIB).is_err());
+        assert!(decode(&plain, 200).is_err());
+
+        #[cfg(feature = "lz4")]
+        {
+            let mut lz4 = lz4_flex::frame::FrameEncoder::new(Vec::new());
+            lz4.write_all(&plain).unwrap();
+            assert_eq!(decode(&lz4.finish().unwrap(), ENCODING_LZ4).unwrap(), plain);
         }
+        #[cfg(feature = "zstd")]
+        assert_eq!(decode(&zstd::encode_all(&plain[..], 0).unwrap(), ENCODING_ZSTD).unwrap(), plain);
+        #[cfg(not(feature = "zstd"))]
+        assert!(decode(&plain, ENCODING_ZSTD).is_err());
+    }
 
-        let entry = BufferEntry::from_bytes(&data[entry_start..]);
-        
-        let buf_start = data_start + entry.offset as usize;
-        let buf_end = buf_start + entry.size as usize;
-        
-        if buf_end > data.len() {
-            return Err(ParseError::InvalidOffset {
-                offset: buf_end as u64,
-                size: data.len(),
-            });
+    #[test]
+    fn test_decode_limits() {
+        // 1000 RLE8 pairs expand to 255 000 bytes
+        let rle8: Vec<u8> = [255, 0].repeat(1000);
+        assert_eq!(rle8_decode(&rle8, 255_000).unwrap().len(), 255_000);
+        assert!(matches!(rle8_decode(&rle8, 254_999), Err(ParseError::LimitExceeded { .. })));
+
+        let rle0 = [0, 4, 255, 1, 2, 3, 4];
+        assert_eq!(rle0_decode(&rle0, 1020).unwrap().len(), 1020);
+        assert!(rle0_decode(&rle0, 1019).is_err());
+
+        let mut zlib = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::best());
+        zlib.write_all(&vec![0; 1 << 20]).unwrap();
+        let zlib = zlib.finish().unwrap();
+        assert_eq!(decode_limited(&zlib, ENCODING_ZLIB, 1 << 20).unwrap().len(), 1 << 20);
+        assert!(matches!(
+            decode_limited(&zlib, ENCODING_ZLIB, 1000),
+            Err(ParseError::LimitExceeded { limit: 1000, .. })
+        ));
+    }
+
+    #[test]
+    fn test_encoded_containers() {
+        // Repeated doubles, a long run of one byte, and a ragged tail
+        let mut plain: Vec<u8> = [1.5f64, 1.5, 1.5, 2.0].iter().flat_map(|v| v.to_le_bytes()).collect();
+        plain.extend([7; 600]);
+        plain.extend([1, 2, 3]);
+        let buffers = vec![plain.clone(), vec![9; 16]];
+
+        for id in [ENCODING_NONE, ENCODING_RLE8, ENCODING_RLE0, ENCODING_ZLIB] {
+            let bytes = pack_container_encoded(&buffers, 42, id).unwrap();
+            assert_eq!(unpack_container(&bytes).unwrap(), buffers, "encoding {}", id);
         }
+        assert_eq!(rle0_decode(&rle0_encode(&plain), plain.len()).unwrap(), plain);
+        assert!(rle0_encode(&plain).len() < plain.len() / 4);
+        assert!(pack_container_encoded(&buffers, 0, ENCODING_LZ4).is_none());
+    }
 
-        let encoded_data = &data[buf_start..buf_end];
-        let decoded_data = decode(encoded_data, entry.encoding);
-        buffers.push(decoded_data);
+    #[test]
+    fn test_container_versions() {
+        let mut bytes = pack_container(&[vec![0u8; 16]], 0);
+        assert_eq!(ContainerHeader::version_of(ContainerHeader::MAGIC), Some(0));
+        assert!(unpack_container(&bytes).is_ok());
+
+        bytes[0] = b'1';
+        assert!(matches!(unpack_container(&bytes), Err(ParseError::UnsupportedVersion(1))));
+
+        bytes[0..4].copy_from_slice(b"PK\x03\x04");
+        assert!(matches!(unpack_container(&bytes), Err(ParseError::BadMagic { found: 0x04034B50 })));
     }
 
-    Ok(buffers)
+    #[test]
+    fn test_decrypt_regression() {
+        // Output of the original word-cast implementation
+        const EXPECTED: [u8; 64] = [
+            0x86, 0xfd, 0x83, 0xb3, 0x12, 0xf3, 0x76, 0x25, 0x1a, 0x25, 0x40, 0x8b, 0x36, 0xd3, 0x6e, 0x95,
+            0xd4, 0xc6, 0xe5, 0xd2, 0x0b, 0x86, 0xe6, 0x72, 0x7e, 0x84, 0x7c, 0x65, 0xc5, 0x7c, 0x08, 0xeb,
+            0x8e, 0xff, 0x0b, 0xa6, 0x30, 0xd8, 0xeb, 0x6a, 0x4e, 0x98, 0xe7, 0xa3, 0xe8, 0x94, 0xa7, 0xc5,
+            0x74, 0x79, 0xd4, 0xce, 0x41, 0xb7, 0xc0, 0xee, 0xaa, 0x40, 0xba, 0xd4, 0xe7, 0x58, 0xb9, 0x2c,
+        ];
+        let input: Vec<u8> = (0..=63u8).map(|b| b.wrapping_mul(37)).collect();
+
+        let mut data = input.clone();
+        decrypt(&mut data, ENCRYPTION_KEY, 0x1234_5678, BLOCK_SIZE);
+        assert_eq!(data, EXPECTED);
+
+        // Same result on an unaligned slice, and encrypt inverts it
+        let mut unaligned = [0u8; 65];
+        unaligned[1..].copy_from_slice(&input);
+        decrypt(&mut unaligned[1..], ENCRYPTION_KEY, 0x1234_5678, BLOCK_SIZE);
+        assert_eq!(unaligned[1..], EXPECTED);
+        encrypt(&mut unaligned[1..], ENCRYPTION_KEY, 0x1234_5678, BLOCK_SIZE);
+        assert_eq!(unaligned[1..], input[..]);
+    }
 }
@@ -3,11 +3,25 @@
 use thiserror::Error;
 
 /// Errors that can occur during parsing.
+///
+/// Each kind of failure has its own variant and a stable numeric
+/// [`code`](Self::code); element-level errors are wrapped in
+/// [`Located`](Self::Located), so match on [`root`](Self::root) to get the
+/// failure itself.
 #[derive(Error, Debug)]
 pub enum ParseError {
     #[error("File too small: expected at least {expected} bytes, got {actual}")]
     FileTooSmall { expected: usize, actual: usize },
 
+    #[error("Not an SPC container: magic 0x{found:08X}, expected 0x{:08X}", super::ContainerHeader::MAGIC)]
+    BadMagic { found: u32 },
+
+    #[error("Checksum mismatch: header says 0x{stored:08X}, contents give 0x{computed:08X}")]
+    ChecksumMismatch { stored: u32, computed: u32 },
+
+    #[error("No buffers in container")]
+    NoBuffers,
+
     #[error("Invalid offset: {offset} exceeds buffer size {size}")]
     InvalidOffset { offset: u64, size: usize },
 
@@ -28,6 +42,176 @@ pub enum ParseError {
 
     #[error("Type mismatch: expected {expected}, got {actual}")]
     TypeMismatch { expected: String, actual: String },
+
+    #[error("{field} is not valid UTF-8")]
+    InvalidUtf8 { field: String },
+
+    #[error("{source} ({})", location(.path, .buffer, .offset, .absolute))]
+    Located {
+        /// Object path within the buffer, e.g. `data/m_blank[512]`
+        path: String,
+        /// Index of the container buffer, if known
+        buffer: Option<usize>,
+        /// Byte offset of the failing element: absolute in the file if
+        /// `absolute`, otherwise within the (decoded) buffer
+        offset: u64,
+        absolute: bool,
+        source: Box<ParseError>,
+    },
+
+    #[error("Unsupported container version {0} (SPC{0}); supported: SPC0")]
+    UnsupportedVersion(u8),
+
+    #[error("Rejected in strict mode: {0}")]
+    Strict(String),
+
+    #[error("Cannot decode buffer with encoding {encoding}: {message}")]
+    Decode { encoding: u8, message: String },
+
+    #[error("{what} exceeds the limit of {limit} bytes")]
+    LimitExceeded { what: String, limit: u64 },
+
+    #[error("Invalid sidecar file {path}: {message}")]
+    InvalidSidecar { path: String, message: String },
+
+    #[error("Invalid calibration file {path}: {message}")]
+    InvalidCalibration { path: String, message: String },
+
+    #[error("Invalid {format} file: {message}")]
+    InvalidImport { format: String, message: String },
+}
+
+/// Where in reading a file an error occurred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Reading the file
+    Io,
+    /// Container header, checksum and buffer table
+    Container,
+    /// Decompressing a buffer
+    Decode,
+    /// StorageObject structure within a buffer
+    Object,
+    /// Spectrum fields of the objects
+    Extract,
+    /// Sidecar, calibration and other vendors' files
+    Import,
+}
+
+impl ParseError {
+    /// The failure itself, without the [`Located`](Self::Located) wrapper.
+    pub fn root(&self) -> &ParseError {
+        match self {
+            ParseError::Located { source, .. } => source.root(),
+            other => other,
+        }
+    }
+
+    /// Stable numeric code of the failure class, for callers that log or
+    /// match errors across versions. The hundreds give the [`Stage`].
+    pub fn code(&self) -> u16 {
+        match self.root() {
+            ParseError::Io(_) => 100,
+            ParseError::FileTooSmall { .. } => 201,
+            ParseError::BadMagic { .. } => 202,
+            ParseError::UnsupportedVersion(_) => 203,
+            ParseError::ChecksumMismatch { .. } => 204,
+            ParseError::NoBuffers => 205,
+            ParseError::InvalidOffset { .. } => 206,
+            ParseError::Decode { .. } => 301,
+            ParseError::LimitExceeded { .. } => 302,
+            ParseError::UnterminatedString(_) => 401,
+            ParseError::VarCountMismatch { .. } => 402,
+            ParseError::ChildCountMismatch { .. } => 403,
+            ParseError::TypeMismatch { .. } => 404,
+            ParseError::MissingField(_) => 501,
+            ParseError::InvalidUtf8 { .. } => 502,
+            ParseError::Strict(_) => 503,
+            ParseError::InvalidSidecar { .. } => 601,
+            ParseError::InvalidCalibration { .. } => 602,
+            ParseError::InvalidImport { .. } => 603,
+            ParseError::Located { .. } => unreachable!("root is never located"),
+        }
+    }
+
+    /// The stage the failure occurred in.
+    pub fn stage(&self) -> Stage {
+        match (self, self.code() / 100) {
+            (_, 1) => Stage::Io,
+            // A short header or bad offset within a buffer's object
+            (ParseError::Located { buffer: Some(_), .. }, 2) => Stage::Object,
+            (_, 2) => Stage::Container,
+            (_, 3) => Stage::Decode,
+            (_, 4) => Stage::Object,
+            (_, 5) => Stage::Extract,
+            _ => Stage::Import,
+        }
+    }
+
+    /// Record where in the current object an error occurred. `path` is
+    /// relative to the object, e.g. `[512]` for a vector element.
+    pub(crate) fn at(self, offset: u64, path: impl Into<String>) -> Self {
+        match self {
+            located @ ParseError::Located { .. } => located,
+            source => ParseError::Located {
+                path: path.into(),
+                buffer: None,
+                offset,
+                absolute: false,
+                source: Box::new(source),
+            },
+        }
+    }
+
+    /// Prefix the path of a located error with the name of the enclosing
+    /// object.
+    pub(crate) fn within(self, name: &str) -> Self {
+        match self {
+            ParseError::Located { path, buffer, offset, absolute, source } => ParseError::Located {
+                path: match path.chars().next() {
+                    None => name.to_string(),
+                    Some('[') => format!("{}{}", name, path),
+                    Some(_) => format!("{}/{}", name, path),
+                },
+                buffer,
+                offset,
+                absolute,
+                source,
+            },
+            other => other,
+        }
+    }
+
+    /// Attach the container buffer of a located error, and make its offset
+    /// absolute if the buffer is stored unencoded at `file_offset`.
+    pub(crate) fn in_buffer(self, index: usize, file_offset: Option<u64>) -> Self {
+        match self {
+            ParseError::Located { path, offset, source, .. } => ParseError::Located {
+                path,
+                buffer: Some(index),
+                offset: offset.saturating_add(file_offset.unwrap_or(0)),
+                absolute: file_offset.is_some(),
+                source,
+            },
+            other => other,
+        }
+    }
+}
+
+fn location(path: &str, buffer: &Option<usize>, offset: &u64, absolute: &bool) -> String {
+    let mut parts = Vec::new();
+    if !path.is_empty() {
+        parts.push(path.to_string());
+    }
+    if let Some(buffer) = buffer {
+        parts.push(format!("buffer[{}]", buffer));
+    }
+    parts.push(if *absolute {
+        format!("file offset {}", offset)
+    } else {
+        format!("offset {} in buffer", offset)
+    });
+    parts.join(", ")
 }
 
 /// Buffer section descriptor {offset, size}.
@@ -45,6 +229,14 @@ impl BufferSection {
             size: u64::from_le_bytes(data[8..16].try_into().unwrap()),
         }
     }
+
+    /// Write as 16 bytes.
+    pub fn to_bytes(&self) -> [u8; 16] {
+        let mut out = [0u8; 16];
+        out[0..8].copy_from_slice(&self.offset.to_le_bytes());
+        out[8..16].copy_from_slice(&self.size.to_le_bytes());
+        out
+    }
 }
 
 /// Main header structure (96 bytes, packed).
@@ -85,6 +277,21 @@ impl PackHeader {
             data: BufferSection::from_bytes(&data[88..104]),
         })
     }
+
+    /// Write header to bytes.
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut out = [0u8; Self::SIZE];
+        out[0..8].copy_from_slice(&self.type_name_offset.to_le_bytes());
+        out[8..16].copy_from_slice(&self.owner_offset.to_le_bytes());
+        out[16..24].copy_from_slice(&self.name_offset.to_le_bytes());
+        out[24..32].copy_from_slice(&self.num_vars.to_le_bytes());
+        out[32..40].copy_from_slice(&self.num_children.to_le_bytes());
+        out[40..56].copy_from_slice(&self.strings.to_bytes());
+        out[56..72].copy_from_slice(&self.vars.to_bytes());
+        out[72..88].copy_from_slice(&self.children.to_bytes());
+        out[88..104].copy_from_slice(&self.data.to_bytes());
+        out
+    }
 }
 
 /// Variable descriptor (40 bytes, packed).
@@ -110,6 +317,17 @@ impl PackVar {
             bytes_size: u64::from_le_bytes(data[32..40].try_into().unwrap()),
         }
     }
+
+    /// Write to bytes.
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut out = [0u8; Self::SIZE];
+        out[0..8].copy_from_slice(&self.owner_offset.to_le_bytes());
+        out[8..16].copy_from_slice(&self.name_offset.to_le_bytes());
+        out[16..24].copy_from_slice(&self.type_offset.to_le_bytes());
+        out[24..32].copy_from_slice(&self.data_offset.to_le_bytes());
+        out[32..40].copy_from_slice(&self.bytes_size.to_le_bytes());
+        out
+    }
 }
 
 /// Child object descriptor (32 bytes, packed).
@@ -133,4 +351,35 @@ impl PackChild {
             size: u64::from_le_bytes(data[24..32].try_into().unwrap()),
         }
     }
+
+    /// Write to bytes.
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut out = [0u8; Self::SIZE];
+        out[0..8].copy_from_slice(&self.owner_offset.to_le_bytes());
+        out[8..16].copy_from_slice(&self.name_offset.to_le_bytes());
+        out[16..24].copy_from_slice(&self.data_offset.to_le_bytes());
+        out[24..32].copy_from_slice(&self.size.to_le_bytes());
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_in_buffer_offset() {
+        let located = |offset: u64, file_offset: Option<u64>| match ParseError::NoBuffers
+            .at(offset, "[2]")
+            .within("m_blank")
+            .in_buffer(1, file_offset)
+        {
+            ParseError::Located { path, buffer, offset, absolute, .. } => (path, buffer, offset, absolute),
+            other => panic!("unexpected error: {:?}", other),
+        };
+        assert_eq!(located(16, Some(100)), ("m_blank[2]".to_string(), Some(1), 116, true));
+        assert_eq!(located(16, None), ("m_blank[2]".to_string(), Some(1), 16, false));
+        // An offset near the end of the u64 range saturates instead of overflowing
+        assert_eq!(located(u64::MAX - 8, Some(100)).2, u64::MAX);
+    }
 }
@@ -1,9 +1,22 @@
 //! Binary parser for the custom storage format.
 
+mod builder;
 mod container;
 mod header;
 mod object;
+mod options;
+mod parallel;
+mod reader;
+mod recover;
+mod view;
 
+pub use builder::*;
 pub use container::*;
 pub use header::*;
 pub use object::*;
+pub use options::{Limits, ParseOptions, Warning, WarningKind};
+pub(crate) use options::Anomalies;
+pub(crate) use parallel::par_map;
+pub use reader::*;
+pub use recover::*;
+pub use view::*;
@@ -1,19 +1,24 @@
 //! StorageObject reconstruction from binary format.
 
-use super::header::{PackChild, PackHeader, PackVar, ParseError};
+use super::header::{BufferSection, PackChild, PackHeader, PackVar, ParseError};
+use super::view::ObjectView;
+use serde::{Serialize, Serializer};
 use std::collections::HashMap;
+use std::fmt::Write;
 
 /// A variable stored in the object.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Variable {
     pub owner: String,
     pub name: String,
     pub type_name: String,
+    /// Raw value bytes, serialized as a hex string
+    #[serde(serialize_with = "serialize_hex")]
     pub data: Vec<u8>,
 }
 
 /// Reconstructed StorageObject from binary format.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct StorageObject {
     pub type_name: String,
     pub owner_name: String,
@@ -24,128 +29,11 @@ pub struct StorageObject {
 
 impl StorageObject {
     /// Parse a StorageObject from raw bytes.
+    ///
+    /// Use [`ObjectView`] to read only parts of a large object without
+    /// copying it.
     pub fn from_bytes(data: &[u8]) -> Result<Self, ParseError> {
-        let header = PackHeader::from_bytes(data)?;
-
-        // Extract strings section
-        let strings_start = header.strings.offset as usize;
-        let strings_end = strings_start + header.strings.size as usize;
-        if strings_end > data.len() {
-            return Err(ParseError::InvalidOffset {
-                offset: header.strings.offset + header.strings.size,
-                size: data.len(),
-            });
-        }
-        let strings_section = &data[strings_start..strings_end];
-
-        // Extract data section
-        let data_start = header.data.offset as usize;
-        let data_end = data_start + header.data.size as usize;
-        if data_end > data.len() {
-            return Err(ParseError::InvalidOffset {
-                offset: header.data.offset + header.data.size,
-                size: data.len(),
-            });
-        }
-        let data_section = &data[data_start..data_end];
-
-        // Read type name, owner, var name
-        let type_name = read_string(strings_section, header.type_name_offset)?;
-        let owner_name = read_string(strings_section, header.owner_offset)?;
-        let var_name = read_string(strings_section, header.name_offset)?;
-
-        // Parse variables
-        let vars_start = header.vars.offset as usize;
-        let vars_end = vars_start + header.vars.size as usize;
-        if vars_end > data.len() {
-            return Err(ParseError::InvalidOffset {
-                offset: header.vars.offset + header.vars.size,
-                size: data.len(),
-            });
-        }
-        let vars_section = &data[vars_start..vars_end];
-
-        let expected_vars_size = header.num_vars as usize * PackVar::SIZE;
-        if header.vars.size as usize != expected_vars_size {
-            return Err(ParseError::VarCountMismatch {
-                expected: header.num_vars,
-                actual: header.vars.size as usize / PackVar::SIZE,
-            });
-        }
-
-        let mut variables = Vec::with_capacity(header.num_vars as usize);
-        for i in 0..header.num_vars as usize {
-            let var_bytes = &vars_section[i * PackVar::SIZE..(i + 1) * PackVar::SIZE];
-            let pack_var = PackVar::from_bytes(var_bytes);
-
-            let owner = read_string(strings_section, pack_var.owner_offset)?;
-            let name = read_string(strings_section, pack_var.name_offset)?;
-            let type_name = read_string(strings_section, pack_var.type_offset)?;
-
-            let var_data_start = pack_var.data_offset as usize;
-            let var_data_end = var_data_start + pack_var.bytes_size as usize;
-            if var_data_end > data_section.len() {
-                return Err(ParseError::InvalidOffset {
-                    offset: pack_var.data_offset + pack_var.bytes_size,
-                    size: data_section.len(),
-                });
-            }
-            let var_data = data_section[var_data_start..var_data_end].to_vec();
-
-            variables.push(Variable {
-                owner,
-                name,
-                type_name,
-                data: var_data,
-            });
-        }
-
-        // Parse children
-        let children_start = header.children.offset as usize;
-        let children_end = children_start + header.children.size as usize;
-        if children_end > data.len() {
-            return Err(ParseError::InvalidOffset {
-                offset: header.children.offset + header.children.size,
-                size: data.len(),
-            });
-        }
-        let children_section = &data[children_start..children_end];
-
-        let expected_children_size = header.num_children as usize * PackChild::SIZE;
-        if header.children.size as usize != expected_children_size {
-            return Err(ParseError::ChildCountMismatch {
-                expected: header.num_children,
-                actual: header.children.size as usize / PackChild::SIZE,
-            });
-        }
-
-        let mut children = Vec::with_capacity(header.num_children as usize);
-        for i in 0..header.num_children as usize {
-            let child_bytes = &children_section[i * PackChild::SIZE..(i + 1) * PackChild::SIZE];
-            let pack_child = PackChild::from_bytes(child_bytes);
-
-            let child_data_start = pack_child.data_offset as usize;
-            let child_data_end = child_data_start + pack_child.size as usize;
-            if child_data_end > data_section.len() {
-                return Err(ParseError::InvalidOffset {
-                    offset: pack_child.data_offset + pack_child.size,
-                    size: data_section.len(),
-                });
-            }
-            let child_data = &data_section[child_data_start..child_data_end];
-
-            // Recursively parse child
-            let child_obj = StorageObject::from_bytes(child_data)?;
-            children.push(child_obj);
-        }
-
-        Ok(Self {
-            type_name,
-            owner_name,
-            var_name,
-            variables,
-            children,
-        })
+        ObjectView::parse(data)?.to_storage_object()
     }
 
     /// Find a variable by name.
@@ -162,24 +50,99 @@ impl StorageObject {
     pub fn vars_by_name(&self) -> HashMap<&str, &Variable> {
         self.variables.iter().map(|v| (v.name.as_str(), v)).collect()
     }
+
+    /// Serialize to the packed binary layout.
+    ///
+    /// Sections are laid out as header, strings, variables, children, data.
+    /// Children are serialized recursively into the data section after the
+    /// variable payloads.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut strings = StringTable::default();
+        let type_name_offset = strings.offset(&self.type_name);
+        let owner_offset = strings.offset(&self.owner_name);
+        let name_offset = strings.offset(&self.var_name);
+
+        let mut data_section = Vec::new();
+        let mut vars_section = Vec::with_capacity(self.variables.len() * PackVar::SIZE);
+        for var in &self.variables {
+            let pack_var = PackVar {
+                owner_offset: strings.offset(&var.owner),
+                name_offset: strings.offset(&var.name),
+                type_offset: strings.offset(&var.type_name),
+                data_offset: data_section.len() as u64,
+                bytes_size: var.data.len() as u64,
+            };
+            vars_section.extend_from_slice(&pack_var.to_bytes());
+            data_section.extend_from_slice(&var.data);
+        }
+
+        let mut children_section = Vec::with_capacity(self.children.len() * PackChild::SIZE);
+        for child in &self.children {
+            let child_bytes = child.to_bytes();
+            let pack_child = PackChild {
+                owner_offset: strings.offset(&child.owner_name),
+                name_offset: strings.offset(&child.var_name),
+                data_offset: data_section.len() as u64,
+                size: child_bytes.len() as u64,
+            };
+            children_section.extend_from_slice(&pack_child.to_bytes());
+            data_section.extend_from_slice(&child_bytes);
+        }
+
+        let strings = strings.bytes;
+        let strings_offset = PackHeader::SIZE as u64;
+        let vars_offset = strings_offset + strings.len() as u64;
+        let children_offset = vars_offset + vars_section.len() as u64;
+        let data_offset = children_offset + children_section.len() as u64;
+
+        let header = PackHeader {
+            type_name_offset,
+            owner_offset,
+            name_offset,
+            num_vars: self.variables.len() as u64,
+            num_children: self.children.len() as u64,
+            strings: BufferSection { offset: strings_offset, size: strings.len() as u64 },
+            vars: BufferSection { offset: vars_offset, size: vars_section.len() as u64 },
+            children: BufferSection { offset: children_offset, size: children_section.len() as u64 },
+            data: BufferSection { offset: data_offset, size: data_section.len() as u64 },
+        };
+
+        let mut out = Vec::with_capacity(data_offset as usize + data_section.len());
+        out.extend_from_slice(&header.to_bytes());
+        out.extend_from_slice(&strings);
+        out.extend_from_slice(&vars_section);
+        out.extend_from_slice(&children_section);
+        out.extend_from_slice(&data_section);
+        out
+    }
 }
 
-/// Read a null-terminated string from the strings section.
-fn read_string(strings: &[u8], offset: u64) -> Result<String, ParseError> {
-    let start = offset as usize;
-    if start >= strings.len() {
-        return Err(ParseError::InvalidOffset {
-            offset,
-            size: strings.len(),
-        });
+/// Serialize raw bytes as a lowercase hex string.
+fn serialize_hex<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    let mut hex = String::with_capacity(data.len() * 2);
+    for byte in data {
+        let _ = write!(hex, "{:02x}", byte);
     }
+    serializer.serialize_str(&hex)
+}
 
-    let slice = &strings[start..];
-    let end = slice
-        .iter()
-        .position(|&b| b == 0)
-        .ok_or(ParseError::UnterminatedString(offset))?;
+/// Deduplicating builder for a strings section.
+#[derive(Default)]
+struct StringTable {
+    bytes: Vec<u8>,
+    offsets: HashMap<String, u64>,
+}
 
-    String::from_utf8(slice[..end].to_vec())
-        .map_err(|_| ParseError::UnterminatedString(offset))
+impl StringTable {
+    /// Offset of a string in the section, appending it if new.
+    fn offset(&mut self, s: &str) -> u64 {
+        if let Some(&offset) = self.offsets.get(s) {
+            return offset;
+        }
+        let offset = self.bytes.len() as u64;
+        self.bytes.extend_from_slice(s.as_bytes());
+        self.bytes.push(0);
+        self.offsets.insert(s.to_string(), offset);
+        offset
+    }
 }
@@ -0,0 +1,117 @@
+//! Parsing options.
+
+use super::header::ParseError;
+
+/// Options for [`SpcFile::from_bytes_with`](crate::SpcFile::from_bytes_with).
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// Fail on anomalies (unreadable, unknown or duplicate objects,
+    /// unexpected value sizes, invalid UTF-8) instead of skipping them
+    /// with a warning.
+    pub strict: bool,
+    /// Limits on decompressed data
+    pub limits: Limits,
+    /// Leave `wavelength_axis` and `raman_shift_axis` unset; outputs and
+    /// [`SpcFile::wavelengths`](crate::SpcFile::wavelengths) generate them
+    /// from the calibration when needed, and
+    /// [`SpcFile::generate_axes`](crate::SpcFile::generate_axes) stores
+    /// them. Saves time and memory when only the intensities are used.
+    pub lazy_axes: bool,
+    /// Do not decode the blank spectrum; `SpcFile::blank` stays empty.
+    /// Saves memory for large files whose blank is not needed.
+    pub skip_blank: bool,
+}
+
+/// Limits on the memory decompression may allocate, so that a small
+/// malicious file cannot expand to gigabytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum decoded size of one buffer, in bytes
+    pub max_buffer_size: usize,
+    /// Maximum decoded size of all compressed buffers of a container
+    /// together, in bytes
+    pub max_total_size: usize,
+}
+
+impl Limits {
+    /// Default maximum decoded size of one buffer (256 MiB).
+    pub const DEFAULT_MAX_BUFFER_SIZE: usize = 256 << 20;
+    /// Default maximum decoded size of a container (1 GiB).
+    pub const DEFAULT_MAX_TOTAL_SIZE: usize = 1 << 30;
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_buffer_size: Self::DEFAULT_MAX_BUFFER_SIZE,
+            max_total_size: Self::DEFAULT_MAX_TOTAL_SIZE,
+        }
+    }
+}
+
+/// A recoverable oddity that was skipped while parsing (an error in strict
+/// mode). Displays as its message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub kind: WarningKind,
+    pub message: String,
+}
+
+/// What a [`Warning`] is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningKind {
+    /// A StorageObject, calibration or config that could not be read and
+    /// was left out
+    UnreadableObject,
+    /// A top-level object the converter does not interpret (kept as an
+    /// extra)
+    UnknownObject,
+    /// A second object of the same name; the last one is used
+    DuplicateObject,
+    /// A string that is not valid UTF-8 (read lossily)
+    InvalidUtf8,
+    /// A config value of unexpected size, or without a value
+    UnexpectedValue,
+    /// A calibration without coefficients, or with more than there are points
+    CalibrationLength,
+    /// A blank with a different number of points than the data
+    BlankLength,
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Collects anomalies as warnings, or turns them into errors in strict mode.
+#[derive(Debug, Default)]
+pub(crate) struct Anomalies {
+    strict: bool,
+    pub warnings: Vec<Warning>,
+}
+
+impl Anomalies {
+    pub fn new(options: &ParseOptions) -> Self {
+        Self {
+            strict: options.strict,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Record an anomaly; an error in strict mode.
+    pub fn report(&mut self, kind: WarningKind, message: impl Into<String>) -> Result<(), ParseError> {
+        let message = message.into();
+        if self.strict {
+            return Err(ParseError::Strict(message));
+        }
+        tracing::debug!("{:?}: {}", kind, message);
+        self.warnings.push(Warning { kind, message });
+        Ok(())
+    }
+
+    /// The warnings as messages.
+    pub fn messages(self) -> Vec<String> {
+        self.warnings.into_iter().map(|warning| warning.message).collect()
+    }
+}
@@ -0,0 +1,22 @@
+//! Optional data parallelism (the `parallel` feature).
+
+/// Map `f` over `items` (with their index), in parallel with the `parallel`
+/// feature. Results are in input order either way.
+#[cfg(feature = "parallel")]
+pub(crate) fn par_map<'a, T: Sync, R: Send>(items: &'a [T], f: impl Fn(usize, &'a T) -> R + Sync + Send) -> Vec<R> {
+    use rayon::prelude::*;
+    items.par_iter().enumerate().map(|(i, item)| f(i, item)).collect()
+}
+
+#[cfg(not(feature = "parallel"))]
+pub(crate) fn par_map<'a, T: Sync, R: Send>(items: &'a [T], f: impl Fn(usize, &'a T) -> R + Sync + Send) -> Vec<R> {
+    items.iter().enumerate().map(|(i, item)| f(i, item)).collect()
+}
+
+/// How many items to process at once, to bound what is held in memory.
+pub(crate) fn batch_size() -> usize {
+    #[cfg(feature = "parallel")]
+    return rayon::current_num_threads().max(1);
+    #[cfg(not(feature = "parallel"))]
+    return 1;
+}
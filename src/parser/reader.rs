@@ -0,0 +1,337 @@
+//! Lazy access to the buffers of a container.
+
+use super::container::{
+    checksum, decode_limited, decrypt, BufferEntry, ContainerHeader, BLOCK_SIZE, ENCODING_NONE, ENCRYPTION_KEY,
+};
+use super::header::ParseError;
+use super::options::Limits;
+use super::parallel::{batch_size, par_map};
+use std::borrow::Cow;
+
+/// A buffer table entry with the decoded buffer.
+pub type DecodedBuffer<'a> = (BufferEntry, Cow<'a, [u8]>);
+
+/// A decrypted container whose buffers are decoded one at a time, on
+/// demand.
+///
+/// ```no_run
+/// # use spc_converter::parser::ContainerReader;
+/// let mut bytes = std::fs::read("spectrum.spc")?;
+/// let reader = ContainerReader::new(&mut bytes)?;
+/// for entry in reader.entries() {
+///     println!("{:?}", entry?);
+/// }
+/// // Only the first buffer is decoded
+/// let first = reader.buffer(0)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct ContainerReader<'a> {
+    header: ContainerHeader,
+    /// The decrypted container, checksum field zeroed
+    data: &'a [u8],
+    limits: Limits,
+}
+
+impl<'a> ContainerReader<'a> {
+    /// Check the header, decrypt `data` in place and verify the checksum.
+    /// No buffer is decoded yet.
+    pub fn new(data: &'a mut [u8]) -> Result<Self, ParseError> {
+        let header = ContainerHeader::parse(data)?;
+
+        // Zero out checksum for verification
+        data[4..8].copy_from_slice(&[0, 0, 0, 0]);
+
+        // Decrypt everything after header
+        if data.len() > ContainerHeader::SIZE {
+            decrypt(&mut data[ContainerHeader::SIZE..], ENCRYPTION_KEY, header.seed, BLOCK_SIZE);
+        }
+
+        // Verify checksum
+        let computed = checksum(data);
+        if computed != header.checksum {
+            return Err(ParseError::ChecksumMismatch {
+                stored: header.checksum,
+                computed,
+            });
+        }
+        tracing::debug!(
+            "container: {} bytes, {} buffers, table at {}",
+            data.len(),
+            header.num_buffers,
+            header.buffers_table_ofs
+        );
+
+        Ok(Self {
+            header,
+            data,
+            limits: Limits::default(),
+        })
+    }
+
+    /// Use the given decompression limits instead of the defaults.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// The container header.
+    pub fn header(&self) -> &ContainerHeader {
+        &self.header
+    }
+
+    /// Number of buffers, as listed in the header.
+    pub fn len(&self) -> usize {
+        self.header.num_buffers as usize
+    }
+
+    /// Whether the container lists no buffers.
+    pub fn is_empty(&self) -> bool {
+        self.header.num_buffers == 0
+    }
+
+    /// The `index`-th buffer table entry.
+    pub fn entry(&self, index: usize) -> Result<BufferEntry, ParseError> {
+        let start = (index as u64)
+            .checked_mul(BufferEntry::SIZE as u64)
+            .and_then(|offset| offset.checked_add(self.header.buffers_table_ofs));
+        match start.filter(|&start| start.saturating_add(BufferEntry::SIZE as u64) <= self.data.len() as u64) {
+            Some(start) => Ok(BufferEntry::from_bytes(&self.data[start as usize..])),
+            None => Err(ParseError::InvalidOffset {
+                offset: start.unwrap_or(u64::MAX),
+                size: self.data.len(),
+            }),
+        }
+    }
+
+    /// All buffer table entries, without decoding any buffer.
+    pub fn entries(&self) -> impl Iterator<Item = Result<BufferEntry, ParseError>> + '_ {
+        (0..self.len()).map(|i| self.entry(i))
+    }
+
+    /// File offset and encoded bytes of a buffer.
+    pub fn encoded(&self, entry: &BufferEntry) -> Result<(u64, &'a [u8]), ParseError> {
+        let start = self.header.buffers_data_ofs.checked_add(entry.offset);
+        let end = start.and_then(|start| start.checked_add(entry.size));
+        match (start, end) {
+            (Some(start), Some(end)) if end <= self.data.len() as u64 => {
+                Ok((start, &self.data[start as usize..end as usize]))
+            }
+            _ => Err(ParseError::InvalidOffset {
+                offset: end.unwrap_or(u64::MAX),
+                size: self.data.len(),
+            }),
+        }
+    }
+
+    /// Decode the `index`-th buffer. Unencoded buffers are borrowed.
+    pub fn buffer(&self, index: usize) -> Result<Cow<'a, [u8]>, ParseError> {
+        let entry = self.entry(index)?;
+        self.decode(index, &entry, self.limits.max_buffer_size)
+    }
+
+    /// Iterate over `(entry, decoded bytes)`, decoding each buffer only when
+    /// it is reached. Compressed buffers together may not exceed
+    /// [`Limits::max_total_size`].
+    pub fn buffers(&self) -> Buffers<'_, 'a> {
+        Buffers {
+            reader: self,
+            index: 0,
+            decoded_total: 0,
+        }
+    }
+
+    /// Decode all buffers, in table order. With the `parallel` feature,
+    /// compressed buffers are decoded concurrently, one batch of as many
+    /// as there are threads at a time. Each buffer of a batch may use an
+    /// equal share of what remains of the total limit; one that needs more
+    /// is decoded again on its own with what the others left, so that the
+    /// buffers held never exceed the total.
+    pub fn decode_all(&self) -> Result<Vec<DecodedBuffer<'a>>, ParseError> {
+        let entries = self.entries().collect::<Result<Vec<_>, _>>()?;
+        let mut buffers = Vec::with_capacity(entries.len());
+        let mut decoded_total = 0usize;
+        let owned_len = |buffer: &Result<Cow<'a, [u8]>, ParseError>| match buffer {
+            Ok(Cow::Owned(decoded)) => decoded.len(),
+            _ => 0,
+        };
+
+        for (batch_index, batch) in entries.chunks(batch_size()).enumerate() {
+            let first = batch_index * batch_size();
+            let remaining = self.limits.max_total_size - decoded_total;
+            let share = self.limits.max_buffer_size.min(remaining / batch.len());
+            let mut decoded = par_map(batch, |i, entry| self.decode(first + i, entry, share));
+            if share < self.limits.max_buffer_size {
+                for i in 0..batch.len() {
+                    if matches!(&decoded[i], Err(e) if matches!(e.root(), ParseError::LimitExceeded { .. })) {
+                        let held: usize = decoded.iter().map(owned_len).sum();
+                        let max_size = self.limits.max_buffer_size.min(remaining - held);
+                        decoded[i] = self.decode(first + i, &batch[i], max_size);
+                    }
+                }
+            }
+            for (i, (entry, buffer)) in batch.iter().zip(decoded).enumerate() {
+                let buffer = buffer?;
+                if let Cow::Owned(ref decoded) = buffer {
+                    decoded_total += decoded.len();
+                    if decoded_total > self.limits.max_total_size {
+                        let start = self.encoded(entry)?.0;
+                        return Err(ParseError::LimitExceeded {
+                            what: "decoded container".to_string(),
+                            limit: self.limits.max_total_size as u64,
+                        }
+                        .at(0, "")
+                        .in_buffer(first + i, Some(start)));
+                    }
+                }
+                buffers.push((*entry, buffer));
+            }
+        }
+
+        Ok(buffers)
+    }
+
+    /// Decode a buffer up to `max_size` bytes; a smaller `max_size` than
+    /// the per-buffer limit is what remains of the container's total.
+    fn decode(&self, index: usize, entry: &BufferEntry, max_size: usize) -> Result<Cow<'a, [u8]>, ParseError> {
+        let (start, encoded) = self.encoded(entry)?;
+        tracing::trace!("buffer[{}]: encoding {}, {} bytes at {}", index, entry.encoding, encoded.len(), start);
+        if entry.encoding == ENCODING_NONE {
+            return Ok(Cow::Borrowed(encoded));
+        }
+        decode_limited(encoded, entry.encoding, max_size).map(Cow::Owned).map_err(|e| {
+            let e = match e {
+                ParseError::LimitExceeded { .. } if max_size < self.limits.max_buffer_size => {
+                    ParseError::LimitExceeded {
+                        what: "decoded container".to_string(),
+                        limit: self.limits.max_total_size as u64,
+                    }
+                }
+                e => e,
+            };
+            e.at(0, "").in_buffer(index, Some(start))
+        })
+    }
+}
+
+/// Iterator over the decoded buffers of a [`ContainerReader`].
+#[derive(Debug)]
+pub struct Buffers<'r, 'a> {
+    reader: &'r ContainerReader<'a>,
+    index: usize,
+    decoded_total: usize,
+}
+
+impl<'a> Iterator for Buffers<'_, 'a> {
+    type Item = Result<DecodedBuffer<'a>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.reader.len() {
+            return None;
+        }
+        let index = self.index;
+        self.index += 1;
+
+        let limits = &self.reader.limits;
+        let max_size = limits.max_buffer_size.min(limits.max_total_size - self.decoded_total);
+        let item = self.reader.entry(index).and_then(|entry| {
+            let buffer = self.reader.decode(index, &entry, max_size)?;
+            if let Cow::Owned(ref decoded) = buffer {
+                self.decoded_total += decoded.len();
+            }
+            Ok((entry, buffer))
+        });
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.reader.len().saturating_sub(self.index);
+        (remaining, Some(remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{encrypt, pack_container, Stage, ENCODING_RLE8};
+
+    #[test]
+    fn test_lazy_buffers() {
+        let buffers = vec![vec![1u8; 10], vec![2u8; 7], Vec::new()];
+        let mut bytes = pack_container(&buffers, 42);
+        let reader = ContainerReader::new(&mut bytes).unwrap();
+        assert_eq!(reader.len(), 3);
+
+        let sizes: Vec<u64> = reader.entries().map(|entry| entry.unwrap().size).collect();
+        assert_eq!(sizes, vec![10, 7, 0]);
+        assert_eq!(reader.buffer(1).unwrap(), buffers[1]);
+
+        let decoded: Vec<Cow<[u8]>> = reader.buffers().map(|item| item.unwrap().1).collect();
+        assert_eq!(decoded, buffers);
+        assert!(decoded.iter().all(|buffer| matches!(buffer, Cow::Borrowed(_))));
+        assert!(reader.buffer(3).is_err());
+    }
+
+    #[test]
+    fn test_decode_all() {
+        // 40 RLE8 buffers of `i + 1` bytes of value `i`
+        let buffers: Vec<Vec<u8>> = (0..40u8).map(|i| vec![i + 1, i]).collect();
+        let mut bytes = pack_container(&buffers, 7);
+        let table = ContainerHeader::SIZE;
+        decrypt(&mut bytes[table..], ENCRYPTION_KEY, 7, BLOCK_SIZE);
+        for i in 0..buffers.len() {
+            bytes[table + i * BufferEntry::SIZE] = ENCODING_RLE8;
+        }
+        bytes[4..8].copy_from_slice(&[0; 4]);
+        let sum = checksum(&bytes);
+        bytes[4..8].copy_from_slice(&sum.to_le_bytes());
+        encrypt(&mut bytes[table..], ENCRYPTION_KEY, 7, BLOCK_SIZE);
+
+        let mut copy = bytes.clone();
+        let mut exact = bytes.clone();
+        let reader = ContainerReader::new(&mut copy).unwrap();
+        let decoded = reader.decode_all().unwrap();
+        assert_eq!(decoded.len(), 40);
+        for (i, (entry, buffer)) in decoded.iter().enumerate() {
+            assert_eq!(entry.encoding, ENCODING_RLE8);
+            assert_eq!(buffer.as_ref(), vec![i as u8; i + 1]);
+        }
+
+        // 1 + 2 + ... + 40 = 820 bytes in total
+        let limits = Limits {
+            max_total_size: 819,
+            ..Limits::default()
+        };
+        let error = ContainerReader::new(&mut bytes).unwrap().with_limits(limits).decode_all().unwrap_err();
+        assert!(error.to_string().contains("decoded container exceeds the limit of 819 bytes"), "{}", error);
+        assert!(matches!(error.root(), ParseError::LimitExceeded { limit: 819, .. }));
+        assert_eq!((error.code(), error.stage()), (302, Stage::Decode));
+
+        // Buffers larger than their share of a batch are decoded on their own
+        let limits = Limits {
+            max_total_size: 820,
+            ..Limits::default()
+        };
+        let decoded = ContainerReader::new(&mut exact).unwrap().with_limits(limits).decode_all().unwrap();
+        assert_eq!(decoded.iter().map(|(_, buffer)| buffer.len()).sum::<usize>(), 820);
+    }
+
+    #[test]
+    fn test_error_classes() {
+        let mut bytes = pack_container(&[vec![1u8; 16]], 0);
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xFF;
+        let error = ContainerReader::new(&mut bytes.clone()).unwrap_err();
+        assert!(matches!(error, ParseError::ChecksumMismatch { stored, computed } if stored != computed));
+        assert_eq!((error.code(), error.stage()), (204, Stage::Container));
+
+        bytes[..4].copy_from_slice(b"RIFF");
+        let error = ContainerReader::new(&mut bytes).unwrap_err();
+        assert!(matches!(error, ParseError::BadMagic { found: 0x46464952 }));
+        assert_eq!(error.code(), 202);
+
+        // A short object header inside a buffer is an object error
+        let located = ParseError::FileTooSmall { expected: 104, actual: 3 }.at(0, "").in_buffer(2, None);
+        assert_eq!((located.code(), located.stage()), (201, Stage::Object));
+    }
+}
@@ -0,0 +1,338 @@
+//! Best-effort recovery of damaged containers.
+//!
+//! A container whose checksum fails is decrypted as usual and every buffer
+//! that still parses is kept. A truncated container cannot be decrypted
+//! normally: the key schedule depends on the original length and on the
+//! ciphertext of every word, including the missing ones. The encrypted body
+//! is processed as four interleaved stripes of 32-bit words, so the key at
+//! the start of each stripe is recovered instead, from words of the buffer
+//! table whose plaintext is known (zero high words and the zero offset of
+//! the first buffer), and for the first stripe by trying all 256 possible
+//! length bytes.
+
+use super::container::{checksum, decode_counted, decrypt, encoding, BufferEntry, ContainerHeader, BLOCK_SIZE, ENCODING_NONE, ENCRYPTION_KEY};
+use super::header::{PackChild, PackHeader, PackVar, ParseError};
+use super::object::{StorageObject, Variable};
+use super::options::Limits;
+use super::view::{read_str, ObjectView};
+
+/// Buffers salvaged from a damaged container.
+#[derive(Debug, Default)]
+pub struct RecoveredContainer {
+    /// Salvaged buffers with their index in the buffer table. A buffer cut
+    /// off by truncation is included up to the end of the file.
+    pub buffers: Vec<(usize, Vec<u8>)>,
+    /// Whether the file was shorter than its buffer table says
+    pub truncated: bool,
+    /// What was wrong with the container
+    pub problems: Vec<String>,
+}
+
+/// Recover what can be decoded from a damaged container.
+///
+/// Fails only if the header is unreadable, or the magic is wrong or of an
+/// unsupported version. Decompression is bounded by the default [`Limits`].
+pub fn recover_container(data: &[u8]) -> Result<RecoveredContainer, ParseError> {
+    recover_container_with(data, &Limits::default())
+}
+
+/// Like [`recover_container`], with the given decompression limits. A
+/// buffer that would exceed them is reported as a problem.
+pub fn recover_container_with(data: &[u8], limits: &Limits) -> Result<RecoveredContainer, ParseError> {
+    let header = ContainerHeader::parse(data)?;
+
+    let mut recovered = RecoveredContainer::default();
+    let mut plain = data.to_vec();
+    plain[4..8].copy_from_slice(&[0, 0, 0, 0]);
+    decrypt(&mut plain[ContainerHeader::SIZE..], ENCRYPTION_KEY, header.seed, BLOCK_SIZE);
+
+    let complete = read_table(&plain, &header).filter(|table| {
+        table
+            .iter()
+            .all(|entry| buffer_end(&header, entry).is_some_and(|end| end <= plain.len() as u64))
+    });
+    let table = match complete {
+        Some(table) => {
+            if checksum(&plain) != header.checksum {
+                recovered.problems.push("checksum mismatch".to_string());
+            }
+            table
+        }
+        None => {
+            recovered.truncated = true;
+            recovered.problems.push(format!("file is truncated at {} bytes", data.len()));
+            match decrypt_truncated(data, &header) {
+                Some((truncated_plain, table)) => {
+                    plain = truncated_plain;
+                    table
+                }
+                None => {
+                    recovered
+                        .problems
+                        .push("the buffer table could not be decrypted".to_string());
+                    return Ok(recovered);
+                }
+            }
+        }
+    };
+
+    let mut decoded_total = 0;
+    for (i, entry) in table.iter().enumerate() {
+        let start = header.buffers_data_ofs.saturating_add(entry.offset);
+        let end = buffer_end(&header, entry).unwrap_or(u64::MAX);
+        if start >= plain.len() as u64 {
+            recovered.problems.push(format!("buffer[{}]: missing", i));
+            continue;
+        }
+        let encoded = &plain[start as usize..end.min(plain.len() as u64) as usize];
+        if end > plain.len() as u64 && entry.encoding != ENCODING_NONE {
+            recovered
+                .problems
+                .push(format!("buffer[{}]: truncated compressed buffer cannot be decoded", i));
+            continue;
+        }
+        if end > plain.len() as u64 {
+            recovered.problems.push(format!(
+                "buffer[{}]: truncated ({} of {} bytes)",
+                i,
+                encoded.len(),
+                entry.size
+            ));
+        }
+        match decode_counted(encoded, entry.encoding, limits, &mut decoded_total) {
+            Ok(buffer) => recovered.buffers.push((i, buffer)),
+            Err(e) => recovered.problems.push(format!("buffer[{}]: {}", i, e)),
+        }
+    }
+
+    Ok(recovered)
+}
+
+/// Salvage a StorageObject from a damaged or truncated buffer.
+///
+/// Intact variables and children are kept; the paths of those that were
+/// lost are appended to `lost`. Returns `None` if not even the object's
+/// header and names are readable.
+pub fn salvage_object(data: &[u8], path: &str, lost: &mut Vec<String>) -> Option<StorageObject> {
+    if let Ok(obj) = ObjectView::parse(data).and_then(|view| view.to_storage_object()) {
+        return Some(obj);
+    }
+
+    let header = PackHeader::from_bytes(data).ok()?;
+    let strings = clipped(data, header.strings.offset, header.strings.size);
+    let var_name = read_str(strings, header.name_offset).ok()?.to_string();
+    let path = if path.is_empty() { var_name.clone() } else { format!("{}/{}", path, var_name) };
+    let mut obj = StorageObject {
+        type_name: read_str(strings, header.type_name_offset).ok()?.to_string(),
+        owner_name: read_str(strings, header.owner_offset).ok()?.to_string(),
+        var_name,
+        variables: Vec::new(),
+        children: Vec::new(),
+    };
+    let data_section = clipped(data, header.data.offset, header.data.size);
+
+    let vars = clipped(data, header.vars.offset, header.vars.size);
+    let mut lost_vars = header.num_vars.saturating_sub((vars.len() / PackVar::SIZE) as u64);
+    for bytes in vars.chunks_exact(PackVar::SIZE) {
+        let var = PackVar::from_bytes(bytes);
+        let payload = var
+            .data_offset
+            .checked_add(var.bytes_size)
+            .filter(|&end| end <= data_section.len() as u64)
+            .map(|end| &data_section[var.data_offset as usize..end as usize]);
+        let names = (
+            read_str(strings, var.owner_offset),
+            read_str(strings, var.name_offset),
+            read_str(strings, var.type_offset),
+        );
+        match (payload, names) {
+            (Some(payload), (Ok(owner), Ok(name), Ok(type_name))) => obj.variables.push(Variable {
+                owner: owner.to_string(),
+                name: name.to_string(),
+                type_name: type_name.to_string(),
+                data: payload.to_vec(),
+            }),
+            _ => lost_vars += 1,
+        }
+    }
+    if lost_vars > 0 {
+        lost.push(format!("{} ({} of {} variables)", path, lost_vars, header.num_vars));
+    }
+
+    let children = clipped(data, header.children.offset, header.children.size);
+    let listed = (children.len() / PackChild::SIZE) as u64;
+    if header.num_children > listed {
+        lost.push(format!("{} ({} unlisted children)", path, header.num_children - listed));
+    }
+    for bytes in children.chunks_exact(PackChild::SIZE) {
+        let child = PackChild::from_bytes(bytes);
+        let name = read_str(strings, child.name_offset).unwrap_or("?");
+        let complete = child
+            .data_offset
+            .checked_add(child.size)
+            .filter(|&end| end <= data_section.len() as u64)
+            .map(|end| &data_section[child.data_offset as usize..end as usize]);
+        // Only complete children are kept; a partial vector is not data
+        match complete.and_then(|bytes| ObjectView::parse(bytes).and_then(|v| v.to_storage_object()).ok()) {
+            Some(child) => obj.children.push(child),
+            None => lost.push(format!("{}/{}", path, name)),
+        }
+    }
+
+    Some(obj)
+}
+
+/// The part of `data` a section would occupy, cut at the end of `data`.
+fn clipped(data: &[u8], offset: u64, size: u64) -> &[u8] {
+    let start = offset.min(data.len() as u64) as usize;
+    let end = offset.saturating_add(size).min(data.len() as u64) as usize;
+    &data[start..end]
+}
+
+fn buffer_end(header: &ContainerHeader, entry: &BufferEntry) -> Option<u64> {
+    header.buffers_data_ofs.checked_add(entry.offset)?.checked_add(entry.size)
+}
+
+/// Read the buffer table of a decrypted container, if it lies within the
+/// data and only names known encodings.
+fn read_table(plain: &[u8], header: &ContainerHeader) -> Option<Vec<BufferEntry>> {
+    let size = header.num_buffers.checked_mul(BufferEntry::SIZE as u64)?;
+    let end = header.buffers_table_ofs.checked_add(size)?;
+    if end > plain.len() as u64 {
+        return None;
+    }
+    let table: Vec<BufferEntry> = plain[header.buffers_table_ofs as usize..end as usize]
+        .chunks_exact(BufferEntry::SIZE)
+        .map(BufferEntry::from_bytes)
+        .collect();
+    table
+        .iter()
+        .all(|entry| encoding(entry.encoding).is_some())
+        .then_some(table)
+}
+
+/// Replicate the low byte of a value across a word, complemented (as in the
+/// key schedule).
+fn repmat(value: u32) -> u32 {
+    let v = value & 0xFF;
+    let v = v | (v << 8);
+    let v = v | (v << 16);
+    !v
+}
+
+fn word(data: &[u8], i: usize) -> u32 {
+    u32::from_le_bytes(data[i * 4..i * 4 + 4].try_into().unwrap())
+}
+
+/// Decrypt words with the given key at the start of each stripe.
+fn decrypt_stripes(data: &mut [u8], keys: [u32; BLOCK_SIZE]) {
+    let num_elements = data.len() / 4;
+    for (j, &key) in keys.iter().enumerate() {
+        let mut current_key = key;
+        for i in (j..num_elements).step_by(BLOCK_SIZE) {
+            let encrypted = word(data, i);
+            data[i * 4..i * 4 + 4].copy_from_slice(&(encrypted ^ current_key).to_le_bytes());
+            current_key = current_key.wrapping_add(!encrypted).wrapping_add(repmat(i as u32));
+        }
+    }
+}
+
+/// Key at the start of the stripe holding word `known`, whose plaintext is
+/// `plaintext`.
+fn stripe_key(body: &[u8], known: usize, plaintext: u32) -> u32 {
+    let mut key = word(body, known) ^ plaintext;
+    for i in (known % BLOCK_SIZE..known).step_by(BLOCK_SIZE) {
+        key = key.wrapping_sub(!word(body, i)).wrapping_sub(repmat(i as u32));
+    }
+    key
+}
+
+/// Decrypt a truncated container, returning the plaintext and buffer table.
+fn decrypt_truncated(data: &[u8], header: &ContainerHeader) -> Option<(Vec<u8>, Vec<BufferEntry>)> {
+    let body = &data[ContainerHeader::SIZE..data.len() - (data.len() - ContainerHeader::SIZE) % 4];
+    // The table must start a stripe-0 word and its first entry be readable
+    let table = header.buffers_table_ofs.checked_sub(ContainerHeader::SIZE as u64)?;
+    let table_end = header.num_buffers.checked_mul(BufferEntry::SIZE as u64)?.checked_add(table)?;
+    if table % 16 != 0 || header.num_buffers == 0 || table_end > body.len() as u64 {
+        return None;
+    }
+    let (t, table_words) = ((table / 4) as usize, (table_end - table) as usize / 4);
+
+    // Entry 0: offset (words 2-3) is zero, as is the high word of its size (5)
+    let mut keys = [0u32; BLOCK_SIZE];
+    keys[1] = stripe_key(body, t + 5, 0);
+    keys[2] = stripe_key(body, t + 2, 0);
+    keys[3] = stripe_key(body, t + 3, 0);
+
+    let mut fallback = None;
+    for length_byte in 0..=255u32 {
+        keys[0] = (ENCRYPTION_KEY ^ header.seed).wrapping_add(repmat(length_byte));
+        let mut prefix = body[..(t + table_words) * 4].to_vec();
+        decrypt_stripes(&mut prefix, keys);
+
+        let entries: Vec<BufferEntry> = prefix[t * 4..]
+            .chunks_exact(BufferEntry::SIZE)
+            .map(BufferEntry::from_bytes)
+            .collect();
+        let plausible = entries.iter().all(|e| encoding(e.encoding).is_some())
+            && entries.windows(2).all(|w| w[1].offset >= w[0].offset.saturating_add(w[0].size));
+        if !plausible {
+            continue;
+        }
+
+        // The original body length (in words), implied by the last buffer
+        let last = entries.last()?;
+        let end = header.buffers_data_ofs.saturating_add(last.offset).saturating_add(last.size);
+        let words = end.saturating_sub(ContainerHeader::SIZE as u64).div_ceil(4);
+        if words as u32 & 0xFF == length_byte {
+            fallback = Some((keys, entries));
+            break;
+        }
+        fallback.get_or_insert((keys, entries));
+    }
+
+    let (keys, entries) = fallback?;
+    let mut plain = data.to_vec();
+    decrypt_stripes(&mut plain[ContainerHeader::SIZE..ContainerHeader::SIZE + body.len()], keys);
+    Some((plain, entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{pack_container_encoded, ENCODING_RLE0};
+
+    #[test]
+    fn test_recover_total_decoded_limit() {
+        let mut bytes = pack_container_encoded(&vec![vec![0u8; 1000]; 4], 0, ENCODING_RLE0).unwrap();
+        bytes[4] ^= 0xff;
+        let limits = Limits {
+            max_total_size: 2500,
+            ..Default::default()
+        };
+        let recovered = recover_container_with(&bytes, &limits).unwrap();
+        let indices: Vec<usize> = recovered.buffers.iter().map(|(i, _)| *i).collect();
+        assert_eq!(indices, [0, 1]);
+        assert!(recovered.problems[1].starts_with("buffer[2]: "));
+        assert!(recovered.problems[1].contains("decoded container"));
+        assert_eq!(recover_container(&bytes).unwrap().buffers.len(), 4);
+    }
+
+    #[test]
+    fn test_overflowing_buffer_table() {
+        let bytes = pack_container_encoded(&vec![vec![0u8; 1000]; 4], 0, ENCODING_RLE0).unwrap();
+        let truncated = &bytes[..bytes.len() - 4];
+        assert!(recover_container(truncated).unwrap().truncated);
+
+        // A buffer count or table offset whose table end overflows a u64
+        let table = ContainerHeader::SIZE as u64 + 1600;
+        for (num_buffers, table) in [(u64::MAX / 24, table), (8, !15)] {
+            let mut corrupt = truncated.to_vec();
+            corrupt[8..16].copy_from_slice(&num_buffers.to_le_bytes());
+            corrupt[16..24].copy_from_slice(&table.to_le_bytes());
+            let recovered = recover_container(&corrupt).unwrap();
+            assert!(recovered.buffers.is_empty());
+            assert_eq!(recovered.problems.last().unwrap(), "the buffer table could not be decrypted");
+        }
+    }
+}
@@ -0,0 +1,306 @@
+//! Zero-copy view of a packed StorageObject.
+//!
+//! [`ObjectView`] borrows the decoded buffer and reads variables and
+//! children on demand, so large vectors can be converted straight into
+//! their final form without first materializing a [`StorageObject`] with a
+//! separate allocation per value.
+//!
+//! Errors are located: they carry the path of the failing element relative
+//! to the parsed object (e.g. `m_blank[512]`) and its offset in the buffer.
+
+use super::header::{BufferSection, PackChild, PackHeader, PackVar, ParseError};
+use super::object::{StorageObject, Variable};
+
+/// A variable borrowed from a packed object.
+#[derive(Debug, Clone, Copy)]
+pub struct VariableView<'a> {
+    pub owner: &'a str,
+    pub name: &'a str,
+    pub type_name: &'a str,
+    pub data: &'a [u8],
+}
+
+/// A packed StorageObject, parsed lazily from a borrowed buffer.
+#[derive(Debug, Clone, Copy)]
+pub struct ObjectView<'a> {
+    pub type_name: &'a str,
+    pub owner_name: &'a str,
+    pub var_name: &'a str,
+    strings: &'a [u8],
+    vars: &'a [u8],
+    children: &'a [u8],
+    data: &'a [u8],
+    /// Offsets of the vars, children and data sections in the root buffer
+    vars_at: u64,
+    children_at: u64,
+    data_at: u64,
+}
+
+impl<'a> ObjectView<'a> {
+    /// Parse the header and check the section bounds. Variables and
+    /// children are only read when accessed.
+    pub fn parse(data: &'a [u8]) -> Result<Self, ParseError> {
+        Self::parse_at(data, 0)
+    }
+
+    /// Parse an object found at `offset` in the root buffer.
+    fn parse_at(data: &'a [u8], offset: u64) -> Result<Self, ParseError> {
+        Self::parse_header(data, offset).map_err(|e| e.at(offset, ""))
+    }
+
+    fn parse_header(data: &'a [u8], offset: u64) -> Result<Self, ParseError> {
+        let header = PackHeader::from_bytes(data)?;
+
+        let strings = section(data, header.strings)?;
+        let data_section = section(data, header.data)?;
+
+        let type_name = read_str(strings, header.type_name_offset)?;
+        let owner_name = read_str(strings, header.owner_offset)?;
+        let var_name = read_str(strings, header.name_offset)?;
+
+        let vars = section(data, header.vars)?;
+        if header.num_vars.checked_mul(PackVar::SIZE as u64) != Some(header.vars.size) {
+            return Err(ParseError::VarCountMismatch {
+                expected: header.num_vars,
+                actual: vars.len() / PackVar::SIZE,
+            });
+        }
+
+        let children = section(data, header.children)?;
+        if header.num_children.checked_mul(PackChild::SIZE as u64) != Some(header.children.size) {
+            return Err(ParseError::ChildCountMismatch {
+                expected: header.num_children,
+                actual: children.len() / PackChild::SIZE,
+            });
+        }
+
+        Ok(Self {
+            type_name,
+            owner_name,
+            var_name,
+            strings,
+            vars,
+            children,
+            data: data_section,
+            vars_at: offset + header.vars.offset,
+            children_at: offset + header.children.offset,
+            data_at: offset + header.data.offset,
+        })
+    }
+
+    /// Number of variables.
+    pub fn num_variables(&self) -> usize {
+        self.vars.len() / PackVar::SIZE
+    }
+
+    /// Number of child objects.
+    pub fn num_children(&self) -> usize {
+        self.children.len() / PackChild::SIZE
+    }
+
+    /// The `index`-th variable.
+    pub fn variable(&self, index: usize) -> Result<VariableView<'a>, ParseError> {
+        let pack_var = self.pack_var(index);
+        let view = || -> Result<_, ParseError> {
+            Ok(VariableView {
+                owner: read_str(self.strings, pack_var.owner_offset)?,
+                name: read_str(self.strings, pack_var.name_offset)?,
+                type_name: read_str(self.strings, pack_var.type_offset)?,
+                data: self.var_data(&pack_var)?,
+            })
+        };
+        view().map_err(|e| self.var_error(e, index))
+    }
+
+    /// All variables, in order.
+    pub fn variables(&self) -> impl Iterator<Item = Result<VariableView<'a>, ParseError>> + '_ {
+        (0..self.num_variables()).map(|i| self.variable(i))
+    }
+
+    /// The `index`-th child object.
+    pub fn child(&self, index: usize) -> Result<ObjectView<'a>, ParseError> {
+        let pack_child = self.pack_child(index);
+        let name = read_str(self.strings, pack_child.name_offset)
+            .map_err(|e| e.at(self.children_at + (index * PackChild::SIZE) as u64, format!("<child {}>", index)))?;
+        let data = self
+            .child_data(&pack_child)
+            .map_err(|e| e.at(self.children_at + (index * PackChild::SIZE) as u64, ""))?;
+        ObjectView::parse_at(data, self.data_at + pack_child.data_offset).map_err(|e| e.within(name))
+    }
+
+    /// All child objects, in order.
+    pub fn children(&self) -> impl Iterator<Item = Result<ObjectView<'a>, ParseError>> + '_ {
+        (0..self.num_children()).map(|i| self.child(i))
+    }
+
+    /// Find a variable by name.
+    pub fn find_var(&self, name: &str) -> Result<Option<VariableView<'a>>, ParseError> {
+        for i in 0..self.num_variables() {
+            let pack_var = self.pack_var(i);
+            let var_name = read_str(self.strings, pack_var.name_offset).map_err(|e| self.var_error(e, i))?;
+            if var_name == name {
+                return self.variable(i).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Find a child object by variable name, without parsing the others.
+    pub fn find_child(&self, var_name: &str) -> Result<Option<ObjectView<'a>>, ParseError> {
+        for i in 0..self.num_children() {
+            let pack_child = self.pack_child(i);
+            let name = read_str(self.strings, pack_child.name_offset)
+                .map_err(|e| e.at(self.children_at + (i * PackChild::SIZE) as u64, format!("<child {}>", i)))?;
+            if name == var_name {
+                return self.child(i).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Read a storage_vector<double>, which stores each element as an
+    /// 8-byte variable, straight into a `Vec<f64>`.
+    pub fn f64_values(&self) -> Result<Vec<f64>, ParseError> {
+        let mut values = Vec::with_capacity(self.num_variables());
+        for i in 0..self.num_variables() {
+            let pack_var = self.pack_var(i);
+            let data = self.var_data(&pack_var).map_err(|e| self.var_error(e, i))?;
+            let bytes: [u8; 8] = data.try_into().map_err(|_| {
+                ParseError::TypeMismatch {
+                    expected: "double (8 bytes)".to_string(),
+                    actual: format!("{} bytes", data.len()),
+                }
+                .at(self.data_at + pack_var.data_offset, format!("[{}]", i))
+            })?;
+            values.push(f64::from_le_bytes(bytes));
+        }
+        Ok(values)
+    }
+
+    /// Copy into an owned [`StorageObject`], recursively.
+    pub fn to_storage_object(&self) -> Result<StorageObject, ParseError> {
+        let variables = self
+            .variables()
+            .map(|var| {
+                var.map(|var| Variable {
+                    owner: var.owner.to_string(),
+                    name: var.name.to_string(),
+                    type_name: var.type_name.to_string(),
+                    data: var.data.to_vec(),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let children = self
+            .children()
+            .map(|child| child.and_then(|child| child.to_storage_object()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(StorageObject {
+            type_name: self.type_name.to_string(),
+            owner_name: self.owner_name.to_string(),
+            var_name: self.var_name.to_string(),
+            variables,
+            children,
+        })
+    }
+
+    fn pack_var(&self, index: usize) -> PackVar {
+        PackVar::from_bytes(&self.vars[index * PackVar::SIZE..(index + 1) * PackVar::SIZE])
+    }
+
+    /// Locate an error reading the `index`-th variable at its table entry.
+    fn var_error(&self, error: ParseError, index: usize) -> ParseError {
+        error.at(self.vars_at + (index * PackVar::SIZE) as u64, format!("[{}]", index))
+    }
+
+    fn pack_child(&self, index: usize) -> PackChild {
+        PackChild::from_bytes(&self.children[index * PackChild::SIZE..(index + 1) * PackChild::SIZE])
+    }
+
+    fn var_data(&self, pack_var: &PackVar) -> Result<&'a [u8], ParseError> {
+        slice(self.data, pack_var.data_offset, pack_var.bytes_size)
+    }
+
+    fn child_data(&self, pack_child: &PackChild) -> Result<&'a [u8], ParseError> {
+        slice(self.data, pack_child.data_offset, pack_child.size)
+    }
+}
+
+/// A header section of the object's buffer.
+fn section(data: &[u8], section: BufferSection) -> Result<&[u8], ParseError> {
+    slice(data, section.offset, section.size)
+}
+
+/// `size` bytes at `offset`, checking the bounds without overflow.
+fn slice(data: &[u8], offset: u64, size: u64) -> Result<&[u8], ParseError> {
+    let end = offset.checked_add(size).filter(|&end| end <= data.len() as u64);
+    match end {
+        Some(end) => Ok(&data[offset as usize..end as usize]),
+        None => Err(ParseError::InvalidOffset {
+            offset: offset.saturating_add(size),
+            size: data.len(),
+        }),
+    }
+}
+
+/// Read a null-terminated string from the strings section.
+pub(crate) fn read_str(strings: &[u8], offset: u64) -> Result<&str, ParseError> {
+    if offset >= strings.len() as u64 {
+        return Err(ParseError::InvalidOffset {
+            offset,
+            size: strings.len(),
+        });
+    }
+
+    let slice = &strings[offset as usize..];
+    let end = slice
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or(ParseError::UnterminatedString(offset))?;
+
+    std::str::from_utf8(&slice[..end]).map_err(|_| ParseError::UnterminatedString(offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_view_matches_storage_object() {
+        let vector = StorageObject {
+            type_name: "storage_vector<double>".to_string(),
+            owner_name: "data".to_string(),
+            var_name: "m_data".to_string(),
+            variables: [1.5, -2.0, 1e300]
+                .iter()
+                .map(|v: &f64| Variable {
+                    owner: "m_data".to_string(),
+                    name: String::new(),
+                    type_name: "double".to_string(),
+                    data: v.to_le_bytes().to_vec(),
+                })
+                .collect(),
+            children: Vec::new(),
+        };
+        let root = StorageObject {
+            type_name: "spectre_file".to_string(),
+            owner_name: String::new(),
+            var_name: "data".to_string(),
+            variables: Vec::new(),
+            children: vec![vector],
+        };
+        let bytes = root.to_bytes();
+
+        let view = ObjectView::parse(&bytes).unwrap();
+        assert_eq!(view.var_name, "data");
+        let child = view.find_child("m_data").unwrap().unwrap();
+        assert_eq!(child.f64_values().unwrap(), vec![1.5, -2.0, 1e300]);
+        assert!(view.find_child("m_blank").unwrap().is_none());
+
+        let owned = view.to_storage_object().unwrap();
+        assert_eq!(owned.to_bytes(), bytes);
+
+        // Truncated buffers are rejected up front
+        assert!(ObjectView::parse(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,142 @@
+//! Averaging replicate acquisitions.
+
+use super::ProcessingStep;
+use crate::spectre::{Calibration, Instrument, SpcFile};
+
+/// Relative tolerance when comparing calibration coefficients and laser
+/// wavelengths between replicates.
+const MATCH_TOLERANCE: f64 = 1e-9;
+
+/// Average replicate spectra point by point into one spectrum.
+///
+/// All inputs must have the same number of points, the same blank length
+/// and matching calibration and laser wavelength. The result takes its UID,
+/// calibration, config, axes and acquisition time from the first spectrum
+/// and keeps the operator, sample, instrument fields and metadata entries
+/// all inputs agree on.
+pub fn average(spectra: &[SpcFile]) -> Result<SpcFile, String> {
+    let Some(first) = spectra.first() else {
+        return Err("no spectra to average".to_string());
+    };
+
+    for (i, spc) in spectra.iter().enumerate().skip(1) {
+        let mismatch = |what: &str| format!("spectrum {} ({}) {} differs from the first ({})", i + 1, spc.uid, what, first.uid);
+        if spc.data.len() != first.data.len() {
+            return Err(mismatch("point count"));
+        }
+        if spc.blank.len() != first.blank.len() {
+            return Err(mismatch("blank length"));
+        }
+        let calibration_matches = match (&first.calibration, &spc.calibration) {
+            (Some(a), Some(b)) => {
+                let knots = |c: &Calibration| c.knots.iter().flat_map(|&(p, w)| [p, w]).collect::<Vec<f64>>();
+                a.model == b.model && all_close(&a.coefficients, &b.coefficients) && all_close(&knots(a), &knots(b))
+            }
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        if !calibration_matches {
+            return Err(mismatch("calibration"));
+        }
+        let laser = |s: &SpcFile| s.config.as_ref().and_then(|c| c.raman_wavelength);
+        let laser_matches = match (laser(first), laser(spc)) {
+            (Some(a), Some(b)) => close(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        if !laser_matches {
+            return Err(mismatch("laser wavelength"));
+        }
+    }
+
+    let n = spectra.len() as f64;
+    let mean = |values: fn(&SpcFile) -> &Vec<f64>| -> Vec<f64> {
+        (0..values(first).len())
+            .map(|i| spectra.iter().map(|s| values(s)[i]).sum::<f64>() / n)
+            .collect()
+    };
+
+    let mut result = first.clone();
+    result.data = mean(|s| &s.data);
+    result.blank = mean(|s| &s.blank);
+    if spectra.iter().any(|s| s.operator != first.operator) {
+        result.operator = None;
+    }
+    if spectra.iter().any(|s| s.sample != first.sample) {
+        result.sample = None;
+    }
+    if let Some(ref mut instrument) = result.instrument {
+        let others: Vec<Instrument> = spectra.iter().map(|s| s.instrument.clone().unwrap_or_default()).collect();
+        if others.iter().any(|other| other.model != instrument.model) {
+            instrument.model = None;
+        }
+        if others.iter().any(|other| other.serial != instrument.serial) {
+            instrument.serial = None;
+        }
+        if others.iter().any(|other| other.firmware != instrument.firmware) {
+            instrument.firmware = None;
+        }
+        if others.iter().any(|other| other.detector_temperature != instrument.detector_temperature) {
+            instrument.detector_temperature = None;
+        }
+        if instrument.is_empty() {
+            result.instrument = None;
+        }
+    }
+    result
+        .metadata
+        .retain(|key, value| spectra.iter().all(|s| s.metadata.get(key) == Some(value)));
+    result.history.push(
+        ProcessingStep::new("average")
+            .param("count", spectra.len())
+            .param("uids", spectra.iter().map(|s| s.uid.clone()).collect::<Vec<_>>()),
+    );
+    Ok(result)
+}
+
+fn all_close(a: &[f64], b: &[f64]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(x, y)| close(*x, *y))
+}
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() <= MATCH_TOLERANCE * a.abs().max(b.abs()).max(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replicate(uid: &str, data: Vec<f64>, a1: f64) -> SpcFile {
+        SpcFile {
+            uid: uid.to_string(),
+            blank: vec![1.0; data.len()],
+            data,
+            calibration: Some(Calibration::legendre(vec![800.0, a1, 0.5, 0.01])),
+            config: None,
+            wavelength_axis: None,
+            raman_shift_axis: None,
+            acquired: None,
+            operator: None,
+            sample: None,
+            instrument: None,
+            provenance: None,
+            stats: None,
+            metadata: Default::default(),
+            history: Vec::new(),
+            extras: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_average() {
+        let a = replicate("CAM-1", vec![1.0, 2.0, 3.0], 50.0);
+        let b = replicate("CAM-1", vec![3.0, 4.0, 5.0], 50.0);
+        let averaged = average(&[a.clone(), b]).unwrap();
+        assert_eq!(averaged.data, vec![2.0, 3.0, 4.0]);
+        assert_eq!(averaged.blank, vec![1.0; 3]);
+        assert_eq!(averaged.history[0].step, "average");
+
+        let shifted = replicate("CAM-2", vec![3.0, 4.0, 5.0], 51.0);
+        assert!(average(&[a.clone(), shifted]).is_err());
+        let shorter = replicate("CAM-1", vec![3.0, 4.0], 50.0);
+        assert!(average(&[a, shorter]).is_err());
+    }
+}
@@ -0,0 +1,178 @@
+//! Baseline (background) estimation and subtraction.
+
+use super::linalg::{polyfit, polyval, solve_banded_spd};
+use super::ProcessingStep;
+use crate::spectre::SpcFile;
+
+/// Default ALS smoothness.
+pub const DEFAULT_ALS_LAMBDA: f64 = 1e5;
+/// Default ALS asymmetry: weight of points above the baseline.
+pub const DEFAULT_ALS_ASYMMETRY: f64 = 0.01;
+/// Default degree of the polynomial baseline.
+pub const DEFAULT_BASELINE_DEGREE: usize = 5;
+
+const ALS_ITERATIONS: usize = 10;
+const POLYNOMIAL_ITERATIONS: usize = 100;
+
+/// Baseline estimation method.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BaselineMethod {
+    /// Asymmetric least squares (Eilers & Boelens): a smooth curve that
+    /// points above it pull on with weight `asymmetry`, points below with
+    /// `1 - asymmetry`. Larger `lambda` gives a stiffer baseline.
+    Als { lambda: f64, asymmetry: f64 },
+    /// Iterative polynomial fit that clips the signal to the fit each round,
+    /// so peaks stop pulling the curve up (modified polyfit).
+    Polynomial { degree: usize },
+}
+
+impl BaselineMethod {
+    /// ALS with the default parameters.
+    pub fn als() -> Self {
+        BaselineMethod::Als {
+            lambda: DEFAULT_ALS_LAMBDA,
+            asymmetry: DEFAULT_ALS_ASYMMETRY,
+        }
+    }
+
+    /// Polynomial of the default degree.
+    pub fn polynomial() -> Self {
+        BaselineMethod::Polynomial {
+            degree: DEFAULT_BASELINE_DEGREE,
+        }
+    }
+
+    fn step(&self) -> ProcessingStep {
+        match *self {
+            BaselineMethod::Als { lambda, asymmetry } => ProcessingStep::new("baseline")
+                .param("method", "als")
+                .param("lambda", lambda)
+                .param("asymmetry", asymmetry),
+            BaselineMethod::Polynomial { degree } => ProcessingStep::new("baseline")
+                .param("method", "polynomial")
+                .param("degree", degree),
+        }
+    }
+}
+
+/// Estimate the baseline under `values`, point for point.
+///
+/// Falls back to the data itself where the estimate cannot be computed
+/// (too few points or a singular system).
+pub fn estimate_baseline(values: &[f64], method: BaselineMethod) -> Vec<f64> {
+    match method {
+        BaselineMethod::Als { lambda, asymmetry } => als(values, lambda, asymmetry),
+        BaselineMethod::Polynomial { degree } => polynomial(values, degree),
+    }
+    .unwrap_or_else(|| values.to_vec())
+}
+
+/// Return `values` with the estimated baseline subtracted.
+pub fn baseline_corrected(values: &[f64], method: BaselineMethod) -> Vec<f64> {
+    let baseline = estimate_baseline(values, method);
+    values.iter().zip(&baseline).map(|(v, b)| v - b).collect()
+}
+
+/// Subtract the baseline from the intensity data in place and record the step.
+pub fn subtract_baseline(spc: &mut SpcFile, method: BaselineMethod) {
+    spc.data = baseline_corrected(&spc.data, method);
+    spc.history.push(method.step());
+}
+
+/// Solve `(W + lambda * D'D) z = W y` repeatedly, reweighting by which side
+/// of the current estimate each point lies on.
+fn als(y: &[f64], lambda: f64, asymmetry: f64) -> Option<Vec<f64>> {
+    let n = y.len();
+    if n < 3 {
+        return None;
+    }
+
+    // Bands of lambda * D'D for the second-difference operator D
+    let mut penalty = vec![[0.0; 3]; n];
+    let row = [1.0, -2.0, 1.0];
+    for r in 0..n - 2 {
+        for (i, &di) in row.iter().enumerate() {
+            for (j, &dj) in row.iter().enumerate().take(i + 1) {
+                penalty[r + i][i - j] += lambda * di * dj;
+            }
+        }
+    }
+
+    let mut weights = vec![1.0; n];
+    let mut z = y.to_vec();
+    for _ in 0..ALS_ITERATIONS {
+        let bands: Vec<Vec<f64>> = penalty
+            .iter()
+            .zip(&weights)
+            .map(|(p, w)| vec![p[0] + w, p[1], p[2]])
+            .collect();
+        let rhs: Vec<f64> = y.iter().zip(&weights).map(|(yi, w)| yi * w).collect();
+        z = solve_banded_spd(&bands, &rhs)?;
+
+        let updated: Vec<f64> = y
+            .iter()
+            .zip(&z)
+            .map(|(yi, zi)| if yi > zi { asymmetry } else { 1.0 - asymmetry })
+            .collect();
+        if updated == weights {
+            break;
+        }
+        weights = updated;
+    }
+    Some(z)
+}
+
+/// Fit a polynomial over the point index (scaled to -1..1), clip the working
+/// signal to the fit and refit until it settles.
+fn polynomial(y: &[f64], degree: usize) -> Option<Vec<f64>> {
+    let n = y.len();
+    if n <= degree {
+        return None;
+    }
+    let scale = (n - 1).max(1) as f64 / 2.0;
+    let x: Vec<f64> = (0..n).map(|i| i as f64 / scale - 1.0).collect();
+
+    let mut working = y.to_vec();
+    let mut fit = Vec::new();
+    for _ in 0..POLYNOMIAL_ITERATIONS {
+        let coefficients = polyfit(&x, &working, degree)?;
+        fit = x.iter().map(|&xi| polyval(&coefficients, xi)).collect::<Vec<f64>>();
+
+        let mut changed = false;
+        for (w, &f) in working.iter_mut().zip(&fit) {
+            if *w > f {
+                *w = f;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    Some(fit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_baseline_removes_background() {
+        let values: Vec<f64> = (0..400)
+            .map(|i| {
+                let x = i as f64;
+                let background = 500.0 + 0.8 * x - 0.001 * x * x;
+                background + 300.0 * (-((x - 200.0) / 6.0).powi(2)).exp()
+            })
+            .collect();
+
+        for method in [BaselineMethod::als(), BaselineMethod::polynomial()] {
+            let corrected = baseline_corrected(&values, method);
+            // Flat region far from the peak ends up near zero
+            assert!(corrected[50].abs() < 10.0, "{:?}: {}", method, corrected[50]);
+            assert!(corrected[350].abs() < 10.0, "{:?}: {}", method, corrected[350]);
+            // The peak survives
+            assert!((corrected[200] - 300.0).abs() < 15.0, "{:?}: {}", method, corrected[200]);
+        }
+    }
+}
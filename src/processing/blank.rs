@@ -0,0 +1,22 @@
+//! Blank (dark/reference) subtraction.
+
+use super::ProcessingStep;
+use crate::spectre::SpcFile;
+
+/// Subtract the blank from the intensity data in place and record the step.
+///
+/// With `clip`, negative results are set to zero.
+pub fn subtract_blank(spc: &mut SpcFile, clip: bool) {
+    let mut data = spc.blank_subtracted();
+    if clip {
+        for v in data.iter_mut() {
+            *v = v.max(0.0);
+        }
+    }
+    spc.data = data;
+    spc.history.push(
+        ProcessingStep::new("subtract_blank")
+            .param("blank_points", spc.blank.len())
+            .param("clip", clip),
+    );
+}
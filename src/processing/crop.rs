@@ -0,0 +1,132 @@
+//! Cropping to an x-range.
+
+use super::ProcessingStep;
+use crate::output::select_axis;
+use crate::spectre::{AxisType, SpcFile};
+
+/// Indices of the points whose axis value lies within `min..=max`
+/// (either bound may be open; the bounds may be given in either order).
+pub fn crop_indices(x: &[f64], min: Option<f64>, max: Option<f64>) -> std::ops::Range<usize> {
+    let (min, max) = match (min, max) {
+        (Some(a), Some(b)) if a > b => (Some(b), Some(a)),
+        bounds => bounds,
+    };
+    let inside = |v: f64| min.is_none_or(|m| v >= m) && max.is_none_or(|m| v <= m);
+    // Axes are monotonic, so the points inside form one contiguous run
+    match x.iter().position(|&v| inside(v)) {
+        Some(start) => {
+            let len = x[start..].iter().take_while(|&&v| inside(v)).count();
+            start..start + len
+        }
+        None => 0..0,
+    }
+}
+
+/// Keep only the points within the x-range and record the step with the
+/// original pixel range. The range is on the `axis` output uses (see
+/// [`select_axis`]): the requested one, else the display axis stored in the
+/// file, else the best available.
+///
+/// Data, blank and the generated axes are cropped together, and the
+/// calibration is re-expressed over the kept pixels (see
+/// [`Calibration::cropped`](crate::Calibration::cropped)), so written files
+/// keep the cropped axes. Fails if no points fall inside the range.
+pub fn crop(spc: &mut SpcFile, axis: Option<AxisType>, min: Option<f64>, max: Option<f64>) -> Result<(), String> {
+    spc.generate_axes();
+    let axis = select_axis(spc, axis).map_err(|e| e.to_string())?;
+    let (range, axis_name) = (crop_indices(&axis.values, min, max), axis.name);
+    if range.is_empty() {
+        return Err(format!(
+            "no points between {} and {} on the {} axis",
+            min.map_or("-inf".to_string(), |v| v.to_string()),
+            max.map_or("inf".to_string(), |v| v.to_string()),
+            axis_name
+        ));
+    }
+
+    let slice = |values: &[f64]| values[range.start.min(values.len())..range.end.min(values.len())].to_vec();
+    let points = spc.data.len();
+    spc.calibration = spc.calibration.as_ref().and_then(|c| c.cropped(range.start, range.len(), points));
+    spc.data = slice(&spc.data);
+    spc.blank = slice(&spc.blank);
+    spc.wavelength_axis = spc.wavelength_axis.as_deref().map(slice);
+    spc.raman_shift_axis = spc.raman_shift_axis.as_deref().map(slice);
+
+    let mut step = ProcessingStep::new("crop").param("axis", axis_name);
+    if let Some(min) = min {
+        step = step.param("xmin", min);
+    }
+    if let Some(max) = max {
+        step = step.param("xmax", max);
+    }
+    spc.history.push(
+        step.param("first_pixel", range.start)
+            .param("last_pixel", range.end - 1),
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crop_indices() {
+        let ascending = [100.0, 200.0, 300.0, 400.0, 500.0];
+        assert_eq!(crop_indices(&ascending, Some(150.0), Some(400.0)), 1..4);
+        assert_eq!(crop_indices(&ascending, Some(400.0), Some(150.0)), 1..4);
+        assert_eq!(crop_indices(&ascending, None, Some(250.0)), 0..2);
+        assert!(crop_indices(&ascending, Some(600.0), None).is_empty());
+
+        let descending = [500.0, 400.0, 300.0, 200.0];
+        assert_eq!(crop_indices(&descending, Some(250.0), None), 0..3);
+    }
+
+    #[test]
+    fn test_crop_axis() {
+        let mut spc = crate::spectre::SpcFileBuilder::new("CAM-1", (0..100).map(f64::from).collect())
+            .calibration(crate::Calibration::legendre(vec![800.0, 10.0]))
+            .laser_wavelength(785.0)
+            .build();
+        // 800-805 nm, not the 800-805 cm⁻¹ of the best (Raman) axis
+        crop(&mut spc, Some(AxisType::Wavelengths), Some(800.0), Some(805.0)).unwrap();
+        assert_eq!(spc.data.len(), 25);
+        assert_eq!(spc.data[0], 50.0);
+        assert!(crop(&mut spc, Some(AxisType::Wavelengths), Some(900.0), None).is_err());
+    }
+
+    #[test]
+    fn test_crop_calibration() {
+        use crate::spectre::{CalibrationModel, SpcFileBuilder};
+        use crate::Calibration;
+
+        let close = |a: &[f64], b: &[f64]| a.len() == b.len() && a.iter().zip(b).all(|(x, y)| (x - y).abs() < 1e-9);
+        let polynomial = Calibration {
+            model: CalibrationModel::Polynomial,
+            coefficients: vec![780.0, 0.12, -1e-5],
+            knots: Vec::new(),
+        };
+        let spline = Calibration {
+            model: CalibrationModel::Spline,
+            coefficients: Vec::new(),
+            knots: vec![(0.0, 780.0), (50.0, 786.5), (99.0, 792.0)],
+        };
+        for calibration in [Calibration::legendre(vec![850.0, 60.0, -0.5, 0.1, 0.02]), polynomial, spline] {
+            let mut spc = SpcFileBuilder::new("CAM-1", (0..100).map(f64::from).collect())
+                .calibration(calibration)
+                .build();
+            let full = spc.wavelengths().unwrap().into_owned();
+            crop(&mut spc, None, Some(full[20]), Some(full[59])).unwrap();
+            assert_eq!(spc.data.len(), 40);
+
+            // A written and re-read file rebuilds the cropped axes
+            let reread = SpcFile::from_bytes(&spc.to_bytes()).unwrap();
+            let model = spc.calibration.as_ref().unwrap().model;
+            if model != CalibrationModel::Spline {
+                assert!(close(reread.wavelength_axis.as_deref().unwrap(), &full[20..60]));
+            }
+            let regenerated = spc.calibration.as_ref().unwrap().generate_wavelength_axis(40).unwrap();
+            assert!(close(&regenerated, &full[20..60]), "{:?}", model);
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Cosmic-ray spike removal.
+
+use super::ProcessingStep;
+use crate::spectre::SpcFile;
+
+/// Default half-width of the median window.
+pub const DEFAULT_DESPIKE_WINDOW: usize = 2;
+/// Default spike threshold, in median absolute deviations.
+pub const DEFAULT_DESPIKE_THRESHOLD: f64 = 6.0;
+
+/// Replace spikes with the local median.
+///
+/// A point is a spike when it deviates from the median of its
+/// `2 * window + 1` neighbourhood by more than `threshold` times the
+/// neighbourhood's median absolute deviation.
+pub fn despiked(values: &[f64], window: usize, threshold: f64) -> Vec<f64> {
+    let n = values.len();
+    let mut result = values.to_vec();
+    if window == 0 || n < 3 {
+        return result;
+    }
+
+    let mut neighbourhood = Vec::with_capacity(2 * window + 1);
+    for i in 0..n {
+        let start = i.saturating_sub(window);
+        let end = (i + window + 1).min(n);
+
+        neighbourhood.clear();
+        neighbourhood.extend_from_slice(&values[start..end]);
+        let med = median(&mut neighbourhood);
+
+        for v in neighbourhood.iter_mut() {
+            *v = (*v - med).abs();
+        }
+        let mad = median(&mut neighbourhood);
+
+        if mad > 0.0 && (values[i] - med).abs() > threshold * mad {
+            result[i] = med;
+        }
+    }
+
+    result
+}
+
+/// Remove spikes from the intensity data in place and record the step.
+pub fn despike(spc: &mut SpcFile, window: usize, threshold: f64) {
+    spc.data = despiked(&spc.data, window, threshold);
+    spc.history.push(
+        ProcessingStep::new("despike")
+            .param("window", window)
+            .param("threshold", threshold),
+    );
+}
+
+/// Running median over `width` points; the window shrinks at the ends.
+pub fn median_filtered(values: &[f64], width: usize) -> Vec<f64> {
+    let (before, after) = window_extent(width);
+    let mut neighbourhood = Vec::with_capacity(width);
+    (0..values.len())
+        .map(|i| {
+            neighbourhood.clear();
+            neighbourhood.extend_from_slice(&values[i.saturating_sub(before)..(i + after + 1).min(values.len())]);
+            median(&mut neighbourhood)
+        })
+        .collect()
+}
+
+/// Points before and after the centre of a `width`-point window.
+pub(crate) fn window_extent(width: usize) -> (usize, usize) {
+    let width = width.max(1);
+    (width / 2, (width - 1) / 2)
+}
+
+/// Median of a scratch buffer (reorders it).
+pub(crate) fn median(values: &mut [f64]) -> f64 {
+    if values.is_empty() {
+        return f64::NAN;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_despike_removes_single_spike() {
+        let values = vec![1.0, 1.1, 0.9, 1.0, 50.0, 1.0, 1.1, 0.9, 1.0];
+        let result = despiked(&values, 2, 6.0);
+        assert!(result[4] < 2.0);
+        assert_eq!(result[0], 1.0);
+        assert_eq!(result[8], 1.0);
+    }
+}
@@ -0,0 +1,207 @@
+//! Least-squares fitting of individual peaks.
+//!
+//! Each peak is fitted on a window around its maximum with a line shape plus
+//! a constant offset, using Levenberg-Marquardt.
+
+use super::linalg::solve;
+use super::peaks::Peak;
+use crate::output::select_best_axis;
+use crate::spectre::SpcFile;
+use serde::Serialize;
+
+/// Default fit window half-width, in multiples of the detected FWHM.
+pub const DEFAULT_FIT_WINDOW: f64 = 1.5;
+
+const MAX_ITERATIONS: usize = 200;
+
+/// Peak line shape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineShape {
+    Gaussian,
+    Lorentzian,
+    /// Pseudo-Voigt: a linear mix of Gaussian and Lorentzian of equal FWHM
+    Voigt,
+}
+
+/// Result of fitting one peak.
+#[derive(Debug, Clone, Serialize)]
+pub struct PeakFit {
+    pub shape: LineShape,
+    /// Fitted center, in axis units
+    pub center: f64,
+    /// Height above the offset
+    pub amplitude: f64,
+    /// Full width at half maximum, in axis units
+    pub fwhm: f64,
+    /// Constant background under the peak
+    pub offset: f64,
+    /// Lorentzian fraction (Voigt only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eta: Option<f64>,
+    /// Root-mean-square residual over the fit window
+    pub rms_residual: f64,
+    /// Coefficient of determination over the fit window
+    pub r_squared: f64,
+    /// Number of points in the fit window
+    pub points: usize,
+}
+
+impl LineShape {
+    /// Evaluate the shape (without offset) for `params = [amplitude, center, fwhm, eta]`.
+    fn eval(self, x: f64, params: &[f64]) -> f64 {
+        let (amplitude, center, fwhm) = (params[0], params[1], params[2].abs().max(f64::MIN_POSITIVE));
+        let u = (x - center) / fwhm;
+        let gaussian = (-4.0 * std::f64::consts::LN_2 * u * u).exp();
+        let lorentzian = 1.0 / (1.0 + 4.0 * u * u);
+        match self {
+            LineShape::Gaussian => amplitude * gaussian,
+            LineShape::Lorentzian => amplitude * lorentzian,
+            LineShape::Voigt => {
+                let eta = params[3].clamp(0.0, 1.0);
+                amplitude * (eta * lorentzian + (1.0 - eta) * gaussian)
+            }
+        }
+    }
+}
+
+/// Fit detected peaks of a spectrum on its best available axis.
+///
+/// Peaks whose fit does not converge are skipped.
+pub fn fit_peaks(spc: &SpcFile, peaks: &[Peak], shape: LineShape) -> Vec<PeakFit> {
+    let axis = select_best_axis(spc);
+    peaks
+        .iter()
+        .filter_map(|peak| fit_peak(&axis.values, &spc.data, peak, shape, DEFAULT_FIT_WINDOW))
+        .collect()
+}
+
+/// Fit one peak on the points within `window` FWHMs of its position.
+pub fn fit_peak(x: &[f64], y: &[f64], peak: &Peak, shape: LineShape, window: f64) -> Option<PeakFit> {
+    let half_width = (peak.fwhm * window).max(f64::EPSILON);
+    let (xs, ys): (Vec<f64>, Vec<f64>) = x
+        .iter()
+        .zip(y)
+        .filter(|(&xi, _)| (xi - peak.position).abs() <= half_width)
+        .map(|(&xi, &yi)| (xi, yi))
+        .unzip();
+
+    let num_params = if shape == LineShape::Voigt { 5 } else { 4 };
+    if xs.len() < num_params + 1 {
+        return None;
+    }
+
+    // [amplitude, center, fwhm, eta, offset]; eta is fixed for non-Voigt shapes
+    let offset = peak.intensity - peak.prominence;
+    let mut params = vec![peak.prominence, peak.position, peak.fwhm, 0.5, offset];
+    let model = |p: &[f64], xi: f64| shape.eval(xi, p) + p[4];
+    let free: Vec<usize> = if shape == LineShape::Voigt {
+        vec![0, 1, 2, 3, 4]
+    } else {
+        vec![0, 1, 2, 4]
+    };
+
+    let sse = |p: &[f64]| -> f64 { xs.iter().zip(&ys).map(|(&xi, &yi)| (yi - model(p, xi)).powi(2)).sum() };
+    let mut current = sse(&params);
+    let mut lambda = 1e-3;
+
+    for _ in 0..MAX_ITERATIONS {
+        // Numeric Jacobian over the free parameters
+        let jacobian: Vec<Vec<f64>> = free
+            .iter()
+            .map(|&k| {
+                let step = 1e-6 * params[k].abs().max(1e-6);
+                let mut hi = params.clone();
+                let mut lo = params.clone();
+                hi[k] += step;
+                lo[k] -= step;
+                xs.iter().map(|&xi| (model(&hi, xi) - model(&lo, xi)) / (2.0 * step)).collect()
+            })
+            .collect();
+        let residuals: Vec<f64> = xs.iter().zip(&ys).map(|(&xi, &yi)| yi - model(&params, xi)).collect();
+
+        let m = free.len();
+        let mut a = vec![vec![0.0; m]; m];
+        let mut g = vec![0.0; m];
+        for r in 0..m {
+            for c in 0..m {
+                a[r][c] = jacobian[r].iter().zip(&jacobian[c]).map(|(p, q)| p * q).sum();
+            }
+            g[r] = jacobian[r].iter().zip(&residuals).map(|(p, q)| p * q).sum();
+        }
+
+        let mut improved = false;
+        while lambda < 1e12 {
+            let mut damped = a.clone();
+            for (i, row) in damped.iter_mut().enumerate() {
+                row[i] += lambda * a[i][i].max(1e-12);
+            }
+            let Some(delta) = solve(damped, g.clone()) else {
+                lambda *= 10.0;
+                continue;
+            };
+            let mut candidate = params.clone();
+            for (&k, d) in free.iter().zip(&delta) {
+                candidate[k] += d;
+            }
+            let candidate_sse = sse(&candidate);
+            if candidate_sse < current {
+                let converged = (current - candidate_sse) <= 1e-12 * current.max(f64::MIN_POSITIVE);
+                params = candidate;
+                current = candidate_sse;
+                lambda = (lambda / 10.0).max(1e-12);
+                improved = !converged;
+                break;
+            }
+            lambda *= 10.0;
+        }
+        if !improved {
+            break;
+        }
+    }
+
+    if !params.iter().all(|p| p.is_finite()) {
+        return None;
+    }
+
+    let mean = ys.iter().sum::<f64>() / ys.len() as f64;
+    let total: f64 = ys.iter().map(|yi| (yi - mean).powi(2)).sum();
+    Some(PeakFit {
+        shape,
+        center: params[1],
+        amplitude: params[0],
+        fwhm: params[2].abs(),
+        offset: params[4],
+        eta: (shape == LineShape::Voigt).then(|| params[3].clamp(0.0, 1.0)),
+        rms_residual: (current / xs.len() as f64).sqrt(),
+        r_squared: if total > 0.0 { 1.0 - current / total } else { 1.0 },
+        points: xs.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_lorentzian() {
+        let x: Vec<f64> = (0..200).map(|i| 500.0 + i as f64 * 0.5).collect();
+        let truth = [40.0, 552.3, 6.0, 0.0, 15.0];
+        let y: Vec<f64> = x.iter().map(|&xi| LineShape::Lorentzian.eval(xi, &truth) + truth[4]).collect();
+
+        // Deliberately rough starting point
+        let peak = Peak {
+            index: 105,
+            position: 552.5,
+            intensity: 55.0,
+            prominence: 35.0,
+            fwhm: 5.0,
+        };
+        let fit = fit_peak(&x, &y, &peak, LineShape::Lorentzian, 3.0).unwrap();
+        assert!((fit.center - 552.3).abs() < 1e-4);
+        assert!((fit.amplitude - 40.0).abs() < 1e-3);
+        assert!((fit.fwhm - 6.0).abs() < 1e-3);
+        assert!((fit.offset - 15.0).abs() < 1e-3);
+        assert!(fit.r_squared > 0.999999);
+    }
+}
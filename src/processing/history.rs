@@ -0,0 +1,46 @@
+//! Processing-history records.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// One applied processing step, in the order it was applied.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ProcessingStep {
+    /// Step name (e.g. "despike", "normalize").
+    pub step: String,
+    /// Parameters the step was run with.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub parameters: BTreeMap<String, serde_json::Value>,
+    /// Version of this library that applied the step.
+    pub version: String,
+}
+
+impl ProcessingStep {
+    /// Create a step with no parameters, stamped with the library version.
+    pub fn new(step: &str) -> Self {
+        Self {
+            step: step.to_string(),
+            parameters: BTreeMap::new(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// Add a parameter.
+    pub fn param(mut self, key: &str, value: impl Into<serde_json::Value>) -> Self {
+        self.parameters.insert(key.to_string(), value.into());
+        self
+    }
+
+    /// One-line summary, e.g. `despike(threshold=6, window=2)`.
+    pub fn summary(&self) -> String {
+        let params: Vec<String> = self
+            .parameters
+            .iter()
+            .map(|(k, v)| match v {
+                serde_json::Value::String(s) => format!("{}={}", k, s),
+                other => format!("{}={}", k, other),
+            })
+            .collect();
+        format!("{}({})", self.step, params.join(", "))
+    }
+}
@@ -0,0 +1,109 @@
+//! Small dense linear algebra helpers for the fitting routines.
+
+/// Solve `a * x = b` by Gaussian elimination with partial pivoting.
+///
+/// Returns `None` if the matrix is singular.
+pub(crate) fn solve(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() < 1e-300 {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+
+        let (upper, lower) = a.split_at_mut(col + 1);
+        let pivot_row = &upper[col];
+        for (offset, row) in lower.iter_mut().enumerate() {
+            let factor = row[col] / pivot_row[col];
+            for (value, &p) in row[col..].iter_mut().zip(&pivot_row[col..]) {
+                *value -= factor * p;
+            }
+            b[col + 1 + offset] -= factor * b[col];
+        }
+    }
+
+    let mut x = vec![0.0; n];
+    for row in (0..n).rev() {
+        let sum: f64 = (row + 1..n).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - sum) / a[row][row];
+    }
+    Some(x)
+}
+
+/// Solve `a * x = b` for a symmetric positive-definite banded matrix by
+/// Cholesky decomposition.
+///
+/// `bands[i][k]` holds `a[i][i - k]` for `k` up to the half bandwidth.
+/// Returns `None` if the matrix is not positive definite.
+pub(crate) fn solve_banded_spd(bands: &[Vec<f64>], b: &[f64]) -> Option<Vec<f64>> {
+    let n = b.len();
+    let width = bands.first().map_or(0, |row| row.len());
+    // l[i][k] holds L[i][i - k]
+    let mut l = vec![vec![0.0; width]; n];
+    for i in 0..n {
+        for k in (0..width.min(i + 1)).rev() {
+            let j = i - k;
+            let mut sum = bands[i][k];
+            for m in 1..width - k {
+                if m > j {
+                    break;
+                }
+                sum -= l[i][k + m] * l[j][m];
+            }
+            if k == 0 {
+                if sum <= 0.0 || !sum.is_finite() {
+                    return None;
+                }
+                l[i][0] = sum.sqrt();
+            } else {
+                l[i][k] = sum / l[j][0];
+            }
+        }
+    }
+
+    let mut z = vec![0.0; n];
+    for i in 0..n {
+        let sum: f64 = (1..width.min(i + 1)).map(|k| l[i][k] * z[i - k]).sum();
+        z[i] = (b[i] - sum) / l[i][0];
+    }
+    let mut x = vec![0.0; n];
+    for i in (0..n).rev() {
+        let sum: f64 = (1..width).filter(|k| i + k < n).map(|k| l[i + k][k] * x[i + k]).sum();
+        x[i] = (z[i] - sum) / l[i][0];
+    }
+    Some(x)
+}
+
+/// Linear least squares: the coefficients `c` minimizing
+/// `sum_i (y_i - rows_i . c)^2`, via the normal equations.
+pub(crate) fn least_squares(rows: &[Vec<f64>], y: &[f64]) -> Option<Vec<f64>> {
+    let n = rows.first()?.len();
+    let mut a = vec![vec![0.0; n]; n];
+    let mut b = vec![0.0; n];
+    for (row, &yi) in rows.iter().zip(y) {
+        for (r, a_row) in a.iter_mut().enumerate() {
+            for (c, value) in a_row.iter_mut().enumerate() {
+                *value += row[r] * row[c];
+            }
+            b[r] += row[r] * yi;
+        }
+    }
+    solve(a, b)
+}
+
+/// Least-squares polynomial fit of degree `degree`; coefficients in
+/// ascending order of power.
+pub(crate) fn polyfit(x: &[f64], y: &[f64], degree: usize) -> Option<Vec<f64>> {
+    let rows: Vec<Vec<f64>> = x
+        .iter()
+        .map(|&xi| (0..=degree).map(|k| xi.powi(k as i32)).collect())
+        .collect();
+    least_squares(&rows, y)
+}
+
+/// Evaluate a polynomial with coefficients in ascending order of power.
+pub(crate) fn polyval(coefficients: &[f64], x: f64) -> f64 {
+    coefficients.iter().rev().fold(0.0, |acc, &c| acc * x + c)
+}
@@ -0,0 +1,216 @@
+//! Sample × point matrices of many spectra, for multivariate analysis (PCA,
+//! PLS) in external tools.
+
+use super::{baseline_corrected, normalized, resample, smoothed, BaselineMethod, NormalizeMode, SmoothKernel};
+use crate::output::{axis_info, select_axis};
+use crate::spectre::{AxisType, SpcFile};
+use serde::Serialize;
+
+/// Alignment and preprocessing for [`SpectraMatrix::from_named`].
+///
+/// Blank subtraction, smoothing and baseline correction run on each
+/// spectrum at its own points, in that order; normalization then runs on
+/// each row of the matrix and mean centering on its columns.
+#[derive(Debug, Clone, Default)]
+pub struct MatrixOptions {
+    /// Axis the spectra are aligned on; every spectrum must have it
+    /// [default: the first spectrum's display axis, else its best one]
+    pub axis: Option<AxisType>,
+    /// Shared x values [default: the first spectrum's, within the range
+    /// every spectrum covers]
+    pub grid: Option<Vec<f64>>,
+    /// Lowest x value kept
+    pub x_min: Option<f64>,
+    /// Highest x value kept
+    pub x_max: Option<f64>,
+    /// Subtract each spectrum's blank.
+    pub subtract_blank: bool,
+    pub smooth: Option<SmoothKernel>,
+    pub baseline: Option<BaselineMethod>,
+    pub normalize: Option<NormalizeMode>,
+    /// Subtract the mean spectrum from every row.
+    pub mean_center: bool,
+}
+
+/// Spectra on one x-axis, one row per sample.
+#[derive(Debug, Clone, Serialize)]
+pub struct SpectraMatrix {
+    /// X-axis shared by all rows.
+    pub axis: AxisType,
+    /// X values, one per column.
+    pub x: Vec<f64>,
+    /// Sample name of each row.
+    pub samples: Vec<String>,
+    /// One row of intensities per sample; NaN where a grid point lies
+    /// outside a spectrum's range.
+    pub intensities: Vec<Vec<f64>>,
+    /// Column means subtracted by [`MatrixOptions::mean_center`], for
+    /// centering new samples the same way.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mean: Option<Vec<f64>>,
+}
+
+impl SpectraMatrix {
+    /// Build a matrix of spectra named by their UIDs (numbered by position
+    /// where UIDs repeat); see [`SpectraMatrix::from_named`].
+    pub fn from_spectra(spectra: &[SpcFile], options: &MatrixOptions) -> Result<Self, String> {
+        let names = unique_names(spectra);
+        let named: Vec<(&str, &SpcFile)> = names.iter().map(String::as_str).zip(spectra).collect();
+        Self::from_named(&named, options)
+    }
+
+    /// Build a matrix of `(name, spectrum)` pairs, in the given order.
+    ///
+    /// Spectra whose x values differ from the shared ones are linearly
+    /// interpolated onto them.
+    pub fn from_named(spectra: &[(&str, &SpcFile)], options: &MatrixOptions) -> Result<Self, String> {
+        let Some((_, first)) = spectra.first() else {
+            return Err("no spectra for the matrix".to_string());
+        };
+        let axis = match options.axis {
+            Some(axis) => axis,
+            None => match select_axis(first, None).map_err(|e| e.to_string())?.name {
+                "Raman Shift" => AxisType::RamanShifts,
+                "Wavelength" => AxisType::Wavelengths,
+                _ => AxisType::Pixels,
+            },
+        };
+
+        let mut aligned = Vec::with_capacity(spectra.len());
+        for (name, spc) in spectra {
+            let Some(x) = axis_info(spc, axis) else {
+                return Err(format!("{} has no {} axis", name, axis_name(axis)));
+            };
+            let mut data = if options.subtract_blank { spc.blank_subtracted() } else { spc.data.clone() };
+            if let Some(kernel) = options.smooth {
+                data = smoothed(&data, kernel);
+            }
+            if let Some(method) = options.baseline {
+                data = baseline_corrected(&data, method);
+            }
+            aligned.push((x.values, data));
+        }
+
+        let mut grid = match options.grid {
+            Some(ref grid) => grid.clone(),
+            None => {
+                let (low, high) = aligned.iter().fold((f64::NEG_INFINITY, f64::INFINITY), |(low, high), (x, _)| {
+                    let (min, max) = x.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(a, b), &v| (a.min(v), b.max(v)));
+                    (low.max(min), high.min(max))
+                });
+                aligned[0].0.iter().copied().filter(|&x| x >= low && x <= high).collect()
+            }
+        };
+        grid.retain(|&x| options.x_min.is_none_or(|min| x >= min) && options.x_max.is_none_or(|max| x <= max));
+        if grid.is_empty() {
+            return Err(format!("the spectra share no {} range", axis_name(axis)));
+        }
+
+        let mut intensities: Vec<Vec<f64>> = aligned
+            .iter()
+            .map(|(x, data)| {
+                let row = if x[..] == grid[..] { data.clone() } else { resample(x, data, &grid) };
+                match options.normalize {
+                    Some(mode) => normalized(&row, mode),
+                    None => row,
+                }
+            })
+            .collect();
+
+        let mean = options.mean_center.then(|| {
+            let n = intensities.len() as f64;
+            let mean: Vec<f64> = (0..grid.len()).map(|j| intensities.iter().map(|row| row[j]).sum::<f64>() / n).collect();
+            for row in &mut intensities {
+                for (value, mean) in row.iter_mut().zip(&mean) {
+                    *value -= mean;
+                }
+            }
+            mean
+        });
+
+        Ok(Self {
+            axis,
+            x: grid,
+            samples: spectra.iter().map(|(name, _)| name.to_string()).collect(),
+            intensities,
+            mean,
+        })
+    }
+
+    /// Number of rows (samples) and columns (points).
+    pub fn shape(&self) -> (usize, usize) {
+        (self.intensities.len(), self.x.len())
+    }
+}
+
+/// UIDs of `spectra`, numbered by position where they repeat.
+pub(crate) fn unique_names(spectra: &[SpcFile]) -> Vec<String> {
+    spectra
+        .iter()
+        .enumerate()
+        .map(|(i, spc)| match spectra.iter().filter(|other| other.uid == spc.uid).count() {
+            1 => spc.uid.clone(),
+            _ => format!("{}_{}", spc.uid, i),
+        })
+        .collect()
+}
+
+fn axis_name(axis: AxisType) -> &'static str {
+    match axis {
+        AxisType::RamanShifts => "Raman shift",
+        AxisType::Wavelengths => "wavelength",
+        AxisType::Pixels => "pixel",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spectre::SpcFileBuilder;
+
+    #[test]
+    fn test_spectra_matrix() {
+        let spectrum = |uid: &str, x: Vec<f64>, data: Vec<f64>| {
+            let mut spc = SpcFileBuilder::new(uid, data).blank(vec![1.0; x.len()]).build();
+            spc.wavelength_axis = Some(x);
+            spc
+        };
+        let spectra = vec![
+            spectrum("A", vec![500.0, 501.0, 502.0, 503.0], vec![2.0, 4.0, 6.0, 8.0]),
+            spectrum("A", vec![500.5, 501.5, 502.5, 503.5], vec![3.0, 5.0, 7.0, 9.0]),
+        ];
+        let options = MatrixOptions {
+            axis: Some(AxisType::Wavelengths),
+            subtract_blank: true,
+            ..Default::default()
+        };
+
+        // The first spectrum's points that both cover, the second interpolated
+        let matrix = SpectraMatrix::from_spectra(&spectra, &options).unwrap();
+        assert_eq!(matrix.samples, ["A_0", "A_1"]);
+        assert_eq!(matrix.x, [501.0, 502.0, 503.0]);
+        assert_eq!(matrix.intensities, [[3.0, 5.0, 7.0], [3.0, 5.0, 7.0]]);
+        assert_eq!(matrix.shape(), (2, 3));
+
+        let centered = MatrixOptions {
+            grid: Some(vec![500.0, 502.0]),
+            mean_center: true,
+            ..options.clone()
+        };
+        let matrix = SpectraMatrix::from_spectra(&spectra, &centered).unwrap();
+        assert_eq!(matrix.mean.as_ref().unwrap()[1], 5.0);
+        assert!(matrix.intensities[1][0].is_nan());
+        assert_eq!(matrix.intensities[0][1], 0.0);
+
+        let outside = MatrixOptions {
+            x_min: Some(600.0),
+            ..options
+        };
+        assert!(SpectraMatrix::from_spectra(&spectra, &outside).is_err());
+        let no_raman = MatrixOptions {
+            axis: Some(AxisType::RamanShifts),
+            ..Default::default()
+        };
+        assert!(SpectraMatrix::from_spectra(&spectra, &no_raman).is_err());
+    }
+}
@@ -0,0 +1,45 @@
+//! Spectral processing operations on extracted spectra.
+//!
+//! Operations that modify an [`SpcFile`](crate::SpcFile) record themselves in
+//! its `history`, so processed exports remain reproducible.
+
+mod average;
+mod baseline;
+mod blank;
+mod crop;
+mod despike;
+mod fit;
+mod history;
+pub(crate) mod linalg;
+mod matrix;
+mod normalize;
+mod peaks;
+mod precision;
+mod resample;
+mod replay;
+mod response;
+mod roi;
+mod sgolay;
+mod similarity;
+mod smooth;
+mod stats;
+
+pub use average::*;
+pub use baseline::*;
+pub use blank::*;
+pub use crop::*;
+pub use despike::*;
+pub use fit::*;
+pub use history::*;
+pub use matrix::*;
+pub use normalize::*;
+pub use peaks::*;
+pub use precision::*;
+pub use resample::*;
+pub use replay::*;
+pub use response::*;
+pub use roi::*;
+pub use sgolay::*;
+pub use similarity::*;
+pub use smooth::*;
+pub use stats::*;
@@ -0,0 +1,58 @@
+//! Intensity normalization.
+
+use super::ProcessingStep;
+use crate::spectre::SpcFile;
+
+/// Normalization method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormalizeMode {
+    /// Divide by the maximum intensity.
+    Max,
+    /// Divide by the summed absolute intensity.
+    Area,
+    /// Divide by the Euclidean (L2) norm.
+    Vector,
+    /// Rescale to the 0..1 range.
+    MinMax,
+}
+
+impl NormalizeMode {
+    /// Name used in processing history.
+    pub fn name(&self) -> &'static str {
+        match self {
+            NormalizeMode::Max => "max",
+            NormalizeMode::Area => "area",
+            NormalizeMode::Vector => "vector",
+            NormalizeMode::MinMax => "minmax",
+        }
+    }
+}
+
+/// Return a normalized copy of `values`.
+///
+/// Values are returned unchanged if the normalization factor is zero or not finite.
+pub fn normalized(values: &[f64], mode: NormalizeMode) -> Vec<f64> {
+    let (offset, scale) = match mode {
+        NormalizeMode::Max => (0.0, values.iter().cloned().fold(f64::NEG_INFINITY, f64::max)),
+        NormalizeMode::Area => (0.0, values.iter().map(|v| v.abs()).sum()),
+        NormalizeMode::Vector => (0.0, values.iter().map(|v| v * v).sum::<f64>().sqrt()),
+        NormalizeMode::MinMax => {
+            let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+            let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            (min, max - min)
+        }
+    };
+
+    if scale == 0.0 || !scale.is_finite() {
+        return values.to_vec();
+    }
+
+    values.iter().map(|v| (v - offset) / scale).collect()
+}
+
+/// Normalize the intensity data in place and record the step.
+pub fn normalize(spc: &mut SpcFile, mode: NormalizeMode) {
+    spc.data = normalized(&spc.data, mode);
+    spc.history
+        .push(ProcessingStep::new("normalize").param("mode", mode.name()));
+}
@@ -0,0 +1,254 @@
+//! Peak detection: local maxima with prominence and full width at half
+//! prominence, referenced to the best available x-axis.
+
+use super::stats::noise_level;
+use crate::output::select_best_axis;
+use crate::spectre::SpcFile;
+use serde::Serialize;
+
+/// Default minimum prominence, in multiples of the estimated noise level.
+pub const DEFAULT_PROMINENCE_NOISE_FACTOR: f64 = 5.0;
+
+/// A detected peak.
+#[derive(Debug, Clone, Serialize)]
+pub struct Peak {
+    /// Index of the peak maximum in the data
+    pub index: usize,
+    /// Axis position of the maximum
+    pub position: f64,
+    pub intensity: f64,
+    /// Height above the higher of the two surrounding minima
+    pub prominence: f64,
+    /// Full width at half prominence, in axis units
+    pub fwhm: f64,
+}
+
+/// Peak selection criteria. Widths and distances are in axis units.
+#[derive(Debug, Clone, Default)]
+pub struct PeakOptions {
+    /// Minimum intensity at the maximum.
+    pub min_height: Option<f64>,
+    /// Minimum prominence [default: 5x the estimated noise level].
+    pub min_prominence: Option<f64>,
+    /// Minimum full width at half prominence.
+    pub min_width: Option<f64>,
+    /// Maximum full width at half prominence.
+    pub max_width: Option<f64>,
+    /// Minimum distance between peaks; of two closer peaks the more
+    /// intense one is kept.
+    pub min_distance: Option<f64>,
+    /// Minimum vertical distance of the maximum to each neighbouring point
+    /// (scipy's `threshold`), in intensity units.
+    pub threshold: Option<f64>,
+}
+
+/// Find peaks in a spectrum, positioned on its best available axis.
+pub fn find_peaks(spc: &SpcFile, options: PeakOptions) -> Vec<Peak> {
+    let axis = select_best_axis(spc);
+    find_peaks_xy(&axis.values, &spc.data, &options)
+}
+
+/// Find peaks in an x/y series.
+///
+/// Without a minimum prominence, peaks must rise
+/// [`DEFAULT_PROMINENCE_NOISE_FACTOR`] times the estimated noise level above
+/// their surroundings.
+pub fn find_peaks_xy(x: &[f64], y: &[f64], options: &PeakOptions) -> Vec<Peak> {
+    let min_prominence = options.min_prominence.unwrap_or_else(|| default_prominence(y));
+    let mut peaks: Vec<Peak> = detect(x, y, min_prominence)
+        .into_iter()
+        .filter(|p| options.min_height.is_none_or(|h| p.intensity >= h))
+        .filter(|p| options.min_width.is_none_or(|w| p.fwhm >= w))
+        .filter(|p| options.max_width.is_none_or(|w| p.fwhm <= w))
+        .filter(|p| options.threshold.is_none_or(|t| neighbour_drop(y, p.index) >= t))
+        .collect();
+
+    if let Some(distance) = options.min_distance {
+        // Keep peaks from the most intense down, dropping close neighbours
+        let mut order: Vec<usize> = (0..peaks.len()).collect();
+        order.sort_by(|&a, &b| peaks[b].intensity.total_cmp(&peaks[a].intensity));
+        let mut keep = vec![true; peaks.len()];
+        for (n, &i) in order.iter().enumerate() {
+            if keep[i] {
+                for &j in &order[n + 1..] {
+                    if (peaks[j].position - peaks[i].position).abs() < distance {
+                        keep[j] = false;
+                    }
+                }
+            }
+        }
+        let mut keep = keep.into_iter();
+        peaks.retain(|_| keep.next().unwrap_or(false));
+    }
+
+    peaks
+}
+
+fn default_prominence(y: &[f64]) -> f64 {
+    let noise = noise_level(y);
+    if noise.is_finite() && noise > 0.0 {
+        return noise * DEFAULT_PROMINENCE_NOISE_FACTOR;
+    }
+    // Noise-free data: 1% of the intensity span
+    let min = y.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = y.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    (max - min) * 0.01
+}
+
+fn detect(x: &[f64], y: &[f64], min_prominence: f64) -> Vec<Peak> {
+    local_maxima(y)
+        .into_iter()
+        .filter_map(|i| {
+            let prominence = prominence(y, i);
+            (prominence >= min_prominence).then(|| Peak {
+                index: i,
+                position: x.get(i).copied().unwrap_or(i as f64),
+                intensity: y[i],
+                prominence,
+                fwhm: width_at(x, y, i, y[i] - prominence / 2.0),
+            })
+        })
+        .collect()
+}
+
+/// Indices of strict local maxima; a flat top counts once, at its middle.
+fn local_maxima(y: &[f64]) -> Vec<usize> {
+    let mut maxima = Vec::new();
+    let mut i = 1;
+    while i + 1 < y.len() {
+        if y[i - 1] < y[i] {
+            let mut end = i;
+            while end + 1 < y.len() && y[end + 1] == y[i] {
+                end += 1;
+            }
+            if end + 1 < y.len() && y[end + 1] < y[i] {
+                maxima.push((i + end) / 2);
+            }
+            i = end + 1;
+        } else {
+            i += 1;
+        }
+    }
+    maxima
+}
+
+/// How far the signal drops from `peak` to the nearer of its neighbours;
+/// for a flat top, to the points either side of the plateau.
+fn neighbour_drop(y: &[f64], peak: usize) -> f64 {
+    let height = y[peak];
+    let left = y[..peak].iter().rev().find(|&&v| v != height);
+    let right = y[peak + 1..].iter().find(|&&v| v != height);
+    match (left, right) {
+        (Some(left), Some(right)) => height - left.max(*right),
+        _ => 0.0,
+    }
+}
+
+/// Peak height above the higher of the lowest points on either side before
+/// the signal rises above the peak (or the data ends).
+fn prominence(y: &[f64], peak: usize) -> f64 {
+    let height = y[peak];
+    let base = |range: &mut dyn Iterator<Item = usize>| {
+        let mut lowest = height;
+        for j in range {
+            if y[j] > height {
+                break;
+            }
+            lowest = lowest.min(y[j]);
+        }
+        lowest
+    };
+    let left = base(&mut (0..peak).rev());
+    let right = base(&mut (peak + 1..y.len()));
+    height - left.max(right)
+}
+
+/// Width in axis units where the signal around `peak` crosses `level`.
+fn width_at(x: &[f64], y: &[f64], peak: usize, level: f64) -> f64 {
+    let mut left = peak as f64;
+    for j in (0..peak).rev() {
+        if y[j] < level {
+            left = j as f64 + (level - y[j]) / (y[j + 1] - y[j]);
+            break;
+        }
+        left = j as f64;
+    }
+    let mut right = peak as f64;
+    for j in peak + 1..y.len() {
+        if y[j] < level {
+            right = (j - 1) as f64 + (y[j - 1] - level) / (y[j - 1] - y[j]);
+            break;
+        }
+        right = j as f64;
+    }
+    (position_at(x, right) - position_at(x, left)).abs()
+}
+
+/// Axis value at a fractional index.
+fn position_at(x: &[f64], index: f64) -> f64 {
+    let i = index.floor() as usize;
+    match (x.get(i), x.get(i + 1)) {
+        (Some(&a), Some(&b)) => a + (b - a) * (index - i as f64),
+        (Some(&a), None) => a,
+        _ => index,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let x: Vec<f64> = (0..100).map(|i| i as f64 * 2.0).collect();
+        let y: Vec<f64> = x
+            .iter()
+            .map(|&xi| 10.0 + 100.0 * (-((xi - 60.0) / 10.0).powi(2)).exp() + 30.0 * (-((xi - 140.0) / 4.0).powi(2)).exp())
+            .collect();
+
+        let peaks = detect(&x, &y, 5.0);
+        assert_eq!(peaks.len(), 2);
+        assert_eq!(peaks[0].position, 60.0);
+        assert!((peaks[0].prominence - 100.0).abs() < 0.1);
+        // Gaussian FWHM = 2 * sqrt(ln 2) * 10
+        assert!((peaks[0].fwhm - 16.65).abs() < 0.2);
+        assert_eq!(peaks[1].position, 140.0);
+
+        assert_eq!(detect(&x, &y, 50.0).len(), 1);
+
+        let narrow = PeakOptions {
+            min_prominence: Some(5.0),
+            max_width: Some(10.0),
+            ..Default::default()
+        };
+        assert_eq!(find_peaks_xy(&x, &y, &narrow)[0].position, 140.0);
+        let apart = PeakOptions {
+            min_prominence: Some(5.0),
+            min_distance: Some(100.0),
+            ..Default::default()
+        };
+        assert_eq!(find_peaks_xy(&x, &y, &apart).len(), 1);
+    }
+
+    #[test]
+    fn test_threshold() {
+        // A sharp peak and a shallow shoulder on its flank
+        let y = [0.0, 20.0, 60.0, 100.0, 60.0, 30.0, 31.0, 31.5, 31.0, 20.0, 0.0];
+        let x: Vec<f64> = (0..y.len()).map(|i| i as f64).collect();
+        let options = PeakOptions {
+            min_prominence: Some(1.0),
+            ..Default::default()
+        };
+        assert_eq!(find_peaks_xy(&x, &y, &options).len(), 2);
+
+        let sharp = PeakOptions {
+            threshold: Some(5.0),
+            ..options
+        };
+        let peaks = find_peaks_xy(&x, &y, &sharp);
+        assert_eq!(peaks.len(), 1);
+        assert_eq!(peaks[0].index, 3);
+
+        assert_eq!(neighbour_drop(&[0.0, 4.0, 4.0, 4.0, 1.0], 2), 3.0);
+    }
+}
@@ -0,0 +1,32 @@
+//! Single-precision export (`--export-precision f32`).
+
+use super::ProcessingStep;
+use crate::spectre::SpcFile;
+
+/// `value` rounded to the nearest `f32`.
+pub fn to_single_precision(value: f64) -> f64 {
+    value as f32 as f64
+}
+
+/// Round the intensities and the blank to single precision in place and
+/// record the step.
+pub fn single_precision(spc: &mut SpcFile) {
+    for value in spc.data.iter_mut().chain(spc.blank.iter_mut()) {
+        *value = to_single_precision(*value);
+    }
+    spc.history.push(ProcessingStep::new("single_precision"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_precision() {
+        assert_eq!(to_single_precision(1069.0), 1069.0);
+        assert_eq!(to_single_precision(0.123456789012), f64::from(0.12345679f32));
+        assert_eq!(to_single_precision(1.0 / 3.0) as f32, 1.0f32 / 3.0);
+        assert_eq!(to_single_precision(1e40), f64::INFINITY);
+        assert!(to_single_precision(f64::NAN).is_nan());
+    }
+}
@@ -0,0 +1,101 @@
+//! Replaying the processing chain stored in a file's config.
+//!
+//! The Spectrum Analyzer Suite stores its display settings with each
+//! spectrum but saves the raw data. The steps enabled there are applied in
+//! the order the suite's parameters dialog lists them: median filter,
+//! smoothing, Savitzky-Golay, baseline removal.
+
+use super::baseline::{subtract_baseline, BaselineMethod};
+use super::despike::median_filtered;
+use super::sgolay::{apply_savitzky_golay, SavitzkyGolay};
+use super::smooth::moving_average;
+use super::ProcessingStep;
+use crate::spectre::SpcFile;
+
+/// Median filter width used when `medfilt` is enabled (the config stores
+/// no width).
+pub const CONFIG_MEDFILT_WIDTH: usize = 3;
+
+/// Apply the processing steps enabled in the file's config, recording each
+/// one.
+///
+/// `smoothing` is taken as the width of a moving average (values of 1 or
+/// less disable it); the baseline is removed by ALS with default
+/// parameters. Frame averaging (`average`) happens during acquisition and is
+/// already reflected in the stored data, so it is not replayed. Files
+/// without a config are left unchanged.
+pub fn apply_config(spc: &mut SpcFile) -> Result<(), String> {
+    let Some(config) = spc.config.clone() else {
+        return Ok(());
+    };
+
+    if config.medfilt == Some(true) {
+        spc.data = median_filtered(&spc.data, CONFIG_MEDFILT_WIDTH);
+        spc.history.push(
+            ProcessingStep::new("median_filter")
+                .param("width", CONFIG_MEDFILT_WIDTH)
+                .param("source", "config"),
+        );
+    }
+
+    if let Some(width) = config.smoothing.filter(|&w| w > 1) {
+        spc.data = moving_average(&spc.data, width as usize);
+        spc.history.push(
+            ProcessingStep::new("smooth")
+                .param("kernel", "boxcar")
+                .param("width", width)
+                .param("source", "config"),
+        );
+    }
+
+    if config.sgolay == Some(true) {
+        let filter = SavitzkyGolay::from_config(&config)
+            .ok_or("Savitzky-Golay is enabled but its settings are incomplete")?;
+        apply_savitzky_golay(spc, &filter).map_err(|e| format!("stored Savitzky-Golay settings: {}", e))?;
+    }
+
+    if config.baseline == Some(true) {
+        subtract_baseline(spc, BaselineMethod::als());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spectre::Config;
+
+    #[test]
+    fn test_apply_config() {
+        let mut spc = SpcFile {
+            uid: "CAM-1".to_string(),
+            data: vec![1.0, 1.0, 9.0, 1.0, 4.0, 4.0, 4.0],
+            blank: Vec::new(),
+            calibration: None,
+            config: Some(Config {
+                medfilt: Some(true),
+                smoothing: Some(3),
+                sgolay: Some(false),
+                ..Default::default()
+            }),
+            wavelength_axis: None,
+            raman_shift_axis: None,
+            acquired: None,
+            operator: None,
+            sample: None,
+            instrument: None,
+            provenance: None,
+            stats: None,
+            metadata: Default::default(),
+            history: Vec::new(),
+            extras: Vec::new(),
+        };
+        apply_config(&mut spc).unwrap();
+
+        let steps: Vec<&str> = spc.history.iter().map(|s| s.step.as_str()).collect();
+        assert_eq!(steps, ["median_filter", "smooth"]);
+        // Median [1, 1, 1, 4, 4, 4, 4], then a 3-point moving average
+        assert_eq!(spc.data, vec![1.0, 1.0, 2.0, 3.0, 4.0, 4.0, 4.0]);
+    }
+}
@@ -0,0 +1,85 @@
+//! Resampling spectra onto another x-axis, and downsampling.
+
+/// Linearly interpolate `y(x)` at each point of `x_new`.
+///
+/// `x` may be ascending or descending. Points outside the range of `x` are
+/// NaN.
+pub fn resample(x: &[f64], y: &[f64], x_new: &[f64]) -> Vec<f64> {
+    let n = x.len().min(y.len());
+    if n == 0 {
+        return vec![f64::NAN; x_new.len()];
+    }
+
+    // Work on an ascending copy
+    let mut points: Vec<(f64, f64)> = x[..n].iter().cloned().zip(y[..n].iter().cloned()).collect();
+    if points.len() > 1 && points[0].0 > points[n - 1].0 {
+        points.reverse();
+    }
+
+    x_new
+        .iter()
+        .map(|&xi| {
+            let upper = points.partition_point(|p| p.0 < xi);
+            if upper < n && points[upper].0 == xi {
+                return points[upper].1;
+            }
+            if upper == 0 || upper == n {
+                return f64::NAN;
+            }
+            let (x0, y0) = points[upper - 1];
+            let (x1, y1) = points[upper];
+            y0 + (y1 - y0) * (xi - x0) / (x1 - x0)
+        })
+        .collect()
+}
+
+/// How [`downsample`] reduces a run of points to one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DownsampleMode {
+    /// Mean of the run
+    #[default]
+    Mean,
+    /// Middle point of the run
+    Decimate,
+}
+
+/// Reduce `values` to `points` values, each standing for an equal run of
+/// consecutive values. Fewer values than `points` are returned unchanged.
+pub fn downsample(values: &[f64], points: usize, mode: DownsampleMode) -> Vec<f64> {
+    let n = values.len();
+    if points == 0 || n <= points {
+        return values.to_vec();
+    }
+    (0..points)
+        .map(|i| {
+            let run = &values[i * n / points..(i + 1) * n / points];
+            match mode {
+                DownsampleMode::Mean => run.iter().sum::<f64>() / run.len() as f64,
+                DownsampleMode::Decimate => run[run.len() / 2],
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resample() {
+        let x = [3.0, 2.0, 1.0];
+        let y = [30.0, 20.0, 10.0];
+        let out = resample(&x, &y, &[0.5, 1.0, 1.5, 3.0, 3.5]);
+        assert!(out[0].is_nan());
+        assert_eq!(&out[1..4], &[10.0, 15.0, 30.0]);
+        assert!(out[4].is_nan());
+    }
+
+    #[test]
+    fn test_downsample() {
+        let values: Vec<f64> = (0..10).map(f64::from).collect();
+        assert_eq!(downsample(&values, 4, DownsampleMode::Mean), vec![0.5, 3.0, 5.5, 8.0]);
+        assert_eq!(downsample(&values, 4, DownsampleMode::Decimate), vec![1.0, 3.0, 6.0, 8.0]);
+        assert_eq!(downsample(&values, 20, DownsampleMode::Mean), values);
+    }
+}
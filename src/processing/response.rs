@@ -0,0 +1,140 @@
+//! Instrument-response (relative intensity) correction.
+//!
+//! A correction curve, e.g. derived from a calibrated white-light source or
+//! a NIST SRM reference, holds the factor each intensity is multiplied by,
+//! as a function of wavelength or pixel index.
+
+use super::resample::resample;
+use super::ProcessingStep;
+use crate::spectre::SpcFile;
+
+/// Axis a response curve is tabulated on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseAxis {
+    /// Wavelength in nm
+    Wavelength,
+    /// Pixel index
+    Pixel,
+}
+
+/// Relative-intensity correction factors tabulated against an axis.
+#[derive(Debug, Clone)]
+pub struct ResponseCurve {
+    pub axis: ResponseAxis,
+    pub x: Vec<f64>,
+    pub factor: Vec<f64>,
+}
+
+impl ResponseCurve {
+    /// Parse a two-column CSV (comma, semicolon, tab or space separated):
+    /// x, then the correction factor. `#` lines are comments. A header row
+    /// is optional; if its first column is `pixel` or `index` the curve is
+    /// tabulated on pixel indices, otherwise on wavelengths in nm.
+    pub fn from_csv(text: &str) -> Result<Self, String> {
+        let mut axis = ResponseAxis::Wavelength;
+        let mut x = Vec::new();
+        let mut factor = Vec::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line
+                .split([',', ';', '\t', ' '])
+                .map(str::trim)
+                .filter(|f| !f.is_empty())
+                .collect();
+            if fields.len() < 2 {
+                return Err(format!("line {}: expected two columns", number + 1));
+            }
+            match (fields[0].parse::<f64>(), fields[1].parse::<f64>()) {
+                (Ok(xi), Ok(fi)) => {
+                    x.push(xi);
+                    factor.push(fi);
+                }
+                _ if x.is_empty() => {
+                    // Header row
+                    let name = fields[0].trim_matches('"').to_ascii_lowercase();
+                    if name == "pixel" || name == "index" {
+                        axis = ResponseAxis::Pixel;
+                    }
+                }
+                _ => return Err(format!("line {}: not a number", number + 1)),
+            }
+        }
+
+        if x.len() < 2 {
+            return Err("a response curve needs at least two points".to_string());
+        }
+        if !x.windows(2).all(|w| w[1] > w[0]) && !x.windows(2).all(|w| w[1] < w[0]) {
+            return Err("the x column must be strictly increasing or decreasing".to_string());
+        }
+        Ok(ResponseCurve { axis, x, factor })
+    }
+
+    /// Correction factors at the given axis positions (NaN outside the curve).
+    pub fn factors_at(&self, x: &[f64]) -> Vec<f64> {
+        resample(&self.x, &self.factor, x)
+    }
+}
+
+/// Multiply the intensity data by the response curve, interpolated onto
+/// the spectrum's wavelength axis (or pixel indices), and record the step.
+///
+/// Fails if the spectrum has no wavelength axis for a wavelength curve, or
+/// extends beyond the curve.
+pub fn correct_response(spc: &mut SpcFile, curve: &ResponseCurve, source: &str) -> Result<(), String> {
+    let x = match curve.axis {
+        ResponseAxis::Wavelength => spc
+            .wavelengths()
+            .map(|axis| axis.into_owned())
+            .ok_or("the response curve is tabulated on wavelength but the file has no calibration")?,
+        ResponseAxis::Pixel => (0..spc.data.len()).map(|i| i as f64).collect(),
+    };
+
+    let factors = curve.factors_at(&x);
+    if let Some(i) = factors.iter().position(|f| !f.is_finite()) {
+        let first = curve.x.first().copied().unwrap_or(f64::NAN);
+        let last = curve.x.last().copied().unwrap_or(f64::NAN);
+        return Err(format!(
+            "the response curve ({} to {}) does not cover x = {} (pixel {})",
+            first, last, x[i], i
+        ));
+    }
+
+    for (v, f) in spc.data.iter_mut().zip(&factors) {
+        *v *= f;
+    }
+    spc.history.push(
+        ProcessingStep::new("response_correction")
+            .param("source", source)
+            .param(
+                "axis",
+                match curve.axis {
+                    ResponseAxis::Wavelength => "wavelength",
+                    ResponseAxis::Pixel => "pixel",
+                },
+            ),
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_response_curve() {
+        let curve = ResponseCurve::from_csv("# SRM 2241\nwavelength_nm,factor\n700,1.0\n800,2.0\n900,4.0\n").unwrap();
+        assert_eq!(curve.axis, ResponseAxis::Wavelength);
+        assert_eq!(curve.factors_at(&[750.0, 850.0, 950.0])[..2], [1.5, 3.0]);
+        assert!(curve.factors_at(&[950.0])[0].is_nan());
+
+        let pixels = ResponseCurve::from_csv("pixel\tfactor\n0\t1\n511\t1.2\n").unwrap();
+        assert_eq!(pixels.axis, ResponseAxis::Pixel);
+
+        assert!(ResponseCurve::from_csv("700,1\n700,2\n").is_err());
+        assert!(ResponseCurve::from_csv("700,1\n800,x\n").is_err());
+    }
+}
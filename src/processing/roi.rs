@@ -0,0 +1,247 @@
+//! Regions of interest: band areas, maxima and area ratios.
+//!
+//! A region set can be loaded from TOML:
+//!
+//! ```toml
+//! [[roi]]
+//! name = "ring"
+//! start = 990.0
+//! end = 1010.0
+//!
+//! [[roi]]
+//! name = "ch"
+//! start = 2850.0
+//! end = 3000.0
+//!
+//! [[ratio]]
+//! numerator = "ring"
+//! denominator = "ch"
+//! ```
+//!
+//! Ranges are in the units of the spectrum's best available axis
+//! (Raman shift, then wavelength, then pixel index).
+
+use crate::output::select_best_axis;
+use crate::spectre::SpcFile;
+use serde::{Deserialize, Serialize};
+
+/// A named x-range.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Roi {
+    pub name: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+/// A ratio of two ROI areas, referenced by name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoiRatio {
+    pub numerator: String,
+    pub denominator: String,
+}
+
+impl RoiRatio {
+    /// Display name, e.g. `ring/ch`.
+    pub fn name(&self) -> String {
+        format!("{}/{}", self.numerator, self.denominator)
+    }
+}
+
+/// A set of regions and ratios.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RoiSet {
+    pub roi: Vec<Roi>,
+    pub ratio: Vec<RoiRatio>,
+}
+
+/// Measurements of one region.
+#[derive(Debug, Clone, Serialize)]
+pub struct RoiResult {
+    pub name: String,
+    /// Trapezoidal area under the intensity curve.
+    pub area: f64,
+    /// Maximum intensity in the region (NaN if the region is empty).
+    pub max_intensity: f64,
+    /// Axis position of the maximum.
+    pub max_position: f64,
+    /// Number of points in the region.
+    pub points: usize,
+}
+
+/// The value of one area ratio.
+#[derive(Debug, Clone, Serialize)]
+pub struct RoiRatioResult {
+    pub name: String,
+    pub value: f64,
+}
+
+/// Measurements of every region of a set on one spectrum.
+#[derive(Debug, Clone, Serialize)]
+pub struct RoiReport {
+    /// Name of the axis the ranges were applied to.
+    pub axis: &'static str,
+    pub regions: Vec<RoiResult>,
+    /// Area ratios in the order of the set's `ratio` entries.
+    pub ratios: Vec<RoiRatioResult>,
+}
+
+/// Measure one region on an x/y series.
+pub fn measure_roi(x: &[f64], y: &[f64], roi: &Roi) -> RoiResult {
+    let (lo, hi) = if roi.start <= roi.end {
+        (roi.start, roi.end)
+    } else {
+        (roi.end, roi.start)
+    };
+
+    let points: Vec<(f64, f64)> = x
+        .iter()
+        .zip(y)
+        .filter(|(&xi, _)| xi >= lo && xi <= hi)
+        .map(|(&xi, &yi)| (xi, yi))
+        .collect();
+
+    let area = points
+        .windows(2)
+        .map(|w| (w[1].0 - w[0].0).abs() * (w[0].1 + w[1].1) / 2.0)
+        .sum();
+
+    let (max_position, max_intensity) = points
+        .iter()
+        .cloned()
+        .fold((f64::NAN, f64::NAN), |best, p| {
+            if best.1.is_nan() || p.1 > best.1 {
+                p
+            } else {
+                best
+            }
+        });
+
+    RoiResult {
+        name: roi.name.clone(),
+        area,
+        max_intensity,
+        max_position,
+        points: points.len(),
+    }
+}
+
+impl RoiSet {
+    /// Check that region names are unique and ratios reference known regions.
+    pub fn validate(&self) -> Result<(), String> {
+        for (i, roi) in self.roi.iter().enumerate() {
+            if self.roi[..i].iter().any(|r| r.name == roi.name) {
+                return Err(format!("duplicate ROI name `{}`", roi.name));
+            }
+        }
+        for ratio in &self.ratio {
+            for name in [&ratio.numerator, &ratio.denominator] {
+                if !self.roi.iter().any(|r| &r.name == name) {
+                    return Err(format!("ratio `{}` references unknown ROI `{}`", ratio.name(), name));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Measure every region and ratio on a spectrum.
+    pub fn evaluate(&self, spc: &SpcFile) -> RoiReport {
+        let axis = select_best_axis(spc);
+        let regions: Vec<RoiResult> = self
+            .roi
+            .iter()
+            .map(|roi| measure_roi(&axis.values, &spc.data, roi))
+            .collect();
+
+        let area_of = |name: &str| {
+            regions
+                .iter()
+                .find(|r| r.name == name)
+                .map(|r| r.area)
+                .unwrap_or(f64::NAN)
+        };
+        let ratios = self
+            .ratio
+            .iter()
+            .map(|r| RoiRatioResult {
+                name: r.name(),
+                value: area_of(&r.numerator) / area_of(&r.denominator),
+            })
+            .collect();
+
+        RoiReport {
+            axis: axis.name,
+            regions,
+            ratios,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spectre::SpcFileBuilder;
+
+    fn roi(name: &str, start: f64, end: f64) -> Roi {
+        Roi {
+            name: name.to_string(),
+            start,
+            end,
+        }
+    }
+
+    #[test]
+    fn test_measure_roi() {
+        let x = [0.0, 1.0, 2.0, 3.0, 4.0];
+        let y = [0.0, 2.0, 4.0, 2.0, 0.0];
+        let result = measure_roi(&x, &y, &roi("band", 0.5, 3.5));
+        assert_eq!(result.points, 3);
+        assert_eq!(result.area, 6.0);
+        assert_eq!((result.max_position, result.max_intensity), (2.0, 4.0));
+
+        // Reversed bounds select the same points; an empty range has no maximum
+        assert_eq!(measure_roi(&x, &y, &roi("band", 3.5, 0.5)).area, 6.0);
+        let empty = measure_roi(&x, &y, &roi("none", 10.0, 20.0));
+        assert_eq!((empty.points, empty.area), (0, 0.0));
+        assert!(empty.max_intensity.is_nan() && empty.max_position.is_nan());
+    }
+
+    #[test]
+    fn test_load_and_validate() {
+        let set: RoiSet = toml::from_str(
+            "[[roi]]\nname = \"ring\"\nstart = 990.0\nend = 1010.0\n\n[[roi]]\nname = \"ch\"\nstart = 2850.0\nend = 3000.0\n\n[[ratio]]\nnumerator = \"ring\"\ndenominator = \"ch\"\n",
+        )
+        .unwrap();
+        assert_eq!(set.roi.len(), 2);
+        assert_eq!(set.ratio[0].name(), "ring/ch");
+        assert!(set.validate().is_ok());
+        assert!(toml::from_str::<RoiSet>("[[roi]]\nname = \"a\"\nstart = 1.0\nstop = 2.0\n").is_err());
+
+        let mut duplicate = set.clone();
+        duplicate.roi.push(roi("ring", 0.0, 1.0));
+        assert_eq!(duplicate.validate().unwrap_err(), "duplicate ROI name `ring`");
+        let mut unknown = set.clone();
+        unknown.ratio[0].denominator = "oh".to_string();
+        assert_eq!(unknown.validate().unwrap_err(), "ratio `ring/oh` references unknown ROI `oh`");
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let spc = SpcFileBuilder::new("CAM-1", vec![0.0, 1.0, 3.0, 1.0, 1.0, 1.0, 0.0]).build();
+        let set = RoiSet {
+            roi: vec![roi("peak", 0.0, 3.0), roi("tail", 3.0, 6.0)],
+            ratio: vec![RoiRatio {
+                numerator: "peak".to_string(),
+                denominator: "tail".to_string(),
+            }],
+        };
+        let report = set.evaluate(&spc);
+        assert_eq!(report.axis, select_best_axis(&spc).name);
+        let areas: Vec<f64> = report.regions.iter().map(|r| r.area).collect();
+        assert_eq!(areas, [4.5, 2.5]);
+        assert_eq!(report.ratios[0].name, "peak/tail");
+        assert_eq!(report.ratios[0].value, 1.8);
+    }
+}
@@ -0,0 +1,140 @@
+//! Savitzky-Golay smoothing and differentiation.
+
+use super::linalg::{polyfit, polyval};
+use super::ProcessingStep;
+use crate::spectre::{Config, SpcFile};
+
+/// Savitzky-Golay filter parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SavitzkyGolay {
+    /// Window length in points (odd)
+    pub window: usize,
+    /// Polynomial order (less than `window`)
+    pub order: usize,
+    /// Derivative order (0 for smoothing), per point spacing
+    pub deriv: usize,
+}
+
+impl SavitzkyGolay {
+    /// The parameters stored in a file's config, if the filter is enabled
+    /// there (`sgolay` is true) and all three are present and non-negative.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        if config.sgolay != Some(true) {
+            return None;
+        }
+        Some(SavitzkyGolay {
+            window: usize::try_from(config.sgolay_window?).ok()?,
+            order: usize::try_from(config.sgolay_order?).ok()?,
+            deriv: usize::try_from(config.sgolay_deriv.unwrap_or(0)).ok()?,
+        })
+    }
+
+    /// Check the parameters against a series of `len` points.
+    pub fn check(&self, len: usize) -> Result<(), String> {
+        if self.window.is_multiple_of(2) {
+            return Err(format!("window {} is not odd", self.window));
+        }
+        if self.order >= self.window {
+            return Err(format!("order {} is not less than window {}", self.order, self.window));
+        }
+        if self.deriv > self.order {
+            return Err(format!("derivative {} exceeds order {}", self.deriv, self.order));
+        }
+        if self.window > len {
+            return Err(format!("window {} exceeds the {} data points", self.window, len));
+        }
+        Ok(())
+    }
+}
+
+/// Return the filtered (or differentiated) copy of `values`.
+///
+/// Interior points are convolved with the filter coefficients; the first and
+/// last half-window are evaluated on the polynomial fitted to the first and
+/// last full window.
+pub fn savitzky_golay(values: &[f64], filter: &SavitzkyGolay) -> Result<Vec<f64>, String> {
+    filter.check(values.len())?;
+    let SavitzkyGolay { window, order, deriv } = *filter;
+    let half = window / 2;
+    let offsets: Vec<f64> = (0..window).map(|j| j as f64 - half as f64).collect();
+
+    // Value of the derivative at offset `at` of the polynomial fitted to `y`
+    let evaluate = |y: &[f64], at: f64| -> Result<f64, String> {
+        let coefficients = polyfit(&offsets, y, order).ok_or("singular Savitzky-Golay system")?;
+        let derivative: Vec<f64> = coefficients
+            .iter()
+            .enumerate()
+            .skip(deriv)
+            .map(|(k, c)| c * ((k - deriv + 1)..=k).product::<usize>() as f64)
+            .collect();
+        Ok(polyval(&derivative, at))
+    };
+
+    // Convolution weights: the fit is linear in y, so fit unit vectors
+    let weights = (0..window)
+        .map(|j| {
+            let mut unit = vec![0.0; window];
+            unit[j] = 1.0;
+            evaluate(&unit, 0.0)
+        })
+        .collect::<Result<Vec<f64>, String>>()?;
+
+    let n = values.len();
+    let mut result = vec![0.0; n];
+    for i in half..n - half {
+        result[i] = values[i - half..=i + half].iter().zip(&weights).map(|(v, w)| v * w).sum();
+    }
+    for i in 0..half {
+        result[i] = evaluate(&values[..window], i as f64 - half as f64)?;
+        result[n - 1 - i] = evaluate(&values[n - window..], half as f64 - i as f64)?;
+    }
+    Ok(result)
+}
+
+/// Filter the intensity data in place and record the step.
+pub fn apply_savitzky_golay(spc: &mut SpcFile, filter: &SavitzkyGolay) -> Result<(), String> {
+    spc.data = savitzky_golay(&spc.data, filter)?;
+    spc.history.push(
+        ProcessingStep::new("savitzky_golay")
+            .param("window", filter.window)
+            .param("order", filter.order)
+            .param("deriv", filter.deriv),
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_savitzky_golay_preserves_polynomials() {
+        let values: Vec<f64> = (0..30).map(|i| 2.0 + 0.5 * i as f64 - 0.1 * (i * i) as f64).collect();
+        let smooth = SavitzkyGolay { window: 7, order: 2, deriv: 0 };
+        for (a, b) in savitzky_golay(&values, &smooth).unwrap().iter().zip(&values) {
+            assert!((a - b).abs() < 1e-9);
+        }
+
+        let slope = SavitzkyGolay { window: 5, order: 2, deriv: 1 };
+        for (i, d) in savitzky_golay(&values, &slope).unwrap().iter().enumerate() {
+            assert!((d - (0.5 - 0.2 * i as f64)).abs() < 1e-9);
+        }
+
+        assert!(savitzky_golay(&values, &SavitzkyGolay { window: 6, order: 2, deriv: 0 }).is_err());
+    }
+
+    #[test]
+    fn test_from_config() {
+        let mut config = Config {
+            sgolay_window: Some(7),
+            sgolay_order: Some(2),
+            ..Default::default()
+        };
+        // Stored settings alone do not enable the filter
+        assert_eq!(SavitzkyGolay::from_config(&config), None);
+        config.sgolay = Some(false);
+        assert_eq!(SavitzkyGolay::from_config(&config), None);
+        config.sgolay = Some(true);
+        assert_eq!(SavitzkyGolay::from_config(&config), Some(SavitzkyGolay { window: 7, order: 2, deriv: 0 }));
+    }
+}
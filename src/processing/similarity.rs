@@ -0,0 +1,243 @@
+//! Spectral similarity measures, hit quality between two spectra and
+//! near-duplicate grouping.
+
+use super::{normalized, MatrixOptions, NormalizeMode, SpectraMatrix};
+use crate::spectre::{AxisType, SpcFile};
+use serde::Serialize;
+
+/// Pearson correlation coefficient between two equal-length spectra.
+///
+/// Returns `None` if the lengths differ, are shorter than 2, or either
+/// spectrum is constant.
+pub fn pearson(a: &[f64], b: &[f64]) -> Option<f64> {
+    if a.len() != b.len() || a.len() < 2 {
+        return None;
+    }
+
+    let n = a.len() as f64;
+    let mean_a = a.iter().sum::<f64>() / n;
+    let mean_b = b.iter().sum::<f64>() / n;
+
+    let mut cov = 0.0;
+    let mut var_a = 0.0;
+    let mut var_b = 0.0;
+    for (x, y) in a.iter().zip(b) {
+        let dx = x - mean_a;
+        let dy = y - mean_b;
+        cov += dx * dy;
+        var_a += dx * dx;
+        var_b += dy * dy;
+    }
+
+    if var_a == 0.0 || var_b == 0.0 {
+        return None;
+    }
+    Some(cov / (var_a.sqrt() * var_b.sqrt()))
+}
+
+/// Angle between two equal-length spectra taken as vectors, in degrees:
+/// 0 for the same shape at any scale, 90 for no overlap.
+///
+/// Returns `None` if the lengths differ or either spectrum is all zeros.
+pub fn spectral_angle(a: &[f64], b: &[f64]) -> Option<f64> {
+    if a.len() != b.len() {
+        return None;
+    }
+    let dot: f64 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f64>().sqrt();
+    let norm_b = b.iter().map(|y| y * y).sum::<f64>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return None;
+    }
+    Some((dot / (norm_a * norm_b)).clamp(-1.0, 1.0).acos().to_degrees())
+}
+
+/// Euclidean distance between two equal-length spectra, each normalized
+/// with `mode` first.
+///
+/// Returns `None` if the lengths differ or the spectra are empty.
+pub fn euclidean_distance(a: &[f64], b: &[f64], mode: NormalizeMode) -> Option<f64> {
+    if a.len() != b.len() || a.is_empty() {
+        return None;
+    }
+    let (a, b) = (normalized(a, mode), normalized(b, mode));
+    Some(a.iter().zip(&b).map(|(x, y)| (x - y).powi(2)).sum::<f64>().sqrt())
+}
+
+/// How closely a sample spectrum matches a reference.
+#[derive(Debug, Clone, Serialize)]
+pub struct SpectrumSimilarity {
+    /// Axis the spectra were compared on.
+    pub axis: AxisType,
+    /// Number of points compared.
+    pub points: usize,
+    /// First and last x value compared.
+    pub range: (f64, f64),
+    /// Pearson correlation coefficient.
+    pub pearson: Option<f64>,
+    /// Hit quality index: the squared cosine of the spectral angle, 1 for
+    /// the same shape.
+    pub hqi: Option<f64>,
+    /// Spectral angle in degrees.
+    pub spectral_angle: Option<f64>,
+    /// Euclidean distance after normalization.
+    pub euclidean: Option<f64>,
+    /// Normalization applied before the Euclidean distance.
+    pub normalization: &'static str,
+}
+
+/// Compare a sample spectrum with a reference, e.g. a daily standard with
+/// its stored reference measurement.
+///
+/// The spectra are aligned and preprocessed as the two rows of a
+/// [`SpectraMatrix`], except that normalization only applies to the
+/// Euclidean distance (vector normalization when `options` sets none) and
+/// mean centering is ignored. The sample is interpolated onto the
+/// reference's points.
+pub fn compare_spectra(reference: &SpcFile, sample: &SpcFile, options: &MatrixOptions) -> Result<SpectrumSimilarity, String> {
+    let mode = options.normalize.unwrap_or(NormalizeMode::Vector);
+    let aligned = MatrixOptions {
+        normalize: None,
+        mean_center: false,
+        ..options.clone()
+    };
+    let matrix = SpectraMatrix::from_named(&[("reference", reference), ("sample", sample)], &aligned)?;
+
+    // Points outside the sample's range (with an explicit grid) are left out
+    let (mut x, mut a, mut b) = (Vec::new(), Vec::new(), Vec::new());
+    for (i, &xi) in matrix.x.iter().enumerate() {
+        let (ai, bi) = (matrix.intensities[0][i], matrix.intensities[1][i]);
+        if ai.is_finite() && bi.is_finite() {
+            x.push(xi);
+            a.push(ai);
+            b.push(bi);
+        }
+    }
+    let (Some(&first), Some(&last)) = (x.first(), x.last()) else {
+        return Err("the spectra have no points in common".to_string());
+    };
+
+    let angle = spectral_angle(&a, &b);
+    Ok(SpectrumSimilarity {
+        axis: matrix.axis,
+        points: x.len(),
+        range: (first, last),
+        pearson: pearson(&a, &b),
+        hqi: angle.map(|angle| angle.to_radians().cos().powi(2)),
+        spectral_angle: angle,
+        euclidean: euclidean_distance(&a, &b, mode),
+        normalization: mode.name(),
+    })
+}
+
+/// A group of mutually similar spectra.
+#[derive(Debug, Clone, Serialize)]
+pub struct SimilarityGroup {
+    /// Indices of the group members in the input slice.
+    pub members: Vec<usize>,
+    /// Lowest pairwise similarity between linked members.
+    pub min_similarity: f64,
+    /// Whether every member has bit-identical intensity data.
+    pub exact: bool,
+}
+
+/// Group spectra whose pairwise Pearson correlation is at least `threshold`.
+///
+/// Groups are formed by single linkage (A~B and B~C puts A, B, C together).
+/// Spectra with different point counts are never grouped. Only groups with
+/// two or more members are returned, ordered by their first member.
+pub fn group_similar(spectra: &[&[f64]], threshold: f64) -> Vec<SimilarityGroup> {
+    let n = spectra.len();
+    let mut parent: Vec<usize> = (0..n).collect();
+    let mut link_min = vec![f64::INFINITY; n];
+
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    for i in 0..n {
+        for j in (i + 1)..n {
+            let similarity = if spectra[i] == spectra[j] {
+                Some(1.0)
+            } else {
+                pearson(spectra[i], spectra[j])
+            };
+            let Some(similarity) = similarity else { continue };
+            if similarity < threshold {
+                continue;
+            }
+
+            let (ri, rj) = (find(&mut parent, i), find(&mut parent, j));
+            let merged_min = link_min[ri].min(link_min[rj]).min(similarity);
+            if ri != rj {
+                parent[rj] = ri;
+            }
+            link_min[ri] = merged_min;
+        }
+    }
+
+    let mut groups: Vec<(usize, Vec<usize>)> = Vec::new();
+    for i in 0..n {
+        let root = find(&mut parent, i);
+        match groups.iter_mut().find(|(r, _)| *r == root) {
+            Some((_, members)) => members.push(i),
+            None => groups.push((root, vec![i])),
+        }
+    }
+
+    groups
+        .into_iter()
+        .filter(|(_, members)| members.len() > 1)
+        .map(|(root, members)| {
+            let exact = members.iter().all(|&m| spectra[m] == spectra[members[0]]);
+            SimilarityGroup {
+                min_similarity: link_min[root],
+                exact,
+                members,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_similar() {
+        let a = [1.0, 2.0, 3.0, 2.0, 1.0];
+        let b = [2.0, 4.0, 6.0, 4.0, 2.0]; // scaled copy of a
+        let c = [3.0, 1.0, 0.0, 1.0, 3.0];
+        let groups = group_similar(&[&a, &c, &b, &a], 0.99);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].members, vec![0, 2, 3]);
+        assert!(!groups[0].exact);
+    }
+
+    #[test]
+    fn test_compare_spectra() {
+        assert_eq!(spectral_angle(&[1.0, 0.0], &[0.0, 2.0]), Some(90.0));
+        assert_eq!(spectral_angle(&[1.0, 2.0], &[0.0, 0.0]), None);
+        assert_eq!(euclidean_distance(&[1.0, 2.0], &[2.0, 4.0], NormalizeMode::Max), Some(0.0));
+
+        let spectrum = |data: Vec<f64>| crate::spectre::SpcFileBuilder::new("CAM-1", data).build();
+        let reference = spectrum(vec![1.0, 2.0, 3.0, 2.0, 1.0]);
+        let scaled = spectrum(vec![2.0, 4.0, 6.0, 4.0, 2.0]);
+        let similarity = compare_spectra(&reference, &scaled, &MatrixOptions::default()).unwrap();
+        assert_eq!(similarity.axis, AxisType::Pixels);
+        assert_eq!((similarity.points, similarity.range), (5, (0.0, 4.0)));
+        assert!((similarity.pearson.unwrap() - 1.0).abs() < 1e-12);
+        assert!((similarity.hqi.unwrap() - 1.0).abs() < 1e-12);
+        assert!(similarity.euclidean.unwrap() < 1e-12);
+
+        let shifted = spectrum(vec![1.0, 1.0, 2.0, 3.0, 2.0, 1.0]);
+        let similarity = compare_spectra(&reference, &shifted, &MatrixOptions::default()).unwrap();
+        assert_eq!(similarity.points, 5);
+        assert!(similarity.pearson.unwrap() < 0.5);
+        assert!(similarity.spectral_angle.unwrap() > 10.0);
+    }
+}
@@ -0,0 +1,106 @@
+//! Smoothing kernels.
+
+use super::despike::window_extent;
+use super::ProcessingStep;
+use crate::spectre::SpcFile;
+
+/// Default kernel width, in points.
+pub const DEFAULT_SMOOTH_WIDTH: f64 = 5.0;
+/// Largest kernel width accepted from the command line, in points.
+pub const MAX_SMOOTH_WIDTH: f64 = 100_000.0;
+
+/// Smoothing kernel. Widths are in points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SmoothKernel {
+    /// Moving average over `width` points
+    Boxcar { width: usize },
+    /// Gaussian with the given full width at half maximum, truncated at
+    /// three standard deviations
+    Gaussian { fwhm: f64 },
+}
+
+/// Moving average over `width` points; the window shrinks at the ends.
+pub fn moving_average(values: &[f64], width: usize) -> Vec<f64> {
+    let (before, after) = window_extent(width);
+    (0..values.len())
+        .map(|i| {
+            let window = &values[i.saturating_sub(before)..(i + after + 1).min(values.len())];
+            window.iter().sum::<f64>() / window.len() as f64
+        })
+        .collect()
+}
+
+/// Gaussian-weighted average; at the ends the kernel is renormalized over
+/// the points available.
+pub fn gaussian_smoothed(values: &[f64], fwhm: f64) -> Vec<f64> {
+    let sigma = fwhm / (8.0 * std::f64::consts::LN_2).sqrt();
+    if !sigma.is_finite() || sigma <= 0.0 {
+        return values.to_vec();
+    }
+    // Beyond the spectrum the kernel has no points to weight
+    let reach = ((3.0 * sigma).ceil() as usize).min(values.len());
+    let kernel: Vec<f64> = (0..=reach)
+        .map(|k| (-0.5 * (k as f64 / sigma).powi(2)).exp())
+        .collect();
+
+    let n = values.len();
+    (0..n)
+        .map(|i| {
+            let (mut sum, mut weight) = (0.0, 0.0);
+            for j in i.saturating_sub(reach)..(i + reach + 1).min(n) {
+                let w = kernel[i.abs_diff(j)];
+                sum += w * values[j];
+                weight += w;
+            }
+            sum / weight
+        })
+        .collect()
+}
+
+/// Return a smoothed copy of `values`.
+pub fn smoothed(values: &[f64], kernel: SmoothKernel) -> Vec<f64> {
+    match kernel {
+        SmoothKernel::Boxcar { width } => moving_average(values, width),
+        SmoothKernel::Gaussian { fwhm } => gaussian_smoothed(values, fwhm),
+    }
+}
+
+/// Smooth the intensity data in place and record the step.
+pub fn smooth(spc: &mut SpcFile, kernel: SmoothKernel) {
+    spc.data = smoothed(&spc.data, kernel);
+    let step = match kernel {
+        SmoothKernel::Boxcar { width } => ProcessingStep::new("smooth")
+            .param("kernel", "boxcar")
+            .param("width", width),
+        SmoothKernel::Gaussian { fwhm } => ProcessingStep::new("smooth")
+            .param("kernel", "gaussian")
+            .param("fwhm", fwhm),
+    };
+    spc.history.push(step);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_smoothing_kernels() {
+        let step: Vec<f64> = (0..10).map(|i| if i < 5 { 0.0 } else { 10.0 }).collect();
+        let boxcar = moving_average(&step, 3);
+        assert_eq!(&boxcar[3..7], &[0.0, 10.0 / 3.0, 20.0 / 3.0, 10.0]);
+
+        let flat = vec![2.0; 20];
+        assert!(gaussian_smoothed(&flat, 4.0).iter().all(|v| (v - 2.0).abs() < 1e-12));
+
+        let spike: Vec<f64> = (0..41).map(|i| if i == 20 { 1.0 } else { 0.0 }).collect();
+        let smoothed = gaussian_smoothed(&spike, 4.0);
+        // Area is preserved away from the ends and the peak is spread symmetrically
+        assert!((smoothed.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        assert!((smoothed[18] - smoothed[22]).abs() < 1e-12);
+        assert!(smoothed[20] < 0.3);
+
+        // A kernel far wider than the spectrum averages all of it
+        let wide = gaussian_smoothed(&[1.0, 2.0, 3.0], 1e300);
+        assert!(wide.iter().all(|v| (v - 2.0).abs() < 1e-12));
+    }
+}
@@ -0,0 +1,95 @@
+//! Summary statistics for spectra.
+
+use super::despike::median;
+use crate::output::select_best_axis;
+use crate::spectre::SpcFile;
+use serde::Serialize;
+
+/// Default detector saturation level (16-bit ADC full scale).
+pub const DEFAULT_SATURATION_LEVEL: f64 = 65535.0;
+
+/// Estimate the noise standard deviation from point-to-point differences.
+///
+/// Uses the median absolute deviation of first differences, which is robust
+/// to peaks and slowly varying backgrounds.
+pub fn noise_level(values: &[f64]) -> f64 {
+    if values.len() < 3 {
+        return f64::NAN;
+    }
+    let mut diffs: Vec<f64> = values.windows(2).map(|w| w[1] - w[0]).collect();
+    let med = median(&mut diffs);
+    for d in diffs.iter_mut() {
+        *d = (*d - med).abs();
+    }
+    // MAD -> sigma for a normal distribution, and differencing adds sqrt(2)
+    median(&mut diffs) / 0.6745 / std::f64::consts::SQRT_2
+}
+
+/// Signal-to-noise ratio: peak height above the median, over the noise level.
+pub fn snr(values: &[f64]) -> f64 {
+    let noise = noise_level(values);
+    if values.is_empty() || noise.is_nan() {
+        return f64::NAN;
+    }
+    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let med = median(&mut values.to_vec());
+    if noise == 0.0 {
+        return f64::INFINITY;
+    }
+    (max - med) / noise
+}
+
+/// Number of points at or above the saturation level.
+pub fn saturated_count(values: &[f64], saturation_level: f64) -> usize {
+    values.iter().filter(|&&v| v >= saturation_level).count()
+}
+
+/// Summary statistics of one spectrum.
+#[derive(Debug, Clone, Serialize)]
+pub struct SpectrumStats {
+    pub points: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    /// Standard deviation of the intensities
+    pub std: f64,
+    /// Estimated noise standard deviation (see [`noise_level`])
+    pub noise: f64,
+    /// Signal-to-noise ratio (see [`snr`])
+    pub snr: f64,
+    /// Intensity span over the noise level
+    pub dynamic_range: f64,
+    /// Points at or above the saturation level
+    pub saturated: usize,
+    /// Name of the best available axis
+    pub axis: &'static str,
+    /// Lowest and highest axis value
+    pub axis_min: f64,
+    pub axis_max: f64,
+}
+
+/// Summarize a spectrum's intensities and axis coverage.
+pub fn summarize(spc: &SpcFile, saturation_level: f64) -> SpectrumStats {
+    let values = &spc.data;
+    let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let noise = noise_level(values);
+    let axis = select_best_axis(spc);
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64;
+
+    SpectrumStats {
+        points: values.len(),
+        min,
+        max,
+        mean,
+        std: variance.sqrt(),
+        noise,
+        snr: snr(values),
+        dynamic_range: (max - min) / noise,
+        saturated: saturated_count(values, saturation_level),
+        axis: axis.name,
+        axis_min: axis.values.iter().cloned().fold(f64::INFINITY, f64::min),
+        axis_max: axis.values.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+    }
+}
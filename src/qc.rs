@@ -0,0 +1,195 @@
+//! Quality-control rules evaluated against extracted spectra.
+//!
+//! Rules are optional; only the ones that are set are checked. A rule set can
+//! be loaded from TOML:
+//!
+//! ```toml
+//! min_snr = 20.0
+//! max_saturated = 0
+//! expected_laser_nm = 785.0
+//! min_points = 1024
+//! require_calibration = true
+//! ```
+
+use crate::processing;
+use crate::spectre::SpcFile;
+use serde::{Deserialize, Serialize};
+
+/// Default tolerance when checking the laser wavelength, in nm.
+pub const DEFAULT_LASER_TOLERANCE_NM: f64 = 0.5;
+
+/// A set of quality-control rules.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QcRules {
+    /// Minimum signal-to-noise ratio.
+    pub min_snr: Option<f64>,
+    /// Maximum number of saturated pixels.
+    pub max_saturated: Option<usize>,
+    /// Intensity at which a pixel counts as saturated.
+    pub saturation_level: Option<f64>,
+    /// Expected Raman laser wavelength in nm.
+    pub expected_laser_nm: Option<f64>,
+    /// Allowed deviation from `expected_laser_nm`.
+    pub laser_tolerance_nm: Option<f64>,
+    /// Minimum number of data points.
+    pub min_points: Option<usize>,
+    /// Maximum number of data points.
+    pub max_points: Option<usize>,
+    /// Require a calibration block.
+    pub require_calibration: bool,
+}
+
+/// The outcome of one rule.
+#[derive(Debug, Clone, Serialize)]
+pub struct QcFinding {
+    pub rule: &'static str,
+    pub passed: bool,
+    pub message: String,
+}
+
+/// All findings for one spectrum.
+#[derive(Debug, Clone, Serialize)]
+pub struct QcReport {
+    pub passed: bool,
+    pub findings: Vec<QcFinding>,
+}
+
+impl QcRules {
+    /// Evaluate every configured rule against a spectrum.
+    pub fn evaluate(&self, spc: &SpcFile) -> QcReport {
+        let mut findings = Vec::new();
+        let mut check = |rule: &'static str, passed: bool, message: String| {
+            findings.push(QcFinding { rule, passed, message });
+        };
+
+        if let Some(min_snr) = self.min_snr {
+            let snr = processing::snr(&spc.data);
+            check("min_snr", snr >= min_snr, format!("SNR {:.1} (minimum {})", snr, min_snr));
+        }
+
+        if let Some(max_saturated) = self.max_saturated {
+            let level = self
+                .saturation_level
+                .unwrap_or(processing::DEFAULT_SATURATION_LEVEL);
+            let count = processing::saturated_count(&spc.data, level);
+            check(
+                "max_saturated",
+                count <= max_saturated,
+                format!("{} saturated pixel(s) at >= {} (maximum {})", count, level, max_saturated),
+            );
+        }
+
+        if let Some(expected) = self.expected_laser_nm {
+            let tolerance = self.laser_tolerance_nm.unwrap_or(DEFAULT_LASER_TOLERANCE_NM);
+            match spc.config.as_ref().and_then(|c| c.raman_wavelength) {
+                Some(laser) => check(
+                    "expected_laser_nm",
+                    (laser - expected).abs() <= tolerance,
+                    format!("laser {} nm (expected {} ± {} nm)", laser, expected, tolerance),
+                ),
+                None => check(
+                    "expected_laser_nm",
+                    false,
+                    format!("no laser wavelength stored (expected {} nm)", expected),
+                ),
+            }
+        }
+
+        let points = spc.data.len();
+        if let Some(min_points) = self.min_points {
+            check(
+                "min_points",
+                points >= min_points,
+                format!("{} point(s) (minimum {})", points, min_points),
+            );
+        }
+        if let Some(max_points) = self.max_points {
+            check(
+                "max_points",
+                points <= max_points,
+                format!("{} point(s) (maximum {})", points, max_points),
+            );
+        }
+
+        if self.require_calibration {
+            let present = spc.has_calibration();
+            check(
+                "require_calibration",
+                present,
+                if present { "calibration present" } else { "calibration missing" }.to_string(),
+            );
+        }
+
+        QcReport {
+            passed: findings.iter().all(|f| f.passed),
+            findings,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spectre::SpcFileBuilder;
+    use crate::Calibration;
+
+    #[test]
+    fn test_load_rules() {
+        let rules: QcRules = toml::from_str(
+            "min_snr = 20.0\nmax_saturated = 0\nexpected_laser_nm = 785.0\nmin_points = 1024\nrequire_calibration = true\n",
+        )
+        .unwrap();
+        assert_eq!(rules.min_snr, Some(20.0));
+        assert_eq!(rules.max_saturated, Some(0));
+        assert_eq!(rules.expected_laser_nm, Some(785.0));
+        assert_eq!(rules.min_points, Some(1024));
+        assert_eq!(rules.max_points, None);
+        assert!(rules.require_calibration);
+
+        assert!(toml::from_str::<QcRules>("min_snr = 20.0\nmax_snr = 90.0\n").is_err());
+        assert!(QcRules::default().evaluate(&SpcFileBuilder::new("CAM-1", vec![1.0]).build()).findings.is_empty());
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let mut data = vec![100.0; 64];
+        data[10] = 70000.0;
+        let spc = SpcFileBuilder::new("CAM-1", data)
+            .calibration(Calibration::legendre(vec![850.0, 60.0]))
+            .laser_wavelength(785.2)
+            .build();
+        let rules = QcRules {
+            max_saturated: Some(0),
+            expected_laser_nm: Some(785.0),
+            min_points: Some(32),
+            max_points: Some(48),
+            require_calibration: true,
+            ..Default::default()
+        };
+        let report = rules.evaluate(&spc);
+        assert!(!report.passed);
+        let outcome: Vec<(&str, bool)> = report.findings.iter().map(|f| (f.rule, f.passed)).collect();
+        assert_eq!(
+            outcome,
+            [
+                ("max_saturated", false),
+                ("expected_laser_nm", true),
+                ("min_points", true),
+                ("max_points", false),
+                ("require_calibration", true),
+            ]
+        );
+
+        // A tighter tolerance, and a file without a laser wavelength
+        let strict = QcRules {
+            expected_laser_nm: Some(785.0),
+            laser_tolerance_nm: Some(0.1),
+            ..Default::default()
+        };
+        assert!(!strict.evaluate(&spc).passed);
+        let bare = SpcFileBuilder::new("CAM-2", vec![100.0; 64]).build();
+        let report = strict.evaluate(&bare);
+        assert!(report.findings[0].message.starts_with("no laser wavelength"));
+    }
+}
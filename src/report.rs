@@ -0,0 +1,164 @@
+//! Machine-readable summary of a conversion run (`--report`).
+
+use serde::Serialize;
+use spc_converter::{output, SpcFile};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[derive(Serialize)]
+pub struct RunReport {
+    /// Start of the run, in seconds since the Unix epoch
+    started: u64,
+    duration_ms: f64,
+    format: String,
+    succeeded: usize,
+    skipped: usize,
+    failed: usize,
+    /// Run-level failure, e.g. a batch output that could not be written
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    files: Vec<FileReport>,
+    #[serde(skip)]
+    timer: Instant,
+}
+
+#[derive(Serialize)]
+pub struct FileReport {
+    input: String,
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    duration_ms: f64,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    summary: Option<FileSummary>,
+}
+
+/// Key metadata of a converted file.
+#[derive(Serialize)]
+struct FileSummary {
+    uid: String,
+    points: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    laser_nm: Option<f64>,
+    calibrated: bool,
+    axis: &'static str,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<String, String>,
+}
+
+impl RunReport {
+    pub fn new(format: &str) -> Self {
+        RunReport {
+            started: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            duration_ms: 0.0,
+            format: format.to_string(),
+            succeeded: 0,
+            skipped: 0,
+            failed: 0,
+            error: None,
+            files: Vec::new(),
+            timer: Instant::now(),
+        }
+    }
+
+    /// Record a converted file.
+    pub fn converted(&mut self, input: &Path, output: &Path, spc: &SpcFile, elapsed: Duration) {
+        self.succeeded += 1;
+        self.files.push(FileReport {
+            input: input.display().to_string(),
+            status: "ok",
+            output: Some(output.display().to_string()),
+            error: None,
+            duration_ms: millis(elapsed),
+            summary: Some(FileSummary::of(spc)),
+        });
+    }
+
+    /// Record a file read for a batch output that is written later; it
+    /// stays `loaded` unless [`written`](Self::written) follows.
+    pub fn loaded(&mut self, input: &Path, spc: &SpcFile, elapsed: Duration) {
+        self.files.push(FileReport {
+            input: input.display().to_string(),
+            status: "loaded",
+            output: None,
+            error: None,
+            duration_ms: millis(elapsed),
+            summary: Some(FileSummary::of(spc)),
+        });
+    }
+
+    /// Record that the batch output holding the loaded files was written.
+    pub fn written(&mut self, output: &Path) {
+        for file in self.files.iter_mut().filter(|file| file.status == "loaded") {
+            file.status = "ok";
+            file.output = Some(output.display().to_string());
+            self.succeeded += 1;
+        }
+    }
+
+    /// Record an output left alone because it already exists.
+    pub fn skipped(&mut self, input: &Path, output: &Path, elapsed: Duration) {
+        self.skipped += 1;
+        self.files.push(FileReport {
+            input: input.display().to_string(),
+            status: "skipped",
+            output: Some(output.display().to_string()),
+            error: None,
+            duration_ms: millis(elapsed),
+            summary: None,
+        });
+    }
+
+    /// Record a file that failed.
+    pub fn failed(&mut self, input: &Path, error: &dyn std::error::Error, elapsed: Duration) {
+        self.failed += 1;
+        self.files.push(FileReport {
+            input: input.display().to_string(),
+            status: "error",
+            output: None,
+            error: Some(error.to_string()),
+            duration_ms: millis(elapsed),
+            summary: None,
+        });
+    }
+
+    /// Record a failure that affects the whole run.
+    pub fn run_failed(&mut self, error: &dyn std::error::Error) {
+        self.error = Some(error.to_string());
+    }
+
+    /// Stop the clock and write the report as JSON ("-" for stdout).
+    pub fn write(&mut self, path: &Path) -> std::io::Result<()> {
+        self.duration_ms = millis(self.timer.elapsed());
+        let json = serde_json::to_string_pretty(self)?;
+        if path.as_os_str() == "-" {
+            println!("{}", json);
+            Ok(())
+        } else {
+            std::fs::write(path, json)
+        }
+    }
+}
+
+impl FileSummary {
+    fn of(spc: &SpcFile) -> Self {
+        FileSummary {
+            uid: spc.uid.clone(),
+            points: spc.data.len(),
+            laser_nm: spc.config.as_ref().and_then(|c| c.raman_wavelength),
+            calibrated: spc.calibration.is_some(),
+            axis: output::select_best_axis(spc).name,
+            metadata: spc.metadata.clone(),
+        }
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,148 @@
+//! Building SpcFiles from scratch.
+
+use super::spc_file::{Calibration, Config, Instrument, SpcFile};
+use std::collections::BTreeMap;
+
+/// Builds an [`SpcFile`] field by field, e.g. for synthetic test inputs or
+/// to store another instrument's data as .spc with
+/// [`to_bytes`](SpcFile::to_bytes).
+///
+/// The axes are generated by [`build`](Self::build) from the calibration
+/// (or a given wavelength axis) and the laser wavelength. Nothing is
+/// checked, so malformed spectra (e.g. a blank of the wrong length) can be
+/// built on purpose.
+///
+/// ```
+/// # use spc_converter::{Calibration, SpcFile};
+/// # use spc_converter::spectre::SpcFileBuilder;
+/// let spc = SpcFileBuilder::new("CAM-1", vec![10.0, 12.0, 11.0])
+///     .blank(vec![1.0; 3])
+///     .calibration(Calibration::legendre(vec![850.0, 60.0, -0.5, 0.1]))
+///     .laser_wavelength(785.0)
+///     .exposure(1000.0)
+///     .build();
+/// assert!(spc.raman_shift_axis.is_some());
+///
+/// let parsed = SpcFile::from_bytes(&spc.to_bytes()).unwrap();
+/// assert_eq!(parsed.data, spc.data);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SpcFileBuilder {
+    spc: SpcFile,
+}
+
+impl SpcFileBuilder {
+    /// Start a spectrum with the given UID and intensities, without blank,
+    /// calibration or config.
+    pub fn new(uid: impl Into<String>, data: Vec<f64>) -> Self {
+        Self {
+            spc: SpcFile {
+                uid: uid.into(),
+                data,
+                blank: Vec::new(),
+                calibration: None,
+                config: None,
+                wavelength_axis: None,
+                raman_shift_axis: None,
+                acquired: None,
+                operator: None,
+                sample: None,
+                instrument: None,
+                provenance: None,
+                stats: None,
+                metadata: BTreeMap::new(),
+                history: Vec::new(),
+                extras: Vec::new(),
+            },
+        }
+    }
+
+    /// Set the blank/reference spectrum.
+    pub fn blank(mut self, blank: Vec<f64>) -> Self {
+        self.spc.blank = blank;
+        self
+    }
+
+    /// Set the calibration the wavelength axis is generated from.
+    pub fn calibration(mut self, calibration: Calibration) -> Self {
+        self.spc.calibration = Some(calibration);
+        self
+    }
+
+    /// Set the wavelengths in nm directly, for data without a pixel
+    /// calibration. Ignored if a calibration is set; not stored in .spc
+    /// files.
+    pub fn wavelength_axis(mut self, wavelengths: Vec<f64>) -> Self {
+        self.spc.wavelength_axis = Some(wavelengths);
+        self
+    }
+
+    /// Replace the config block.
+    pub fn config(mut self, config: Config) -> Self {
+        self.spc.config = Some(config);
+        self
+    }
+
+    /// Set the Raman laser wavelength in nm.
+    pub fn laser_wavelength(mut self, laser_wavelength: f64) -> Self {
+        self.config_mut().raman_wavelength = Some(laser_wavelength);
+        self
+    }
+
+    /// Set the exposure time as stored.
+    pub fn exposure(mut self, exposure: f64) -> Self {
+        self.config_mut().exposure = Some(exposure);
+        self
+    }
+
+    /// Set the camera gain.
+    pub fn gain(mut self, gain: f64) -> Self {
+        self.config_mut().gain = Some(gain);
+        self
+    }
+
+    /// Set the acquisition date and time (RFC 3339 or free text).
+    pub fn acquired(mut self, acquired: impl Into<String>) -> Self {
+        self.spc.acquired = Some(acquired.into());
+        self
+    }
+
+    /// Set the operator.
+    pub fn operator(mut self, operator: impl Into<String>) -> Self {
+        self.spc.operator = Some(operator.into());
+        self
+    }
+
+    /// Set the sample name.
+    pub fn sample(mut self, sample: impl Into<String>) -> Self {
+        self.spc.sample = Some(sample.into());
+        self
+    }
+
+    /// Set the camera and detector identification.
+    pub fn instrument(mut self, instrument: Instrument) -> Self {
+        self.spc.instrument = Some(instrument);
+        self
+    }
+
+    /// Add a metadata entry (not stored in .spc files).
+    pub fn metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.spc.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    fn config_mut(&mut self) -> &mut Config {
+        self.spc.config.get_or_insert_with(Config::default)
+    }
+
+    /// Finish the spectrum, generating its axes.
+    pub fn build(mut self) -> SpcFile {
+        if let Some(calibration) = self.spc.calibration.take() {
+            self.spc.set_calibration(calibration);
+        }
+        if let Some(laser) = self.spc.config.as_ref().and_then(|config| config.raman_wavelength) {
+            self.spc.set_laser_wavelength(laser);
+        }
+        self.spc
+    }
+}
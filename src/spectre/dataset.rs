@@ -0,0 +1,124 @@
+//! Containers holding several spectra.
+
+use super::spc_file::SpcFile;
+use crate::parser::{unpack_buffers_in_place, Anomalies, ParseError, ParseOptions};
+
+/// All spectra in a container.
+///
+/// Multi-accumulation sessions store one `data` object per spectrum, named
+/// `data` or `data1`, `data2`, ...; [`SpcFile::from_bytes`] keeps only the
+/// last `data` object. Every spectrum shares the file's calibration, config
+/// and extras, and with several spectra, its position is kept as `spectrum`
+/// metadata.
+#[derive(Debug, Clone)]
+pub struct SpcDataset {
+    /// The spectra, in file order.
+    pub spectra: Vec<SpcFile>,
+}
+
+impl SpcDataset {
+    /// Parse from raw file bytes (handles container encryption/compression).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        Self::from_bytes_with(bytes, &ParseOptions::default()).map(|(dataset, _)| dataset)
+    }
+
+    /// Parse with the given options, returning the anomalies that were
+    /// skipped as warnings; see [`SpcFile::from_bytes_with`].
+    pub fn from_bytes_with(bytes: &[u8], options: &ParseOptions) -> Result<(Self, Vec<String>), ParseError> {
+        Self::from_bytes_in_place_with(&mut bytes.to_vec(), options)
+    }
+
+    /// Like [`from_bytes_with`](Self::from_bytes_with), decrypting in place.
+    pub fn from_bytes_in_place_with(
+        bytes: &mut [u8],
+        options: &ParseOptions,
+    ) -> Result<(Self, Vec<String>), ParseError> {
+        let (file_offsets, buffers): (Vec<_>, Vec<_>) = unpack_buffers_in_place(bytes, &options.limits)?.into_iter().unzip();
+        let mut anomalies = Anomalies::new(options);
+        let mut spectra = SpcFile::spectra_from_buffers(&buffers, &file_offsets, false, true, options.skip_blank, &mut anomalies)?;
+        let numbered = spectra.len() > 1;
+        for (i, spc) in spectra.iter_mut().enumerate() {
+            if numbered {
+                spc.metadata.insert("spectrum".to_string(), i.to_string());
+            }
+            if !options.lazy_axes {
+                spc.generate_axes();
+            }
+        }
+        Ok((Self { spectra }, anomalies.messages()))
+    }
+
+    /// Read from a file path.
+    pub fn from_file(path: &std::path::Path) -> Result<Self, ParseError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// Number of spectra.
+    pub fn len(&self) -> usize {
+        self.spectra.len()
+    }
+
+    /// Whether there are no spectra (never the case for a parsed file).
+    pub fn is_empty(&self) -> bool {
+        self.spectra.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{pack_container, StorageObject, Variable};
+    use crate::spectre::Config;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_multiple_data_objects() {
+        let spectrum = |uid: &str, data: Vec<f64>| SpcFile {
+            uid: uid.to_string(),
+            blank: vec![0.0; data.len()],
+            data,
+            calibration: None,
+            config: Some(Config {
+                raman_wavelength: Some(785.0),
+                ..Default::default()
+            }),
+            wavelength_axis: None,
+            raman_shift_axis: None,
+            acquired: None,
+            operator: None,
+            sample: None,
+            instrument: None,
+            provenance: None,
+            stats: None,
+            metadata: BTreeMap::new(),
+            history: Vec::new(),
+            extras: Vec::new(),
+        };
+        let mut objects = spectrum("A", vec![1.0, 2.0]).to_storage_objects();
+        let mut second = spectrum("B", vec![3.0, 4.0]).to_storage_objects().remove(0);
+        second.var_name = "data1".to_string();
+        second.variables.push(Variable {
+            owner: String::new(),
+            name: "m_time".to_string(),
+            type_name: "double".to_string(),
+            data: 1.5f64.to_le_bytes().to_vec(),
+        });
+        objects.push(second);
+        let buffers: Vec<Vec<u8>> = objects.iter().map(StorageObject::to_bytes).collect();
+        let bytes = pack_container(&buffers, 0);
+
+        let dataset = SpcDataset::from_bytes(&bytes).unwrap();
+        assert_eq!(dataset.len(), 2);
+        assert_eq!(dataset.spectra[1].uid, "B");
+        assert_eq!(dataset.spectra[1].data, vec![3.0, 4.0]);
+        assert_eq!(dataset.spectra[1].config.as_ref().unwrap().raman_wavelength, Some(785.0));
+        assert_eq!(dataset.spectra[0].metadata["spectrum"], "0");
+        // Per-spectrum timestamps in the object tree
+        assert_eq!(dataset.spectra[1].metadata["time_s"], "1.5");
+
+        // A single SpcFile keeps `data1` as an extra
+        let spc = SpcFile::from_bytes(&bytes).unwrap();
+        assert_eq!(spc.uid, "A");
+        assert_eq!(spc.extras.len(), 1);
+    }
+}
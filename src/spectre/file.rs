@@ -35,7 +35,7 @@ impl SpectreFile {
         let buffers = crate::parser::unpack_container(bytes)?;
         
         if buffers.is_empty() {
-            return Err(ParseError::MissingField("No buffers in container".to_string()));
+            return Err(ParseError::NoBuffers);
         }
 
         // Parse the first buffer as a StorageObject
@@ -69,7 +69,7 @@ fn extract_string_child(obj: &StorageObject, name: &str) -> Result<String, Parse
         .unwrap_or(data_var.data.len());
 
     String::from_utf8(data_var.data[..end].to_vec())
-        .map_err(|_| ParseError::MissingField(format!("{} (invalid UTF-8)", name)))
+        .map_err(|_| ParseError::InvalidUtf8 { field: name.to_string() })
 }
 
 /// Extract a storage_vector<double> child as Vec<f64>.
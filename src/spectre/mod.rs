@@ -1,7 +1,24 @@
 //! SpectreFile extraction from StorageObject.
 
+mod builder;
+mod dataset;
 mod file;
+mod provenance;
+mod recover;
+mod series;
+#[cfg(feature = "async")]
+mod stream;
+pub mod sidecar;
 mod spc_file;
+pub(crate) mod timestamp;
+mod writer;
 
+pub use builder::SpcFileBuilder;
+pub use dataset::SpcDataset;
 pub use file::*;
-pub use spc_file::{SpcFile, Calibration, Config, AxisType};
+pub use provenance::Provenance;
+pub use recover::RecoveryReport;
+pub use series::{TimeBase, TimeSeries};
+pub use spc_file::{SpcFile, Calibration, CalibrationModel, Config, ConfigValue, FromConfigValue, AxisType, Instrument};
+pub(crate) use spc_file::legendre;
+pub use writer::DEFAULT_SEED;
@@ -0,0 +1,123 @@
+//! Best-effort extraction from damaged files.
+
+use super::spc_file::SpcFile;
+use crate::parser::{recover_container_with, salvage_object, Anomalies, Limits, ParseError, ParseOptions};
+use serde::Serialize;
+
+/// What [`SpcFile::recover`] salvaged and lost.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RecoveryReport {
+    /// Top-level objects recovered, completely or in part
+    pub salvaged: Vec<String>,
+    /// Objects and variables that were lost, by path (e.g. `data/m_blank`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub lost: Vec<String>,
+    /// Container-level problems (checksum, truncation, unreadable buffers)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub problems: Vec<String>,
+}
+
+impl SpcFile {
+    /// Extract whatever can be salvaged from a damaged file, e.g. one
+    /// truncated by a crash during acquisition or with a bad checksum.
+    ///
+    /// Intact StorageObjects are located and parsed; a missing UID or blank
+    /// is left empty. Fails if no intact intensity data remains.
+    pub fn recover(bytes: &[u8]) -> Result<(Self, RecoveryReport), ParseError> {
+        Self::recover_with(bytes, &Limits::default())
+    }
+
+    /// Like [`SpcFile::recover`], with the given decompression limits.
+    pub fn recover_with(bytes: &[u8], limits: &Limits) -> Result<(Self, RecoveryReport), ParseError> {
+        let container = recover_container_with(bytes, limits)?;
+        let mut report = RecoveryReport {
+            problems: container.problems,
+            ..Default::default()
+        };
+
+        let mut buffers = Vec::new();
+        for (i, buffer) in &container.buffers {
+            match salvage_object(buffer, "", &mut report.lost) {
+                Some(obj) => {
+                    report.salvaged.push(obj.var_name.clone());
+                    buffers.push(obj.to_bytes());
+                }
+                None => report.problems.push(format!("buffer[{}]: no readable StorageObject", i)),
+            }
+        }
+
+        let mut anomalies = Anomalies::new(&ParseOptions::default());
+        let mut spc = Self::from_buffers(&buffers, &[], true, false, &mut anomalies)?;
+        spc.generate_axes();
+        report.problems.extend(anomalies.messages());
+        Ok((spc, report))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{checksum, decrypt, encrypt, ContainerHeader, BLOCK_SIZE, ENCRYPTION_KEY};
+    use crate::spectre::{Calibration, Config};
+
+    #[test]
+    fn test_recover_truncated() {
+        let spc = SpcFile {
+            uid: "CAM-1".to_string(),
+            data: (0..300).map(|i| i as f64 * 1.5).collect(),
+            blank: vec![100.0; 300],
+            calibration: Some(Calibration::legendre(vec![850.0, 60.0, -0.5, 0.1])),
+            config: Some(Config {
+                raman_wavelength: Some(785.0),
+                ..Default::default()
+            }),
+            wavelength_axis: None,
+            raman_shift_axis: None,
+            acquired: None,
+            operator: None,
+            sample: None,
+            instrument: None,
+            provenance: None,
+            stats: None,
+            metadata: Default::default(),
+            history: Vec::new(),
+            extras: Vec::new(),
+        };
+        let bytes = spc.to_bytes();
+
+        // Intact files recover completely
+        let (recovered, report) = SpcFile::recover(&bytes).unwrap();
+        assert_eq!(recovered.data, spc.data);
+        assert!(report.lost.is_empty() && report.problems.is_empty());
+
+        // Cut inside m_blank (data object: m_data, m_blank, m_uid): m_data
+        // survives, the blank, UID, calibration and config are lost
+        let cut = bytes.len() * 3 / 4 + 1;
+        assert!(SpcFile::from_bytes(&bytes[..cut]).is_err());
+        let (recovered, report) = SpcFile::recover(&bytes[..cut]).unwrap();
+        assert_eq!(recovered.data, spc.data);
+        assert!(recovered.blank.is_empty());
+        assert!(recovered.calibration.is_none());
+        assert_eq!(report.salvaged, vec!["data"]);
+        assert!(report.lost.contains(&"data/m_blank".to_string()));
+        assert!(report.problems.iter().any(|p| p.contains("truncated")));
+
+        // A corrupted checksum alone loses nothing
+        let mut flipped = bytes.clone();
+        flipped[4] ^= 1;
+        let (recovered, report) = SpcFile::recover(&flipped).unwrap();
+        assert_eq!(recovered.config.unwrap().raman_wavelength, Some(785.0));
+        assert_eq!(report.problems, vec!["checksum mismatch"]);
+
+        // A huge buffer count in a truncated file is rejected, not overflowed
+        let mut plain = bytes.clone();
+        plain[4..8].copy_from_slice(&[0; 4]);
+        decrypt(&mut plain[ContainerHeader::SIZE..], ENCRYPTION_KEY, 0, BLOCK_SIZE);
+        plain[8..16].copy_from_slice(&(u64::MAX / 24).to_le_bytes());
+        plain[16..24].copy_from_slice(&(ContainerHeader::SIZE as u64 + 1600).to_le_bytes());
+        let sum = checksum(&plain);
+        plain[4..8].copy_from_slice(&sum.to_le_bytes());
+        encrypt(&mut plain[ContainerHeader::SIZE..], ENCRYPTION_KEY, 0, BLOCK_SIZE);
+        assert!(SpcFile::recover(&plain[..cut]).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+//! Kinetic (time-series) acquisitions.
+
+use super::dataset::SpcDataset;
+use super::spc_file::{AxisType, SpcFile};
+use serde::Serialize;
+
+/// What the times of a [`TimeSeries`] count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeBase {
+    /// Acquisition times in seconds (`time_s` metadata)
+    Seconds,
+    /// Sequence numbers stored with each spectrum (`sequence` metadata)
+    Sequence,
+    /// Position in the session, when nothing else is stored
+    Index,
+}
+
+impl TimeBase {
+    /// Column name for tabular output.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimeBase::Seconds => "time_s",
+            TimeBase::Sequence => "sequence",
+            TimeBase::Index => "index",
+        }
+    }
+}
+
+/// Spectra of one session as a time × x matrix.
+#[derive(Debug, Clone, Serialize)]
+pub struct TimeSeries {
+    /// What `times` count.
+    pub time_base: TimeBase,
+    /// Time of each spectrum, in acquisition order.
+    pub times: Vec<f64>,
+    /// X-axis shared by all spectra.
+    pub axis: AxisType,
+    /// X values.
+    pub x: Vec<f64>,
+    /// One row of intensities per time.
+    pub intensities: Vec<Vec<f64>>,
+}
+
+impl TimeSeries {
+    /// Arrange spectra of one session by time.
+    ///
+    /// Times come from the `time_s` metadata of every spectrum, else their
+    /// `sequence` metadata, else their position. Spectra are sorted by
+    /// time. All must have the same number of points; the x-axis is the
+    /// first spectrum's best one (Raman shift, wavelength, else pixel).
+    pub fn from_spectra(spectra: &[SpcFile]) -> Result<Self, String> {
+        let Some(first) = spectra.first() else {
+            return Err("no spectra in the series".to_string());
+        };
+        if let Some((i, spc)) = spectra.iter().enumerate().find(|(_, spc)| spc.data.len() != first.data.len()) {
+            return Err(format!(
+                "spectrum {} has {} points, the first {}",
+                i,
+                spc.data.len(),
+                first.data.len()
+            ));
+        }
+
+        let stamps = |key: &str| -> Option<Vec<f64>> {
+            spectra.iter().map(|spc| spc.metadata.get(key)?.parse().ok()).collect()
+        };
+        let (time_base, times) = match (stamps("time_s"), stamps("sequence")) {
+            (Some(times), _) => (TimeBase::Seconds, times),
+            (None, Some(sequence)) => (TimeBase::Sequence, sequence),
+            (None, None) => (TimeBase::Index, (0..spectra.len()).map(|i| i as f64).collect()),
+        };
+
+        let (axis, x) = if let Some(raman) = first.raman_shifts() {
+            (AxisType::RamanShifts, raman.into_owned())
+        } else if let Some(wavelength) = first.wavelengths() {
+            (AxisType::Wavelengths, wavelength.into_owned())
+        } else {
+            (AxisType::Pixels, (0..first.data.len()).map(|i| i as f64).collect())
+        };
+
+        let mut order: Vec<usize> = (0..spectra.len()).collect();
+        order.sort_by(|&a, &b| times[a].total_cmp(&times[b]));
+        Ok(Self {
+            time_base,
+            times: order.iter().map(|&i| times[i]).collect(),
+            axis,
+            x,
+            intensities: order.iter().map(|&i| spectra[i].data.clone()).collect(),
+        })
+    }
+}
+
+impl SpcDataset {
+    /// The spectra as a time series; see [`TimeSeries::from_spectra`].
+    pub fn time_series(&self) -> Result<TimeSeries, String> {
+        TimeSeries::from_spectra(&self.spectra)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_from_spectra() {
+        let spectrum = |time: &str, data: Vec<f64>| SpcFile {
+            uid: "CAM-1".to_string(),
+            data,
+            blank: Vec::new(),
+            calibration: None,
+            config: None,
+            wavelength_axis: Some(vec![500.0, 501.0]),
+            raman_shift_axis: None,
+            acquired: None,
+            operator: None,
+            sample: None,
+            instrument: None,
+            provenance: None,
+            stats: None,
+            metadata: BTreeMap::from([("time_s".to_string(), time.to_string())]),
+            history: Vec::new(),
+            extras: Vec::new(),
+        };
+        let spectra = vec![spectrum("2.5", vec![3.0, 4.0]), spectrum("0.5", vec![1.0, 2.0])];
+        let series = TimeSeries::from_spectra(&spectra).unwrap();
+        assert_eq!(series.time_base, TimeBase::Seconds);
+        assert_eq!(series.times, vec![0.5, 2.5]);
+        assert_eq!(series.axis, AxisType::Wavelengths);
+        assert_eq!(series.intensities, vec![vec![1.0, 2.0], vec![3.0, 4.0]]);
+
+        // Without timestamps, spectra keep their order
+        let mut unstamped = spectra.clone();
+        unstamped[1].metadata.clear();
+        let series = TimeSeries::from_spectra(&unstamped).unwrap();
+        assert_eq!(series.time_base, TimeBase::Index);
+        assert_eq!(series.intensities[0], vec![3.0, 4.0]);
+
+        unstamped[1].data.push(5.0);
+        assert!(TimeSeries::from_spectra(&unstamped).is_err());
+    }
+}
@@ -0,0 +1,121 @@
+//! Sidecar metadata files stored next to an .spc file.
+//!
+//! A sidecar is named after the measurement with an extra extension,
+//! e.g. `measurement.spc.toml` or `measurement.spc.json`. Its fields are
+//! flattened into `key: value` strings; nested tables use dotted keys.
+
+use crate::parser::ParseError;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Sidecar extensions, in lookup order.
+pub const SIDECAR_EXTENSIONS: [&str; 2] = ["toml", "json"];
+
+/// Find the sidecar file for an input path, if one exists.
+pub fn find_sidecar(input_path: &Path) -> Option<PathBuf> {
+    SIDECAR_EXTENSIONS.iter().find_map(|ext| {
+        let mut name = input_path.as_os_str().to_owned();
+        name.push(".");
+        name.push(ext);
+        let candidate = PathBuf::from(name);
+        candidate.is_file().then_some(candidate)
+    })
+}
+
+/// Read a sidecar file (TOML or JSON, chosen by extension) as flat metadata.
+pub fn read_sidecar(path: &Path) -> Result<BTreeMap<String, String>, ParseError> {
+    let text = std::fs::read_to_string(path)?;
+    let invalid = |message: String| ParseError::InvalidSidecar {
+        path: path.display().to_string(),
+        message,
+    };
+
+    let value = match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => {
+            let table: toml::Table = toml::from_str(&text).map_err(|e| invalid(e.to_string()))?;
+            toml_to_json(toml::Value::Table(table))
+        }
+        Some("json") => serde_json::from_str(&text).map_err(|e| invalid(e.to_string()))?,
+        _ => return Err(invalid("expected a .toml or .json extension".to_string())),
+    };
+
+    if !value.is_object() {
+        return Err(invalid("top level must be a table/object".to_string()));
+    }
+
+    let mut metadata = BTreeMap::new();
+    flatten("", &value, &mut metadata);
+    Ok(metadata)
+}
+
+/// Flatten a JSON value into dotted `key -> string` entries.
+fn flatten(prefix: &str, value: &serde_json::Value, out: &mut BTreeMap<String, String>) {
+    use serde_json::Value;
+
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(&key, child, out);
+            }
+        }
+        Value::String(s) => {
+            out.insert(prefix.to_string(), s.clone());
+        }
+        Value::Null => {}
+        Value::Array(items) => {
+            let parts: Vec<String> = items
+                .iter()
+                .map(|item| match item {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                })
+                .collect();
+            out.insert(prefix.to_string(), parts.join(", "));
+        }
+        other => {
+            out.insert(prefix.to_string(), other.to_string());
+        }
+    }
+}
+
+/// Convert a TOML value to JSON, rendering datetimes as strings.
+fn toml_to_json(value: toml::Value) -> serde_json::Value {
+    use serde_json::Value;
+
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::from(i),
+        toml::Value::Float(f) => Value::from(f),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(dt) => Value::String(dt.to_string()),
+        toml::Value::Array(items) => Value::Array(items.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(table) => Value::Object(
+            table
+                .into_iter()
+                .map(|(k, v)| (k, toml_to_json(v)))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flatten_nested_sidecar() {
+        let value = toml_to_json(toml::Value::Table(
+            toml::from_str("operator = \"J. Doe\"\nreplicate = 3\n[location]\nlab = \"B12\"\n").unwrap(),
+        ));
+        let mut out = BTreeMap::new();
+        flatten("", &value, &mut out);
+        assert_eq!(out["operator"], "J. Doe");
+        assert_eq!(out["replicate"], "3");
+        assert_eq!(out["location.lab"], "B12");
+    }
+}
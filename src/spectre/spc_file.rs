@@ -1,44 +1,143 @@
 //! Complete SPC file extraction including calibration and config.
 
-use crate::parser::{ParseError, StorageObject, unpack_container};
-use serde::Serialize;
+use crate::parser::{par_map, unpack_buffers_in_place, Anomalies, ObjectView, ParseError, ParseOptions, StorageObject, VariableView, Warning, WarningKind};
+use super::provenance::Provenance;
+use crate::processing::{ProcessingStep, SpectrumStats};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
 
 /// Calibration coefficients for converting pixel index to wavelength.
-/// Uses Legendre polynomial expansion: λ(x) = Σ aₖPₖ(x)
+/// The Spectrum Analyzer Suite uses a Legendre polynomial expansion:
+/// λ(x) = Σ aₖPₖ(x)
 /// where x is normalized pixel index (-1 to 1) and Pₖ are Legendre polynomials:
 ///   P₀(x) = 1
 ///   P₁(x) = x
 ///   P₂(x) = ½(3x² - 1)
 ///   P₃(x) = ½(5x³ - 3x)
-#[derive(Debug, Clone, Serialize, Default)]
+/// and higher orders follow Bonnet's recurrence
+///   (k+1)Pₖ₊₁(x) = (2k+1)xPₖ(x) - kPₖ₋₁(x)
+///
+/// External calibrations may instead use a plain polynomial in the raw
+/// pixel index or a cubic spline through (pixel, wavelength) knots; see
+/// [`CalibrationModel`].
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
 pub struct Calibration {
-    /// Legendre polynomial coefficients [a0, a1, a2, a3]
+    /// How `coefficients` / `knots` map pixels to wavelengths
+    #[serde(default, skip_serializing_if = "CalibrationModel::is_legendre")]
+    pub model: CalibrationModel,
+    /// Polynomial coefficients [a0, a1, ...], usually four (Legendre and
+    /// polynomial models)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub coefficients: Vec<f64>,
+    /// (pixel, wavelength) knots (spline model)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub knots: Vec<(f64, f64)>,
+}
+
+/// Calibration model.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CalibrationModel {
+    /// λ = Σ aₖPₖ(x) with x the pixel index scaled to -1..1
+    #[default]
+    Legendre,
+    /// λ = Σ aₖpᵏ with p the raw pixel index (0 to n-1)
+    Polynomial,
+    /// Natural cubic spline through the knots, extended linearly beyond them
+    Spline,
+}
+
+impl CalibrationModel {
+    fn is_legendre(&self) -> bool {
+        *self == CalibrationModel::Legendre
+    }
 }
 
 impl Calibration {
+    /// A Legendre calibration as stored in .spc files.
+    pub fn legendre(coefficients: Vec<f64>) -> Self {
+        Calibration {
+            model: CalibrationModel::Legendre,
+            coefficients,
+            knots: Vec::new(),
+        }
+    }
+
+    /// Read a calibration from a JSON or TOML file (chosen by extension),
+    /// e.g. `{"coefficients": [a0, a1, a2, a3]}` as in the JSON output, or
+    /// `{"model": "spline", "knots": [[0, 780.1], [1023, 921.6], ...]}`.
+    pub fn from_file(path: &Path) -> Result<Self, ParseError> {
+        let text = std::fs::read_to_string(path)?;
+        let invalid = |message: String| ParseError::InvalidCalibration {
+            path: path.display().to_string(),
+            message,
+        };
+
+        let calibration: Calibration = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => toml::from_str(&text).map_err(|e| invalid(e.to_string()))?,
+            Some("json") => serde_json::from_str(&text).map_err(|e| invalid(e.to_string()))?,
+            _ => return Err(invalid("expected a .toml or .json extension".to_string())),
+        };
+        match calibration.model {
+            CalibrationModel::Legendre | CalibrationModel::Polynomial if calibration.coefficients.is_empty() => {
+                Err(invalid("no coefficients".to_string()))
+            }
+            CalibrationModel::Spline if calibration.knots.len() < 2 => {
+                Err(invalid("a spline needs at least two knots".to_string()))
+            }
+            CalibrationModel::Spline if NaturalSpline::new(&calibration.knots).is_none() => {
+                Err(invalid("spline knots must have distinct pixel positions".to_string()))
+            }
+            _ => Ok(calibration),
+        }
+    }
+
+    /// Whether the calibration has enough data to evaluate.
+    fn is_usable(&self) -> bool {
+        match self.model {
+            CalibrationModel::Legendre | CalibrationModel::Polynomial => !self.coefficients.is_empty(),
+            CalibrationModel::Spline => self.knots.len() >= 2,
+        }
+    }
+
     /// Convert pixel index (0 to n-1) to wavelength (nm).
-    /// Uses Legendre polynomial expansion as defined in the Spectrum Analyzer Suite.
     pub fn pixel_to_wavelength(&self, pixel: usize, num_pixels: usize) -> Option<f64> {
-        if self.coefficients.len() != 4 || num_pixels == 0 {
+        self.fractional_pixel_to_wavelength(pixel as f64, num_pixels)
+    }
+
+    /// Wavelength at a fractional pixel index.
+    fn fractional_pixel_to_wavelength(&self, pixel: f64, num_pixels: usize) -> Option<f64> {
+        self.wavelength_fn(num_pixels).map(|wavelength| wavelength(pixel))
+    }
+
+    /// The wavelength at a fractional pixel index of a `num_pixels`
+    /// spectrum, with a spline solved once for all pixels.
+    fn wavelength_fn(&self, num_pixels: usize) -> Option<impl Fn(f64) -> f64 + '_> {
+        if !self.is_usable() || num_pixels == 0 {
             return None;
         }
-        
-        // Normalize pixel to -1..1 range: x = 2i/(N-1) - 1
-        let x = 2.0 * (pixel as f64) / ((num_pixels - 1) as f64) - 1.0;
-        
-        // Legendre polynomial evaluation:
-        // P₀(x) = 1
-        // P₁(x) = x
-        // P₂(x) = ½(3x² - 1)
-        // P₃(x) = ½(5x³ - 3x)
-        let p0 = 1.0;
-        let p1 = x;
-        let p2 = 0.5 * (3.0 * x * x - 1.0);
-        let p3 = 0.5 * (5.0 * x * x * x - 3.0 * x);
-        
-        let c = &self.coefficients;
-        Some(c[0] * p0 + c[1] * p1 + c[2] * p2 + c[3] * p3)
+        let spline = match self.model {
+            CalibrationModel::Spline => Some(NaturalSpline::new(&self.knots)?),
+            _ => None,
+        };
+
+        Some(move |pixel: f64| match spline {
+            Some(ref spline) => spline.eval(pixel),
+            None if self.model == CalibrationModel::Polynomial => {
+                self.coefficients.iter().rev().fold(0.0, |acc, &c| acc * pixel + c)
+            }
+            None => {
+                // Normalize pixel to -1..1 range: x = 2i/(N-1) - 1
+                let x = 2.0 * pixel / ((num_pixels - 1) as f64) - 1.0;
+
+                let basis = legendre(x, self.coefficients.len());
+                self.coefficients.iter().zip(&basis).map(|(c, p)| c * p).sum()
+            }
+        })
     }
     
     /// Convert pixel index to Raman shift (cm⁻¹) given laser wavelength.
@@ -47,36 +146,114 @@ impl Calibration {
         // Raman shift = 1e7 * (1/λ_laser - 1/λ)
         Some(1e7 * (1.0 / laser_wavelength - 1.0 / wavelength))
     }
+
+    /// Convert a wavelength (nm) to a fractional pixel index (0 to n-1).
+    /// Returns `None` if the wavelength lies outside the detector's range.
+    pub fn wavelength_to_pixel(&self, wavelength: f64, num_pixels: usize) -> Option<f64> {
+        if num_pixels < 2 || !wavelength.is_finite() {
+            return None;
+        }
+        let at = self.wavelength_fn(num_pixels)?;
+        let (mut lo, mut hi) = (0.0, (num_pixels - 1) as f64);
+        let (first, last) = (at(lo), at(hi));
+        // Allow for rounding in values computed from the end pixels
+        let margin = 1e-9 * (last - first).abs();
+        if wavelength < first.min(last) - margin || wavelength > first.max(last) + margin {
+            return None;
+        }
+
+        // Bisection; the calibration is monotonic across the detector
+        let rising = last > first;
+        for _ in 0..64 {
+            let mid = 0.5 * (lo + hi);
+            let above = at(mid) > wavelength;
+            if above == rising {
+                hi = mid;
+            } else {
+                lo = mid;
+            }
+        }
+        Some(0.5 * (lo + hi))
+    }
+
+    /// Convert a Raman shift (cm⁻¹) to a fractional pixel index given the
+    /// laser wavelength.
+    pub fn raman_shift_to_pixel(&self, raman_shift: f64, num_pixels: usize, laser_wavelength: f64) -> Option<f64> {
+        // λ = 1 / (1/λ_laser - shift/1e7)
+        let inverse = 1.0 / laser_wavelength - raman_shift / 1e7;
+        if inverse <= 0.0 {
+            return None;
+        }
+        self.wavelength_to_pixel(1.0 / inverse, num_pixels)
+    }
     
-    /// Generate wavelength axis for all pixels.
-    pub fn generate_wavelength_axis(&self, num_pixels: usize) -> Option<Vec<f64>> {
-        if self.coefficients.len() != 4 || num_pixels == 0 {
+    /// The calibration of pixels `start..start + len` of a `num_pixels`
+    /// spectrum, re-expressed for a `len`-pixel spectrum starting at the
+    /// first of them, so a cropped spectrum keeps its axes. `None` if the
+    /// calibration cannot be evaluated or a Legendre calibration would
+    /// cover fewer than two pixels.
+    pub fn cropped(&self, start: usize, len: usize, num_pixels: usize) -> Option<Calibration> {
+        if !self.is_usable() || start + len > num_pixels {
             return None;
         }
-        
-        let axis: Vec<f64> = (0..num_pixels)
-            .map(|i| self.pixel_to_wavelength(i, num_pixels).unwrap())
-            .collect();
-        
-        Some(axis)
+        let start = start as f64;
+        let mut calibration = self.clone();
+        match self.model {
+            CalibrationModel::Legendre => {
+                if len < 2 || num_pixels < 2 {
+                    return None;
+                }
+                // x = scale·x' + offset, with x and x' the old and new scaled pixel
+                let scale = (len - 1) as f64 / (num_pixels - 1) as f64;
+                let offset = scale + 2.0 * start / (num_pixels - 1) as f64 - 1.0;
+                let power = compose_affine(&legendre_to_power(&self.coefficients), scale, offset);
+                calibration.coefficients = power_to_legendre(&power);
+            }
+            CalibrationModel::Polynomial => {
+                calibration.coefficients = compose_affine(&self.coefficients, 1.0, start);
+            }
+            CalibrationModel::Spline => {
+                for knot in &mut calibration.knots {
+                    knot.0 -= start;
+                }
+            }
+        }
+        Some(calibration)
+    }
+
+    /// The same calibration as a Legendre model, as .spc files store it,
+    /// for a `num_pixels` spectrum. A polynomial is converted exactly;
+    /// `None` for a spline, which has no polynomial form, or fewer than two
+    /// pixels.
+    pub fn to_legendre(&self, num_pixels: usize) -> Option<Calibration> {
+        match self.model {
+            CalibrationModel::Legendre => Some(self.clone()),
+            CalibrationModel::Polynomial if num_pixels >= 2 => {
+                // p = h·x + h, with p the raw pixel index and x it scaled to -1..1
+                let half = (num_pixels - 1) as f64 / 2.0;
+                let power = compose_affine(&self.coefficients, half, half);
+                Some(Calibration::legendre(power_to_legendre(&power)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Generate wavelength axis for all pixels.
+    pub fn generate_wavelength_axis(&self, num_pixels: usize) -> Option<Vec<f64>> {
+        let at = self.wavelength_fn(num_pixels)?;
+        Some((0..num_pixels).map(|i| at(i as f64)).collect())
     }
     
     /// Generate Raman shift axis for all pixels.
     pub fn generate_raman_shift_axis(&self, num_pixels: usize, laser_wavelength: f64) -> Option<Vec<f64>> {
-        if self.coefficients.len() != 4 || num_pixels == 0 {
-            return None;
-        }
-        
-        let axis: Vec<f64> = (0..num_pixels)
-            .map(|i| self.pixel_to_raman_shift(i, num_pixels, laser_wavelength).unwrap())
-            .collect();
-        
-        Some(axis)
+        let wavelengths = self.generate_wavelength_axis(num_pixels)?;
+        // Raman shift = 1e7 * (1/λ_laser - 1/λ)
+        Some(wavelengths.iter().map(|wavelength| 1e7 * (1.0 / laser_wavelength - 1.0 / wavelength)).collect())
     }
 }
 
 /// Axis type enumeration for display preferences.
-#[derive(Debug, Clone, Copy, Serialize, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum AxisType {
     /// Display as pixel indices
@@ -99,7 +276,11 @@ impl From<i32> for AxisType {
 }
 
 /// Configuration parameters stored with the spectrum.
-#[derive(Debug, Clone, Serialize, Default)]
+///
+/// Deserializing the `config` of the JSON output and writing it with
+/// [`to_storage_object`](Config::to_storage_object) gives back the binary
+/// config block.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Config {
     /// Raman laser wavelength in nm (typically 785, 532, etc.)
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -137,19 +318,279 @@ pub struct Config {
     /// Preferred axis type for display
     #[serde(skip_serializing_if = "Option::is_none")]
     pub axis: Option<AxisType>,
-    /// Any other config values as key-value pairs
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    pub other: Vec<(String, String)>,
+    /// Any other config values, with their stored types
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub other: Vec<(String, ConfigValue)>,
+}
+
+/// A config value other than the known [`Config`] fields.
+///
+/// Serialized as a plain JSON value, except `Bytes`, which is
+/// `{"bytes": "<hex>"}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ConfigValue {
+    Bool(bool),
+    Int(i64),
+    Double(f64),
+    String(String),
+    /// A value of a type the converter does not interpret
+    Bytes {
+        #[serde(with = "hex")]
+        bytes: Vec<u8>,
+    },
+}
+
+impl ConfigValue {
+    /// Interpret the `data` variable of a `dynamic_var<T>` by its type
+    /// name, or by its size when the type is not recognized (8 bytes:
+    /// double, 4: int, 1: bool).
+    fn from_variable(var: &VariableView) -> Self {
+        let data = var.data;
+        let type_name = var.type_name;
+        let has = |words: &[&str]| words.iter().any(|word| type_name.contains(word));
+        match data.len() {
+            _ if has(&["string", "char"]) => {
+                let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+                match std::str::from_utf8(&data[..end]) {
+                    Ok(text) => ConfigValue::String(text.to_string()),
+                    Err(_) => ConfigValue::Bytes { bytes: data.to_vec() },
+                }
+            }
+            1 => ConfigValue::Bool(data[0] != 0),
+            4 if has(&["float"]) => ConfigValue::Double(f32::from_le_bytes(data.try_into().unwrap()) as f64),
+            4 => ConfigValue::Int(i32::from_le_bytes(data.try_into().unwrap()) as i64),
+            8 if has(&["int", "long"]) => ConfigValue::Int(i64::from_le_bytes(data.try_into().unwrap())),
+            8 => ConfigValue::Double(f64::from_le_bytes(data.try_into().unwrap())),
+            _ => ConfigValue::Bytes { bytes: data.to_vec() },
+        }
+    }
+}
+
+/// Types [`Config::get`] converts config values to.
+///
+/// Numbers convert between ints and doubles when no precision is lost,
+/// strings are parsed, ints 0 and 1 are booleans, and any value formats
+/// as a `String`.
+pub trait FromConfigValue: Sized {
+    /// The value as `Self`, if it converts.
+    fn from_config_value(value: &ConfigValue) -> Option<Self>;
+}
+
+impl FromConfigValue for ConfigValue {
+    fn from_config_value(value: &ConfigValue) -> Option<Self> {
+        Some(value.clone())
+    }
+}
+
+impl FromConfigValue for f64 {
+    fn from_config_value(value: &ConfigValue) -> Option<Self> {
+        match value {
+            ConfigValue::Double(v) => Some(*v),
+            ConfigValue::Int(v) => Some(*v as f64),
+            ConfigValue::String(v) => v.trim().parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+impl FromConfigValue for i64 {
+    fn from_config_value(value: &ConfigValue) -> Option<Self> {
+        match value {
+            ConfigValue::Int(v) => Some(*v),
+            ConfigValue::Double(v) if v.fract() == 0.0 && v.abs() < 2f64.powi(63) => Some(*v as i64),
+            ConfigValue::String(v) => v.trim().parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+impl FromConfigValue for i32 {
+    fn from_config_value(value: &ConfigValue) -> Option<Self> {
+        i64::from_config_value(value)?.try_into().ok()
+    }
+}
+
+impl FromConfigValue for usize {
+    fn from_config_value(value: &ConfigValue) -> Option<Self> {
+        i64::from_config_value(value)?.try_into().ok()
+    }
+}
+
+impl FromConfigValue for bool {
+    fn from_config_value(value: &ConfigValue) -> Option<Self> {
+        match value {
+            ConfigValue::Bool(v) => Some(*v),
+            ConfigValue::Int(0) => Some(false),
+            ConfigValue::Int(1) => Some(true),
+            ConfigValue::String(v) => v.trim().parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+impl FromConfigValue for String {
+    fn from_config_value(value: &ConfigValue) -> Option<Self> {
+        Some(value.to_string())
+    }
+}
+
+impl FromConfigValue for AxisType {
+    fn from_config_value(value: &ConfigValue) -> Option<Self> {
+        i32::from_config_value(value).map(AxisType::from)
+    }
+}
+
+impl std::fmt::Display for ConfigValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigValue::Bool(value) => write!(f, "{}", value),
+            ConfigValue::Int(value) => write!(f, "{}", value),
+            ConfigValue::Double(value) => write!(f, "{}", value),
+            ConfigValue::String(value) => write!(f, "{}", value),
+            ConfigValue::Bytes { bytes } => bytes.iter().try_for_each(|byte| write!(f, "{:02x}", byte)),
+        }
+    }
+}
+
+impl Config {
+    /// A parameter by its name in the file, from the known fields or
+    /// `other`, converted to `T` (see [`FromConfigValue`]).
+    ///
+    /// ```
+    /// # use spc_converter::spectre::{Config, ConfigValue};
+    /// let config = Config {
+    ///     raman_wavelength: Some(785.0),
+    ///     smoothing: Some(5),
+    ///     other: vec![("cooler".to_string(), ConfigValue::String("-60".to_string()))],
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(config.get::<f64>("smoothing"), Some(5.0));
+    /// assert_eq!(config.get::<i32>("raman_wavelength"), Some(785));
+    /// assert_eq!(config.get::<i64>("cooler"), Some(-60));
+    /// assert_eq!(config.get::<bool>("cooler"), None);
+    /// assert_eq!(config.get::<f64>("gain"), None);
+    /// ```
+    pub fn get<T: FromConfigValue>(&self, key: &str) -> Option<T> {
+        T::from_config_value(&self.value(key)?)
+    }
+
+    /// A parameter by its name in the file, with its stored type.
+    pub fn value(&self, key: &str) -> Option<ConfigValue> {
+        let double = |v: Option<f64>| v.map(ConfigValue::Double);
+        let int = |v: Option<i32>| v.map(|v| ConfigValue::Int(v as i64));
+        let bool = |v: Option<bool>| v.map(ConfigValue::Bool);
+        match key {
+            "raman_wavelength" => double(self.raman_wavelength),
+            "exposure" => double(self.exposure),
+            "gain" => double(self.gain),
+            "smoothing" => int(self.smoothing),
+            "average" => int(self.average),
+            "sgolay_window" => int(self.sgolay_window),
+            "sgolay_order" => int(self.sgolay_order),
+            "sgolay_deriv" => int(self.sgolay_deriv),
+            "axis" => int(self.axis.map(|axis| axis as i32)),
+            "medfilt" => bool(self.medfilt),
+            "baseline" => bool(self.baseline),
+            "sgolay" => bool(self.sgolay),
+            _ => self.other.iter().find(|(name, _)| name == key).map(|(_, value)| value.clone()),
+        }
+    }
+}
+
+/// Hex strings for [`ConfigValue::Bytes`].
+mod hex {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&super::ConfigValue::Bytes { bytes: bytes.to_vec() })
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        if hex.len() % 2 != 0 {
+            return Err(de::Error::custom("odd number of hex digits"));
+        }
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(hex.get(i..i + 2).unwrap_or("x"), 16).map_err(de::Error::custom))
+            .collect()
+    }
+}
+
+/// JSON writes NaN and infinite values as `null`; they are read back as NaN.
+mod nullable {
+    use serde::{Deserialize, Deserializer};
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<f64>, D::Error> {
+        let values = Vec::<Option<f64>>::deserialize(deserializer)?;
+        Ok(values.into_iter().map(|value| value.unwrap_or(f64::NAN)).collect())
+    }
+
+    pub fn deserialize_option<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<f64>>, D::Error> {
+        let values = Option::<Vec<Option<f64>>>::deserialize(deserializer)?;
+        Ok(values.map(|values| values.into_iter().map(|value| value.unwrap_or(f64::NAN)).collect()))
+    }
+}
+
+/// Camera and detector identification, from variables of the `data`
+/// object or entries of the config.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Instrument {
+    /// Camera or spectrometer model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Serial number, when stored apart from the UID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial: Option<String>,
+    /// Detector temperature as stored (usually °C)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detector_temperature: Option<f64>,
+    /// Firmware version
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub firmware: Option<String>,
+}
+
+impl Instrument {
+    /// Whether no field is known.
+    pub fn is_empty(&self) -> bool {
+        self.entries().is_empty()
+    }
+
+    /// The known fields as `instrument.<field>` keys and text values, as
+    /// written by the text formats and read by
+    /// [`SpcFile::extend_metadata`].
+    pub fn entries(&self) -> Vec<(&'static str, String)> {
+        let temperature = self.detector_temperature.map(|t| t.to_string());
+        [
+            ("instrument.model", self.model.clone()),
+            ("instrument.serial", self.serial.clone()),
+            ("instrument.detector_temperature", temperature),
+            ("instrument.firmware", self.firmware.clone()),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value?)))
+        .collect()
+    }
 }
 
 /// Complete extracted data from an SPC file.
-#[derive(Debug, Clone, Serialize)]
+///
+/// The JSON output deserializes back into an `SpcFile`, so an edited
+/// JSON file can be written as a `.spc` again with
+/// [`to_bytes`](SpcFile::to_bytes). The axes are taken as given; call
+/// [`set_calibration`](SpcFile::set_calibration) or
+/// [`set_laser_wavelength`](SpcFile::set_laser_wavelength) to regenerate
+/// them after editing the calibration or laser wavelength.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SpcFile {
     /// Unique identifier for this measurement (typically camera serial number).
     pub uid: String,
     /// Spectral intensity data (Y-axis values).
+    #[serde(deserialize_with = "nullable::deserialize")]
     pub data: Vec<f64>,
     /// Blank/reference spectrum for calibration.
+    #[serde(default, deserialize_with = "nullable::deserialize")]
     pub blank: Vec<f64>,
     /// Calibration data if present.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -158,104 +599,770 @@ pub struct SpcFile {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub config: Option<Config>,
     /// Generated wavelength axis (if calibration is present).
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "nullable::deserialize_option")]
     pub wavelength_axis: Option<Vec<f64>>,
     /// Generated Raman shift axis (if calibration and raman_wavelength are present).
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "nullable::deserialize_option")]
     pub raman_shift_axis: Option<Vec<f64>>,
+    /// Acquisition date and time: RFC 3339 UTC when the file stores a Unix
+    /// timestamp, else the text as stored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acquired: Option<String>,
+    /// Operator who acquired the spectrum.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operator: Option<String>,
+    /// Sample name or label.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample: Option<String>,
+    /// Camera and detector identification, if any is stored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instrument: Option<Instrument>,
+    /// Source file and conversion details, when requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+    /// Intensity statistics and axis range, when requested; set with
+    /// [`processing::summarize`](crate::processing::summarize) after the
+    /// last change to the data (not read back from JSON).
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<SpectrumStats>,
+    /// User-supplied metadata and per-spectrum stamps (`time_s`,
+    /// `sequence`).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+    /// Processing steps applied to the data, in order (not read back from
+    /// JSON).
+    #[serde(skip_deserializing, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<ProcessingStep>,
+    /// Top-level objects other than data, calibration and config, kept so
+    /// that nothing is lost when the file is written back (not read back
+    /// from JSON).
+    #[serde(skip_deserializing, skip_serializing_if = "Vec::is_empty")]
+    pub extras: Vec<StorageObject>,
 }
 
 impl SpcFile {
     /// Parse from raw file bytes (handles container encryption/compression).
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        Self::from_bytes_in_place(&mut bytes.to_vec())
+    }
+
+    /// Parse from raw file bytes, decrypting them in place.
+    ///
+    /// Unencoded buffers are read directly from `bytes` and each vector is
+    /// decoded straight into its final `Vec<f64>`, so apart from compressed
+    /// buffers no intermediate copies are made.
+    pub fn from_bytes_in_place(bytes: &mut [u8]) -> Result<Self, ParseError> {
+        Self::from_bytes_in_place_with(bytes, &ParseOptions::default()).map(|(spc, _)| spc)
+    }
+
+    /// Parse from raw file bytes with the given options, returning the
+    /// anomalies that were skipped as warnings (always empty in strict mode).
+    pub fn from_bytes_with(bytes: &[u8], options: &ParseOptions) -> Result<(Self, Vec<String>), ParseError> {
+        Self::from_bytes_in_place_with(&mut bytes.to_vec(), options)
+    }
+
+    /// Like [`from_bytes_with`](Self::from_bytes_with), decrypting in place.
+    pub fn from_bytes_in_place_with(
+        bytes: &mut [u8],
+        options: &ParseOptions,
+    ) -> Result<(Self, Vec<String>), ParseError> {
+        let (spc, warnings) = Self::from_bytes_in_place_with_report(bytes, options)?;
+        Ok((spc, warnings.into_iter().map(|warning| warning.message).collect()))
+    }
+
+    /// Like [`from_bytes_with`](Self::from_bytes_with), with each warning's
+    /// [kind](WarningKind) so callers can act on some and ignore others.
+    pub fn from_bytes_with_report(bytes: &[u8], options: &ParseOptions) -> Result<(Self, Vec<Warning>), ParseError> {
+        Self::from_bytes_in_place_with_report(&mut bytes.to_vec(), options)
+    }
+
+    /// Like [`from_bytes_with_report`](Self::from_bytes_with_report),
+    /// decrypting in place.
+    pub fn from_bytes_in_place_with_report(
+        bytes: &mut [u8],
+        options: &ParseOptions,
+    ) -> Result<(Self, Vec<Warning>), ParseError> {
         // First unpack the container (decrypt + decompress)
-        let buffers = unpack_container(bytes)?;
-        
+        let (file_offsets, buffers): (Vec<_>, Vec<_>) = unpack_buffers_in_place(bytes, &options.limits)?.into_iter().unzip();
+        let mut anomalies = Anomalies::new(options);
+        let mut spc = Self::from_buffers(&buffers, &file_offsets, false, options.skip_blank, &mut anomalies)?;
+        if !options.lazy_axes {
+            spc.generate_axes();
+        }
+        Ok((spc, anomalies.warnings))
+    }
+
+    /// Build from decoded StorageObject buffers, without generating the
+    /// axes. With `partial`, a missing UID or blank is left empty instead
+    /// of being an error; with `skip_blank`, the blank is not read at all.
+    /// Errors are located at the `file_offsets` of unencoded buffers, where
+    /// known.
+    pub(super) fn from_buffers<B: AsRef<[u8]> + Sync>(
+        buffers: &[B],
+        file_offsets: &[Option<u64>],
+        partial: bool,
+        skip_blank: bool,
+        anomalies: &mut Anomalies,
+    ) -> Result<Self, ParseError> {
+        let mut spectra = Self::spectra_from_buffers(buffers, file_offsets, partial, false, skip_blank, anomalies)?;
+        Ok(spectra.pop().expect("one data object"))
+    }
+
+    /// Like [`from_buffers`](Self::from_buffers), but with `all`, every
+    /// `data` object (also those named `data1`, `data2`, ...) becomes a
+    /// spectrum sharing the calibration, config and extras. Otherwise only
+    /// the last `data` object is read and duplicates are anomalies.
+    #[tracing::instrument(name = "parse_objects", level = "debug", skip_all, fields(buffers = buffers.len()))]
+    pub(super) fn spectra_from_buffers<B: AsRef<[u8]> + Sync>(
+        buffers: &[B],
+        file_offsets: &[Option<u64>],
+        partial: bool,
+        all: bool,
+        skip_blank: bool,
+        anomalies: &mut Anomalies,
+    ) -> Result<Vec<Self>, ParseError> {
         if buffers.is_empty() {
-            return Err(ParseError::MissingField("No buffers in container".to_string()));
+            return Err(ParseError::NoBuffers);
         }
 
-        // Parse each buffer as a StorageObject
-        let mut data_obj: Option<StorageObject> = None;
-        let mut calibration_obj: Option<StorageObject> = None;
-        let mut config_obj: Option<StorageObject> = None;
+        // Locate errors within the object in buffer `i`
+        let locate = |i: usize, name: &'static str| {
+            let file_offset = file_offsets.get(i).copied().flatten();
+            move |e: ParseError| e.within(name).in_buffer(i, file_offset)
+        };
+
+        // Find the objects (with their buffer index); their contents are only read below
+        let mut data_objs: Vec<(usize, ObjectView)> = Vec::new();
+        let mut calibration_obj: Option<(usize, ObjectView)> = None;
+        let mut config_obj: Option<(usize, ObjectView)> = None;
+        let mut extras = Vec::new();
         
-        for buffer in &buffers {
-            if let Ok(obj) = StorageObject::from_bytes(buffer) {
-                match obj.var_name.as_str() {
-                    "data" => data_obj = Some(obj),
-                    "calibration" => calibration_obj = Some(obj),
-                    "config" => config_obj = Some(obj),
-                    _ => {} // Ignore unknown objects
+        let views = par_map(buffers, |_, buffer| ObjectView::parse(buffer.as_ref()));
+        for (i, view) in views.into_iter().enumerate() {
+            let obj = match view {
+                Ok(obj) => obj,
+                Err(e) => {
+                    let e = e.in_buffer(i, file_offsets.get(i).copied().flatten());
+                    anomalies.report(WarningKind::UnreadableObject, format!("unreadable StorageObject: {}", e))?;
+                    continue;
+                }
+            };
+            tracing::trace!("buffer[{}]: object '{}' ({})", i, obj.var_name, obj.type_name);
+            let slot = match obj.var_name {
+                "data" if !all => {
+                    if !data_objs.is_empty() {
+                        anomalies.report(WarningKind::DuplicateObject, format!("duplicate object 'data' in buffer[{}]", i))?;
+                    }
+                    data_objs = vec![(i, obj)];
+                    continue;
                 }
+                name if all && is_data_name(name) => {
+                    data_objs.push((i, obj));
+                    continue;
+                }
+                "calibration" => &mut calibration_obj,
+                "config" => &mut config_obj,
+                name => {
+                    anomalies.report(WarningKind::UnknownObject, format!("unknown object '{}' in buffer[{}]", name, i))?;
+                    match obj.to_storage_object() {
+                        Ok(extra) => extras.push(extra),
+                        Err(e) => {
+                            let e = e.within(name).in_buffer(i, file_offsets.get(i).copied().flatten());
+                            anomalies.report(WarningKind::UnreadableObject, format!("unreadable object '{}': {}", name, e))?
+                        }
+                    }
+                    continue;
+                }
+            };
+            if slot.is_some() {
+                anomalies.report(WarningKind::DuplicateObject, format!("duplicate object '{}' in buffer[{}]", obj.var_name, i))?;
             }
+            *slot = Some((i, obj));
         }
         
         // Data object is required
-        let data_obj = data_obj.ok_or_else(|| ParseError::MissingField("data".to_string()))?;
-        
-        // Extract SpectreFile data
-        let uid = extract_string_child(&data_obj, "m_uid")?;
-        let data = extract_double_vector_child(&data_obj, "m_data")?;
-        let blank = extract_double_vector_child(&data_obj, "m_blank")?;
-        
+        if data_objs.is_empty() {
+            return Err(ParseError::MissingField("data".to_string()));
+        }
+
         // Extract calibration if present
-        let calibration = calibration_obj.and_then(|obj| {
-            extract_double_vector(&obj).ok().map(|coefficients| Calibration { coefficients })
-        });
+        let calibration = match calibration_obj.map(|(i, obj)| obj.f64_values().map_err(locate(i, "calibration"))) {
+            Some(Ok(coefficients)) => Some(Calibration::legendre(coefficients)),
+            Some(Err(e)) => {
+                anomalies.report(WarningKind::UnreadableObject, format!("unreadable calibration: {}", e))?;
+                None
+            }
+            None => None,
+        };
+        if calibration.as_ref().is_some_and(|cal| cal.coefficients.is_empty()) {
+            anomalies.report(WarningKind::CalibrationLength, "calibration has no coefficients; no axes generated")?;
+        }
         
         // Extract config if present
-        let config = config_obj.and_then(|obj| extract_config(&obj).ok());
-        
-        // Generate axes if possible
-        let num_pixels = data.len();
-        let wavelength_axis = calibration.as_ref()
-            .and_then(|cal| cal.generate_wavelength_axis(num_pixels));
-        
-        let raman_shift_axis = calibration.as_ref()
-            .and_then(|cal| {
-                config.as_ref()
-                    .and_then(|cfg| cfg.raman_wavelength)
-                    .and_then(|laser| cal.generate_raman_shift_axis(num_pixels, laser))
+        let config = match config_obj.map(|(i, obj)| (i, extract_config(&obj, anomalies))) {
+            Some((_, Ok(config))) => Some(config),
+            Some((_, Err(ParseError::Strict(message)))) => return Err(ParseError::Strict(message)),
+            Some((i, Err(e))) => {
+                let e = locate(i, "config")(e);
+                anomalies.report(WarningKind::UnreadableObject, format!("unreadable config: {}", e))?;
+                None
+            }
+            None => None,
+        };
+
+        let mut spectra = Vec::with_capacity(data_objs.len());
+        for (data_index, data_obj) in data_objs {
+            let in_data = locate(data_index, "data");
+
+            // Extract SpectreFile data
+            let uid = match extract_string_child(&data_obj, "m_uid", anomalies) {
+                Err(ParseError::MissingField(_)) if partial => String::new(),
+                uid => uid.map_err(in_data)?,
+            };
+            let data = extract_double_vector_child(&data_obj, "m_data").map_err(in_data)?;
+            let blank = if skip_blank {
+                Vec::new()
+            } else {
+                match extract_double_vector_child(&data_obj, "m_blank") {
+                    Err(ParseError::MissingField(_)) if partial => Vec::new(),
+                    blank => blank.map_err(in_data)?,
+                }
+            };
+            if !blank.is_empty() && blank.len() != data.len() {
+                anomalies.report(WarningKind::BlankLength, format!("blank has {} points but data has {}", blank.len(), data.len()))?;
+            }
+            if let Some(count) = calibration.as_ref().map(|cal| cal.coefficients.len()).filter(|&count| count > data.len()) {
+                anomalies.report(
+                    WarningKind::CalibrationLength,
+                    format!("calibration has {} coefficients but data has {} points", count, data.len()),
+                )?;
+            }
+
+            let labels = extract_labels(&data_obj, config.as_ref());
+            spectra.push(Self {
+                uid,
+                data,
+                blank,
+                calibration: calibration.clone(),
+                config: config.clone(),
+                wavelength_axis: None,
+                raman_shift_axis: None,
+                acquired: labels.acquired,
+                operator: labels.operator,
+                sample: labels.sample,
+                instrument: extract_instrument(&data_obj, config.as_ref()),
+                provenance: None,
+                stats: None,
+                metadata: extract_stamps(&data_obj),
+                history: Vec::new(),
+                extras: extras.clone(),
             });
-        
-        Ok(Self {
-            uid,
-            data,
-            blank,
-            calibration,
-            config,
-            wavelength_axis,
-            raman_shift_axis,
-        })
+        }
+        tracing::debug!(
+            "extracted {} spectra of {} points{}{}",
+            spectra.len(),
+            spectra.first().map_or(0, |spc| spc.data.len()),
+            if calibration.is_some() { ", calibrated" } else { "" },
+            if config.is_some() { ", with config" } else { "" }
+        );
+        Ok(spectra)
     }
 
     /// Read from a file path.
     pub fn from_file(path: &std::path::Path) -> Result<Self, ParseError> {
-        let bytes = std::fs::read(path)?;
-        Self::from_bytes(&bytes)
+        let mut bytes = std::fs::read(path)?;
+        Self::from_bytes_in_place(&mut bytes)
+    }
+
+    /// Read from a file path with the given options; see
+    /// [`from_bytes_with`](Self::from_bytes_with).
+    pub fn from_file_with(
+        path: &std::path::Path,
+        options: &ParseOptions,
+    ) -> Result<(Self, Vec<String>), ParseError> {
+        let mut bytes = std::fs::read(path)?;
+        Self::from_bytes_in_place_with(&mut bytes, options)
+    }
+
+    /// Read from the current position of any seekable source (an archive
+    /// entry, a network stream, an in-memory cursor) to its end. Error
+    /// offsets count from that position.
+    pub fn from_reader<R: Read + Seek>(reader: R) -> Result<Self, ParseError> {
+        Self::from_reader_with(reader, &ParseOptions::default()).map(|(spc, _)| spc)
+    }
+
+    /// Read from a seekable source with the given options; see
+    /// [`from_bytes_with`](Self::from_bytes_with).
+    pub fn from_reader_with<R: Read + Seek>(
+        mut reader: R,
+        options: &ParseOptions,
+    ) -> Result<(Self, Vec<String>), ParseError> {
+        // The remaining length sizes the buffer in one allocation
+        let start = reader.stream_position()?;
+        let end = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(start))?;
+        let mut bytes = Vec::with_capacity(end.saturating_sub(start) as usize);
+        reader.read_to_end(&mut bytes)?;
+        Self::from_bytes_in_place_with(&mut bytes, options)
     }
     
+    /// Add metadata entries; `acquired`, `operator`, `sample` and the
+    /// `instrument.*` keys of [`Instrument::entries`] set those fields
+    /// instead.
+    pub fn extend_metadata(&mut self, entries: impl IntoIterator<Item = (String, String)>) {
+        for (key, value) in entries {
+            let temperature = value.trim().parse::<f64>().ok();
+            match key.as_str() {
+                "acquired" => self.acquired = Some(value),
+                "operator" => self.operator = Some(value),
+                "sample" => self.sample = Some(value),
+                "instrument.model" | "instrument.serial" | "instrument.firmware" => {
+                    let instrument = self.instrument.get_or_insert_with(Instrument::default);
+                    let field = match key.as_str() {
+                        "instrument.model" => &mut instrument.model,
+                        "instrument.serial" => &mut instrument.serial,
+                        _ => &mut instrument.firmware,
+                    };
+                    *field = Some(value);
+                }
+                "instrument.detector_temperature" if temperature.is_some() => {
+                    self.instrument.get_or_insert_with(Instrument::default).detector_temperature = temperature;
+                }
+                _ => {
+                    self.metadata.insert(key, value);
+                }
+            }
+        }
+    }
+
     /// Check if this file has calibration data.
     pub fn has_calibration(&self) -> bool {
         self.calibration.is_some()
     }
     
+    /// Replace the calibration and regenerate the wavelength and Raman
+    /// shift axes from it.
+    pub fn set_calibration(&mut self, calibration: Calibration) {
+        let num_pixels = self.data.len();
+        self.wavelength_axis = calibration.generate_wavelength_axis(num_pixels);
+        self.raman_shift_axis = self
+            .config
+            .as_ref()
+            .and_then(|cfg| cfg.raman_wavelength)
+            .and_then(|laser| calibration.generate_raman_shift_axis(num_pixels, laser));
+        self.calibration = Some(calibration);
+    }
+
+    /// Set the Raman excitation wavelength in nm, creating the config block
+    /// if needed, and regenerate the Raman shift axis from it.
+    ///
+    /// Without a calibration (e.g. for imported files), the Raman shift
+    /// axis is computed from the wavelength axis, or kept as is if there is
+    /// none.
+    pub fn set_laser_wavelength(&mut self, laser_wavelength: f64) {
+        self.config.get_or_insert_with(Config::default).raman_wavelength = Some(laser_wavelength);
+        self.raman_shift_axis = match (&self.calibration, &self.wavelength_axis) {
+            (Some(cal), _) => cal.generate_raman_shift_axis(self.data.len(), laser_wavelength),
+            (None, Some(wavelengths)) => Some(
+                wavelengths
+                    .iter()
+                    .map(|&nm| 1e7 * (1.0 / laser_wavelength - 1.0 / nm))
+                    .collect(),
+            ),
+            (None, None) => self.raman_shift_axis.take(),
+        };
+    }
+
     /// Check if this file has Raman shift data.
     pub fn has_raman_shift(&self) -> bool {
         self.raman_shift_axis.is_some()
+            || (self.calibration.as_ref().is_some_and(Calibration::is_usable)
+                && !self.data.is_empty()
+                && self.config.as_ref().and_then(|cfg| cfg.raman_wavelength).is_some())
+    }
+
+    /// The wavelength axis: the stored one or, for files parsed with
+    /// [`lazy_axes`](ParseOptions::lazy_axes), generated from the
+    /// calibration.
+    pub fn wavelengths(&self) -> Option<Cow<'_, [f64]>> {
+        match self.wavelength_axis {
+            Some(ref axis) => Some(Cow::Borrowed(axis)),
+            None => self.calibration.as_ref()?.generate_wavelength_axis(self.data.len()).map(Cow::Owned),
+        }
+    }
+
+    /// The Raman shift axis: the stored one or, for files parsed with
+    /// [`lazy_axes`](ParseOptions::lazy_axes), generated from the
+    /// calibration and laser wavelength.
+    pub fn raman_shifts(&self) -> Option<Cow<'_, [f64]>> {
+        match self.raman_shift_axis {
+            Some(ref axis) => Some(Cow::Borrowed(axis)),
+            None => {
+                let laser = self.config.as_ref()?.raman_wavelength?;
+                self.calibration.as_ref()?.generate_raman_shift_axis(self.data.len(), laser).map(Cow::Owned)
+            }
+        }
+    }
+
+    /// Store the axes that [`wavelengths`](Self::wavelengths) and
+    /// [`raman_shifts`](Self::raman_shifts) would generate, so they are
+    /// computed once and serialized. Needed before the number of points
+    /// changes, as the calibration covers the original pixels.
+    pub fn generate_axes(&mut self) {
+        if self.wavelength_axis.is_none() {
+            self.wavelength_axis = self.wavelengths().map(Cow::into_owned);
+        }
+        if self.raman_shift_axis.is_none() {
+            self.raman_shift_axis = self.raman_shifts().map(Cow::into_owned);
+        }
+    }
+
+    /// Intensities with the blank spectrum subtracted point by point.
+    /// Points beyond the end of the blank are returned unchanged.
+    pub fn blank_subtracted(&self) -> Vec<f64> {
+        self.data
+            .iter()
+            .enumerate()
+            .map(|(i, v)| v - self.blank.get(i).copied().unwrap_or(0.0))
+            .collect()
+    }
+}
+
+/// Natural cubic spline through (x, y) knots; beyond the outer knots the
+/// end tangents are followed.
+struct NaturalSpline {
+    /// Knots sorted by x
+    knots: Vec<(f64, f64)>,
+    /// Second derivative at each knot, zero at the ends
+    m: Vec<f64>,
+}
+
+impl NaturalSpline {
+    /// Solve for the spline through `knots`. `None` if there are fewer than
+    /// two knots or two share an x position.
+    fn new(knots: &[(f64, f64)]) -> Option<Self> {
+        let mut knots = knots.to_vec();
+        knots.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let n = knots.len();
+        let h: Vec<f64> = knots.windows(2).map(|w| w[1].0 - w[0].0).collect();
+        if n < 2 || h.iter().any(|&step| step <= 0.0) {
+            return None;
+        }
+
+        // Second derivatives m, with m₀ = mₙ₋₁ = 0 (Thomas algorithm)
+        let mut m = vec![0.0; n];
+        if n > 2 {
+            let mut diag = vec![0.0; n];
+            let mut rhs = vec![0.0; n];
+            for i in 1..n - 1 {
+                diag[i] = 2.0 * (h[i - 1] + h[i]);
+                rhs[i] = 6.0 * ((knots[i + 1].1 - knots[i].1) / h[i] - (knots[i].1 - knots[i - 1].1) / h[i - 1]);
+            }
+            for i in 2..n - 1 {
+                let factor = h[i - 1] / diag[i - 1];
+                diag[i] -= factor * h[i - 1];
+                rhs[i] -= factor * rhs[i - 1];
+            }
+            for i in (1..n - 1).rev() {
+                m[i] = (rhs[i] - h[i] * m[i + 1]) / diag[i];
+            }
+        }
+        Some(NaturalSpline { knots, m })
+    }
+
+    /// Evaluate the spline at `x`.
+    fn eval(&self, x: f64) -> f64 {
+        let (knots, m) = (&self.knots, &self.m);
+        let n = knots.len();
+        let segment = knots[1..n - 1].iter().take_while(|k| k.0 <= x).count();
+        let (x0, y0) = knots[segment];
+        let (x1, y1) = knots[segment + 1];
+        let step = x1 - x0;
+        let slope = |at_end: bool| {
+            let base = (y1 - y0) / step;
+            if at_end {
+                base + step * (2.0 * m[segment + 1] + m[segment]) / 6.0
+            } else {
+                base - step * (2.0 * m[segment] + m[segment + 1]) / 6.0
+            }
+        };
+        if x < x0 {
+            return y0 + slope(false) * (x - x0);
+        }
+        if x > x1 {
+            return y1 + slope(true) * (x - x1);
+        }
+        let (a, b) = ((x1 - x) / step, (x - x0) / step);
+        a * y0 + b * y1 + ((a * a * a - a) * m[segment] + (b * b * b - b) * m[segment + 1]) * step * step / 6.0
+    }
+}
+
+/// Legendre polynomials P₀..Pₙ₋₁ at `x`, by Bonnet's recurrence.
+pub(crate) fn legendre(x: f64, count: usize) -> Vec<f64> {
+    let mut p = Vec::with_capacity(count);
+    for k in 0..count {
+        let value = match k {
+            0 => 1.0,
+            1 => x,
+            _ => {
+                let k = k as f64;
+                ((2.0 * k - 1.0) * x * p[p.len() - 1] - (k - 1.0) * p[p.len() - 2]) / k
+            }
+        };
+        p.push(value);
+    }
+    p
+}
+
+/// Power-series coefficients (of 1, x, x², ...) of each Legendre
+/// polynomial up to `count - 1`.
+fn legendre_power_series(count: usize) -> Vec<Vec<f64>> {
+    let mut p: Vec<Vec<f64>> = Vec::with_capacity(count);
+    for k in 0..count {
+        let series = match k {
+            0 => vec![1.0],
+            1 => vec![0.0, 1.0],
+            _ => {
+                // kPₖ = (2k-1)xPₖ₋₁ - (k-1)Pₖ₋₂
+                let kf = k as f64;
+                let mut series = vec![0.0; k + 1];
+                for (i, c) in p[k - 1].iter().enumerate() {
+                    series[i + 1] += (2.0 * kf - 1.0) * c / kf;
+                }
+                for (i, c) in p[k - 2].iter().enumerate() {
+                    series[i] -= (kf - 1.0) * c / kf;
+                }
+                series
+            }
+        };
+        p.push(series);
     }
+    p
 }
 
-/// Extract a storage_string child as a String.
-fn extract_string_child(obj: &StorageObject, name: &str) -> Result<String, ParseError> {
+/// Σ aₖPₖ(x) as a power series in x.
+fn legendre_to_power(coefficients: &[f64]) -> Vec<f64> {
+    let mut power = vec![0.0; coefficients.len()];
+    for (c, series) in coefficients.iter().zip(legendre_power_series(coefficients.len())) {
+        for (i, s) in series.iter().enumerate() {
+            power[i] += c * s;
+        }
+    }
+    power
+}
+
+/// A power series in x as Legendre coefficients.
+fn power_to_legendre(power: &[f64]) -> Vec<f64> {
+    let basis = legendre_power_series(power.len());
+    let mut rest = power.to_vec();
+    let mut coefficients = vec![0.0; power.len()];
+    for k in (0..power.len()).rev() {
+        coefficients[k] = rest[k] / basis[k][k];
+        for (i, s) in basis[k].iter().enumerate() {
+            rest[i] -= coefficients[k] * s;
+        }
+    }
+    coefficients
+}
+
+/// The power series of p(scale·x + offset), for a power series p.
+fn compose_affine(power: &[f64], scale: f64, offset: f64) -> Vec<f64> {
+    let mut result = vec![0.0; power.len()];
+    for &c in power.iter().rev() {
+        // result = result·(scale·x + offset) + c
+        let mut next = vec![0.0; power.len()];
+        for (i, r) in result.iter().enumerate() {
+            next[i] += r * offset;
+            if i + 1 < next.len() {
+                next[i + 1] += r * scale;
+            }
+        }
+        next[0] += c;
+        result = next;
+    }
+    result
+}
+
+/// Names of the acquisition time (in seconds) of a spectrum in kinetic
+/// sessions, as a variable or `dynamic_var` child of its `data` object.
+const TIME_NAMES: [&str; 4] = ["m_time", "m_timestamp", "time", "timestamp"];
+/// Names of its sequence number.
+const SEQUENCE_NAMES: [&str; 4] = ["m_sequence", "m_frame", "sequence", "frame"];
+
+/// Names of the acquisition date, when stored as text.
+const DATE_NAMES: [&str; 4] = ["m_date", "m_datetime", "date", "datetime"];
+/// Names of the operator label.
+const OPERATOR_NAMES: [&str; 4] = ["m_operator", "m_user", "operator", "user"];
+/// Names of the sample label.
+const SAMPLE_NAMES: [&str; 6] = ["m_sample", "m_sample_name", "m_label", "sample", "sample_name", "label"];
+
+/// Names of the camera model.
+const MODEL_NAMES: [&str; 6] = ["m_model", "m_camera_model", "model", "camera", "camera_model", "device"];
+/// Names of the serial number.
+const SERIAL_NAMES: [&str; 5] = ["m_serial", "m_serial_number", "serial", "serial_number", "sn"];
+/// Names of the detector temperature.
+const TEMPERATURE_NAMES: [&str; 7] = [
+    "m_temperature",
+    "m_detector_temperature",
+    "m_ccd_temperature",
+    "temperature",
+    "detector_temperature",
+    "ccd_temperature",
+    "sensor_temperature",
+];
+/// Names of the firmware version.
+const FIRMWARE_NAMES: [&str; 5] = ["m_firmware", "m_firmware_version", "firmware", "firmware_version", "fw_version"];
+
+/// Variables of an object and the `data` variable of its children, by name.
+fn named_vars<'a>(obj: &'a ObjectView<'a>) -> impl Iterator<Item = (&'a str, VariableView<'a>)> + 'a {
+    let dynamic_vars = obj
+        .children()
+        .filter_map(Result::ok)
+        .filter_map(|child| Some((child.var_name, child.find_var("data").ok()??)));
+    obj.variables().filter_map(Result::ok).map(|var| (var.name, var)).chain(dynamic_vars)
+}
+
+/// The timestamp and sequence number of a spectrum, as `time_s` and
+/// `sequence` metadata.
+fn extract_stamps(obj: &ObjectView) -> BTreeMap<String, String> {
+    let mut stamps = BTreeMap::new();
+    for (name, var) in named_vars(obj) {
+        let Some(value) = stamp_value(&var) else {
+            continue;
+        };
+        if TIME_NAMES.contains(&name) {
+            stamps.entry("time_s".to_string()).or_insert_with(|| value.to_string());
+        } else if SEQUENCE_NAMES.contains(&name) {
+            stamps.entry("sequence".to_string()).or_insert_with(|| (value as i64).to_string());
+        }
+    }
+    stamps
+}
+
+/// Acquisition time, operator and sample of a spectrum.
+#[derive(Default)]
+struct Labels {
+    acquired: Option<String>,
+    operator: Option<String>,
+    sample: Option<String>,
+}
+
+/// The labels of a spectrum, from its `data` object, else from string
+/// entries of the config. Numeric timestamps from 1973 on are taken as
+/// Unix seconds; smaller ones are relative times and only kept as
+/// `time_s`.
+fn extract_labels(obj: &ObjectView, config: Option<&Config>) -> Labels {
+    let mut labels = Labels::default();
+    for (name, var) in named_vars(obj) {
+        let slot = if TIME_NAMES.contains(&name) || DATE_NAMES.contains(&name) {
+            &mut labels.acquired
+        } else if OPERATOR_NAMES.contains(&name) {
+            &mut labels.operator
+        } else if SAMPLE_NAMES.contains(&name) {
+            &mut labels.sample
+        } else {
+            continue;
+        };
+        let value = match (is_text(&var), stamp_value(&var)) {
+            (true, _) => text_value(&var),
+            (false, Some(seconds)) if seconds >= 1e8 && TIME_NAMES.contains(&name) => {
+                Some(super::timestamp::rfc3339(seconds as i64))
+            }
+            _ => None,
+        };
+        if slot.is_none() {
+            *slot = value;
+        }
+    }
+    for (name, value) in config.map(|c| c.other.as_slice()).unwrap_or_default() {
+        let ConfigValue::String(value) = value else {
+            continue;
+        };
+        let slot = if OPERATOR_NAMES.contains(&name.as_str()) {
+            &mut labels.operator
+        } else if SAMPLE_NAMES.contains(&name.as_str()) {
+            &mut labels.sample
+        } else {
+            continue;
+        };
+        if slot.is_none() && !value.trim().is_empty() {
+            *slot = Some(value.trim().to_string());
+        }
+    }
+    labels
+}
+
+/// The instrument of a spectrum, from its `data` object, else from the
+/// config; `None` when nothing is stored.
+fn extract_instrument(obj: &ObjectView, config: Option<&Config>) -> Option<Instrument> {
+    let is_instrument_name = |name: &str| {
+        [&MODEL_NAMES[..], &SERIAL_NAMES, &TEMPERATURE_NAMES, &FIRMWARE_NAMES].iter().any(|names| names.contains(&name))
+    };
+    let data_values = named_vars(obj)
+        .filter(|(name, _)| is_instrument_name(name))
+        .map(|(name, var)| (name, ConfigValue::from_variable(&var)));
+    let config_values = config.into_iter().flat_map(|c| &c.other).map(|(name, value)| (name.as_str(), value.clone()));
+
+    let mut instrument = Instrument::default();
+    for (name, value) in data_values.chain(config_values) {
+        if matches!(value, ConfigValue::Bytes { .. }) {
+            continue;
+        }
+        let text = Some(value.to_string().trim().to_string()).filter(|text| !text.is_empty());
+        if MODEL_NAMES.contains(&name) {
+            instrument.model = instrument.model.or(text);
+        } else if SERIAL_NAMES.contains(&name) {
+            instrument.serial = instrument.serial.or(text);
+        } else if TEMPERATURE_NAMES.contains(&name) {
+            instrument.detector_temperature = instrument.detector_temperature.or(f64::from_config_value(&value));
+        } else if FIRMWARE_NAMES.contains(&name) {
+            instrument.firmware = instrument.firmware.or(text);
+        }
+    }
+    (!instrument.is_empty()).then_some(instrument)
+}
+
+fn is_text(var: &VariableView) -> bool {
+    var.type_name.contains("char") || var.type_name.contains("string")
+}
+
+/// A NUL-terminated string variable, trimmed; `None` when empty.
+fn text_value(var: &VariableView) -> Option<String> {
+    let end = var.data.iter().position(|&b| b == 0).unwrap_or(var.data.len());
+    let text = String::from_utf8_lossy(&var.data[..end]).trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+fn stamp_value(var: &VariableView) -> Option<f64> {
+    if is_text(var) {
+        return None;
+    }
+    let float = var.type_name.contains("double") || var.type_name.contains("float");
+    let value = match (var.data.len(), float) {
+        (8, true) => f64::from_le_bytes(var.data.try_into().ok()?),
+        (8, false) => i64::from_le_bytes(var.data.try_into().ok()?) as f64,
+        (4, true) => f32::from_le_bytes(var.data.try_into().ok()?) as f64,
+        (4, false) => i32::from_le_bytes(var.data.try_into().ok()?) as f64,
+        _ => return None,
+    };
+    value.is_finite().then_some(value)
+}
+
+/// Whether a top-level object holds a spectrum: `data`, or `data1`,
+/// `data2`, ... in multi-accumulation files.
+fn is_data_name(name: &str) -> bool {
+    name.strip_prefix("data").is_some_and(|suffix| suffix.bytes().all(|b| b.is_ascii_digit()))
+}
+
+fn extract_string_child(obj: &ObjectView, name: &str, anomalies: &mut Anomalies) -> Result<String, ParseError> {
     let child = obj
-        .find_child(name)
+        .find_child(name)?
         .ok_or_else(|| ParseError::MissingField(name.to_string()))?;
 
     // storage_string stores: "size" (size_t) and "data" (char array)
     let data_var = child
         .find_var("data")
+        .map_err(|e| e.within(name))?
         .ok_or_else(|| ParseError::MissingField(format!("{}.data", name)))?;
 
     // Data is null-terminated string bytes
@@ -265,95 +1372,62 @@ fn extract_string_child(obj: &StorageObject, name: &str) -> Result<String, Parse
         .position(|&b| b == 0)
         .unwrap_or(data_var.data.len());
 
-    String::from_utf8(data_var.data[..end].to_vec())
-        .map_err(|_| ParseError::MissingField(format!("{} (invalid UTF-8)", name)))
+    let bytes = &data_var.data[..end];
+    if std::str::from_utf8(bytes).is_err() {
+        anomalies.report(WarningKind::InvalidUtf8, format!("{} is not valid UTF-8", name))?;
+    }
+    Ok(String::from_utf8_lossy(bytes).into_owned())
 }
 
 /// Extract a storage_vector<double> child as Vec<f64>.
-fn extract_double_vector_child(obj: &StorageObject, name: &str) -> Result<Vec<f64>, ParseError> {
-    let child = obj
-        .find_child(name)
-        .ok_or_else(|| ParseError::MissingField(name.to_string()))?;
-
-    extract_double_vector(child)
-}
-
-/// Extract a storage_vector<double> from a StorageObject.
-fn extract_double_vector(obj: &StorageObject) -> Result<Vec<f64>, ParseError> {
-    // storage_vector<double> stores each element as a variable with empty name
-    let mut values = Vec::with_capacity(obj.variables.len());
-
-    for var in &obj.variables {
-        if var.data.len() != 8 {
-            return Err(ParseError::TypeMismatch {
-                expected: "double (8 bytes)".to_string(),
-                actual: format!("{} bytes", var.data.len()),
-            });
-        }
-
-        let value = f64::from_le_bytes(var.data[..8].try_into().unwrap());
-        values.push(value);
-    }
-
-    Ok(values)
+fn extract_double_vector_child(obj: &ObjectView, name: &str) -> Result<Vec<f64>, ParseError> {
+    obj.find_child(name)?
+        .ok_or_else(|| ParseError::MissingField(name.to_string()))?
+        .f64_values()
+        .map_err(|e| e.within(name))
 }
 
 /// Extract config parameters from a StorageObject.
 /// The config stores wndParametersDialog fields as child objects (dynamic_var<T>),
 /// each containing a "data" variable with the actual value.
-fn extract_config(obj: &StorageObject) -> Result<Config, ParseError> {
+fn extract_config(obj: &ObjectView, anomalies: &mut Anomalies) -> Result<Config, ParseError> {
     let mut config = Config::default();
     
     // The config object contains children for each parameter
     // Each child is a dynamic_var<T> which stores the value in a variable named "data"
-    for child in &obj.children {
+    for child in obj.children() {
+        let child = child?;
         // Try to find a "data" variable in the child
-        if let Some(data_var) = child.find_var("data") {
-            let name = child.var_name.as_str();
+        if let Some(data_var) = child.find_var("data")? {
+            let name = child.var_name;
             
-            if data_var.data.len() == 8 {
-                // Double value
-                let value = f64::from_le_bytes(data_var.data[..8].try_into().unwrap());
-                match name {
-                    "raman_wavelength" => config.raman_wavelength = Some(value),
-                    "exposure" => config.exposure = Some(value),
-                    "gain" => config.gain = Some(value),
-                    _ => {
-                        // Store as generic double param
-                        config.other.push((name.to_string(), format!("{}", value)));
-                    }
-                }
-            } else if data_var.data.len() == 4 {
-                // Int32 value
-                let value = i32::from_le_bytes(data_var.data[..4].try_into().unwrap());
-                match name {
-                    "smoothing" => config.smoothing = Some(value),
-                    "average" => config.average = Some(value),
-                    "sgolay_window" => config.sgolay_window = Some(value),
-                    "sgolay_order" => config.sgolay_order = Some(value),
-                    "sgolay_deriv" => config.sgolay_deriv = Some(value),
-                    "axis" => config.axis = Some(AxisType::from(value)),
-                    _ => {
-                        config.other.push((name.to_string(), format!("{}", value)));
-                    }
-                }
-            } else if data_var.data.len() == 1 {
-                // Bool value (stored as single byte)
-                let value = data_var.data[0] != 0;
-                match name {
-                    "medfilt" => config.medfilt = Some(value),
-                    "baseline" => config.baseline = Some(value),
-                    "sgolay" => config.sgolay = Some(value),
-                    _ => {
-                        config.other.push((name.to_string(), format!("{}", value)));
-                    }
+            match (name, ConfigValue::from_variable(&data_var)) {
+                ("raman_wavelength", ConfigValue::Double(value)) => config.raman_wavelength = Some(value),
+                ("exposure", ConfigValue::Double(value)) => config.exposure = Some(value),
+                ("gain", ConfigValue::Double(value)) => config.gain = Some(value),
+                ("smoothing", ConfigValue::Int(value)) => config.smoothing = Some(value as i32),
+                ("average", ConfigValue::Int(value)) => config.average = Some(value as i32),
+                ("sgolay_window", ConfigValue::Int(value)) => config.sgolay_window = Some(value as i32),
+                ("sgolay_order", ConfigValue::Int(value)) => config.sgolay_order = Some(value as i32),
+                ("sgolay_deriv", ConfigValue::Int(value)) => config.sgolay_deriv = Some(value as i32),
+                ("axis", ConfigValue::Int(value)) => config.axis = Some(AxisType::from(value as i32)),
+                ("medfilt", ConfigValue::Bool(value)) => config.medfilt = Some(value),
+                ("baseline", ConfigValue::Bool(value)) => config.baseline = Some(value),
+                ("sgolay", ConfigValue::Bool(value)) => config.sgolay = Some(value),
+                (_, ConfigValue::Bytes { bytes }) => {
+                    anomalies.report(WarningKind::UnexpectedValue, format!("config/{}: unexpected {}-byte value", name, bytes.len()))?;
+                    config.other.push((name.to_string(), ConfigValue::Bytes { bytes }));
                 }
+                (_, value) => config.other.push((name.to_string(), value)),
             }
+        } else {
+            anomalies.report(WarningKind::UnexpectedValue, format!("config/{}: no value", child.var_name))?;
         }
     }
     
     // Also check variables on the object itself (for simpler storage)
-    for var in &obj.variables {
+    for var in obj.variables() {
+        let var = var?;
         if var.data.len() == 8 {
             let value = f64::from_le_bytes(var.data[..8].try_into().unwrap());
             if var.name == "raman_wavelength" && config.raman_wavelength.is_none() {
@@ -365,3 +1439,433 @@ fn extract_config(obj: &StorageObject) -> Result<Config, ParseError> {
     Ok(config)
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inverse_calibration() {
+        let cal = Calibration::legendre(vec![850.0, 60.0, -0.5, 0.1]);
+        for pixel in [0, 137, 511] {
+            let wavelength = cal.pixel_to_wavelength(pixel, 512).unwrap();
+            let back = cal.wavelength_to_pixel(wavelength, 512).unwrap();
+            assert!((back - pixel as f64).abs() < 1e-6);
+
+            let shift = cal.pixel_to_raman_shift(pixel, 512, 785.0).unwrap();
+            let back = cal.raman_shift_to_pixel(shift, 512, 785.0).unwrap();
+            assert!((back - pixel as f64).abs() < 1e-6);
+        }
+        assert!(cal.wavelength_to_pixel(700.0, 512).is_none());
+    }
+
+    #[test]
+    fn test_legendre_orders() {
+        // P₄(x) = (35x⁴ - 30x² + 3) / 8
+        let x: f64 = 0.3;
+        let p = legendre(x, 5);
+        assert!((p[3] - 0.5 * (5.0 * x.powi(3) - 3.0 * x)).abs() < 1e-12);
+        assert!((p[4] - (35.0 * x.powi(4) - 30.0 * x * x + 3.0) / 8.0).abs() < 1e-12);
+
+        let linear = Calibration::legendre(vec![800.0, 50.0]);
+        assert_eq!(linear.generate_wavelength_axis(3), Some(vec![750.0, 800.0, 850.0]));
+    }
+
+    #[test]
+    fn test_alternative_models() {
+        let polynomial = Calibration {
+            model: CalibrationModel::Polynomial,
+            coefficients: vec![500.0, 0.5, 0.001],
+            knots: Vec::new(),
+        };
+        assert_eq!(polynomial.pixel_to_wavelength(10, 100), Some(500.0 + 5.0 + 0.1));
+
+        // A spline through points on a line reproduces the line, also beyond the knots
+        let spline = Calibration {
+            model: CalibrationModel::Spline,
+            coefficients: Vec::new(),
+            knots: vec![(0.0, 500.0), (40.0, 520.0), (99.0, 549.5), (60.0, 530.0)],
+        };
+        for pixel in [0, 25, 50, 99, 120] {
+            let wavelength = spline.pixel_to_wavelength(pixel, 100).unwrap();
+            assert!((wavelength - (500.0 + 0.5 * pixel as f64)).abs() < 1e-9);
+        }
+        let back = spline.wavelength_to_pixel(512.5, 100).unwrap();
+        assert!((back - 25.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_json_write_back() {
+        let mut spc = SpcFile {
+            uid: "CAM-1".to_string(),
+            data: vec![1.0, 2.0, 3.0],
+            blank: vec![0.5; 3],
+            calibration: Some(Calibration::legendre(vec![800.0, 50.0])),
+            config: Some(Config {
+                raman_wavelength: Some(785.0),
+                axis: Some(AxisType::RamanShifts),
+                other: vec![("cooling".to_string(), ConfigValue::Bool(true))],
+                ..Default::default()
+            }),
+            wavelength_axis: None,
+            raman_shift_axis: None,
+            acquired: None,
+            operator: None,
+            sample: None,
+            instrument: None,
+            provenance: None,
+            stats: None,
+            metadata: BTreeMap::new(),
+            history: Vec::new(),
+            extras: Vec::new(),
+        };
+        spc.set_laser_wavelength(785.0);
+
+        // Edit the exported JSON and write it back as a .spc
+        let mut json: serde_json::Value = serde_json::to_value(&spc).unwrap();
+        json["config"]["exposure"] = 250.0.into();
+        let edited: SpcFile = serde_json::from_value(json).unwrap();
+        assert_eq!(edited.raman_shift_axis, spc.raman_shift_axis);
+
+        let back = SpcFile::from_bytes(&edited.to_bytes()).unwrap();
+        let config = back.config.unwrap();
+        assert_eq!(config.exposure, Some(250.0));
+        assert_eq!(config.axis, Some(AxisType::RamanShifts));
+        assert_eq!(config.other, vec![("cooling".to_string(), ConfigValue::Bool(true))]);
+        assert_eq!(back.calibration.unwrap().coefficients, vec![800.0, 50.0]);
+    }
+
+    #[test]
+    fn test_acquisition_labels() {
+        use crate::parser::{pack_container, StorageObject, Variable};
+
+        let mut spc = SpcFile {
+            uid: "CAM-1".to_string(),
+            data: vec![1.0, 2.0],
+            blank: Vec::new(),
+            calibration: None,
+            config: Some(Config {
+                other: vec![("operator".to_string(), ConfigValue::String("jdoe".to_string()))],
+                ..Default::default()
+            }),
+            wavelength_axis: None,
+            raman_shift_axis: None,
+            acquired: None,
+            operator: None,
+            sample: Some("A1".to_string()),
+            instrument: None,
+            provenance: None,
+            stats: None,
+            metadata: BTreeMap::new(),
+            history: Vec::new(),
+            extras: Vec::new(),
+        };
+
+        // A Unix timestamp in the data object, the operator in the config
+        let mut objects = spc.to_storage_objects();
+        objects[0].variables.push(Variable {
+            owner: String::new(),
+            name: "m_timestamp".to_string(),
+            type_name: "double".to_string(),
+            data: 1_709_296_205f64.to_le_bytes().to_vec(),
+        });
+        let buffers: Vec<Vec<u8>> = objects.iter().map(StorageObject::to_bytes).collect();
+        let read = SpcFile::from_bytes(&pack_container(&buffers, 0)).unwrap();
+        assert_eq!(read.acquired.as_deref(), Some("2024-03-01T12:30:05Z"));
+        assert_eq!(read.operator.as_deref(), Some("jdoe"));
+        assert_eq!(read.sample.as_deref(), Some("A1"));
+
+        // Labels are written back as strings of the data object
+        spc.acquired = Some("01Mar2024".to_string());
+        spc.config = None;
+        spc.operator = Some("J. Doe".to_string());
+        let back = SpcFile::from_bytes(&spc.to_bytes()).unwrap();
+        assert_eq!((back.acquired, back.operator, back.sample), (spc.acquired, spc.operator, spc.sample));
+    }
+
+    #[test]
+    fn test_instrument() {
+        let mut spc = SpcFile {
+            uid: "CAM-1".to_string(),
+            data: vec![1.0, 2.0],
+            blank: Vec::new(),
+            calibration: None,
+            config: Some(Config {
+                other: vec![
+                    ("temperature".to_string(), ConfigValue::Double(-60.5)),
+                    ("firmware".to_string(), ConfigValue::Int(12)),
+                ],
+                ..Default::default()
+            }),
+            wavelength_axis: None,
+            raman_shift_axis: None,
+            acquired: None,
+            operator: None,
+            sample: None,
+            instrument: None,
+            provenance: None,
+            stats: None,
+            metadata: BTreeMap::new(),
+            history: Vec::new(),
+            extras: Vec::new(),
+        };
+
+        // From the config
+        let read = SpcFile::from_bytes(&spc.to_bytes()).unwrap();
+        let instrument = read.instrument.unwrap();
+        assert_eq!(instrument.detector_temperature, Some(-60.5));
+        assert_eq!(instrument.firmware.as_deref(), Some("12"));
+        assert_eq!(instrument.model, None);
+
+        // Written back into the data object
+        spc.config = None;
+        spc.extend_metadata([
+            ("instrument.model".to_string(), "Pulsar 785".to_string()),
+            ("instrument.detector_temperature".to_string(), "-20".to_string()),
+            ("room".to_string(), "B12".to_string()),
+        ]);
+        assert_eq!(spc.metadata.keys().collect::<Vec<_>>(), ["room"]);
+        let back = SpcFile::from_bytes(&spc.to_bytes()).unwrap();
+        assert_eq!(back.instrument, spc.instrument);
+        assert_eq!(back.instrument.unwrap().entries()[1], ("instrument.detector_temperature", "-20".to_string()));
+    }
+
+    #[test]
+    fn test_config_value_serde() {
+        let values = vec![
+            ConfigValue::Bool(true),
+            ConfigValue::Int(-3),
+            ConfigValue::Double(2.0),
+            ConfigValue::String("ccd".to_string()),
+            ConfigValue::Bytes { bytes: vec![0x0a, 0xff] },
+        ];
+        let json = serde_json::to_string(&values).unwrap();
+        assert_eq!(json, r#"[true,-3,2.0,"ccd",{"bytes":"0aff"}]"#);
+        assert_eq!(serde_json::from_str::<Vec<ConfigValue>>(&json).unwrap(), values);
+    }
+
+    #[test]
+    fn test_config_get() {
+        let config = Config {
+            exposure: Some(1.5),
+            average: Some(-4),
+            axis: Some(AxisType::RamanShifts),
+            baseline: Some(true),
+            other: vec![
+                ("frames".to_string(), ConfigValue::Double(10.0)),
+                ("cooler".to_string(), ConfigValue::String(" -60.5 ".to_string())),
+                ("binning".to_string(), ConfigValue::Int(1)),
+                ("big".to_string(), ConfigValue::Double(1e19)),
+                ("wide".to_string(), ConfigValue::Int(1 << 40)),
+                ("raw".to_string(), ConfigValue::Bytes { bytes: vec![0x0a, 0xff] }),
+            ],
+            ..Default::default()
+        };
+
+        // Known fields and `other` are found by name
+        assert_eq!(config.value("exposure"), Some(ConfigValue::Double(1.5)));
+        assert_eq!(config.value("binning"), Some(ConfigValue::Int(1)));
+        assert_eq!(config.get::<ConfigValue>("raw"), Some(ConfigValue::Bytes { bytes: vec![0x0a, 0xff] }));
+        assert_eq!(config.get::<f64>("gain"), None);
+        assert_eq!(config.get::<f64>("missing"), None);
+
+        // Numbers convert only without loss of precision
+        assert_eq!(config.get::<f64>("average"), Some(-4.0));
+        assert_eq!(config.get::<i64>("frames"), Some(10));
+        assert_eq!(config.get::<i64>("exposure"), None);
+        assert_eq!(config.get::<i64>("big"), None);
+        assert_eq!(config.get::<i64>("wide"), Some(1 << 40));
+        assert_eq!(config.get::<i32>("wide"), None);
+        assert_eq!(config.get::<usize>("average"), None);
+        assert_eq!(config.get::<usize>("frames"), Some(10));
+
+        // Strings are parsed after trimming; bools come from 0 and 1
+        assert_eq!(config.get::<f64>("cooler"), Some(-60.5));
+        assert_eq!(config.get::<i64>("cooler"), None);
+        assert_eq!(config.get::<bool>("baseline"), Some(true));
+        assert_eq!(config.get::<bool>("binning"), Some(true));
+        assert_eq!(config.get::<bool>("average"), None);
+        assert_eq!(config.get::<f64>("baseline"), None);
+
+        // Anything formats as a string; the axis converts from its code
+        assert_eq!(config.get::<String>("exposure").as_deref(), Some("1.5"));
+        assert_eq!(config.get::<String>("raw").as_deref(), Some("0aff"));
+        assert_eq!(config.get::<AxisType>("axis"), Some(AxisType::RamanShifts));
+        assert_eq!(config.get::<AxisType>("binning"), Some(AxisType::Wavelengths));
+    }
+
+    #[test]
+    fn test_strict_parsing() {
+        use crate::parser::{pack_container, StorageObject};
+
+        let spc = SpcFile {
+            uid: "CAM-1".to_string(),
+            data: vec![1.0, 2.0, 3.0],
+            blank: Vec::new(),
+            calibration: None,
+            config: None,
+            wavelength_axis: None,
+            raman_shift_axis: None,
+            acquired: None,
+            operator: None,
+            sample: None,
+            instrument: None,
+            provenance: None,
+            stats: None,
+            metadata: BTreeMap::new(),
+            history: Vec::new(),
+            extras: Vec::new(),
+        };
+        let mut objects = spc.to_storage_objects();
+        objects.push(StorageObject {
+            type_name: "vendor_extension".to_string(),
+            owner_name: String::new(),
+            var_name: "extension".to_string(),
+            variables: Vec::new(),
+            children: Vec::new(),
+        });
+        let buffers: Vec<Vec<u8>> = objects.iter().map(StorageObject::to_bytes).collect();
+        let bytes = pack_container(&buffers, 0);
+
+        let (lenient, warnings) = SpcFile::from_bytes_with(&bytes, &ParseOptions::default()).unwrap();
+        assert_eq!(lenient.data, spc.data);
+        assert_eq!(warnings, vec!["unknown object 'extension' in buffer[1]"]);
+        // Unknown objects are preserved and written back
+        assert_eq!(lenient.extras.len(), 1);
+        assert_eq!(lenient.to_bytes(), bytes);
+
+        let strict = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        assert!(matches!(SpcFile::from_bytes_with(&bytes, &strict), Err(ParseError::Strict(_))));
+        assert!(SpcFile::from_bytes_with(&spc.to_bytes(), &strict).is_ok());
+    }
+
+    #[test]
+    fn test_warning_report() {
+        use crate::spectre::SpcFileBuilder;
+
+        let spc = SpcFileBuilder::new("CAM-1", vec![1.0, 2.0, 3.0])
+            .blank(vec![0.5; 2])
+            .calibration(Calibration::legendre(vec![850.0, 60.0, -0.5, 0.1]))
+            .build();
+        let (parsed, warnings) = SpcFile::from_bytes_with_report(&spc.to_bytes(), &ParseOptions::default()).unwrap();
+        assert_eq!(parsed.blank.len(), 2);
+        let kinds: Vec<WarningKind> = warnings.iter().map(|warning| warning.kind).collect();
+        assert_eq!(kinds, vec![WarningKind::BlankLength, WarningKind::CalibrationLength]);
+        assert_eq!(warnings[1].to_string(), "calibration has 4 coefficients but data has 3 points");
+
+        // The string API reports the same messages
+        let (_, messages) = SpcFile::from_bytes_with(&spc.to_bytes(), &ParseOptions::default()).unwrap();
+        assert_eq!(messages, warnings.iter().map(ToString::to_string).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let spc = SpcFile {
+            uid: "CAM-1".to_string(),
+            data: vec![1.0, 2.0, 3.0],
+            blank: vec![0.5; 3],
+            calibration: Some(Calibration::legendre(vec![850.0, 60.0, -0.5, 0.1])),
+            config: None,
+            wavelength_axis: None,
+            raman_shift_axis: None,
+            acquired: None,
+            operator: None,
+            sample: None,
+            instrument: None,
+            provenance: None,
+            stats: None,
+            metadata: BTreeMap::new(),
+            history: Vec::new(),
+            extras: Vec::new(),
+        };
+        // A file embedded after other data, as in an archive
+        let mut archive = b"header".to_vec();
+        archive.extend(spc.to_bytes());
+        let mut cursor = std::io::Cursor::new(archive);
+        cursor.set_position(6);
+
+        let parsed = SpcFile::from_reader(&mut cursor).unwrap();
+        assert_eq!(parsed.uid, "CAM-1");
+        assert_eq!(parsed.blank, spc.blank);
+        assert_eq!(parsed.wavelength_axis.map(|axis| axis.len()), Some(3));
+        assert!(SpcFile::from_reader(&mut cursor).is_err());
+    }
+
+    #[test]
+    fn test_lazy_axes() {
+        let spc = crate::spectre::SpcFileBuilder::new("CAM-1", vec![1.0, 2.0, 3.0, 4.0])
+            .blank(vec![0.5; 4])
+            .calibration(Calibration::legendre(vec![850.0, 60.0, -0.5, 0.1]))
+            .laser_wavelength(785.0)
+            .build();
+        let bytes = spc.to_bytes();
+        let eager = SpcFile::from_bytes(&bytes).unwrap();
+        let lazy = ParseOptions {
+            lazy_axes: true,
+            ..Default::default()
+        };
+        let (mut parsed, _) = SpcFile::from_bytes_with(&bytes, &lazy).unwrap();
+
+        assert!(parsed.wavelength_axis.is_none() && parsed.raman_shift_axis.is_none());
+        assert!(parsed.has_raman_shift());
+        assert_eq!(parsed.wavelengths().as_deref(), eager.wavelength_axis.as_deref());
+        assert_eq!(parsed.raman_shifts().as_deref(), eager.raman_shift_axis.as_deref());
+        assert!(matches!(eager.wavelengths(), Some(Cow::Borrowed(_))));
+
+        // Cropping stores the axes of the original pixels first
+        crate::processing::crop(&mut parsed, None, None, Some(eager.raman_shift_axis.as_ref().unwrap()[1])).unwrap();
+        assert_eq!(parsed.raman_shift_axis.as_deref(), Some(&eager.raman_shift_axis.as_ref().unwrap()[..2]));
+        assert_eq!(parsed.wavelengths().as_deref(), Some(&eager.wavelength_axis.as_ref().unwrap()[..2]));
+
+        let no_blank = ParseOptions {
+            skip_blank: true,
+            ..Default::default()
+        };
+        assert!(!eager.blank.is_empty());
+        assert!(SpcFile::from_bytes_with(&bytes, &no_blank).unwrap().0.blank.is_empty());
+    }
+
+    #[test]
+    fn test_located_errors() {
+        use crate::parser::{pack_container, unpack_container_in_place, StorageObject};
+
+        let spc = SpcFile {
+            uid: "CAM-1".to_string(),
+            data: vec![1.0, 2.0, 3.0, 4.0],
+            blank: vec![0.5; 4],
+            calibration: None,
+            config: None,
+            wavelength_axis: None,
+            raman_shift_axis: None,
+            acquired: None,
+            operator: None,
+            sample: None,
+            instrument: None,
+            provenance: None,
+            stats: None,
+            metadata: BTreeMap::new(),
+            history: Vec::new(),
+            extras: Vec::new(),
+        };
+        let mut objects = spc.to_storage_objects();
+        // Truncate the third blank element to a 4-byte marker
+        objects[0].children[1].variables[2].data = vec![0xAB, 0xCD, 0xEF, 0x01];
+        let buffers: Vec<Vec<u8>> = objects.iter().map(StorageObject::to_bytes).collect();
+        let bytes = pack_container(&buffers, 0);
+
+        match SpcFile::from_bytes(&bytes) {
+            Err(ParseError::Located { path, buffer, offset, absolute, .. }) => {
+                assert_eq!(path, "data/m_blank[2]");
+                assert_eq!(buffer, Some(0));
+                assert!(absolute);
+                let mut plain = bytes.clone();
+                unpack_container_in_place(&mut plain).unwrap();
+                assert_eq!(plain[offset as usize..offset as usize + 4], [0xAB, 0xCD, 0xEF, 0x01]);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! Parsing from asynchronous byte streams.
+
+use super::spc_file::SpcFile;
+use crate::parser::{ParseError, ParseOptions};
+use futures_core::Stream;
+use std::io;
+
+impl SpcFile {
+    /// Parse from an asynchronous stream of byte chunks, without blocking
+    /// the runtime while the input arrives. The stream is read to the end
+    /// before parsing; under tokio, wrap an `AsyncRead` with
+    /// `tokio_util::io::ReaderStream`:
+    ///
+    /// ```ignore
+    /// let spc = SpcFile::from_async_reader(ReaderStream::new(upload)).await?;
+    /// ```
+    pub async fn from_async_reader<S, B>(stream: S) -> Result<Self, ParseError>
+    where
+        S: Stream<Item = io::Result<B>>,
+        B: AsRef<[u8]>,
+    {
+        Self::from_async_reader_with(stream, &ParseOptions::default()).await.map(|(spc, _)| spc)
+    }
+
+    /// Like [`from_async_reader`](Self::from_async_reader), with options
+    /// and warnings as in [`from_bytes_with`](Self::from_bytes_with).
+    /// Streams longer than `options.limits.max_total_size` are rejected.
+    pub async fn from_async_reader_with<S, B>(stream: S, options: &ParseOptions) -> Result<(Self, Vec<String>), ParseError>
+    where
+        S: Stream<Item = io::Result<B>>,
+        B: AsRef<[u8]>,
+    {
+        let mut bytes = read_stream(stream, options.limits.max_total_size).await?;
+        Self::from_bytes_in_place_with(&mut bytes, options)
+    }
+}
+
+async fn read_stream<S, B>(stream: S, limit: usize) -> io::Result<Vec<u8>>
+where
+    S: Stream<Item = io::Result<B>>,
+    B: AsRef<[u8]>,
+{
+    let mut stream = std::pin::pin!(stream);
+    let mut bytes = Vec::new();
+    while let Some(chunk) = std::future::poll_fn(|cx| stream.as_mut().poll_next(cx)).await {
+        let chunk = chunk?;
+        if bytes.len() + chunk.as_ref().len() > limit {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("input exceeds {} bytes", limit)));
+        }
+        bytes.extend_from_slice(chunk.as_ref());
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::task::{Context, Poll, Waker};
+
+    struct Chunks(Vec<io::Result<Vec<u8>>>);
+
+    impl Stream for Chunks {
+        type Item = io::Result<Vec<u8>>;
+
+        fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            Poll::Ready((!self.0.is_empty()).then(|| self.0.remove(0)))
+        }
+    }
+
+    fn ready<T>(future: impl Future<Output = T>) -> T {
+        match std::pin::pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(value) => value,
+            Poll::Pending => panic!("stream was not ready"),
+        }
+    }
+
+    #[test]
+    fn test_from_async_reader() {
+        let spc = SpcFile {
+            uid: "CAM-1".to_string(),
+            data: vec![1.0, 2.0, 3.0],
+            blank: vec![0.5; 3],
+            calibration: None,
+            config: None,
+            wavelength_axis: None,
+            raman_shift_axis: None,
+            acquired: None,
+            operator: None,
+            sample: None,
+            instrument: None,
+            provenance: None,
+            stats: None,
+            metadata: Default::default(),
+            history: Vec::new(),
+            extras: Vec::new(),
+        };
+        let chunks = spc.to_bytes().chunks(100).map(|chunk| Ok(chunk.to_vec())).collect();
+        let parsed = ready(SpcFile::from_async_reader(Chunks(chunks))).unwrap();
+        assert_eq!(parsed.uid, "CAM-1");
+        assert_eq!(parsed.data, spc.data);
+
+        let failing = Chunks(vec![Ok(vec![0; 8]), Err(io::Error::other("connection reset"))]);
+        assert!(matches!(ready(SpcFile::from_async_reader(failing)), Err(ParseError::Io(_))));
+    }
+}
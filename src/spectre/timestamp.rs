@@ -0,0 +1,63 @@
+//! Calendar conversion of acquisition times (UTC, proleptic Gregorian).
+
+/// Seconds since the Unix epoch as RFC 3339 UTC, e.g. `2024-03-01T12:30:05Z`.
+pub(crate) fn rfc3339(unix_seconds: i64) -> String {
+    let days = unix_seconds.div_euclid(86_400);
+    let seconds = unix_seconds.rem_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Year, month, day, hour, minute and second of an RFC 3339 time or a
+/// plain `YYYY-MM-DD` date. Any time zone offset is ignored.
+pub(crate) fn parse_rfc3339(text: &str) -> Option<[u32; 6]> {
+    let text = text.trim();
+    let field = |range: std::ops::Range<usize>| -> Option<u32> { text.get(range)?.parse().ok() };
+    let date = [field(0..4)?, field(5..7)?, field(8..10)?];
+    if text.get(4..5) != Some("-") || text.get(7..8) != Some("-") {
+        return None;
+    }
+    let time = match text.get(10..11) {
+        Some("T" | "t" | " ") => [field(11..13)?, field(14..16)?, field(17..19).unwrap_or(0)],
+        _ => [0, 0, 0],
+    };
+    let valid = (1..=12).contains(&date[1]) && (1..=31).contains(&date[2]) && time[0] < 24 && time[1] < 60;
+    valid.then_some([date[0], date[1], date[2], time[0], time[1], time[2]])
+}
+
+/// Year, month and day of a day count since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc3339() {
+        assert_eq!(rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc3339(1_709_296_205), "2024-03-01T12:30:05Z");
+        assert_eq!(rfc3339(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(parse_rfc3339("2024-03-01T12:30:05+01:00"), Some([2024, 3, 1, 12, 30, 5]));
+        assert_eq!(parse_rfc3339("2024-03-01"), Some([2024, 3, 1, 0, 0, 0]));
+        assert_eq!(parse_rfc3339("01Mar2024"), None);
+    }
+}
@@ -0,0 +1,328 @@
+//! Encoding SpcFile back into the SPC0 container format.
+//!
+//! Mirrors the layout described in spc.md section 3, using MSVC-style type
+//! names as written by the Spectrum Analyzer Suite.
+
+use super::spc_file::{Calibration, Config, ConfigValue, SpcFile};
+use crate::parser::{pack_container, pack_container_encoded, StorageObject, StorageObjectBuilder, Variable, DOUBLE_VECTOR_TYPE};
+use std::path::Path;
+
+const SPECTRE_FILE_TYPE: &str = "class SpectreFile";
+const CONFIG_TYPE: &str = "class wndParametersDialog";
+
+/// Seed used when encrypting written files.
+pub const DEFAULT_SEED: u32 = 0;
+
+impl SpcFile {
+    /// Build the top-level StorageObjects (`data`, then `calibration` and
+    /// `config` if present, then any preserved extras). Calibrations are
+    /// stored as Legendre coefficients, converting a polynomial; a spline
+    /// cannot be stored and is left out, see [`SpcFile::check_storable`]. The
+    /// acquisition time, operator and sample are stored as `m_date`,
+    /// `m_operator` and `m_sample` strings of the data object, and the
+    /// instrument as `m_model`, `m_serial` and `m_firmware` strings and an
+    /// `m_temperature` double.
+    pub fn to_storage_objects(&self) -> Vec<StorageObject> {
+        let mut data = StorageObjectBuilder::new(SPECTRE_FILE_TYPE)
+            .name("data")
+            .double_vec("m_data", &self.data)
+            .double_vec("m_blank", &self.blank)
+            .string("m_uid", &self.uid);
+        for (name, label) in [("m_date", &self.acquired), ("m_operator", &self.operator), ("m_sample", &self.sample)] {
+            if let Some(label) = label {
+                data = data.string(name, label);
+            }
+        }
+        if let Some(ref instrument) = self.instrument {
+            for (name, label) in [
+                ("m_model", &instrument.model),
+                ("m_serial", &instrument.serial),
+                ("m_firmware", &instrument.firmware),
+            ] {
+                if let Some(label) = label {
+                    data = data.string(name, label);
+                }
+            }
+            if let Some(temperature) = instrument.detector_temperature {
+                data = data.variable("m_temperature", "double", temperature.to_le_bytes().to_vec());
+            }
+        }
+        let mut objects = vec![data.build()];
+
+        if let Some(calibration) = self.calibration.as_ref().and_then(|c| c.to_legendre(self.data.len())) {
+            objects.push(calibration.to_storage_object());
+        }
+        if let Some(ref config) = self.config {
+            objects.push(config.to_storage_object());
+        }
+        objects.extend(self.extras.iter().cloned());
+
+        objects
+    }
+
+    /// Fail if the calibration cannot be stored in an .spc file: a spline,
+    /// or a polynomial over fewer than two points.
+    pub fn check_storable(&self) -> std::io::Result<()> {
+        match self.calibration {
+            Some(ref calibration) if calibration.to_legendre(self.data.len()).is_none() => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "a {} calibration of {} points cannot be stored in .spc files; only Legendre and polynomial ones can",
+                    format!("{:?}", calibration.model).to_lowercase(),
+                    self.data.len()
+                ),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Encode as a complete .spc file.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let buffers: Vec<Vec<u8>> = self
+            .to_storage_objects()
+            .iter()
+            .map(StorageObject::to_bytes)
+            .collect();
+        pack_container(&buffers, DEFAULT_SEED)
+    }
+
+    /// Encode as a .spc file with every buffer stored in the given
+    /// [encoding](crate::parser::ENCODINGS). `None` if the encoding cannot
+    /// be written.
+    pub fn to_bytes_encoded(&self, encoding: u8) -> Option<Vec<u8>> {
+        let buffers: Vec<Vec<u8>> = self
+            .to_storage_objects()
+            .iter()
+            .map(StorageObject::to_bytes)
+            .collect();
+        pack_container_encoded(&buffers, DEFAULT_SEED, encoding)
+    }
+
+    /// Write as a .spc file, failing if the calibration cannot be stored.
+    pub fn write_file(&self, path: &Path) -> std::io::Result<()> {
+        self.check_storable()?;
+        std::fs::write(path, self.to_bytes())
+    }
+}
+
+impl Calibration {
+    /// Build the top-level `calibration` object (a storage_vector<double>).
+    pub fn to_storage_object(&self) -> StorageObject {
+        StorageObjectBuilder::new(DOUBLE_VECTOR_TYPE)
+            .name("calibration")
+            .doubles(&self.coefficients)
+            .build()
+    }
+}
+
+impl Config {
+    /// Build the top-level `config` object, one dynamic_var<T> child per field.
+    ///
+    /// Entries in `other` keep their types; ints that do not fit 32 bits
+    /// are written as `__int64`, and uninterpreted bytes as they were read.
+    pub fn to_storage_object(&self) -> StorageObject {
+        let mut children = Vec::new();
+
+        let doubles = [
+            ("raman_wavelength", self.raman_wavelength),
+            ("exposure", self.exposure),
+            ("gain", self.gain),
+        ];
+        for (name, value) in doubles {
+            if let Some(v) = value {
+                children.push(dynamic_var(name, "double", v.to_le_bytes().to_vec()));
+            }
+        }
+
+        let ints = [
+            ("smoothing", self.smoothing),
+            ("average", self.average),
+            ("sgolay_window", self.sgolay_window),
+            ("sgolay_order", self.sgolay_order),
+            ("sgolay_deriv", self.sgolay_deriv),
+            ("axis", self.axis.map(|a| a as i32)),
+        ];
+        for (name, value) in ints {
+            if let Some(v) = value {
+                children.push(dynamic_var(name, "int", v.to_le_bytes().to_vec()));
+            }
+        }
+
+        let bools = [
+            ("medfilt", self.medfilt),
+            ("baseline", self.baseline),
+            ("sgolay", self.sgolay),
+        ];
+        for (name, value) in bools {
+            if let Some(v) = value {
+                children.push(dynamic_var(name, "bool", vec![v as u8]));
+            }
+        }
+
+        for (name, value) in &self.other {
+            let (type_name, data) = match value {
+                ConfigValue::Bool(v) => ("bool", vec![*v as u8]),
+                ConfigValue::Int(v) => match i32::try_from(*v) {
+                    Ok(v) => ("int", v.to_le_bytes().to_vec()),
+                    Err(_) => ("__int64", v.to_le_bytes().to_vec()),
+                },
+                ConfigValue::Double(v) => ("double", v.to_le_bytes().to_vec()),
+                ConfigValue::String(v) => ("class std::basic_string<char>", [v.as_bytes(), &[0]].concat()),
+                ConfigValue::Bytes { bytes } => ("bytes", bytes.clone()),
+            };
+            children.push(dynamic_var(name, type_name, data));
+        }
+
+        StorageObject {
+            type_name: CONFIG_TYPE.to_string(),
+            owner_name: String::new(),
+            var_name: "config".to_string(),
+            variables: Vec::new(),
+            children,
+        }
+    }
+}
+
+/// A dynamic_var<T> config entry holding its value in a `data` variable.
+fn dynamic_var(name: &str, type_name: &str, data: Vec<u8>) -> StorageObject {
+    StorageObject {
+        type_name: format!("class dynamic_var<{}>", type_name),
+        owner_name: CONFIG_TYPE.to_string(),
+        var_name: name.to_string(),
+        variables: vec![Variable {
+            owner: String::new(),
+            name: "data".to_string(),
+            type_name: type_name.to_string(),
+            data,
+        }],
+        children: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spectre::AxisType;
+
+    #[test]
+    fn test_round_trip() {
+        let original = SpcFile {
+            uid: "CAM-42".to_string(),
+            data: vec![1.0, 2.5, 3.0, 2.0, 1.5],
+            blank: vec![0.1, 0.2, 0.1, 0.2, 0.1],
+            calibration: Some(Calibration::legendre(vec![800.0, 50.0, 0.5, 0.01])),
+            config: Some(Config {
+                raman_wavelength: Some(785.0),
+                smoothing: Some(3),
+                sgolay: Some(true),
+                axis: Some(AxisType::RamanShifts),
+                other: vec![
+                    ("temperature".to_string(), ConfigValue::Double(-60.5)),
+                    ("frames".to_string(), ConfigValue::Int(10)),
+                    ("operator".to_string(), ConfigValue::String("jd".to_string())),
+                ],
+                ..Default::default()
+            }),
+            wavelength_axis: None,
+            raman_shift_axis: None,
+            acquired: None,
+            operator: None,
+            sample: None,
+            instrument: None,
+            provenance: None,
+            stats: None,
+            metadata: Default::default(),
+            history: Vec::new(),
+            extras: Vec::new(),
+        };
+
+        let spc = SpcFile::from_bytes(&original.to_bytes()).unwrap();
+        assert_eq!(spc.uid, "CAM-42");
+        assert_eq!(spc.data, original.data);
+        assert_eq!(spc.blank, original.blank);
+        assert_eq!(spc.calibration.unwrap().coefficients, vec![800.0, 50.0, 0.5, 0.01]);
+        let config = spc.config.unwrap();
+        assert_eq!(config.raman_wavelength, Some(785.0));
+        assert_eq!(config.smoothing, Some(3));
+        assert_eq!(config.sgolay, Some(true));
+        assert_eq!(config.axis, Some(AxisType::RamanShifts));
+        assert_eq!(config.other, original.config.unwrap().other);
+        assert!(spc.raman_shift_axis.is_some());
+    }
+
+    #[test]
+    fn test_calibration_models() {
+        use crate::spectre::{CalibrationModel, SpcFileBuilder};
+
+        let polynomial = Calibration {
+            model: CalibrationModel::Polynomial,
+            coefficients: vec![780.0, 0.12, -1e-5, 2e-9],
+            knots: Vec::new(),
+        };
+        let spc = SpcFileBuilder::new("CAM-1", vec![0.0; 1024]).calibration(polynomial.clone()).build();
+        spc.check_storable().unwrap();
+        let back = SpcFile::from_bytes(&spc.to_bytes()).unwrap();
+        assert_eq!(back.calibration.as_ref().unwrap().model, CalibrationModel::Legendre);
+        let expected = polynomial.generate_wavelength_axis(1024).unwrap();
+        let read = back.wavelength_axis.unwrap();
+        assert!(read.iter().zip(&expected).all(|(a, b)| (a - b).abs() < 1e-9));
+
+        let spline = Calibration {
+            model: CalibrationModel::Spline,
+            coefficients: Vec::new(),
+            knots: vec![(0.0, 780.0), (511.0, 850.0), (1023.0, 915.0)],
+        };
+        let spc = SpcFileBuilder::new("CAM-1", vec![0.0; 1024]).calibration(spline).build();
+        let error = spc.check_storable().unwrap_err();
+        assert!(error.to_string().contains("spline calibration"));
+        assert!(spc.write_file(Path::new("unused.spc")).is_err());
+    }
+
+    #[test]
+    fn test_extras_round_trip() {
+        use crate::spectre::SpcFileBuilder;
+
+        let variable = |owner: &str, name: &str, data: &[u8]| Variable {
+            owner: owner.to_string(),
+            name: name.to_string(),
+            type_name: "double".to_string(),
+            data: data.to_vec(),
+        };
+        let extension = StorageObject {
+            type_name: "vendor_extension".to_string(),
+            owner_name: String::new(),
+            var_name: "extension".to_string(),
+            variables: vec![variable("", "m_gain", &2.5f64.to_le_bytes())],
+            children: vec![StorageObject {
+                type_name: "storage_vector<double>".to_string(),
+                owner_name: "extension".to_string(),
+                var_name: "m_offsets".to_string(),
+                variables: vec![variable("m_offsets", "", &[0xde, 0xad, 0xbe, 0xef, 0, 0, 0, 0])],
+                children: Vec::new(),
+            }],
+        };
+        let mut spc = SpcFileBuilder::new("CAM-1", vec![1.0, 2.0, 3.0])
+            .calibration(Calibration::legendre(vec![800.0, 20.0]))
+            .laser_wavelength(785.0)
+            .build();
+        spc.extras.push(extension);
+
+        // Extras follow data, calibration and config, and survive a re-read
+        let objects = spc.to_storage_objects();
+        let names: Vec<&str> = objects.iter().map(|object| object.var_name.as_str()).collect();
+        assert_eq!(names, ["data", "calibration", "config", "extension"]);
+        let bytes = spc.to_bytes();
+        let read = SpcFile::from_bytes(&bytes).unwrap();
+        assert_eq!(read.extras.len(), 1);
+        let extra = &read.extras[0];
+        assert_eq!(extra.type_name, "vendor_extension");
+        assert_eq!(extra.find_var("m_gain").unwrap().data, 2.5f64.to_le_bytes());
+        assert_eq!(extra.children[0].var_name, "m_offsets");
+        assert_eq!(read.to_bytes(), bytes);
+
+        let json = serde_json::to_value(&read).unwrap();
+        assert_eq!(json["extras"][0]["children"][0]["variables"][0]["data"], "deadbeef00000000");
+        spc.extras.clear();
+        assert!(serde_json::to_value(&spc).unwrap().get("extras").is_none());
+    }
+}
@@ -0,0 +1,300 @@
+//! Structural validation of .spc files.
+//!
+//! Unlike [`SpcFile::from_bytes`](crate::SpcFile::from_bytes), which stops at
+//! the first error, validation keeps going where it can and collects every
+//! problem it finds in the container, the buffer table, the StorageObjects
+//! and the required fields.
+
+use crate::parser::{
+    checksum, decode_counted, decrypt, encoding, BufferEntry, ContainerHeader, Limits, ParseError, StorageObject, BLOCK_SIZE,
+    ENCRYPTION_KEY,
+};
+use crate::calibration::check_calibration;
+use crate::spectre::Calibration;
+use serde::Serialize;
+
+/// How serious a problem is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The file cannot be (fully) extracted.
+    Error,
+    /// The file can be extracted but something is unusual.
+    Warning,
+}
+
+/// One problem found in a file.
+#[derive(Debug, Clone, Serialize)]
+pub struct Problem {
+    pub severity: Severity,
+    /// Where the problem is: `container`, `buffer[i]`, or an object path
+    /// such as `data/m_uid`.
+    pub location: String,
+    pub message: String,
+}
+
+/// All problems found in one file.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ValidationReport {
+    /// No errors (warnings are allowed).
+    pub valid: bool,
+    pub problems: Vec<Problem>,
+}
+
+impl ValidationReport {
+    fn error(&mut self, location: impl Into<String>, message: impl Into<String>) {
+        self.push(Severity::Error, location.into(), message.into());
+    }
+
+    fn warning(&mut self, location: impl Into<String>, message: impl Into<String>) {
+        self.push(Severity::Warning, location.into(), message.into());
+    }
+
+    fn push(&mut self, severity: Severity, location: String, message: String) {
+        self.problems.push(Problem { severity, location, message });
+    }
+}
+
+/// Validate the raw bytes of an .spc file. Decompression is bounded by the
+/// default [`Limits`].
+pub fn validate(bytes: &[u8]) -> ValidationReport {
+    validate_with(bytes, &Limits::default())
+}
+
+/// Like [`validate`], with the given decompression limits. A buffer that
+/// would exceed them is reported as an error.
+pub fn validate_with(bytes: &[u8], limits: &Limits) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    let buffers = check_container(bytes, limits, &mut report);
+
+    let mut objects = Vec::new();
+    for (i, buffer) in buffers.iter().enumerate() {
+        match StorageObject::from_bytes(buffer) {
+            Ok(obj) => objects.push(obj),
+            Err(e) => report.error(format!("buffer[{}]", i), format!("invalid StorageObject: {}", e)),
+        }
+    }
+    if !buffers.is_empty() {
+        check_objects(&objects, &mut report);
+    }
+
+    report.valid = !report.problems.iter().any(|p| p.severity == Severity::Error);
+    report
+}
+
+/// Check the header, checksum and buffer table; returns the decoded buffers
+/// that lie within bounds.
+fn check_container(bytes: &[u8], limits: &Limits, report: &mut ValidationReport) -> Vec<Vec<u8>> {
+    let header = match ContainerHeader::parse(bytes) {
+        Ok(header) => header,
+        // Check the rest as if it were SPC0
+        Err(ParseError::BadMagic { found }) if bytes.len() >= ContainerHeader::SIZE => {
+            report.error(
+                "container",
+                format!("bad magic 0x{:08X} (expected 0x{:08X})", found, ContainerHeader::MAGIC),
+            );
+            ContainerHeader::from_bytes(bytes).unwrap()
+        }
+        Err(e) => {
+            report.error("container", e.to_string());
+            return Vec::new();
+        }
+    };
+
+    let mut data = bytes.to_vec();
+    data[4..8].copy_from_slice(&[0, 0, 0, 0]);
+    decrypt(&mut data[ContainerHeader::SIZE..], ENCRYPTION_KEY, header.seed, BLOCK_SIZE);
+
+    let computed = checksum(&data);
+    if computed != header.checksum {
+        report.error(
+            "container",
+            format!("checksum mismatch: stored 0x{:08X}, computed 0x{:08X}", header.checksum, computed),
+        );
+    }
+
+    if header.num_buffers == 0 {
+        report.error("container", "no buffers");
+    }
+    let table_size = header.num_buffers.checked_mul(BufferEntry::SIZE as u64);
+    let table_end = table_size.and_then(|size| size.checked_add(header.buffers_table_ofs));
+    if table_end.is_none_or(|end| end > data.len() as u64) {
+        report.error(
+            "container",
+            format!(
+                "buffer table ({} entries at offset {}) exceeds file size {}",
+                header.num_buffers,
+                header.buffers_table_ofs,
+                data.len()
+            ),
+        );
+        return Vec::new();
+    }
+
+    let mut buffers = Vec::new();
+    let mut decoded_total = 0;
+    for i in 0..header.num_buffers {
+        let location = format!("buffer[{}]", i);
+        // Within the table, checked above
+        let entry_start = header.buffers_table_ofs + i * BufferEntry::SIZE as u64;
+        let entry = BufferEntry::from_bytes(&data[entry_start as usize..]);
+
+        if encoding(entry.encoding).is_none() {
+            report.error(&location, format!("unknown encoding {}", entry.encoding));
+            continue;
+        }
+        let end = header
+            .buffers_data_ofs
+            .checked_add(entry.offset)
+            .and_then(|start| start.checked_add(entry.size).map(|end| (start, end)));
+        match end {
+            Some((start, end)) if end <= data.len() as u64 => {
+                match decode_counted(&data[start as usize..end as usize], entry.encoding, limits, &mut decoded_total) {
+                    Ok(buffer) => buffers.push(buffer),
+                    Err(e) => report.error(&location, e.to_string()),
+                }
+            }
+            _ => report.error(
+                &location,
+                format!(
+                    "data ({} bytes at offset {}) exceeds file size {}",
+                    entry.size,
+                    header.buffers_data_ofs.saturating_add(entry.offset),
+                    data.len()
+                ),
+            ),
+        }
+    }
+
+    buffers
+}
+
+/// Check that the required objects and fields are present and well-formed.
+fn check_objects(objects: &[StorageObject], report: &mut ValidationReport) {
+    for (i, obj) in objects.iter().enumerate() {
+        if objects[..i].iter().any(|o| o.var_name == obj.var_name) {
+            report.warning(obj.var_name.as_str(), "duplicate object; only the last one is used");
+        }
+        if !matches!(obj.var_name.as_str(), "data" | "calibration" | "config") {
+            report.warning(obj.var_name.as_str(), format!("unknown object of type {}", obj.type_name));
+        }
+    }
+
+    let find = |name: &str| objects.iter().rev().find(|o| o.var_name == name);
+
+    let mut num_points = None;
+    match find("data") {
+        Some(data) => {
+            if data.find_child("m_uid").and_then(|c| c.find_var("data")).is_none() {
+                report.error("data/m_uid", "missing");
+            }
+            let data_len = check_double_vector(data.find_child("m_data"), "data/m_data", report);
+            let blank_len = check_double_vector(data.find_child("m_blank"), "data/m_blank", report);
+            num_points = data_len;
+            if let (Some(data_len), Some(blank_len)) = (data_len, blank_len) {
+                if data_len == 0 {
+                    report.warning("data/m_data", "no data points");
+                }
+                if blank_len != 0 && blank_len != data_len {
+                    report.warning(
+                        "data/m_blank",
+                        format!("{} points, data has {}", blank_len, data_len),
+                    );
+                }
+            }
+        }
+        None => report.error("data", "missing"),
+    }
+
+    if let Some(calibration) = find("calibration") {
+        if let Some(n) = check_double_vector(Some(calibration), "calibration", report) {
+            if n == 0 {
+                report.warning("calibration", "no coefficients; no axes can be generated");
+            } else if let Some(num_points) = num_points.filter(|&n| n > 0) {
+                let coefficients = calibration
+                    .variables
+                    .iter()
+                    .map(|v| f64::from_le_bytes(v.data[..8].try_into().unwrap()))
+                    .collect();
+                for problem in check_calibration(&Calibration::legendre(coefficients), num_points) {
+                    report.warning("calibration", problem);
+                }
+            }
+        }
+    }
+
+    if let Some(config) = find("config") {
+        for child in &config.children {
+            let location = format!("config/{}", child.var_name);
+            match child.find_var("data") {
+                Some(var) if matches!(var.data.len(), 1 | 4 | 8) => {}
+                Some(var) => report.warning(location, format!("unsupported value size {} bytes", var.data.len())),
+                None => report.warning(location, "no value"),
+            }
+        }
+    }
+}
+
+/// Check a storage_vector<double>, returning its length if well-formed.
+fn check_double_vector(obj: Option<&StorageObject>, location: &str, report: &mut ValidationReport) -> Option<usize> {
+    let Some(obj) = obj else {
+        report.error(location, "missing");
+        return None;
+    };
+    let bad = obj.variables.iter().filter(|v| v.data.len() != 8).count();
+    if bad > 0 {
+        report.error(location, format!("{} element(s) are not 8-byte doubles", bad));
+        return None;
+    }
+    Some(obj.variables.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{pack_container, pack_container_encoded, ENCODING_RLE0};
+
+    #[test]
+    fn test_reports_every_problem() {
+        let mut bytes = pack_container(&[vec![0u8; 8]], 0);
+        bytes[4] ^= 0xff;
+
+        let report = validate(&bytes);
+        assert!(!report.valid);
+        let messages: Vec<&str> = report.problems.iter().map(|p| p.message.as_str()).collect();
+        assert!(messages[0].starts_with("checksum mismatch"));
+        assert!(messages[1].starts_with("invalid StorageObject"));
+        assert_eq!(report.problems[2].location, "data");
+    }
+
+    #[test]
+    fn test_total_decoded_limit() {
+        let bytes = pack_container_encoded(&vec![vec![0u8; 1000]; 4], 0, ENCODING_RLE0).unwrap();
+        let limits = Limits {
+            max_total_size: 2500,
+            ..Default::default()
+        };
+        let report = validate_with(&bytes, &limits);
+        let limited: Vec<&str> = report
+            .problems
+            .iter()
+            .filter(|p| p.message.contains("decoded container"))
+            .map(|p| p.location.as_str())
+            .collect();
+        assert_eq!(limited, ["buffer[2]", "buffer[3]"]);
+        assert!(!validate(&bytes).problems.iter().any(|p| p.message.contains("decoded container")));
+    }
+
+    #[test]
+    fn test_overflowing_buffer_table() {
+        let bytes = pack_container(&[vec![0u8; 8]], 0);
+        for (num_buffers, table) in [(u64::MAX / 24, 80u64), (8, !15)] {
+            let mut corrupt = bytes.clone();
+            corrupt[8..16].copy_from_slice(&num_buffers.to_le_bytes());
+            corrupt[16..24].copy_from_slice(&table.to_le_bytes());
+            let report = validate(&corrupt);
+            assert!(!report.valid);
+            assert!(report.problems.iter().any(|p| p.message.contains("exceeds file size")));
+        }
+    }
+}
@@ -0,0 +1,35 @@
+//! WebAssembly wrapper for parsing in the browser, e.g. a drag-and-drop
+//! converter page without a server.
+//!
+//! Build with `--no-default-features --features wasm` for
+//! `wasm32-unknown-unknown` and run `wasm-bindgen` on the result.
+
+use crate::formats::{ReadOptions, Registry};
+use crate::output;
+use wasm_bindgen::prelude::*;
+
+/// Parse every spectrum of a file's bytes into an array of objects shaped
+/// like the JSON output. `name` is the file name; its extension helps pick
+/// the format. Throws an `Error` with the parser's message on failure.
+#[wasm_bindgen(js_name = parseSpc)]
+pub fn parse_spc(bytes: &[u8], name: &str) -> Result<JsValue, JsError> {
+    let mut bytes = bytes.to_vec();
+    let registry = Registry::default();
+    let reader = registry
+        .reader_for(std::path::Path::new(name), &bytes)
+        .ok_or_else(|| JsError::new("unrecognized input format"))?;
+    let (spectra, _) = reader.read(&mut bytes, &ReadOptions::default())?;
+
+    let documents = spectra
+        .iter()
+        .map(|spc| output::to_json_string_spc(spc, false))
+        .collect::<Result<Vec<_>, _>>()?;
+    js_sys::JSON::parse(&format!("[{}]", documents.join(",")))
+        .map_err(|_| JsError::new("could not convert the spectrum to a JavaScript value"))
+}
+
+/// The converter version.
+#[wasm_bindgen]
+pub fn version() -> String {
+    env!("CARGO_PKG_VERSION").to_string()
+}